@@ -0,0 +1,25 @@
+use criterion::{black_box,criterion_group,criterion_main,BenchmarkId,Criterion};
+use delta_inc::diff::Diff;
+
+/// Construct a pseudo-random buffer of `n` bytes.
+fn bytes(n: usize) -> Vec<u8> {
+    let mut x : u32 = 12345;
+    (0..n).map(|_| { x = x.wrapping_mul(1103515245).wrapping_add(12345); (x >> 16) as u8 }).collect()
+}
+
+fn bench_diff_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diff_bytes");
+    for n in [1_000_000, 4_000_000, 16_000_000] {
+        let before = bytes(n);
+        // A single edit in the middle
+        let mut after = before.clone();
+        after.splice(n/2..n/2+10, [0;20]);
+        group.bench_with_input(BenchmarkId::new("myers",n), &n, |b,_| {
+            b.iter(|| black_box(before.diff(&after)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_diff_bytes);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+use criterion::{black_box,criterion_group,criterion_main,BenchmarkId,Criterion};
+use delta_inc::diff::VecDelta;
+
+/// Construct a delta over a sequence of `n` elements which rewrites
+/// every `gap`th element with two elements.
+fn delta(n: usize, gap: usize) -> VecDelta<usize> {
+    let mut delta = VecDelta::new();
+    for (k,i) in (0..n).step_by(gap).enumerate() {
+        // Account for earlier rewrites each adding one element
+        let offset = i + k;
+        delta.try_push(offset .. offset + 1, &[0,0]).unwrap();
+    }
+    delta
+}
+
+/// Apply a delta by splicing each rewrite in turn.
+fn transform_splice(delta: &VecDelta<usize>, vec: &mut Vec<usize>) {
+    for rw in delta.iter() {
+        vec.splice(rw.region().as_range(), rw.data().iter().cloned());
+    }
+}
+
+fn bench_transform(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transform");
+    for n in [1_000, 10_000, 100_000] {
+        let vec : Vec<usize> = (0..n).collect();
+        let delta = delta(n,100);
+        group.bench_with_input(BenchmarkId::new("single_pass",n), &n, |b,_| {
+            b.iter(|| {
+                let mut v = vec.clone();
+                delta.transform(&mut v);
+                black_box(v)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("splice",n), &n, |b,_| {
+            b.iter(|| {
+                let mut v = vec.clone();
+                transform_splice(&delta, &mut v);
+                black_box(v)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_transform);
+criterion_main!(benches);
@@ -0,0 +1,147 @@
+//! Derive macros for the `Diff` and `Transform` traits of `delta_inc`,
+//! which are re-exported from `delta_inc::diff` when its `derive`
+//! feature is enabled.  These apply to structs whose fields can each be
+//! diffed and transformed, where the delta of the struct describes the
+//! change (if any) to each field.
+use proc_macro::TokenStream;
+use proc_macro2::{Span,TokenStream as TokenStream2};
+use quote::{format_ident,quote};
+use syn::{Data,DeriveInput,Fields,Ident,Index,Member,Type,parse_macro_input};
+
+/// Derive `Diff` for a struct.  This generates a companion struct named
+/// after the original with a `Delta` suffix (e.g. `PointDelta` for
+/// `Point`), which has the same fields as the original but where each
+/// holds an `Option` of the field's delta.  A field's delta is `None`
+/// when it is unchanged (as determined by `PartialEq`).  Every field
+/// must implement `Diff` and `PartialEq`.  The companion struct
+/// implements `Clone`, `Debug` and `PartialEq` whenever every field
+/// delta does.
+#[proc_macro_derive(Diff)]
+pub fn derive_diff(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_diff(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Derive `Transform` for a struct which also derives `Diff`, using
+/// its companion delta struct.  Applying this transforms each field
+/// whose delta is present in turn.  Every field must implement
+/// `Transform` with the same delta as its `Diff`.
+#[proc_macro_derive(Transform)]
+pub fn derive_transform(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_transform(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Generate the companion delta struct and the `Diff` implementation.
+fn expand_diff(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let (members,types) = fields_of(input)?;
+    let name = &input.ident;
+    let vis = &input.vis;
+    let delta = delta_name(name);
+    let (impl_generics,type_generics,where_clause) = input.generics.split_for_impl();
+    let mut bounds : Vec<TokenStream2> = where_clause.map(|w| w.predicates.iter().map(|p| quote!(#p)).collect()).unwrap_or_default();
+    bounds.extend(types.iter().map(|t| quote!(#t: ::delta_inc::diff::Diff + ::core::cmp::PartialEq)));
+    let doc = format!("The delta of a `{name}`, holding the delta of each field which changes.");
+    let generics = &input.generics.params;
+    let decl = match &input.data {
+        Data::Struct(s) if matches!(s.fields,Fields::Named(_)) => quote! {
+            #vis struct #delta<#generics> where #(#bounds),* {
+                #( pub #members: ::core::option::Option<<#types as ::delta_inc::diff::Diff>::Delta> ),*
+            }
+        },
+        _ => quote! {
+            #vis struct #delta<#generics>( #( pub ::core::option::Option<<#types as ::delta_inc::diff::Diff>::Delta> ),* ) where #(#bounds),*;
+        }
+    };
+    // Standard traits are implemented directly (rather than derived)
+    // since they depend on the field deltas, not the generic parameters.
+    let field_deltas : Vec<TokenStream2> = types.iter().map(|t| quote!(<#t as ::delta_inc::diff::Diff>::Delta)).collect();
+    let debug = match &input.data {
+        Data::Struct(s) if matches!(s.fields,Fields::Named(_)) => {
+            let labels = members.iter().map(|m| quote!(#m).to_string());
+            quote!(f.debug_struct(stringify!(#delta)) #( .field(#labels, &self.#members) )* .finish())
+        }
+        _ => quote!(f.debug_tuple(stringify!(#delta)) #( .field(&self.#members) )* .finish())
+    };
+    Ok(quote! {
+        #[doc = #doc]
+        #decl
+
+        impl #impl_generics ::core::clone::Clone for #delta #type_generics
+        where #(#bounds,)* #(#field_deltas: ::core::clone::Clone),* {
+            fn clone(&self) -> Self {
+                #delta { #( #members: ::core::clone::Clone::clone(&self.#members) ),* }
+            }
+        }
+
+        impl #impl_generics ::core::fmt::Debug for #delta #type_generics
+        where #(#bounds,)* #(#field_deltas: ::core::fmt::Debug),* {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #debug
+            }
+        }
+
+        impl #impl_generics ::core::cmp::PartialEq for #delta #type_generics
+        where #(#bounds,)* #(#field_deltas: ::core::cmp::PartialEq),* {
+            fn eq(&self, other: &Self) -> bool {
+                true #( && self.#members == other.#members )*
+            }
+        }
+
+        impl #impl_generics ::delta_inc::diff::Diff for #name #type_generics where #(#bounds),* {
+            type Delta = #delta #type_generics;
+
+            fn diff(&self, other: &Self) -> Self::Delta {
+                #delta {
+                    #( #members: if self.#members == other.#members {
+                        ::core::option::Option::None
+                    } else {
+                        ::core::option::Option::Some(::delta_inc::diff::Diff::diff(&self.#members, &other.#members))
+                    } ),*
+                }
+            }
+        }
+    })
+}
+
+/// Generate the `Transform` implementation.
+fn expand_transform(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let (members,types) = fields_of(input)?;
+    let name = &input.ident;
+    let delta = delta_name(name);
+    let (impl_generics,type_generics,where_clause) = input.generics.split_for_impl();
+    let mut bounds : Vec<TokenStream2> = where_clause.map(|w| w.predicates.iter().map(|p| quote!(#p)).collect()).unwrap_or_default();
+    bounds.extend(types.iter().map(|t| quote! {
+        #t: ::delta_inc::diff::Diff + ::core::cmp::PartialEq + ::delta_inc::diff::Transform<Delta = <#t as ::delta_inc::diff::Diff>::Delta>
+    }));
+    Ok(quote! {
+        impl #impl_generics ::delta_inc::diff::Transform for #name #type_generics where #(#bounds),* {
+            type Delta = #delta #type_generics;
+
+            fn transform(&mut self, d: &Self::Delta) {
+                #( if let ::core::option::Option::Some(d) = &d.#members {
+                    ::delta_inc::diff::Transform::transform(&mut self.#members, d);
+                } )*
+            }
+        }
+    })
+}
+
+/// Determine the name of the companion delta struct.
+fn delta_name(name: &Ident) -> Ident {
+    format_ident!("{}Delta", name)
+}
+
+/// Extract the members and types of the fields of a struct, or report
+/// an error if the input is not a struct.
+fn fields_of(input: &DeriveInput) -> syn::Result<(Vec<Member>,Vec<Type>)> {
+    let Data::Struct(s) = &input.data else {
+        return Err(syn::Error::new(Span::call_site(), "only structs can be diffed field-wise"));
+    };
+    let members = s.fields.iter().enumerate().map(|(i,f)| match &f.ident {
+        Some(id) => Member::Named(id.clone()),
+        None => Member::Unnamed(Index::from(i))
+    }).collect();
+    let types = s.fields.iter().map(|f| f.ty.clone()).collect();
+    Ok((members,types))
+}
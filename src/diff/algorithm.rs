@@ -0,0 +1,159 @@
+use alloc::vec::Vec;
+use core::hash::Hash;
+use crate::util::{hash_all,hash_of};
+use super::{VecDelta,common_prefix_suffix,histogram_subsequence,longest_common_subsequence};
+use super::{myers_subsequence,patience_subsequence};
+use super::slice::extract_delta;
+
+/// Identifies one of the algorithms available for diffing slices.
+/// This allows callers to choose, on a per call basis, the tradeoff
+/// between speed, memory usage and quality of delta.  For example:
+///
+/// ```
+/// use delta_inc::diff::DiffAlgorithm;
+///
+/// let before = [1,2,3];
+/// let mut after = before.to_vec();
+/// let delta = DiffAlgorithm::Patience.diff(&before,&[1,4,3]);
+/// delta.transform(&mut after);
+/// assert_eq!(after,[1,4,3]);
+/// ```
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum DiffAlgorithm {
+    /// The classical dynamic programming algorithm for the _longest
+    /// common subsequence_ (see `longest_common_subsequence()`).
+    Lcs,
+    /// Myers' `O(ND)` algorithm (see `myers_subsequence()`).
+    #[default]
+    Myers,
+    /// The _patience diff_ algorithm (see `patience_subsequence()`).
+    Patience,
+    /// The _histogram diff_ algorithm (see `histogram_subsequence()`).
+    Histogram
+}
+
+impl DiffAlgorithm {
+    /// Determine a common subsequence of two slices using this
+    /// algorithm.  This produces a mapping from elements in `lhs` to
+    /// elements in `rhs` in the same form as
+    /// `longest_common_subsequence()`.
+    pub fn subsequence<T:Hash+Eq>(self, lhs: &[T], rhs: &[T]) -> Vec<Option<usize>> {
+        match self {
+            DiffAlgorithm::Lcs => longest_common_subsequence(lhs,rhs),
+            DiffAlgorithm::Myers => myers_subsequence(lhs,rhs),
+            DiffAlgorithm::Patience => patience_subsequence(lhs,rhs),
+            DiffAlgorithm::Histogram => histogram_subsequence(lhs,rhs)
+        }
+    }
+
+    /// Compute a delta between two slices using this algorithm.  As
+    /// a preprocessing step, every element is hashed exactly once.
+    /// Thereafter, elements are compared by hash first and only
+    /// compared directly when their hashes match.  This is
+    /// significant when elements are expensive to compare.
+    pub fn diff<T:Clone+Hash+Eq>(self, lhs: &[T], rhs: &[T]) -> VecDelta<T> {
+        self.diff_hashed(lhs, rhs, hash_of)
+    }
+
+    /// Compute a delta between two slices using this algorithm where
+    /// elements are hashed using a given function (see `diff()`).
+    /// The hash function must be consistent with equality (i.e. equal
+    /// elements must have equal hashes).  However, it need not be
+    /// collision free, since elements whose hashes match are always
+    /// compared directly before being matched.  Thus, collisions only
+    /// cost time (and, for `Patience` and `Histogram`, may affect
+    /// which anchors are chosen) but never the correctness of the
+    /// resulting delta.
+    pub fn diff_hashed<T:Clone+Eq,F:Fn(&T)->u64>(self, lhs: &[T], rhs: &[T], hasher: F) -> VecDelta<T> {
+        let lhs_h = hash_all(lhs,&hasher);
+        let rhs_h = hash_all(rhs,&hasher);
+        let (pre,suf) = common_prefix_suffix(&lhs_h,&rhs_h);
+        let mapping = self.subsequence(&lhs_h[pre..lhs.len()-suf],&rhs_h[pre..rhs.len()-suf]);
+        extract_delta(pre, &mapping, &rhs[pre..rhs.len()-suf])
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod algorithm_tests {
+    use alloc::vec::Vec;
+    use core::hash::{Hash,Hasher};
+    use core::sync::atomic::{AtomicUsize,Ordering};
+    use super::DiffAlgorithm;
+
+    const ALGORITHMS : [DiffAlgorithm;4] = [DiffAlgorithm::Lcs,DiffAlgorithm::Myers,DiffAlgorithm::Patience,DiffAlgorithm::Histogram];
+
+    #[test]
+    fn algorithm_test_01() {
+        assert_eq!(DiffAlgorithm::default(),DiffAlgorithm::Myers);
+    }
+
+    #[test]
+    fn algorithm_test_02() {
+        for alg in ALGORITHMS {
+            let mut vec = Vec::from([1,2,3]);
+            let delta = alg.diff(&[1,2,3],&[4,1,2,5,6]);
+            assert_eq!(delta.len(),2);
+            delta.transform(&mut vec);
+            assert_eq!(vec,[4,1,2,5,6]);
+        }
+    }
+
+    #[test]
+    fn algorithm_test_03() {
+        for alg in ALGORITHMS {
+            let mut vec = Vec::from(["a","b","c"]);
+            let delta = alg.diff(&["a","b","c"],&[]);
+            assert_eq!(delta.len(),1);
+            delta.transform(&mut vec);
+            assert!(vec.is_empty());
+        }
+    }
+
+    #[test]
+    fn algorithm_test_04() {
+        // Comparisons only made on hash collisions
+        #[derive(Clone,Debug,Eq)]
+        struct Expensive(usize);
+        static COMPARISONS : AtomicUsize = AtomicUsize::new(0);
+        impl PartialEq for Expensive {
+            fn eq(&self, other: &Self) -> bool {
+                COMPARISONS.fetch_add(1,Ordering::Relaxed);
+                self.0 == other.0
+            }
+        }
+        impl Hash for Expensive {
+            fn hash<H:Hasher>(&self, state: &mut H) { self.0.hash(state) }
+        }
+        let lhs : Vec<Expensive> = (0..100).map(Expensive).collect();
+        let rhs : Vec<Expensive> = (0..100).map(|i| Expensive(i + 50)).collect();
+        for alg in ALGORITHMS {
+            COMPARISONS.store(0,Ordering::Relaxed);
+            let delta = alg.diff(&lhs,&rhs);
+            // Only the 50 matching pairs are ever compared (though
+            // possibly more than once), rather than all 100*100 pairs.
+            assert!(COMPARISONS.load(Ordering::Relaxed) <= 4 * 50);
+            let mut vec = lhs.clone();
+            delta.transform(&mut vec);
+            assert_eq!(vec,rhs);
+        }
+    }
+
+    #[test]
+    fn algorithm_test_05() {
+        // User-supplied hasher
+        let mut vec = Vec::from([10,20,30]);
+        let delta = DiffAlgorithm::Histogram.diff_hashed(&[10,20,30],&[10,25,30],|x| (x / 10) as u64);
+        delta.transform(&mut vec);
+        assert_eq!(vec,[10,25,30]);
+        // Every hash collides
+        for alg in ALGORITHMS {
+            let mut vec = Vec::from([1,2,3,4]);
+            alg.diff_hashed(&[1,2,3,4],&[2,5,4,1],|_| 0).transform(&mut vec);
+            assert_eq!(vec,[2,5,4,1]);
+        }
+    }
+}
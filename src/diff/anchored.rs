@@ -0,0 +1,345 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::Hash;
+use core::ops::Range;
+use crate::util::hash_of;
+use super::{VecDelta,common_prefix_suffix,diff_trimmed,longest_common_subsequence,myers_subsequence};
+use super::histogram::MAX_STALLS;
+
+/// Compute a delta between two slices by first _anchoring_ on
+/// elements which are unique in both, before falling back to the
+/// _longest common subsequence_ algorithm between anchors.  This is
+/// the key idea behind _patience diff_, and it tends to produce
+/// deltas which better respect the logical structure of a change.
+/// For example, consider inserting a function between two existing
+/// functions in a sequence of lines:
+///
+/// ```txt
+///  fn f() {        fn f() {
+///  }               }
+///
+///  fn h() {        fn g() {
+///  }               }
+///
+///                  fn h() {
+///                  }
+/// ```
+///
+/// Here, plain LCS is free to match the unimportant `}` and blank
+/// lines of `f()` and `h()` against those of `g()`, thereby
+/// interleaving many small rewrites.  However, lines such as `fn f()
+/// {` are unique in both sequences and, hence, make good anchors.
+///
+/// Elements are identified using a given `key` function, which
+/// allows (for example) lines to be anchored on their trimmed
+/// content.  Anchors must still be equal according to `PartialEq`
+/// and, hence, the resulting delta remains correct regardless of the
+/// key function chosen.
+pub fn diff_anchored<T,K,F>(lhs: &[T], rhs: &[T], key: F) -> VecDelta<T>
+where T:Clone+PartialEq, K:Hash+Eq, F:Fn(&T)->K {
+    diff_trimmed(lhs, rhs, |l,r| anchored_subsequence(l,r,key))
+}
+
+/// Determine a common subsequence of two slices, anchored on those
+/// elements whose keys are unique in both.  This produces a mapping
+/// from elements in `lhs` to elements in `rhs` in the same form as
+/// `longest_common_subsequence()`.  Anchors are chosen as the longest
+/// increasing sequence of unique matches, and the gaps between them
+/// are then filled using `longest_common_subsequence()`.
+pub fn anchored_subsequence<T,K,F>(lhs: &[T], rhs: &[T], key: F) -> Vec<Option<usize>>
+where T:Clone+PartialEq, K:Hash+Eq, F:Fn(&T)->K {
+    let lkeys : Vec<K> = lhs.iter().map(&key).collect();
+    let rkeys : Vec<K> = rhs.iter().map(&key).collect();
+    // Identify candidate anchors
+    let candidates : Vec<(usize,usize)> = unique_matches(&lkeys,&rkeys)
+        .into_iter()
+        .filter(|&(i,j)| lhs[i] == rhs[j])
+        .collect();
+    // Select those which are consistently ordered
+    let anchors = longest_increasing(&candidates);
+    // Fill gaps between anchors
+    let mut res = vec![None;lhs.len()];
+    let (mut i, mut j) = (0,0);
+    for (ai,aj) in anchors {
+        fill_gap(&mut res, lhs, rhs, i..ai, j..aj);
+        res[ai] = Some(aj);
+        i = ai + 1;
+        j = aj + 1;
+    }
+    fill_gap(&mut res, lhs, rhs, i..lhs.len(), j..rhs.len());
+    res
+}
+
+/// Compute a delta between two slices using the _patience diff_
+/// algorithm (see `patience_subsequence()`).  This typically produces
+/// more intuitive deltas than plain LCS for source-code-like inputs,
+/// since it avoids pairing up unrelated but frequently occurring
+/// elements (e.g. blank lines or closing braces).
+pub fn diff_patience<T:Clone+Hash+Eq>(lhs: &[T], rhs: &[T]) -> VecDelta<T> {
+    diff_trimmed(lhs, rhs, patience_subsequence)
+}
+
+/// Determine a common subsequence of two slices using the _patience
+/// diff_ algorithm.  This produces a mapping from elements in `lhs`
+/// to elements in `rhs` in the same form as
+/// `longest_common_subsequence()`.  The algorithm proceeds as
+/// follows: firstly, the common prefix and suffix are matched;
+/// secondly, elements unique to both sides of what remains are used as
+/// anchors; finally, the gaps between anchors are solved in the same
+/// way (using an explicit work stack, rather than recursion).  As for
+/// `histogram_subsequence()`, a gap which repeatedly fails to shrink
+/// is solved using `myers_subsequence()` instead.
+/// Observe that an element which is not unique overall may become
+/// unique within a gap.  When no anchors remain, the gap is solved
+/// using `myers_subsequence()`.
+pub fn patience_subsequence<T:Hash+Eq>(lhs: &[T], rhs: &[T]) -> Vec<Option<usize>> {
+    let mut res = vec![None;lhs.len()];
+    // Subproblems are given as (lstart,lend,rstart,rend,stalls)
+    let mut work = vec![(0,lhs.len(),0,rhs.len(),0)];
+    while let Some((ls,le,rs,re,stalls)) = work.pop() {
+        // Match common prefix and suffix
+        let (pre,suf) = common_prefix_suffix(&lhs[ls..le],&rhs[rs..re]);
+        for k in 0..pre { res[ls + k] = Some(rs + k); }
+        for k in 1..=suf { res[le - k] = Some(re - k); }
+        let (ls,le,rs,re) = (ls + pre, le - suf, rs + pre, re - suf);
+        if ls == le || rs == re { continue; }
+        // Identify anchors
+        let (l,r) = (&lhs[ls..le],&rhs[rs..re]);
+        let anchors = if stalls < MAX_STALLS { longest_increasing(&unique_matches(l,r)) } else { Vec::new() };
+        if anchors.is_empty() {
+            let mapping = myers_subsequence(l,r);
+            for (k,m) in mapping.into_iter().enumerate() {
+                res[ls + k] = m.map(|v| v + rs);
+            }
+        } else {
+            // Gaps which are more than half of this subproblem stall
+            let size = l.len() + r.len();
+            let mut push = |i: usize, ie: usize, j: usize, je: usize| {
+                let stalls = if 2 * ((ie - i) + (je - j)) > size { stalls + 1 } else { 0 };
+                work.push((i,ie,j,je,stalls));
+            };
+            let (mut i, mut j) = (ls,rs);
+            for (ai,aj) in anchors {
+                push(i,ls + ai,j,rs + aj);
+                res[ls + ai] = Some(rs + aj);
+                i = ls + ai + 1;
+                j = rs + aj + 1;
+            }
+            push(i,le,j,re);
+        }
+    }
+    res
+}
+
+/// Identify all pairs `(i,j)` where `lhs[i]` occurs exactly once in
+/// `lhs`, `rhs[j]` occurs exactly once in `rhs` and they are equal.
+/// The resulting pairs are sorted by `i`.  Keys are bucketed by their
+/// hash and, hence, a hash collision between two distinct keys simply
+/// causes them to be treated as non-unique.
+pub(crate) fn unique_matches<K:Hash+Eq>(lhs: &[K], rhs: &[K]) -> Vec<(usize,usize)> {
+    // Maps each hash to counts and last seen indices on each side.
+    let mut buckets : BTreeMap<u64,(usize,usize,usize,usize)> = BTreeMap::new();
+    for (i,k) in lhs.iter().enumerate() {
+        let e = buckets.entry(hash_of(k)).or_insert((0,0,0,0));
+        e.0 += 1;
+        e.1 = i;
+    }
+    for (j,k) in rhs.iter().enumerate() {
+        // Elements not present in lhs can never be anchors
+        if let Some(e) = buckets.get_mut(&hash_of(k)) {
+            e.2 += 1;
+            e.3 = j;
+        }
+    }
+    let mut matches : Vec<(usize,usize)> = buckets.values()
+        .filter(|e| e.0 == 1 && e.2 == 1 && lhs[e.1] == rhs[e.3])
+        .map(|e| (e.1,e.3))
+        .collect();
+    matches.sort_unstable();
+    matches
+}
+
+/// Select the longest subsequence of a given sequence of pairs `(i,j)`
+/// (sorted by `i`) which is also increasing in `j`.  This uses the
+/// _patience sorting_ algorithm and runs in `O(n log n)` time.
+pub(crate) fn longest_increasing(pairs: &[(usize,usize)]) -> Vec<(usize,usize)> {
+    // Index of the last pair of the best run of each length.
+    let mut tails : Vec<usize> = Vec::new();
+    // Predecessor of each pair in the best run ending with it.
+    let mut prev : Vec<Option<usize>> = vec![None;pairs.len()];
+    for (k,&(_,j)) in pairs.iter().enumerate() {
+        let p = tails.partition_point(|&t| pairs[t].1 < j);
+        if p > 0 { prev[k] = Some(tails[p-1]); }
+        if p == tails.len() { tails.push(k); } else { tails[p] = k; }
+    }
+    // Walk back through predecessors
+    let mut res = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(k) = next {
+        res.push(pairs[k]);
+        next = prev[k];
+    }
+    res.reverse();
+    res
+}
+
+/// Fill the portion of a mapping between two anchors using the
+/// _longest common subsequence_ of the corresponding subslices.
+fn fill_gap<T:Clone+PartialEq>(res: &mut [Option<usize>], lhs: &[T], rhs: &[T], l: Range<usize>, r: Range<usize>) {
+    if l.is_empty() || r.is_empty() { return; }
+    let mapping = longest_common_subsequence(&lhs[l.clone()], &rhs[r.clone()]);
+    for (k,m) in mapping.into_iter().enumerate() {
+        res[l.start + k] = m.map(|v| v + r.start);
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod anchored_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,diff_anchored,diff_patience};
+    use super::longest_increasing;
+
+    #[test]
+    fn test_lis_01() {
+        let v = longest_increasing(&[(0,3),(1,1),(2,2),(3,0)]);
+        assert_eq!(v,vec![(1,1),(2,2)]);
+    }
+
+    #[test]
+    fn test_anchored_01() {
+        check(&[1,2,3],&[1,2,3],0);
+    }
+
+    #[test]
+    fn test_anchored_02() {
+        check(&[1,2,3],&[4,5,6],1);
+    }
+
+    #[test]
+    fn test_anchored_03() {
+        check(&[0,0,1,0,0],&[0,1,0],2);
+    }
+
+    #[test]
+    fn test_anchored_04() {
+        // Inserting a function between two others
+        let before = ["fn f() {","  x","}","","fn h() {","  z","}"];
+        let after = ["fn f() {","  x","}","","fn g() {","  y","}","","fn h() {","  z","}"];
+        let delta = check(&before,&after,1);
+        let rw = delta.get(0).unwrap();
+        // Must be a pure insertion
+        assert_eq!(rw.region().length,0);
+        assert_eq!(rw.data().len(),4);
+    }
+
+    #[test]
+    fn test_anchored_05() {
+        // Lines with unimportant braces moved around
+        let before = ["a","}","b","}","c","}"];
+        let after = ["a","}","c","}","b","}"];
+        check(&before,&after,2);
+    }
+
+    #[test]
+    fn test_anchored_06() {
+        // Anchor on trimmed lines
+        let before = ["a"," b","c"];
+        let after = ["a","b ","c"];
+        let mut vec = before.to_vec();
+        let delta = diff_anchored(&before,&after,|l| l.trim());
+        assert_eq!(delta.len(),1);
+        delta.transform(&mut vec);
+        assert_eq!(&vec,&after);
+    }
+
+    #[test]
+    fn test_anchored_07() {
+        // Round trip agrees with regular diff
+        let seqs : [&[usize];6] = [&[],&[1],&[1,2,1,2],&[2,1,3,1,2],&[3,3,3],&[1,4,2,5,3,1]];
+        for a in seqs {
+            for b in seqs {
+                let mut v1 = a.to_vec();
+                let mut v2 = a.to_vec();
+                diff_anchored(a,b,|x| *x).transform(&mut v1);
+                a.diff(b).transform(&mut v2);
+                assert_eq!(&v1,b);
+                assert_eq!(v1,v2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_patience_01() {
+        check_patience(&[1,2,3],&[1,2,3],0);
+    }
+
+    #[test]
+    fn test_patience_02() {
+        check_patience(&[1,2,3],&[4,5,6],1);
+    }
+
+    #[test]
+    fn test_patience_03() {
+        // Inserting a function between two others
+        let before = ["fn f() {","  x","}","","fn h() {","  z","}"];
+        let after = ["fn f() {","  x","}","","fn g() {","  y","}","","fn h() {","  z","}"];
+        check_patience(&before,&after,1);
+    }
+
+    #[test]
+    fn test_patience_04() {
+        // Elements unique only within a gap
+        let before = ["a","x","b","x","c"];
+        let after = ["a","y","b","x","c"];
+        let delta = check_patience(&before,&after,1);
+        assert_eq!(delta.get(0).unwrap().region().offset,1);
+    }
+
+    #[test]
+    fn test_patience_05() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..20).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let mut vec = a.clone();
+                diff_patience(a,b).transform(&mut vec);
+                assert_eq!(&vec,b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_patience_06() {
+        // Deeply nested gaps
+        let n = 8000;
+        let before : Vec<usize> = (1..=n).flat_map(|i| [i,i-1]).collect();
+        let after : Vec<usize> = (1..=n).collect();
+        let mut vec = before.clone();
+        diff_patience(&before,&after).transform(&mut vec);
+        assert_eq!(vec,after);
+    }
+
+    fn check_patience<T:Clone+Eq+core::hash::Hash+core::fmt::Debug>(from: &[T], to: &[T], num: usize) -> crate::diff::VecDelta<T> {
+        let mut vec : Vec<T> = from.to_vec();
+        let delta = diff_patience(from,to);
+        assert_eq!(delta.len(),num);
+        delta.transform(&mut vec);
+        assert_eq!(&vec,to);
+        delta
+    }
+
+    fn check<T:Clone+Eq+core::hash::Hash+core::fmt::Debug>(from: &[T], to: &[T], num: usize) -> crate::diff::VecDelta<T> {
+        let mut vec : Vec<T> = from.to_vec();
+        let delta = diff_anchored(from,to,|x| x.clone());
+        assert_eq!(delta.len(),num);
+        delta.transform(&mut vec);
+        assert_eq!(&vec,to);
+        delta
+    }
+}
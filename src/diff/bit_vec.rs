@@ -0,0 +1,239 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+use super::{Diff,Transform};
+
+/// Number of bits held in each word of a `BitVec`.
+const BITS : usize = u64::BITS as usize;
+
+/// A growable sequence of bits which are packed into `u64` words, such
+/// that bit `i` is held in bit `i % 64` of word `i / 64`.  This is
+/// suitable for representing large boolean masks, which can be diffed
+/// and transformed a word at a time (see `BitDelta`).  For example:
+///
+/// ```
+/// use delta_inc::diff::{BitVec,Diff,Transform};
+///
+/// let before : BitVec = (0..1000).map(|i| i % 3 == 0).collect();
+/// let mut after = before.clone();
+/// for i in 100..300 { after.set(i,true); }
+/// let delta = before.diff(&after);
+/// let mut bits = before.clone();
+/// bits.transform(&delta);
+/// assert_eq!(bits,after);
+/// ```
+#[derive(Clone,Debug,Default,PartialEq,Eq,Hash)]
+pub struct BitVec {
+    /// Packed bits, where any bits beyond the length are zero.
+    words: Vec<u64>,
+    /// Number of bits in this sequence.
+    len: usize
+}
+
+impl BitVec {
+    /// Construct an empty `BitVec`.
+    pub const fn new() -> Self { BitVec{words: Vec::new(), len: 0} }
+
+    /// Construct a `BitVec` of a given length where every bit is zero.
+    pub fn zeros(len: usize) -> Self { BitVec{words: alloc::vec![0;len.div_ceil(BITS)], len} }
+
+    /// Construct a `BitVec` of a given length from packed words, where
+    /// bit `i` is held in bit `i % 64` of word `i / 64`.  Any words (or
+    /// bits) beyond the given length are ignored, and any missing words
+    /// are taken as zero.
+    pub fn from_words(words: &[u64], len: usize) -> Self {
+        let mut bits = BitVec::zeros(len);
+        let n = bits.words.len().min(words.len());
+        bits.words[..n].copy_from_slice(&words[..n]);
+        bits.clear_tail();
+        bits
+    }
+
+    /// Get the number of bits in this sequence.
+    pub fn len(&self) -> usize { self.len }
+
+    /// Check whether this sequence contains any bits or not.
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Get the packed words of this sequence.  Any bits of the last
+    /// word beyond the length are zero.
+    pub fn as_words(&self) -> &[u64] { &self.words }
+
+    /// Get the bit at a given index.  This will `panic` if the index is
+    /// out of bounds.
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < self.len,"index {i} out of bounds");
+        self.words[i / BITS] & (1 << (i % BITS)) != 0
+    }
+
+    /// Set the bit at a given index.  This will `panic` if the index is
+    /// out of bounds.
+    pub fn set(&mut self, i: usize, bit: bool) {
+        assert!(i < self.len,"index {i} out of bounds");
+        let (w,mask) = (i / BITS, 1 << (i % BITS));
+        if bit { self.words[w] |= mask; } else { self.words[w] &= !mask; }
+    }
+
+    /// Append a bit onto the end of this sequence.
+    pub fn push(&mut self, bit: bool) {
+        self.resize(self.len + 1);
+        self.set(self.len - 1, bit);
+    }
+
+    /// Change the length of this sequence, either by removing bits from
+    /// the end or by appending zeros.
+    pub fn resize(&mut self, len: usize) {
+        self.words.resize(len.div_ceil(BITS),0);
+        self.len = len;
+        self.clear_tail();
+    }
+
+    /// Get the number of bits which are set.
+    pub fn count_ones(&self) -> usize { self.words.iter().map(|w| w.count_ones() as usize).sum() }
+
+    /// Iterate the bits of this sequence.
+    pub fn iter(&self) -> impl Iterator<Item=bool> + '_ { (0..self.len).map(|i| self.get(i)) }
+
+    /// Invert all bits within a given range.  This will `panic` if the
+    /// range is out of bounds.
+    pub fn flip(&mut self, range: Range<usize>) {
+        assert!(range.end <= self.len,"range {range:?} out of bounds");
+        let mut i = range.start;
+        while i < range.end {
+            let (w,offset) = (i / BITS, i % BITS);
+            let n = (BITS - offset).min(range.end - i);
+            self.words[w] ^= mask(n) << offset;
+            i += n;
+        }
+    }
+
+    /// Clear any bits of the last word beyond the length.
+    fn clear_tail(&mut self) {
+        if let Some(last) = self.words.last_mut() {
+            let used = self.len - (self.len - 1) / BITS * BITS;
+            *last &= mask(used);
+        }
+    }
+}
+
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I:IntoIterator<Item=bool>>(iter: I) -> Self {
+        let mut bits = BitVec::new();
+        for b in iter { bits.push(b); }
+        bits
+    }
+}
+
+/// Construct a mask of the given number of low bits (up to `64`).
+fn mask(n: usize) -> u64 { if n >= BITS { u64::MAX } else { (1 << n) - 1 } }
+
+// ===================================================================
+// BitDelta
+// ===================================================================
+
+/// A delta between two bit sequences, consisting of the length of the
+/// updated sequence and the _runs_ of bits which differ.  Since a bit
+/// which differs must be inverted, each run is simply a range of bits
+/// to flip (i.e. there is no need to store the updated bits).  When the
+/// length changes, the original sequence is truncated or extended with
+/// zeros before the runs are flipped.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct BitDelta {
+    len: usize,
+    runs: Vec<Range<usize>>
+}
+
+impl BitDelta {
+    /// Get the length of the sequence after this delta is applied.
+    pub fn target_len(&self) -> usize { self.len }
+
+    /// Get the (sorted and disjoint) runs of bits flipped by this delta.
+    pub fn runs(&self) -> &[Range<usize>] { &self.runs }
+
+    /// Get the number of runs in this delta.
+    pub fn len(&self) -> usize { self.runs.len() }
+
+    /// Check whether this delta flips any bits or not.  Note that an
+    /// empty delta may still change the length of a sequence.
+    pub fn is_empty(&self) -> bool { self.runs.is_empty() }
+}
+
+/// Diffing of bit sequences compares them a word at a time, such that
+/// words which are identical are skipped quickly.
+impl Diff for BitVec {
+    type Delta = BitDelta;
+
+    fn diff(&self, other: &BitVec) -> BitDelta {
+        let mut runs : Vec<Range<usize>> = Vec::new();
+        for (w,&after) in other.words.iter().enumerate() {
+            // Bits beyond either length are taken as zero
+            let before = self.words.get(w).map_or(0, |&b| b & mask(other.len - w * BITS));
+            let mut x = before ^ after;
+            while x != 0 {
+                let start = x.trailing_zeros() as usize;
+                let n = (x >> start).trailing_ones() as usize;
+                let run = w*BITS+start..w*BITS+start+n;
+                match runs.last_mut() {
+                    Some(r) if r.end == run.start => r.end = run.end,
+                    _ => runs.push(run)
+                }
+                x &= !(mask(n) << start);
+            }
+        }
+        BitDelta{len: other.len, runs}
+    }
+}
+
+/// Applying a `BitDelta` will `panic` if any run is beyond the updated
+/// length.
+impl Transform for BitVec {
+    type Delta = BitDelta;
+
+    fn transform(&mut self, d: &BitDelta) {
+        self.resize(d.len);
+        for r in &d.runs { self.flip(r.clone()); }
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod bit_vec_tests {
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,Transform};
+    use super::BitVec;
+
+    fn bitvecs() -> Vec<BitVec> {
+        (0..12).map(|i| (0..i*23).map(|j| (j*i+3)%5 < 2 || (j/40)%3 == 1).collect()).collect()
+    }
+
+    #[test]
+    fn bit_vec_test_01() {
+        for a in bitvecs() {
+            for b in bitvecs() {
+                let delta = a.diff(&b);
+                let mut bits = a.clone();
+                bits.transform(&delta);
+                assert_eq!(bits,b);
+                assert!(delta.runs().windows(2).all(|w| w[0].end < w[1].start));
+            }
+        }
+    }
+
+    #[test]
+    fn bit_vec_test_02() {
+        let mut a = BitVec::zeros(200);
+        let mut b = a.clone();
+        b.flip(60..130);
+        assert_eq!(b.count_ones(),70);
+        let delta = a.diff(&b);
+        assert_eq!((delta.len(),delta.runs().first()),(1,Some(&(60..130))));
+        // Truncation clears the tail
+        a.flip(0..200);
+        a.resize(70);
+        assert_eq!((a.count_ones(),a.as_words()),(70,&[u64::MAX,63][..]));
+        assert_eq!(BitVec::from_words(&[u64::MAX],3).as_words(),[7]);
+        assert!(a.diff(&BitVec::from_words(&[u64::MAX,63,1],70)).is_empty());
+    }
+}
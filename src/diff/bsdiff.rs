@@ -0,0 +1,263 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::util::Region;
+use super::VecDelta;
+
+/// A binary patch in the style of `bsdiff`, which is typically much
+/// smaller (once compressed) than a `VecDelta` for binary files such
+/// as executables.  This is because small changes scattered throughout
+/// a region (e.g. where addresses embedded in machine code shift) are
+/// recorded as byte-wise differences against an approximately matching
+/// region of the old file, rather than as replacement data.  A patch
+/// consists of a sequence of _controls_ `(x,y,z)` which are applied in
+/// turn:
+///
+/// 1. `x` bytes of the old file are added (modulo 256) to the next `x`
+///    bytes of _difference_ data, and appended to the new file.
+/// 2. `y` bytes of _extra_ data are appended to the new file.
+/// 3. The position in the old file is advanced by `z` (which may be
+///    negative).
+///
+/// A patch is computed using `diff_binary()`.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct BinaryPatch {
+    controls: Vec<(usize,usize,isize)>,
+    diff: Vec<u8>,
+    extra: Vec<u8>
+}
+
+impl BinaryPatch {
+    /// Get the controls which make up this patch.
+    pub fn controls(&self) -> &[(usize,usize,isize)] { &self.controls }
+
+    /// Get the difference data of this patch, of which most bytes are
+    /// typically zero.
+    pub fn diff_data(&self) -> &[u8] { &self.diff }
+
+    /// Get the extra data of this patch, which is copied directly.
+    pub fn extra_data(&self) -> &[u8] { &self.extra }
+
+    /// Apply this patch to the old file, producing the new file.  This
+    /// operation will `panic` if this patch is malformed.
+    pub fn apply(&self, old: &[u8]) -> Vec<u8> {
+        self.walk(old).0
+    }
+
+    /// Convert this patch into a `VecDelta` over the old file.  Bytes
+    /// of the new file which equal the corresponding byte of the old
+    /// file (i.e. whose difference is zero), and which follow those
+    /// already accounted for, are unchanged by the delta.  Everything
+    /// else becomes replacement data.
+    pub fn to_delta(&self, old: &[u8]) -> VecDelta<u8> {
+        let (new,matches) = self.walk(old);
+        let mut hunks = Vec::new();
+        let (mut cursor, mut start) = (0,0);
+        for (i,pos) in matches.into_iter().enumerate() {
+            match pos {
+                Some(pos) if pos >= cursor => {
+                    if pos > cursor || i > start {
+                        hunks.push((Region::new(cursor,pos - cursor),&new[start..i]));
+                    }
+                    cursor = pos + 1;
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        if cursor < old.len() || start < new.len() {
+            hunks.push((Region::new(cursor,old.len() - cursor),&new[start..]));
+        }
+        VecDelta::from_source(hunks)
+    }
+
+    /// Apply this patch to the old file, producing the new file along
+    /// with the position of the byte in the old file (if any) which
+    /// each byte of the new file is unchanged from.
+    fn walk(&self, old: &[u8]) -> (Vec<u8>,Vec<Option<usize>>) {
+        let mut new = Vec::new();
+        let mut matches = Vec::new();
+        let (mut diff, mut extra) = (self.diff.iter(), self.extra.iter());
+        let mut pos = 0isize;
+        for &(x,y,z) in &self.controls {
+            for d in diff.by_ref().take(x) {
+                let o = usize::try_from(pos).ok().and_then(|p| old.get(p).map(|b| (p,*b)));
+                let (p,b) = o.expect("patch out of bounds");
+                new.push(b.wrapping_add(*d));
+                matches.push(if *d == 0 { Some(p) } else { None });
+                pos += 1;
+            }
+            for e in extra.by_ref().take(y) {
+                new.push(*e);
+                matches.push(None);
+            }
+            pos += z;
+        }
+        (new,matches)
+    }
+}
+
+/// Compute a binary patch between two byte sequences using the
+/// algorithm of `bsdiff` (Percival, _"Naive differences of executable
+/// code"_, 2003).  This uses a suffix array of the old file to find
+/// long (exact) matches for regions of the new file, which are then
+/// extended forwards and backwards to include approximately matching
+/// bytes.  The patch can be converted into a `VecDelta` using
+/// `BinaryPatch::to_delta()`.
+pub fn diff_binary(old: &[u8], new: &[u8]) -> BinaryPatch {
+    let sa = suffix_array(old);
+    let (n,m) = (old.len() as isize, new.len() as isize);
+    let at = |s: &[u8], i: isize| s[i as usize];
+    let mut patch = BinaryPatch::default();
+    let (mut scan, mut len, mut pos) = (0isize,0isize,0isize);
+    let (mut lastscan, mut lastpos, mut lastoffset) = (0isize,0isize,0isize);
+    while scan < m {
+        let mut oldscore = 0;
+        scan += len;
+        let mut scsc = scan;
+        while scan < m {
+            (len,pos) = search(&sa, old, &new[scan as usize..]);
+            while scsc < scan + len {
+                if scsc + lastoffset < n && at(old,scsc + lastoffset) == at(new,scsc) { oldscore += 1; }
+                scsc += 1;
+            }
+            if (len == oldscore && len != 0) || len > oldscore + 8 { break; }
+            if scan + lastoffset < n && at(old,scan + lastoffset) == at(new,scan) { oldscore -= 1; }
+            scan += 1;
+        }
+        if len != oldscore || scan == m {
+            // Extend the previous match forwards
+            let (mut s, mut sf, mut lenf) = (0,0,0);
+            let mut i = 0;
+            while lastscan + i < scan && lastpos + i < n {
+                if at(old,lastpos + i) == at(new,lastscan + i) { s += 1; }
+                i += 1;
+                if s * 2 - i > sf * 2 - lenf { sf = s; lenf = i; }
+            }
+            // Extend the next match backwards
+            let mut lenb = 0;
+            if scan < m {
+                let (mut s, mut sb) = (0,0);
+                let mut i = 1;
+                while scan >= lastscan + i && pos >= i {
+                    if at(old,pos - i) == at(new,scan - i) { s += 1; }
+                    if s * 2 - i > sb * 2 - lenb { sb = s; lenb = i; }
+                    i += 1;
+                }
+            }
+            // Resolve any overlap between the two extensions
+            if lastscan + lenf > scan - lenb {
+                let overlap = (lastscan + lenf) - (scan - lenb);
+                let (mut s, mut ss, mut lens) = (0,0,0);
+                for i in 0..overlap {
+                    if at(new,lastscan + lenf - overlap + i) == at(old,lastpos + lenf - overlap + i) { s += 1; }
+                    if at(new,scan - lenb + i) == at(old,pos - lenb + i) { s -= 1; }
+                    if s > ss { ss = s; lens = i + 1; }
+                }
+                lenf += lens - overlap;
+                lenb -= lens;
+            }
+            for i in 0..lenf {
+                patch.diff.push(at(new,lastscan + i).wrapping_sub(at(old,lastpos + i)));
+            }
+            let extra = (scan - lenb) - (lastscan + lenf);
+            patch.extra.extend_from_slice(&new[(lastscan + lenf) as usize..(scan - lenb) as usize]);
+            patch.controls.push((lenf as usize,extra as usize,(pos - lenb) - (lastpos + lenf)));
+            lastscan = scan - lenb;
+            lastpos = pos - lenb;
+            lastoffset = pos - scan;
+        }
+    }
+    patch
+}
+
+/// Construct the suffix array for a given sequence, including the
+/// empty suffix (which comes first).  This uses prefix doubling, where
+/// suffixes are repeatedly sorted by the ranks of their first `k` and
+/// next `k` elements.
+fn suffix_array(s: &[u8]) -> Vec<usize> {
+    let n = s.len();
+    let mut sa : Vec<usize> = (0..=n).collect();
+    // Rank of each suffix, where the empty suffix has rank zero
+    let mut rank : Vec<usize> = s.iter().map(|&b| b as usize + 1).chain([0]).collect();
+    let mut tmp = vec![0;n + 1];
+    let mut k = 1;
+    loop {
+        let key = |i: usize| (rank[i], if i + k <= n { rank[i + k] } else { 0 });
+        sa.sort_unstable_by_key(|&i| key(i));
+        tmp[sa[0]] = 0;
+        for w in 1..=n {
+            tmp[sa[w]] = tmp[sa[w-1]] + usize::from(key(sa[w-1]) != key(sa[w]));
+        }
+        core::mem::swap(&mut rank, &mut tmp);
+        if rank[sa[n]] == n || k > n { return sa; }
+        k *= 2;
+    }
+}
+
+/// Find the longest match for (a prefix of) `new` amongst the suffixes
+/// of `old`, returning its length and position.
+fn search(sa: &[usize], old: &[u8], new: &[u8]) -> (isize,isize) {
+    let (mut st, mut en) = (0,sa.len() - 1);
+    while en - st >= 2 {
+        let x = st + (en - st) / 2;
+        let suffix = &old[sa[x]..];
+        let k = suffix.len().min(new.len());
+        if suffix[..k] < new[..k] { st = x; } else { en = x; }
+    }
+    let x = matchlen(&old[sa[st]..], new);
+    let y = matchlen(&old[sa[en]..], new);
+    if x > y { (x as isize,sa[st] as isize) } else { (y as isize,sa[en] as isize) }
+}
+
+/// Determine the length of the common prefix of two slices.
+fn matchlen(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x,y)| x == y).count()
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod bsdiff_tests {
+    use alloc::vec::Vec;
+    use crate::diff::Diff;
+    use super::{diff_binary,suffix_array};
+
+    #[test]
+    fn bsdiff_test_01() {
+        assert_eq!(suffix_array(b"banana"),[6,5,3,1,0,4,2]);
+        assert_eq!(suffix_array(b""),[0]);
+        assert_eq!(suffix_array(b"aaaa"),[4,3,2,1,0]);
+    }
+
+    #[test]
+    fn bsdiff_test_02() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<u8>> = (0..40).map(|i| (0..i).map(|j| ((j * i + 3) % 5) as u8).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let patch = diff_binary(a,b);
+                assert_eq!(&patch.apply(a),b);
+                assert_eq!(&patch.to_delta(a).apply(a),b);
+            }
+        }
+    }
+
+    #[test]
+    fn bsdiff_test_03() {
+        // Simulate relocated code, where every 16th byte changes.
+        let old : Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+        let mut new = old.clone();
+        for i in (0..new.len()).step_by(16) { new[i] = new[i].wrapping_add(4); }
+        new.splice(1000..1000, *b"inserted");
+        let patch = diff_binary(&old,&new);
+        assert_eq!(patch.apply(&old),new);
+        // Almost all differences are zero, with little extra data
+        let nonzero = patch.diff_data().iter().filter(|&&b| b != 0).count();
+        assert!(nonzero <= 260);
+        assert!(patch.extra_data().len() <= 16);
+        // Whereas a delta needs replacement data for every change
+        assert!(old.diff(&new).len() >= 256);
+    }
+}
@@ -0,0 +1,120 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+use crate::util::Region;
+use super::{DeltaError,VecDelta};
+use super::vec_delta::check_follows;
+
+/// A `DeltaBuilder` provides a safe and fluent way of constructing a
+/// `VecDelta` by hand.  Unlike `VecDelta::try_push()`, all ranges are
+/// given in terms of the _original sequence_ and, hence, there is no
+/// need to account for the effect of earlier rewrites.  For example,
+/// the following transforms `"abcdef"` into `"aXcdfY"`:
+///
+/// ```
+/// use delta_inc::diff::DeltaBuilder;
+/// let delta = DeltaBuilder::new()
+///     .replace(1..2, &['X'])
+///     .remove(4..5)
+///     .insert(6, &['Y'])
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// Rewrites must be given in order and must not overlap, though they
+/// may be adjacent (in which case they are merged).  This is checked
+/// as each rewrite is added, and the first violation is reported by
+/// `build()`.
+#[derive(Clone,Debug)]
+pub struct DeltaBuilder<T> {
+    /// Rewrites added so far, in terms of the original sequence.
+    hunks: Vec<(Region,Vec<T>)>,
+    /// First error encountered (if any).
+    error: Option<DeltaError>
+}
+
+impl<T:Clone> DeltaBuilder<T> {
+    /// Construct an empty `DeltaBuilder`.
+    pub const fn new() -> Self { DeltaBuilder{hunks: Vec::new(), error: None} }
+
+    /// Replace the elements in a given range of the original sequence
+    /// with some data.
+    pub fn replace(mut self, range: Range<usize>, data: &[T]) -> Self {
+        let region : Region = range.into();
+        if self.error.is_some() || (region.length == 0 && data.is_empty()) {
+            return self;
+        }
+        match self.hunks.last_mut().map(|(last,items)| (check_follows(*last,region),last,items)) {
+            Some((Err(e),_,_)) => self.error = Some(e),
+            Some((Ok(()),last,items)) if last.offset + last.length == region.offset => {
+                last.length += region.length;
+                items.extend_from_slice(data);
+            }
+            _ => self.hunks.push((region,data.to_vec()))
+        }
+        self
+    }
+
+    /// Insert some data at a given position in the original sequence.
+    pub fn insert(self, at: usize, data: &[T]) -> Self {
+        self.replace(at..at, data)
+    }
+
+    /// Remove the elements in a given range of the original sequence.
+    pub fn remove(self, range: Range<usize>) -> Self {
+        self.replace(range, &[])
+    }
+
+    /// Construct the final delta, or return the first error
+    /// encountered.
+    pub fn build(self) -> Result<VecDelta<T>,DeltaError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(VecDelta::from_source(self.hunks.iter().map(|(r,d)| (*r,d.as_slice()))))
+        }
+    }
+}
+
+impl<T:Clone> Default for DeltaBuilder<T> {
+    fn default() -> Self { Self::new() }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod builder_tests {
+    use alloc::vec;
+    use crate::diff::{DeltaError,Diff};
+    use crate::util::Region;
+    use super::DeltaBuilder;
+
+    #[test]
+    fn builder_test_01() {
+        let d = DeltaBuilder::new().replace(1..2,&['X']).remove(4..5).insert(6,&['Y']).build().unwrap();
+        let before = vec!['a','b','c','d','e','f'];
+        let after = vec!['a','X','c','d','f','Y'];
+        assert_eq!(d.apply(&before),after);
+        assert_eq!(d,before.diff(&after));
+    }
+
+    #[test]
+    fn builder_test_02() {
+        // Adjacent rewrites are merged
+        let d = DeltaBuilder::new().remove(1..2).insert(2,&[7]).replace(2..3,&[8]).build().unwrap();
+        assert_eq!(d.len(),1);
+        assert_eq!(d.apply(&[0,1,2,3]),[0,7,8,3]);
+        // Empty rewrites are ignored
+        let d = DeltaBuilder::<u8>::new().remove(1..1).build().unwrap();
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn builder_test_03() {
+        // First error is reported
+        let r = DeltaBuilder::<u8>::new().remove(2..4).remove(3..5).remove(0..1).build();
+        assert_eq!(r,Err(DeltaError::Overlapping(Region::new(2,2),Region::new(3,2))));
+        let r = DeltaBuilder::new().remove(2..4).insert(1,&[0]).build();
+        assert_eq!(r,Err(DeltaError::Unordered(Region::new(2,2),Region::new(1,0))));
+    }
+}
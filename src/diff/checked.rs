@@ -0,0 +1,140 @@
+use alloc::vec::Vec;
+use core::hash::Hash;
+use crate::util::checksum_items;
+use super::{DeltaError,VecDelta};
+
+/// A `VecDelta` paired with checksums of the sequence it applies to
+/// and of the sequence it is expected to produce.  These are verified
+/// by `try_transform()` and, hence, a delta which is corrupted or
+/// applied to the wrong sequence fails, rather than silently producing
+/// garbage.  When the `serde` feature is enabled, the checksums are
+/// serialised alongside the delta.  For example:
+///
+/// ```
+/// use delta_inc::diff::{CheckedDelta,DeltaError,Diff};
+///
+/// let (before,after) = (vec![1,2,3],vec![1,4,3]);
+/// let delta = CheckedDelta::new(before.diff(&after),&before);
+/// let mut other = vec![1,2,4];
+/// assert!(matches!(delta.try_transform(&mut other),Err(DeltaError::ChecksumMismatch{..})));
+/// let mut vec = before.clone();
+/// delta.try_transform(&mut vec).unwrap();
+/// assert_eq!(vec,after);
+/// ```
+///
+/// Checksums are computed using `checksum_items()`, whose layout is
+/// fixed and forms part of the wire format.  Thus, a delta checked on
+/// one platform can be applied on another (e.g. a 64-bit host and a
+/// 32-bit or `wasm` target).  Since the checksum is not
+/// cryptographically secure, it protects against accidents rather
+/// than tampering.
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize,serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "T: serde::Serialize", deserialize = "T: Clone + serde::Deserialize<'de>")))]
+pub struct CheckedDelta<T> {
+    /// The delta being checked.
+    delta: VecDelta<T>,
+    /// Checksum of the sequence this delta applies to.
+    before: u64,
+    /// Checksum of the sequence this delta produces.
+    after: u64
+}
+
+impl<T:Clone+Hash> CheckedDelta<T> {
+    /// Construct a checked delta from a delta and the sequence it
+    /// applies to.  This operation will `panic` if the delta is
+    /// malformed with respect to `before`.
+    pub fn new(delta: VecDelta<T>, before: &[T]) -> Self {
+        let after = checksum_items(&delta.apply(before));
+        CheckedDelta{delta, before: checksum_items(before), after}
+    }
+
+    /// Get the delta being checked.
+    pub fn delta(&self) -> &VecDelta<T> { &self.delta }
+
+    /// Consume this checked delta, returning the underlying delta.
+    pub fn into_delta(self) -> VecDelta<T> { self.delta }
+
+    /// Get the checksum of the sequence this delta applies to.
+    pub fn before_checksum(&self) -> u64 { self.before }
+
+    /// Get the checksum of the sequence this delta produces.
+    pub fn after_checksum(&self) -> u64 { self.after }
+
+    /// Apply this delta to a given `Vec`, thus transforming it.  This
+    /// first checks the `Vec` matches the checksum of the sequence
+    /// this delta applies to, and that every rewrite lies within
+    /// bounds (see `VecDelta::try_transform()`).  Finally, the result
+    /// is checked against the checksum of the sequence this delta is
+    /// expected to produce.  If any check fails, an error is returned
+    /// and the given `Vec` is left unchanged.
+    pub fn try_transform(&self, vec: &mut Vec<T>) -> Result<(),DeltaError> {
+        check(self.before, vec)?;
+        let mut out = vec.clone();
+        self.delta.try_transform(&mut out)?;
+        check(self.after, &out)?;
+        *vec = out;
+        Ok(())
+    }
+}
+
+/// Check the checksum of a sequence is as expected.
+fn check<T:Hash>(expected: u64, items: &[T]) -> Result<(),DeltaError> {
+    let found = checksum_items(items);
+    if found == expected { Ok(()) } else { Err(DeltaError::ChecksumMismatch{expected, found}) }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod checked_tests {
+    use alloc::vec;
+    use crate::diff::{DeltaError,Diff,VecDelta,VecRewrite};
+    use crate::util::{Region,checksum_items};
+    use super::CheckedDelta;
+
+    #[test]
+    fn checked_test_01() {
+        let (before,after) = (vec!['a','b','c'],vec!['a','x','c','d']);
+        let delta = CheckedDelta::new(before.diff(&after),&before);
+        let mut vec = before.clone();
+        delta.try_transform(&mut vec).unwrap();
+        assert_eq!(vec,after);
+        // Applying again fails, leaving the vec unchanged
+        let r = delta.try_transform(&mut vec);
+        assert_eq!(r,Err(DeltaError::ChecksumMismatch{expected: delta.before_checksum(), found: checksum_items(&after)}));
+        assert_eq!(vec,after);
+    }
+
+    #[test]
+    fn checked_test_02() {
+        // Corrupted delta is detected after applying it
+        let before = [1,2,3];
+        let mut delta = CheckedDelta::new(before.diff(&[1,5,3]),&before);
+        delta.delta = VecDelta::try_from(vec![VecRewrite::new(Region::new(1,1),vec![6])]).unwrap();
+        let mut vec = before.to_vec();
+        assert!(matches!(delta.try_transform(&mut vec),Err(DeltaError::ChecksumMismatch{..})));
+        assert_eq!(vec,before);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn checked_test_03() {
+        let before = [1,2,3];
+        let delta = CheckedDelta::new(before.diff(&[1,5]),&before);
+        let json = serde_json::to_string(&delta).unwrap();
+        assert!(json.starts_with(r#"{"delta":[{"region":{"offset":1,"length":2},"data":[5]}],"before":"#));
+        assert_eq!(serde_json::from_str::<CheckedDelta<i32>>(&json).unwrap(),delta);
+    }
+
+    #[test]
+    fn checked_test_04() {
+        // Checksums are part of the wire format
+        let before = [1u32,2,3];
+        let delta = CheckedDelta::new(before.diff(&[1,5]),&before);
+        assert_eq!(delta.before_checksum(),0x27fb_5875_f8a7_e306);
+        assert_eq!(delta.after_checksum(),0x2731_8027_7999_1513);
+    }
+}
@@ -0,0 +1,115 @@
+use alloc::vec::Vec;
+use super::{common_prefix_suffix,myers_subsequence};
+
+/// A contiguous block of elements produced by `diff_chunks()`.
+/// Blocks which are `Equal` or `Deleted` are slices of the left-hand
+/// side, whilst those which are `Inserted` are slices of the
+/// right-hand side.
+#[derive(Debug,PartialEq,Eq)]
+pub enum Chunk<'a,T> {
+    /// Elements common to both sides.
+    Equal(&'a [T]),
+    /// Elements only present in the right-hand side.
+    Inserted(&'a [T]),
+    /// Elements only present in the left-hand side.
+    Deleted(&'a [T])
+}
+
+// Derived implementations would needlessly require `T:Clone`.
+impl<T> Clone for Chunk<'_,T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for Chunk<'_,T> {}
+
+/// Compute the difference between two slices as a sequence of
+/// chunks, which is convenient for rendering.  For example, given
+/// `lhs="abcde"` and `rhs="aXce"`, this yields:
+///
+/// ```txt
+///  Equal("a"), Deleted("b"), Inserted("X"), Equal("c"), Deleted("d"), Equal("e")
+/// ```
+///
+/// Concatenating the `Equal` and `Deleted` chunks gives `lhs`, whilst
+/// concatenating the `Equal` and `Inserted` chunks gives `rhs`.  Where
+/// both occur together, a `Deleted` chunk always precedes an
+/// `Inserted` chunk.  Adjacent chunks never have the same kind, and
+/// chunks are never empty.
+pub fn diff_chunks<'a,T:PartialEq>(lhs: &'a [T], rhs: &'a [T]) -> impl Iterator<Item=Chunk<'a,T>> {
+    let (pre,suf) = common_prefix_suffix(lhs,rhs);
+    let mapping = myers_subsequence(&lhs[pre..lhs.len()-suf],&rhs[pre..rhs.len()-suf]);
+    let mut chunks = Vec::new();
+    // Walk the mapping, where lhs[eq..i] is the current run of equal
+    // elements.
+    let (mut eq, mut i, mut j) = (0,pre,pre);
+    let matches = mapping.iter().enumerate().filter_map(|(k,m)| m.map(|m| (pre+k,pre+m)));
+    for (l,r) in matches.chain([(lhs.len()-suf,rhs.len()-suf)]) {
+        if l > i || r > j {
+            push_equal(&mut chunks, &lhs[eq..i]);
+            push_change(&mut chunks, &lhs[i..l], &rhs[j..r]);
+            eq = l;
+        }
+        i = l + 1;
+        j = r + 1;
+    }
+    push_equal(&mut chunks, &lhs[eq..]);
+    chunks.into_iter()
+}
+
+/// Append a (possibly empty) block of equal elements.
+fn push_equal<'a,T>(chunks: &mut Vec<Chunk<'a,T>>, items: &'a [T]) {
+    if !items.is_empty() { chunks.push(Chunk::Equal(items)); }
+}
+
+/// Append a (possibly empty) block of deleted elements followed by a
+/// (possibly empty) block of inserted elements.
+fn push_change<'a,T>(chunks: &mut Vec<Chunk<'a,T>>, deleted: &'a [T], inserted: &'a [T]) {
+    if !deleted.is_empty() { chunks.push(Chunk::Deleted(deleted)); }
+    if !inserted.is_empty() { chunks.push(Chunk::Inserted(inserted)); }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod chunk_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use super::{Chunk,diff_chunks};
+
+    #[test]
+    fn chunk_test_01() {
+        let chunks : Vec<_> = diff_chunks(b"abcde",b"aXce").collect();
+        assert_eq!(chunks,[Chunk::Equal(&b"a"[..]),Chunk::Deleted(b"b"),Chunk::Inserted(b"X"),
+                           Chunk::Equal(b"c"),Chunk::Deleted(b"d"),Chunk::Equal(b"e")]);
+    }
+
+    #[test]
+    fn chunk_test_02() {
+        let chunks : Vec<_> = diff_chunks(b"abc",b"abc").collect();
+        assert_eq!(chunks,[Chunk::Equal(&b"abc"[..])]);
+        let chunks : Vec<Chunk<'_,u8>> = diff_chunks(b"",b"").collect();
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn chunk_test_03() {
+        // Chunks reconstruct both sides
+        let seqs = [vec![],vec![1],vec![1,2,3],vec![3,2,1],vec![0,1,0,2,0],vec![2,2,1,1,3,3]];
+        for lhs in &seqs {
+            for rhs in &seqs {
+                let (mut l, mut r) = (Vec::new(),Vec::new());
+                for c in diff_chunks(lhs,rhs) {
+                    match c {
+                        Chunk::Equal(s) => { l.extend_from_slice(s); r.extend_from_slice(s); }
+                        Chunk::Deleted(s) => l.extend_from_slice(s),
+                        Chunk::Inserted(s) => r.extend_from_slice(s)
+                    }
+                }
+                assert_eq!(&l,lhs);
+                assert_eq!(&r,rhs);
+            }
+        }
+    }
+}
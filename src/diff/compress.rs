@@ -0,0 +1,206 @@
+use alloc::vec::Vec;
+use super::VecDelta;
+use super::vcdiff::{VcdiffError,decode_vcdiff,encode_vcdiff};
+
+/// A compression scheme which can be applied to encoded deltas.  Each
+/// scheme is identified by a distinct tag, which is recorded at the
+/// start of a compressed delta and used to select the scheme needed to
+/// decompress it.  Tags below `0x80` are reserved for the schemes
+/// provided by this crate.
+pub trait Codec {
+    /// Get the tag identifying this scheme.
+    fn tag(&self) -> u8;
+    /// Compress some bytes.
+    fn compress(&self, data: &[u8]) -> Vec<u8>;
+    /// Decompress some bytes, returning `None` if they are malformed.
+    fn decompress(&self, data: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// A scheme which leaves bytes uncompressed.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct Store;
+
+impl Codec for Store {
+    fn tag(&self) -> u8 { 0 }
+    fn compress(&self, data: &[u8]) -> Vec<u8> { data.to_vec() }
+    fn decompress(&self, data: &[u8]) -> Option<Vec<u8>> { Some(data.to_vec()) }
+}
+
+/// A scheme which compresses bytes using DEFLATE (RFC 1951), given a
+/// compression level between `0` and `10`.
+#[cfg(feature = "deflate")]
+#[derive(Clone,Copy,Debug)]
+pub struct Deflate(pub u8);
+
+#[cfg(feature = "deflate")]
+impl Default for Deflate {
+    fn default() -> Self { Deflate(6) }
+}
+
+#[cfg(feature = "deflate")]
+impl Codec for Deflate {
+    fn tag(&self) -> u8 { 1 }
+    fn compress(&self, data: &[u8]) -> Vec<u8> { miniz_oxide::deflate::compress_to_vec(data,self.0) }
+    fn decompress(&self, data: &[u8]) -> Option<Vec<u8>> { miniz_oxide::inflate::decompress_to_vec(data).ok() }
+}
+
+/// A scheme which compresses bytes using Zstandard (RFC 8878), given a
+/// compression level between `1` and `22` (or `0` for the default).
+#[cfg(feature = "zstd")]
+#[derive(Clone,Copy,Debug,Default)]
+pub struct Zstd(pub i32);
+
+#[cfg(feature = "zstd")]
+impl Codec for Zstd {
+    fn tag(&self) -> u8 { 2 }
+    fn compress(&self, data: &[u8]) -> Vec<u8> { zstd::bulk::compress(data,self.0).unwrap() }
+    fn decompress(&self, data: &[u8]) -> Option<Vec<u8>> { zstd::stream::decode_all(data).ok() }
+}
+
+/// Errors arising from decoding a compressed delta.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum CompressError {
+    /// The compressed delta is empty.
+    Truncated,
+    /// The compressed delta was produced by a scheme with the given tag
+    /// which is unknown (or whose feature is not enabled).
+    UnknownCodec(u8),
+    /// The compressed bytes could not be decompressed.
+    Corrupt,
+    /// The decompressed bytes are not a valid VCDIFF file.
+    Vcdiff(VcdiffError)
+}
+
+impl core::fmt::Display for CompressError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CompressError::Truncated => write!(f,"truncated compressed delta"),
+            CompressError::UnknownCodec(t) => write!(f,"unknown compression scheme ({t:#04x})"),
+            CompressError::Corrupt => write!(f,"compressed delta could not be decompressed"),
+            CompressError::Vcdiff(e) => write!(f,"{e}")
+        }
+    }
+}
+
+impl core::error::Error for CompressError {}
+
+/// Encode a delta over bytes as a VCDIFF file (see `encode_vcdiff()`),
+/// given the length of the source it applies to, and compress it with
+/// a given scheme.  The result begins with the tag of the scheme.  For
+/// example:
+///
+/// ```
+/// use delta_inc::diff::Diff;
+/// use delta_inc::diff::compress::{Store,compress_delta,decompress_delta};
+///
+/// let (old,new) = (b"hello world",b"hello there world");
+/// let data = compress_delta(&old.diff(new), old.len(), &Store);
+/// assert_eq!(decompress_delta(old, &data).unwrap().apply(old), new);
+/// ```
+///
+/// This will `panic` if the delta extends beyond the source.
+pub fn compress_delta<C:Codec+?Sized>(delta: &VecDelta<u8>, source_len: usize, codec: &C) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.push(codec.tag());
+    out.extend(codec.compress(&encode_vcdiff(delta,source_len)));
+    out
+}
+
+/// Decompress and decode a delta produced by `compress_delta()`, given
+/// the source it applies to.  The scheme is determined automatically
+/// from those provided by this crate (subject to enabled features).
+pub fn decompress_delta(source: &[u8], data: &[u8]) -> Result<VecDelta<u8>,CompressError> {
+    decompress_delta_with(source, data, &[])
+}
+
+/// Decompress and decode a delta produced by `compress_delta()`, given
+/// the source it applies to and some additional schemes to consider
+/// (which take precedence over those provided by this crate).
+pub fn decompress_delta_with(source: &[u8], data: &[u8], codecs: &[&dyn Codec]) -> Result<VecDelta<u8>,CompressError> {
+    let (&tag,rest) = data.split_first().ok_or(CompressError::Truncated)?;
+    let codec = codecs.iter().copied().chain(builtin().map(|c| c as &dyn Codec)).find(|c| c.tag() == tag);
+    let bytes = codec.ok_or(CompressError::UnknownCodec(tag))?.decompress(rest).ok_or(CompressError::Corrupt)?;
+    decode_vcdiff(source,&bytes).map_err(CompressError::Vcdiff)
+}
+
+/// Decompress and apply a delta produced by `compress_delta()` to the
+/// source it applies to, producing the updated bytes.
+pub fn apply_compressed(source: &[u8], data: &[u8]) -> Result<Vec<u8>,CompressError> {
+    Ok(decompress_delta(source,data)?.apply(source))
+}
+
+/// Determine the schemes provided by this crate.
+fn builtin() -> impl Iterator<Item=&'static dyn Codec> {
+    let codecs : [Option<&'static dyn Codec>;3] = [
+        Some(&Store),
+        #[cfg(feature = "deflate")] Some(&Deflate(6)),
+        #[cfg(not(feature = "deflate"))] None,
+        #[cfg(feature = "zstd")] Some(&Zstd(0)),
+        #[cfg(not(feature = "zstd"))] None
+    ];
+    codecs.into_iter().flatten()
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod compress_tests {
+    use alloc::vec::Vec;
+    use crate::diff::Diff;
+    use super::{Codec,CompressError,Store,apply_compressed,compress_delta,decompress_delta,decompress_delta_with};
+
+    /// A (toy) scheme which inverts every byte.
+    struct Invert;
+
+    impl Codec for Invert {
+        fn tag(&self) -> u8 { 0x80 }
+        fn compress(&self, data: &[u8]) -> Vec<u8> { data.iter().map(|b| !b).collect() }
+        fn decompress(&self, data: &[u8]) -> Option<Vec<u8>> { Some(self.compress(data)) }
+    }
+
+    fn check<C:Codec>(codec: &C) {
+        let seqs : Vec<Vec<u8>> = (0..10).map(|i| (0..i*5).map(|j| ((j*i+3)%5) as u8).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let data = compress_delta(&a.diff(b),a.len(),codec);
+                assert_eq!(data[0],codec.tag());
+                assert_eq!(decompress_delta_with(a,&data,&[&Invert]).unwrap().apply(a),*b);
+            }
+        }
+    }
+
+    #[test]
+    fn compress_test_01() {
+        check(&Store);
+        check(&Invert);
+        // Custom scheme must be given explicitly
+        let data = compress_delta(&[1,2].diff(&[3]),2,&Invert);
+        assert_eq!(decompress_delta(&[1,2],&data),Err(CompressError::UnknownCodec(0x80)));
+        assert_eq!(decompress_delta(&[1,2],&[]),Err(CompressError::Truncated));
+        assert!(matches!(apply_compressed(&[1,2],&[0,1,2]),Err(CompressError::Vcdiff(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "deflate")]
+    fn compress_test_02() {
+        check(&super::Deflate(1));
+        check(&super::Deflate::default());
+        let old = "line\n".repeat(100);
+        let new = "line\nanother line\n".repeat(100);
+        let delta = old.as_bytes().diff(new.as_bytes());
+        let data = compress_delta(&delta,old.len(),&super::Deflate::default());
+        assert!(data.len() < compress_delta(&delta,old.len(),&Store).len() / 4);
+        assert_eq!(apply_compressed(old.as_bytes(),&data).unwrap(),new.as_bytes());
+        assert_eq!(decompress_delta(old.as_bytes(),&[1,0xFF]),Err(CompressError::Corrupt));
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn compress_test_03() {
+        check(&super::Zstd(3));
+        check(&super::Zstd::default());
+        assert_eq!(decompress_delta(&[],&[2,0xFF]),Err(CompressError::Corrupt));
+    }
+}
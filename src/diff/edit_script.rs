@@ -0,0 +1,177 @@
+use alloc::vec::Vec;
+use crate::util::Region;
+use super::VecDelta;
+
+/// An individual edit within an `EditScript`.
+#[derive(Debug,PartialEq,Eq)]
+pub enum Edit<'a,T> {
+    /// Retain the next `n` elements of the original sequence.
+    Keep(usize),
+    /// Insert the given elements at the current position.
+    Insert(&'a [T]),
+    /// Remove the next `n` elements of the original sequence.
+    Delete(usize)
+}
+
+// Derived implementations would needlessly require `T:Clone`.
+impl<T> Clone for Edit<'_,T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for Edit<'_,T> {}
+
+/// An `EditScript` is the classic representation of a delta as a
+/// sequence of edits which are applied by walking the original
+/// sequence from left to right.  For example, transforming `"abcde"`
+/// into `"aXce"` is described by the script:
+///
+/// ```txt
+///  Keep(1), Delete(1), Insert("X"), Keep(1), Delete(1)
+/// ```
+///
+/// An `EditScript` borrows its inserted data and, hence, is typically
+/// obtained from an existing `VecDelta`.  Conversions in both
+/// directions are lossless, in the sense that converting a `VecDelta`
+/// into an `EditScript` and back again gives the original delta.
+/// Trailing elements which are kept need not be mentioned, since the
+/// script does not record the length of the original sequence.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct EditScript<'a,T> {
+    edits: Vec<Edit<'a,T>>
+}
+
+impl<'a,T> EditScript<'a,T> {
+    /// Construct an empty `EditScript`.
+    pub const fn new() -> Self { EditScript{edits: Vec::new()} }
+
+    /// Get the number of edits in this script.
+    pub fn len(&self) -> usize { self.edits.len() }
+
+    /// Check whether this script contains any edits or not.
+    pub fn is_empty(&self) -> bool { self.edits.is_empty() }
+
+    /// Get the `ith` edit in this script.
+    pub fn get(&self, ith: usize) -> Option<&Edit<'a,T>> { self.edits.get(ith) }
+
+    /// Iterate the edits in this script.
+    pub fn iter(&self) -> core::slice::Iter<'_,Edit<'a,T>> { self.edits.iter() }
+
+    /// Append an edit onto the end of this script.  Empty edits are
+    /// dropped, and consecutive `Keep` (resp. `Delete`) edits are
+    /// combined.
+    pub fn push(&mut self, edit: Edit<'a,T>) {
+        match (self.edits.last_mut(),&edit) {
+            (_,Edit::Keep(0)|Edit::Delete(0)) => {}
+            (_,Edit::Insert([])) => {}
+            (Some(Edit::Keep(n)),Edit::Keep(m)) => { *n += *m; }
+            (Some(Edit::Delete(n)),Edit::Delete(m)) => { *n += *m; }
+            _ => { self.edits.push(edit); }
+        }
+    }
+}
+
+impl<T> Default for EditScript<'_,T> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<'a,T> From<&'a VecDelta<T>> for EditScript<'a,T> {
+    fn from(delta: &'a VecDelta<T>) -> Self {
+        let mut script = EditScript::new();
+        let mut pos = 0;
+        for (r,data) in delta.iter_source() {
+            script.push(Edit::Keep(r.offset - pos));
+            script.push(Edit::Delete(r.length));
+            script.push(Edit::Insert(data));
+            pos = r.offset + r.length;
+        }
+        script
+    }
+}
+
+impl<T:Clone> From<&EditScript<'_,T>> for VecDelta<T> {
+    fn from(script: &EditScript<'_,T>) -> Self {
+        // Collect each maximal run of deletions and insertions into a
+        // single hunk (in source coordinates).
+        let mut hunks : Vec<(Region,Vec<T>)> = Vec::new();
+        let mut pos = 0;
+        let mut open = false;
+        for edit in script.iter() {
+            match edit {
+                Edit::Keep(n) => {
+                    pos += n;
+                    open = false;
+                }
+                Edit::Delete(_) | Edit::Insert(_) if !open => {
+                    hunks.push((Region::new(pos,0),Vec::new()));
+                    open = true;
+                }
+                _ => {}
+            }
+            match (edit,hunks.last_mut()) {
+                (Edit::Delete(n),Some((r,_))) => {
+                    r.length += n;
+                    pos += n;
+                }
+                (Edit::Insert(data),Some((_,d))) => d.extend_from_slice(data),
+                _ => {}
+            }
+        }
+        VecDelta::from_source(hunks.iter().map(|(r,d)| (*r,d.as_slice())))
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod edit_script_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,VecDelta};
+    use super::{Edit,EditScript};
+
+    #[test]
+    fn edit_script_test_01() {
+        let before = "abcde".as_bytes();
+        let after = "aXce".as_bytes();
+        let delta = before.diff(after);
+        let script = EditScript::from(&delta);
+        assert_eq!(script.iter().copied().collect::<Vec<_>>(),
+                   vec![Edit::Keep(1),Edit::Delete(1),Edit::Insert(&b"X"[..]),Edit::Keep(1),Edit::Delete(1)]);
+        assert_eq!(VecDelta::from(&script),delta);
+    }
+
+    #[test]
+    fn edit_script_test_02() {
+        // Round trip for arbitrary pairs
+        let seqs = [vec![],vec![1],vec![1,2,3],vec![3,2,1],vec![0,1,0,2,0],vec![2,2,1,1,3,3]];
+        for lhs in &seqs {
+            for rhs in &seqs {
+                let delta = lhs.diff(rhs);
+                let script = EditScript::from(&delta);
+                assert_eq!(VecDelta::from(&script),delta);
+                let mut vec = lhs.clone();
+                VecDelta::from(&script).transform(&mut vec);
+                assert_eq!(&vec,rhs);
+            }
+        }
+    }
+
+    #[test]
+    fn edit_script_test_03() {
+        // Edits are normalised when pushed
+        let mut script = EditScript::new();
+        script.push(Edit::Keep(1));
+        script.push(Edit::Keep(2));
+        script.push(Edit::Insert(&[1]));
+        script.push(Edit::Insert(&[2]));
+        script.push(Edit::Delete(0));
+        script.push(Edit::Delete(1));
+        script.push(Edit::Delete(1));
+        assert_eq!(script.len(),4);
+        let mut vec = vec![0,0,0,7,8,9];
+        VecDelta::from(&script).transform(&mut vec);
+        assert_eq!(vec,[0,0,0,1,2,9]);
+    }
+}
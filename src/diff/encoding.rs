@@ -0,0 +1,198 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+use crate::util::Region;
+use super::{DeltaError,VecDelta,check_follows};
+
+/// The unit in which offsets into a string are measured.  A
+/// `VecDelta<char>` measures offsets in `char`s (i.e. Unicode scalar
+/// values), whilst other tools commonly use UTF-8 bytes or UTF-16 code
+/// units (e.g. JavaScript strings and the Language Server Protocol).
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum OffsetEncoding {
+    /// Offsets are measured in UTF-8 bytes.
+    Utf8,
+    /// Offsets are measured in UTF-16 code units.
+    Utf16,
+    /// Offsets are measured in `char`s.
+    Utf32
+}
+
+impl OffsetEncoding {
+    /// Determine the length of a given `char` in this encoding.
+    pub fn len_of(self, c: char) -> usize {
+        match self {
+            OffsetEncoding::Utf8 => c.len_utf8(),
+            OffsetEncoding::Utf16 => c.len_utf16(),
+            OffsetEncoding::Utf32 => 1
+        }
+    }
+
+    /// Determine the length of a given string in this encoding.
+    pub fn len_of_str(self, text: &str) -> usize {
+        match self {
+            OffsetEncoding::Utf8 => text.len(),
+            _ => text.chars().map(|c| self.len_of(c)).sum()
+        }
+    }
+}
+
+impl VecDelta<char> {
+    /// Translate this delta into a sequence of edits whose ranges are
+    /// given in terms of a given encoding.  As for the edits of a Language
+    /// Server Protocol `TextEdit[]`, each range refers to the original
+    /// text (rather than the text produced by preceding edits).  For
+    /// example:
+    ///
+    /// ```
+    /// use delta_inc::diff::{Diff,OffsetEncoding};
+    ///
+    /// let (before,after) : (Vec<char>,Vec<char>) = ("😀 a".chars().collect(),"😀 b".chars().collect());
+    /// let edits = before.diff(&after).to_encoded("😀 a", OffsetEncoding::Utf16);
+    /// assert_eq!(edits, [(3..4, "b".to_string())]);
+    /// ```
+    ///
+    /// This operation will `panic` if this delta is malformed with
+    /// respect to `source`.
+    pub fn to_encoded(&self, source: &str, encoding: OffsetEncoding) -> Vec<(Range<usize>,String)> {
+        let mut cursor = Cursor::new(source, encoding);
+        let mut edits = Vec::new();
+        for (r,data) in self.iter_source() {
+            let start = cursor.encode(r.offset).expect("delta out of bounds");
+            let end = cursor.encode(r.offset + r.length).expect("delta out of bounds");
+            edits.push((start..end,data.iter().collect()));
+        }
+        edits
+    }
+
+    /// Construct a delta from a sequence of edits whose ranges are given
+    /// in terms of a given encoding, and refer to the original text (see
+    /// `to_encoded()`).  The edits must be sorted and disjoint, and each
+    /// range must lie within the text and fall on `char` boundaries (e.g.
+    /// not in the middle of a UTF-16 surrogate pair).  For example:
+    ///
+    /// ```
+    /// use delta_inc::diff::{OffsetEncoding,VecDelta};
+    ///
+    /// let delta = VecDelta::from_encoded("😀 a", &[(0..2, "🙂")], OffsetEncoding::Utf16).unwrap();
+    /// let after : String = delta.apply(&"😀 a".chars().collect::<Vec<_>>()).into_iter().collect();
+    /// assert_eq!(after, "🙂 a");
+    /// ```
+    pub fn from_encoded<S:AsRef<str>>(source: &str, edits: &[(Range<usize>,S)], encoding: OffsetEncoding) -> Result<Self,DeltaError> {
+        let mut cursor = Cursor::new(source, encoding);
+        let mut last : Option<Region> = None;
+        let mut rewrites = Vec::new();
+        for (range,text) in edits {
+            let region = Region::new(range.start, range.end.saturating_sub(range.start));
+            if let Some(l) = last { check_follows(l,region)?; }
+            let bounds = || DeltaError::RegionOutOfBounds{region, len: encoding.len_of_str(source)};
+            let mut ends = [range.start,range.end];
+            for e in &mut ends {
+                let offset = *e;
+                *e = cursor.decode(offset).ok_or_else(bounds)?;
+                if cursor.offset != offset { return Err(DeltaError::NotCharBoundary(offset)); }
+            }
+            let [start,end] = ends;
+            if start > end { return Err(bounds()); }
+            rewrites.push((Region::new(start,end-start),text.as_ref().chars().collect::<Vec<_>>()));
+            last = Some(region);
+        }
+        Ok(VecDelta::from_source(rewrites.iter().map(|(r,d)| (*r,&d[..]))))
+    }
+}
+
+/// Walks forwards through a string, translating between `char`
+/// offsets and offsets in a given encoding.  Offsets must be visited
+/// in ascending order.
+struct Cursor<'a> {
+    chars: core::str::Chars<'a>,
+    encoding: OffsetEncoding,
+    /// Current offset in `char`s.
+    index: usize,
+    /// Current offset in the encoding.
+    offset: usize
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str, encoding: OffsetEncoding) -> Self {
+        Cursor{chars: text.chars(), encoding, index: 0, offset: 0}
+    }
+
+    /// Translate a `char` offset into the encoding, returning `None`
+    /// if it is beyond the end of the string.
+    fn encode(&mut self, index: usize) -> Option<usize> {
+        while self.index < index {
+            self.offset += self.encoding.len_of(self.chars.next()?);
+            self.index += 1;
+        }
+        Some(self.offset)
+    }
+
+    /// Translate an offset in the encoding into a `char` offset,
+    /// returning `None` if it is beyond the end of the string.  If the
+    /// offset does not fall on a `char` boundary, this stops at the
+    /// following boundary.
+    fn decode(&mut self, offset: usize) -> Option<usize> {
+        while self.offset < offset {
+            self.offset += self.encoding.len_of(self.chars.next()?);
+            self.index += 1;
+        }
+        Some(self.index)
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod encoding_tests {
+    use alloc::string::{String,ToString};
+    use alloc::vec::Vec;
+    use crate::diff::{DeltaError,Diff,VecDelta};
+    use crate::util::Region;
+    use super::OffsetEncoding;
+
+    const ENCODINGS : [OffsetEncoding;3] = [OffsetEncoding::Utf8,OffsetEncoding::Utf16,OffsetEncoding::Utf32];
+
+    #[test]
+    fn encoding_test_01() {
+        let texts = ["","a","héllo","😀😀","a😀b€c","x😀","€€€€"];
+        for a in texts {
+            for b in texts {
+                let (ac,bc) : (Vec<char>,Vec<char>) = (a.chars().collect(),b.chars().collect());
+                let delta = ac.diff(&bc);
+                for e in ENCODINGS {
+                    let edits = delta.to_encoded(a,e);
+                    assert_eq!(VecDelta::from_encoded(a,&edits,e).unwrap(),delta);
+                    // Applying edits in reverse produces target
+                    let mut s : Vec<u16> = a.encode_utf16().collect();
+                    if e == OffsetEncoding::Utf16 {
+                        for (r,t) in edits.iter().rev() { s.splice(r.clone(),t.encode_utf16()); }
+                        assert_eq!(String::from_utf16(&s).unwrap(),b);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn encoding_test_02() {
+        let text = "a😀b";
+        let e = OffsetEncoding::Utf16;
+        assert_eq!(e.len_of_str(text),4);
+        // Middle of surrogate pair
+        let r = VecDelta::from_encoded(text,&[(2..3,"x")],e);
+        assert_eq!(r,Err(DeltaError::NotCharBoundary(2)));
+        // Beyond end
+        let r = VecDelta::from_encoded(text,&[(4..5,"x")],e);
+        assert_eq!(r,Err(DeltaError::RegionOutOfBounds{region: Region::new(4,1), len: 4}));
+        // Unordered
+        let r = VecDelta::from_encoded(text,&[(3..4,"x"),(0..1,"y")],e);
+        assert_eq!(r,Err(DeltaError::Unordered(Region::new(3,1),Region::new(0,1))));
+        // Valid
+        let delta = VecDelta::from_encoded(text,&[(0..1,"y".to_string()),(3..4,"x".to_string())],e).unwrap();
+        let after : String = delta.apply(&text.chars().collect::<Vec<_>>()).into_iter().collect();
+        assert_eq!(after,"y😀x");
+    }
+}
@@ -0,0 +1,168 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Result,Write};
+use crate::util::Region;
+use crate::diff::{Diff,VecDelta};
+use super::unified::Range;
+
+// Escape sequences used for colouring
+const RED : &str = "\x1b[31m";
+const GREEN : &str = "\x1b[32m";
+const CYAN : &str = "\x1b[36m";
+const RESET : &str = "\x1b[0m";
+const INVERSE : &str = "\x1b[7m";
+const NO_INVERSE : &str = "\x1b[27m";
+
+/// Options controlling how a line-based delta is rendered for a
+/// terminal using ANSI escape sequences.  Hunks are laid out as for
+/// `UnifiedFormat`, except that deleted lines are coloured red,
+/// inserted lines green and hunk headers cyan.  Line terminators are
+/// not shown.  For example:
+///
+/// ```
+/// use delta_inc::diff::Diff;
+/// use delta_inc::diff::format::ansi::AnsiFormat;
+///
+/// let before = ["a\n", "b\n"];
+/// let after = ["a\n", "c\n"];
+/// let text = AnsiFormat::new().context(0).render(&before, &before.diff(&after));
+/// assert_eq!(text, "\x1b[36m@@ -2 +2 @@\x1b[0m\n\x1b[31m-b\x1b[0m\n\x1b[32m+c\x1b[0m\n");
+/// ```
+///
+/// Optionally, when lines are replaced, the characters which differ
+/// within each line can also be highlighted (by inverting their
+/// colours).  In this case, the `i`th deleted line of a rewrite is
+/// compared against its `i`th inserted line.
+#[derive(Clone,Copy,Debug)]
+pub struct AnsiFormat {
+    /// Number of unchanged lines shown around each change.
+    context: usize,
+    /// Whether or not to highlight changes within lines.
+    intraline: bool
+}
+
+impl AnsiFormat {
+    /// Construct the default format, which shows three lines of context
+    /// and does not highlight changes within lines.
+    pub const fn new() -> Self { AnsiFormat{context: 3, intraline: false} }
+
+    /// Set the number of unchanged lines shown around each change.
+    pub fn context(mut self, lines: usize) -> Self {
+        self.context = lines;
+        self
+    }
+
+    /// Set whether or not changes within replaced lines are
+    /// highlighted.
+    pub fn intraline(mut self, enable: bool) -> Self {
+        self.intraline = enable;
+        self
+    }
+
+    /// Render a delta against the `before` lines it applies to.  This
+    /// operation will `panic` if the delta is malformed with respect to
+    /// `before`.
+    pub fn render<T:AsRef<str>>(&self, before: &[T], delta: &VecDelta<T>) -> String {
+        let mut out = String::new();
+        self.write(&mut out, before, delta).unwrap();
+        out
+    }
+
+    /// Render a delta against the `before` lines it applies to, writing
+    /// the result to a given output (see `render()`).
+    pub fn write<T:AsRef<str>,W:Write>(&self, out: &mut W, before: &[T], delta: &VecDelta<T>) -> Result {
+        for h in delta.hunks(before.len(),self.context) {
+            let (from,to) = (h.before(),h.after());
+            let (f,t) = (Range(from.offset,from.length),Range(to.offset,to.length));
+            writeln!(out,"{CYAN}@@ -{f} +{t} @@{RESET}")?;
+            let mut cursor = from.offset;
+            for (r,data) in h.rewrites() {
+                for line in &before[cursor..r.offset] { write_line(out,"",' ',line.as_ref(),&[])?; }
+                self.write_rewrite(out,&before[r.as_range()],data)?;
+                cursor = end(r);
+            }
+            for line in &before[cursor..end(&from)] { write_line(out,"",' ',line.as_ref(),&[])?; }
+        }
+        Ok(())
+    }
+
+    /// Write the deleted and inserted lines of a single rewrite.
+    fn write_rewrite<T:AsRef<str>,W:Write>(&self, out: &mut W, deleted: &[T], inserted: &[T]) -> Result {
+        // Determine the changed characters of each paired line
+        let mut spans : Vec<(Vec<Region>,Vec<Region>)> = Vec::new();
+        if self.intraline {
+            for (l,r) in deleted.iter().zip(inserted) {
+                let d = l.as_ref().diff(r.as_ref());
+                let old = d.iter_source().map(|(r,_)| r).collect();
+                let new = d.iter().map(|(r,t)| Region::new(r.offset,t.len())).collect();
+                spans.push((old,new));
+            }
+        }
+        for (i,line) in deleted.iter().enumerate() {
+            let s = spans.get(i).map_or(&[][..],|s| &s.0);
+            write_line(out,RED,'-',line.as_ref(),s)?;
+        }
+        for (i,line) in inserted.iter().enumerate() {
+            let s = spans.get(i).map_or(&[][..],|s| &s.1);
+            write_line(out,GREEN,'+',line.as_ref(),s)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for AnsiFormat {
+    fn default() -> Self { Self::new() }
+}
+
+/// Determine the end of a given region.
+fn end(r: &Region) -> usize { r.offset + r.length }
+
+/// Write a single line in a given colour (if any), where the given
+/// regions of bytes are highlighted.  The line terminator is omitted.
+fn write_line<W:Write>(out: &mut W, colour: &str, sign: char, line: &str, spans: &[Region]) -> Result {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    write!(out,"{colour}{sign}")?;
+    let mut cursor = 0;
+    for r in spans {
+        let (start,finish) = (r.offset.min(line.len()),end(r).min(line.len()));
+        if start == finish { continue; }
+        write!(out,"{}{INVERSE}{}{NO_INVERSE}",&line[cursor..start],&line[start..finish])?;
+        cursor = finish;
+    }
+    out.write_str(&line[cursor..])?;
+    if !colour.is_empty() { out.write_str(RESET)?; }
+    out.write_char('\n')
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod ansi_tests {
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,VecDelta};
+    use super::AnsiFormat;
+
+    fn lines(text: &str) -> Vec<&str> { text.split_inclusive('\n').collect() }
+
+    #[test]
+    fn ansi_test_01() {
+        let before = lines("a\nb\nc\nd\n");
+        let after = lines("a\nc\nd\ne\n");
+        let text = AnsiFormat::new().context(1).render(&before,&before.diff(&after));
+        assert_eq!(text,"\x1b[36m@@ -1,4 +1,4 @@\x1b[0m\n a\n\x1b[31m-b\x1b[0m\n c\n d\n\x1b[32m+e\x1b[0m\n");
+        // Nothing to render
+        assert_eq!(AnsiFormat::new().render(&before,&VecDelta::new()),"");
+    }
+
+    #[test]
+    fn ansi_test_02() {
+        // Intra-line highlights
+        let before = lines("let x = 1;\nfoo\n");
+        let after = lines("let y = 1;\n");
+        let text = AnsiFormat::new().intraline(true).render(&before,&before.diff(&after));
+        assert_eq!(text,"\x1b[36m@@ -1,2 +1 @@\x1b[0m\n\x1b[31m-let \x1b[7mx\x1b[27m = 1;\x1b[0m\n\
+                         \x1b[31m-foo\x1b[0m\n\x1b[32m+let \x1b[7my\x1b[27m = 1;\x1b[0m\n");
+    }
+}
@@ -0,0 +1,218 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Result,Write};
+use crate::util::Region;
+use crate::diff::Chunk;
+
+/// Options controlling how a line-based diff is rendered as a classic
+/// _context diff_, such as produced by `diff -c`.  This is driven from
+/// the chunks produced by `diff_chunks()` and, as for `UnifiedFormat`,
+/// each line is expected to include its terminator.  For example:
+///
+/// ```
+/// use delta_inc::diff::diff_chunks;
+/// use delta_inc::diff::format::context::ContextFormat;
+///
+/// let before = ["a\n", "b\n", "c\n"];
+/// let after = ["a\n", "x\n", "c\n"];
+/// let text = ContextFormat::new().context(1).render(diff_chunks(&before, &after));
+/// assert_eq!(text, "***************\n*** 1,3 ****\n  a\n! b\n  c\n--- 1,3 ----\n  a\n! x\n  c\n");
+/// ```
+///
+/// Within each hunk, lines which are replaced are marked with `!`,
+/// whilst lines which are only deleted (resp. inserted) are marked
+/// with `-` (resp. `+`).  A side of a hunk without any marked lines is
+/// omitted, leaving just its range.
+#[derive(Clone,Copy,Debug)]
+pub struct ContextFormat<'a> {
+    /// Number of unchanged lines shown around each change.
+    context: usize,
+    /// File names shown in the `***` and `---` header lines (if any).
+    header: Option<(&'a str,&'a str)>
+}
+
+impl<'a> ContextFormat<'a> {
+    /// Construct the default format, which shows three lines of context
+    /// and no header.
+    pub const fn new() -> Self { ContextFormat{context: 3, header: None} }
+
+    /// Set the number of unchanged lines shown around each change.
+    pub fn context(mut self, lines: usize) -> Self {
+        self.context = lines;
+        self
+    }
+
+    /// Include a header naming the original and updated files.
+    pub fn header(mut self, from: &'a str, to: &'a str) -> Self {
+        self.header = Some((from,to));
+        self
+    }
+
+    /// Render a sequence of chunks.  If there are no changes then
+    /// nothing is rendered (not even the header).
+    pub fn render<'c,T,I>(&self, chunks: I) -> String
+    where T:AsRef<str>+'c, I:IntoIterator<Item=Chunk<'c,T>> {
+        let mut out = String::new();
+        self.write(&mut out, chunks).unwrap();
+        out
+    }
+
+    /// Render a sequence of chunks, writing the result to a given
+    /// output (see `render()`).
+    pub fn write<'c,T,I,W>(&self, out: &mut W, chunks: I) -> Result
+    where T:AsRef<str>+'c, I:IntoIterator<Item=Chunk<'c,T>>, W:Write {
+        let (old,new,changes) = split(chunks);
+        if changes.is_empty() { return Ok(()); }
+        if let Some((from,to)) = self.header {
+            writeln!(out,"*** {from}")?;
+            writeln!(out,"--- {to}")?;
+        }
+        let mut i = 0;
+        while i < changes.len() {
+            // Determine the changes making up this hunk
+            let mut j = i + 1;
+            while j < changes.len() && changes[j].0.offset - end(&changes[j-1].0) <= 2 * self.context {
+                j += 1;
+            }
+            let hunk = &changes[i..j];
+            writeln!(out,"***************")?;
+            writeln!(out,"*** {} ****",Range(self.around(hunk,|c| c.0,old.len())))?;
+            self.write_side(out,&old,hunk,|c| (c.0,c.1),'-')?;
+            writeln!(out,"--- {} ----",Range(self.around(hunk,|c| c.1,new.len())))?;
+            self.write_side(out,&new,hunk,|c| (c.1,c.0),'+')?;
+            i = j;
+        }
+        Ok(())
+    }
+
+    /// Determine the region of one side covered by a hunk, including
+    /// its context.
+    fn around<F:Fn(&(Region,Region))->Region>(&self, hunk: &[(Region,Region)], side: F, len: usize) -> Region {
+        let start = side(&hunk[0]).offset.saturating_sub(self.context);
+        let finish = (end(&side(&hunk[hunk.len()-1])) + self.context).min(len);
+        Region::new(start,finish - start)
+    }
+
+    /// Write the lines of one side of a hunk, where `side` selects the
+    /// region of this side (and the other side) covered by a change.
+    /// Nothing is written if this side is unchanged throughout.
+    fn write_side<T,F,W>(&self, out: &mut W, lines: &[&T], hunk: &[(Region,Region)], side: F, mark: char) -> Result
+    where T:AsRef<str>+?Sized, F:Fn(&(Region,Region))->(Region,Region), W:Write {
+        if hunk.iter().all(|c| side(c).0.length == 0) { return Ok(()); }
+        let region = self.around(hunk,|c| side(c).0,lines.len());
+        let mut cursor = region.offset;
+        for c in hunk {
+            let (this,other) = side(c);
+            let mark = if other.length == 0 { mark } else { '!' };
+            write_lines(out,' ',&lines[cursor..this.offset])?;
+            write_lines(out,mark,&lines[this.as_range()])?;
+            cursor = end(&this);
+        }
+        write_lines(out,' ',&lines[cursor..end(&region)])
+    }
+}
+
+impl Default for ContextFormat<'_> {
+    fn default() -> Self { Self::new() }
+}
+
+/// Split a sequence of chunks into the lines of each side, along with
+/// the region of each side covered by every change.
+pub(super) fn split<'c,T:'c,I>(chunks: I) -> (Vec<&'c T>,Vec<&'c T>,Vec<(Region,Region)>)
+where I:IntoIterator<Item=Chunk<'c,T>> {
+    let (mut old, mut new, mut changes) = (Vec::new(),Vec::new(),Vec::new());
+    let mut open = false;
+    for chunk in chunks {
+        if !open && !matches!(chunk,Chunk::Equal(_)) {
+            changes.push((Region::new(old.len(),0),Region::new(new.len(),0)));
+        }
+        open = !matches!(chunk,Chunk::Equal(_));
+        match chunk {
+            Chunk::Equal(items) => {
+                old.extend(items);
+                new.extend(items);
+            }
+            Chunk::Deleted(items) => {
+                old.extend(items);
+                changes.last_mut().unwrap().0.length += items.len();
+            }
+            Chunk::Inserted(items) => {
+                new.extend(items);
+                changes.last_mut().unwrap().1.length += items.len();
+            }
+        }
+    }
+    (old,new,changes)
+}
+
+/// Determine the end of a given region.
+fn end(r: &Region) -> usize { r.offset + r.length }
+
+/// A range of lines as shown in a hunk header.  Following `diff`,
+/// lines are numbered from one and given as the first and last line
+/// (inclusive), where an empty range is identified by the line before
+/// it.
+struct Range(Region);
+
+impl core::fmt::Display for Range {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result {
+        match self.0.length {
+            0 => write!(f,"{}",self.0.offset),
+            1 => write!(f,"{}",self.0.offset + 1),
+            n => write!(f,"{},{}",self.0.offset + 1,self.0.offset + n)
+        }
+    }
+}
+
+/// Write a block of lines, each with a given mark.
+fn write_lines<T:AsRef<str>+?Sized,W:Write>(out: &mut W, mark: char, lines: &[&T]) -> Result {
+    for line in lines {
+        let line = line.as_ref();
+        write!(out,"{mark} {line}")?;
+        if !line.ends_with('\n') {
+            out.write_str("\n\\ No newline at end of file\n")?;
+        }
+    }
+    Ok(())
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod context_tests {
+    use alloc::vec::Vec;
+    use crate::diff::diff_chunks;
+    use super::ContextFormat;
+
+    fn lines(text: &str) -> Vec<&str> { text.split_inclusive('\n').collect() }
+
+    #[test]
+    fn context_test_01() {
+        let before = lines("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n");
+        let after = lines("a\nB\nc\nd\ne\nf\ng\nh\nj\nk\n");
+        let text = ContextFormat::new().context(1).header("a/x","b/x").render(diff_chunks(&before,&after));
+        assert_eq!(text,"*** a/x\n--- b/x\n***************\n*** 1,3 ****\n  a\n! b\n  c\n--- 1,3 ----\n  a\n! B\n  c\n\
+                         ***************\n*** 8,10 ****\n  h\n- i\n  j\n--- 8,10 ----\n  h\n  j\n+ k\n");
+        // More context merges hunks
+        let text = ContextFormat::new().render(diff_chunks(&before,&after));
+        assert!(text.starts_with("***************\n*** 1,10 ****\n"));
+        assert_eq!(text.matches("***************").count(),1);
+    }
+
+    #[test]
+    fn context_test_02() {
+        // Unchanged sides are omitted
+        let after = lines("a\nb\n");
+        let text = ContextFormat::new().render(diff_chunks(&[],&after));
+        assert_eq!(text,"***************\n*** 0 ****\n--- 1,2 ----\n+ a\n+ b\n");
+        let text = ContextFormat::new().render(diff_chunks(&after,&after[..1]));
+        assert_eq!(text,"***************\n*** 1,2 ****\n  a\n- b\n--- 1 ----\n");
+        // Missing newline at end of file
+        let text = ContextFormat::new().render(diff_chunks(&["a"],&["a\n"]));
+        assert_eq!(text,"***************\n*** 1 ****\n! a\n\\ No newline at end of file\n--- 1 ----\n! a\n");
+        // Nothing to render
+        assert_eq!(ContextFormat::new().header("a","b").render(diff_chunks(&after,&after)),"");
+    }
+}
@@ -0,0 +1,371 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Result,Write};
+use crate::util::Region;
+use crate::diff::{StrDelta,VecDelta};
+use super::unified::{Range,parse_header};
+
+/// Maximum length of a pattern which is extended to be unique when
+/// adding context (i.e. `Match_MaxBits` in diff-match-patch).
+const MATCH_MAX_BITS : usize = 32;
+
+/// Options controlling how a string delta is rendered in the _patch_
+/// text format of diff-match-patch (i.e. as produced by
+/// `patch_toText()`), which is understood by its implementations in
+/// many languages.  For example:
+///
+/// ```
+/// use delta_inc::diff::Diff;
+/// use delta_inc::diff::format::dmp::DmpFormat;
+///
+/// let text = DmpFormat::new().render("abcdefgh", &"abcdefgh".diff("abcXefgh"));
+/// assert_eq!(text, "@@ -1,8 +1,8 @@\n abc\n-d\n+X\n efgh\n");
+/// ```
+///
+/// Unlike a unified diff, a patch describes changes to characters
+/// rather than lines, with the text of each change percent-encoded
+/// (as for `encodeURI()`).  Positions are given as `char` offsets,
+/// which agree with implementations counting UTF-16 code units for
+/// text within the Basic Multilingual Plane.  Following
+/// diff-match-patch, each hunk is positioned in terms of the text with
+/// all earlier hunks applied, and its context is extended until it
+/// occurs only once in that text.
+#[derive(Clone,Copy,Debug)]
+pub struct DmpFormat {
+    /// Number of unchanged characters shown around each change (i.e.
+    /// `Patch_Margin` in diff-match-patch).
+    margin: usize
+}
+
+impl DmpFormat {
+    /// Construct the default format, which uses a margin of four
+    /// characters.
+    pub const fn new() -> Self { DmpFormat{margin: 4} }
+
+    /// Set the number of unchanged characters shown around each
+    /// change.  Changes separated by no more than twice this are
+    /// combined into the same hunk.
+    pub fn margin(mut self, chars: usize) -> Self {
+        self.margin = chars;
+        self
+    }
+
+    /// Render a delta against the string it applies to.  This
+    /// operation will `panic` if the delta is malformed with respect
+    /// to `before`.
+    pub fn render(&self, before: &str, delta: &StrDelta) -> String {
+        let mut out = String::new();
+        self.write(&mut out, before, delta).unwrap();
+        out
+    }
+
+    /// Render a delta against the string it applies to, writing the
+    /// result to a given output (see `render()`).
+    pub fn write<W:Write>(&self, out: &mut W, before: &str, delta: &StrDelta) -> Result {
+        let chars : Vec<char> = before.chars().collect();
+        let after : Vec<char> = delta.apply(before).chars().collect();
+        // Convert byte offsets into char offsets
+        let offsets : Vec<usize> = before.char_indices().map(|(i,_)| i).chain([before.len()]).collect();
+        let index = |b: usize| offsets.binary_search(&b).expect("rewrite not on char boundary");
+        let rewrites : Vec<(Region,Vec<char>)> = delta.iter_source().map(|(r,text)| {
+            let start = index(r.offset);
+            (Region::new(start,index(r.offset + r.length) - start),text.chars().collect())
+        }).collect();
+        let mut shift = 0isize;
+        let mut i = 0;
+        while i < rewrites.len() {
+            // Determine the rewrites making up this hunk
+            let mut j = i + 1;
+            while j < rewrites.len() && rewrites[j].0.offset - end(&rewrites[j-1].0) <= 2 * self.margin {
+                j += 1;
+            }
+            let hunk = &rewrites[i..j];
+            let first = hunk[0].0.offset;
+            let len = end(&hunk[j-i-1].0) - first;
+            let delta : isize = hunk.iter().map(|(r,d)| d.len() as isize - r.length as isize).sum();
+            // The text with all earlier hunks applied, and the position
+            // of this hunk within it.
+            let start = (first as isize + shift) as usize;
+            let text : Vec<char> = after[..start].iter().chain(&chars[first..]).copied().collect();
+            let (from,to) = self.context(&text, start, len);
+            let len1 = to - from;
+            writeln!(out,"@@ -{} +{} @@",Range(from,len1),Range(from,(len1 as isize + delta) as usize))?;
+            write_op(out,' ',&text[from..start])?;
+            let mut cursor = first;
+            for (r,data) in hunk {
+                write_op(out,' ',&chars[cursor..r.offset])?;
+                write_op(out,'-',&chars[r.as_range()])?;
+                write_op(out,'+',data)?;
+                cursor = end(r);
+            }
+            write_op(out,' ',&text[start + len..to])?;
+            shift += delta;
+            i = j;
+        }
+        Ok(())
+    }
+
+    /// Determine the context surrounding a hunk which covers `len`
+    /// characters from `start` in a given text.  This is extended in
+    /// steps of the margin until the hunk (with its context) occurs
+    /// only once, then extended by a further step.
+    fn context(&self, text: &[char], start: usize, len: usize) -> (usize,usize) {
+        let around = |padding: usize| (start.saturating_sub(padding),(start + len + padding).min(text.len()));
+        let mut padding = 0;
+        let (mut from, mut to) = around(padding);
+        while self.margin > 0 && !unique(text,&text[from..to]) && to - from < MATCH_MAX_BITS - 2 * self.margin {
+            padding += self.margin;
+            (from,to) = around(padding);
+        }
+        around(padding + self.margin)
+    }
+}
+
+impl Default for DmpFormat {
+    fn default() -> Self { Self::new() }
+}
+
+/// Determine the end of a given region.
+fn end(r: &Region) -> usize { r.offset + r.length }
+
+/// Check whether a pattern occurs exactly once in a given text, where
+/// (as for `indexOf()`) the empty pattern occurs at every position.
+fn unique(text: &[char], pattern: &[char]) -> bool {
+    if pattern.is_empty() { return text.is_empty(); }
+    text.windows(pattern.len()).filter(|w| *w == pattern).nth(1).is_none()
+}
+
+/// Write a single operation of a hunk (unless it is empty), whose text
+/// is percent-encoded.
+fn write_op<W:Write>(out: &mut W, sign: char, text: &[char]) -> Result {
+    if text.is_empty() { return Ok(()); }
+    out.write_char(sign)?;
+    for &c in text {
+        if c.is_ascii_alphanumeric() || " -_.!~*'();/?:@&=+$,#".contains(c) {
+            out.write_char(c)?;
+        } else {
+            let mut buf = [0;4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                write!(out,"%{b:02X}")?;
+            }
+        }
+    }
+    out.write_char('\n')
+}
+
+/// A patch read from the diff-match-patch patch format (see
+/// `parse()`).  This consists of a delta over the characters of a
+/// string, along with the characters each hunk expects to find (i.e.
+/// its context and removed characters).
+#[derive(Clone,Debug,PartialEq)]
+pub struct DmpPatch {
+    /// The delta described by this patch.
+    delta: VecDelta<char>,
+    /// The region of the original string covered by each hunk, along
+    /// with the characters expected there.
+    expected: Vec<(Region,Vec<char>)>
+}
+
+impl DmpPatch {
+    /// Get the delta described by this patch, in terms of the
+    /// characters of the original string.
+    pub fn delta(&self) -> &VecDelta<char> { &self.delta }
+
+    /// Consume this patch, returning the delta it describes.
+    pub fn into_delta(self) -> VecDelta<char> { self.delta }
+
+    /// Check the characters of a given string match those expected by
+    /// every hunk of this patch.  If not, the first mismatch is
+    /// reported.  Unlike `patch_apply()` in diff-match-patch, hunks
+    /// must match exactly at their stated positions.  A hunk which
+    /// extends beyond the end of the string is reported as a mismatch
+    /// at the end of the string.
+    pub fn check(&self, before: &str) -> core::result::Result<(),DmpError> {
+        let chars : Vec<char> = before.chars().collect();
+        for (region,expected) in &self.expected {
+            if end(region) > chars.len() { return Err(DmpError::ContextMismatch(chars.len())); }
+            for (i,c) in expected.iter().enumerate() {
+                let n = region.offset + i;
+                if chars.get(n) != Some(c) { return Err(DmpError::ContextMismatch(n)); }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply this patch to a given string, having first checked it
+    /// matches that expected (see `check()`).
+    pub fn apply(&self, before: &str) -> core::result::Result<String,DmpError> {
+        self.check(before)?;
+        let chars : Vec<char> = before.chars().collect();
+        Ok(self.delta.apply(&chars).into_iter().collect())
+    }
+}
+
+/// Errors arising from parsing or applying a diff-match-patch patch.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum DmpError {
+    /// The given line of the patch is malformed (e.g. an invalid hunk
+    /// header or escape sequence, or a hunk whose length differs from
+    /// that stated in its header).
+    Malformed(usize),
+    /// The given character (counting from zero) of the string being
+    /// patched does not match that expected by the patch.
+    ContextMismatch(usize)
+}
+
+impl core::fmt::Display for DmpError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result {
+        match self {
+            DmpError::Malformed(n) => write!(f,"malformed patch at line {n}"),
+            DmpError::ContextMismatch(n) => write!(f,"patch does not match string at character {n}")
+        }
+    }
+}
+
+impl core::error::Error for DmpError {}
+
+/// Parse text in the diff-match-patch patch format (i.e. as produced
+/// by `patch_toText()`) into a patch.  Hunks must be given in order
+/// and, following diff-match-patch, each is positioned in terms of
+/// the string with all earlier hunks applied.  Line numbers reported
+/// in errors start from one.
+pub fn parse(text: &str) -> core::result::Result<DmpPatch,DmpError> {
+    let lines : Vec<&str> = text.split('\n').collect();
+    let mut hunks : Vec<(Region,Vec<char>)> = Vec::new();
+    let mut expected : Vec<(Region,Vec<char>)> = Vec::new();
+    let mut shift = 0isize;
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].is_empty() { i += 1; continue; }
+        let header = i + 1;
+        let (start,old,new) = parse_header(lines[i]).ok_or(DmpError::Malformed(header))?;
+        // Determine the position of this hunk in the original string
+        let previous = expected.last().map_or(0,|(r,_)| end(r));
+        let start = usize::try_from(start as isize - shift).ok().filter(|&s| s >= previous);
+        let start = start.ok_or(DmpError::Malformed(header))?;
+        let mut context = Vec::new();
+        let mut added = 0;
+        let mut open = false;
+        i += 1;
+        while i < lines.len() && !lines[i].starts_with('@') {
+            let line = lines[i];
+            let mut parts = line.chars();
+            let sign = parts.next();
+            let data : Vec<char> = decode(parts.as_str()).ok_or(DmpError::Malformed(i + 1))?.chars().collect();
+            let pos = start + context.len();
+            match sign {
+                None => {}
+                Some(' ') => {
+                    open = false;
+                    added += data.len();
+                    context.extend(data);
+                }
+                Some('-') => {
+                    if !open { hunks.push((Region::new(pos,0),Vec::new())); open = true; }
+                    let (r,_) = hunks.last_mut().unwrap();
+                    r.length += data.len();
+                    context.extend(data);
+                }
+                Some('+') => {
+                    if !open { hunks.push((Region::new(pos,0),Vec::new())); open = true; }
+                    let (_,d) = hunks.last_mut().unwrap();
+                    added += data.len();
+                    d.extend(data);
+                }
+                Some(_) => return Err(DmpError::Malformed(i + 1))
+            }
+            i += 1;
+        }
+        if context.len() != old || added != new { return Err(DmpError::Malformed(header)); }
+        shift += new as isize - old as isize;
+        expected.push((Region::new(start,context.len()),context));
+    }
+    let delta = VecDelta::from_source(hunks.iter().map(|(r,d)| (*r,d.as_slice())));
+    Ok(DmpPatch{delta,expected})
+}
+
+/// Decode percent-encoded text, returning `None` if an escape sequence
+/// is invalid or the result is not valid UTF-8.
+fn decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).filter(|h| h.iter().all(u8::is_ascii_hexdigit))?;
+            out.push(u8::from_str_radix(core::str::from_utf8(hex).ok()?,16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod dmp_tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,StrDelta};
+    use super::{DmpError,DmpFormat,parse};
+
+    const TEXT1 : &str = "The quick brown fox jumps over the lazy dog.";
+    const TEXT2 : &str = "That quick brown fox jumped over a lazy dog.";
+
+    #[test]
+    fn dmp_test_01() {
+        // Examples from diff-match-patch, including rolling context
+        let text = DmpFormat::new().render(TEXT1,&TEXT1.diff(TEXT2));
+        assert_eq!(text,"@@ -1,11 +1,12 @@\n Th\n-e\n+at\n  quick b\n@@ -22,18 +22,17 @@\n jump\n-s\n+ed\n  over \n-the\n+a\n  laz\n");
+        let text = DmpFormat::new().render(TEXT2,&TEXT2.diff(TEXT1));
+        assert_eq!(text,"@@ -1,8 +1,7 @@\n Th\n-at\n+e\n  qui\n@@ -21,17 +21,18 @@\n jump\n-ed\n+s\n  over \n-a\n+the\n  laz\n");
+        assert_eq!(parse(&text).unwrap().apply(TEXT2).unwrap(),TEXT1);
+        // Nothing to render
+        assert_eq!(DmpFormat::new().render(TEXT1,&StrDelta::new()),"");
+    }
+
+    #[test]
+    fn dmp_test_02() {
+        // Character encoding
+        let (before,after) = ("`1234567890-=[]\\;',./","~!@#$%^&*()_+{}|:\"<>?");
+        let text = DmpFormat::new().render(before,&before.diff(after));
+        assert_eq!(text,"@@ -1,21 +1,21 @@\n-%601234567890-=%5B%5D%5C;',./\n+~!@#$%25%5E&*()_+%7B%7D%7C:%22%3C%3E?\n");
+        assert_eq!(parse(&text).unwrap().apply(before).unwrap(),after);
+        let text = DmpFormat::new().render("a\nb",&"a\nb".diff("a\nc\u{e9}"));
+        assert_eq!(text,"@@ -1,3 +1,4 @@\n a%0A\n-b\n+c%C3%A9\n");
+    }
+
+    #[test]
+    fn dmp_test_03() {
+        // Round trip for a range of inputs
+        let seqs : Vec<String> = (0..25).map(|i| (0..i).map(|j| ['a','b','\n','%','\u{e9}'][(j * i + 3) % 5]).collect()).collect();
+        for margin in [0,1,4] {
+            for a in &seqs {
+                for b in &seqs {
+                    let text = DmpFormat::new().margin(margin).render(a,&a.diff(b));
+                    assert_eq!(&parse(&text).unwrap().apply(a).unwrap(),b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dmp_test_04() {
+        assert_eq!(parse("@@ -1,2 +1,2 @\n"),Err(DmpError::Malformed(1)));
+        assert_eq!(parse("@@ -1,2 +1,2 @@\n ab\n*c\n"),Err(DmpError::Malformed(3)));
+        assert_eq!(parse("@@ -1,2 +1,2 @@\n a%G0\n"),Err(DmpError::Malformed(2)));
+        assert_eq!(parse("@@ -1,3 +1,2 @@\n ab\n"),Err(DmpError::Malformed(1)));
+        let patch = parse("@@ -1,3 +1,3 @@\n ab\n-c\n+d\n").unwrap();
+        assert_eq!(patch.apply("abx"),Err(DmpError::ContextMismatch(2)));
+        assert_eq!(patch.apply("ab"),Err(DmpError::ContextMismatch(2)));
+        assert_eq!(patch.apply("abc").unwrap(),"abd");
+        // Hunk beyond the end of the string
+        let patch = parse("@@ -10,0 +1 @@\n+d\n").unwrap();
+        assert_eq!(patch.apply(""),Err(DmpError::ContextMismatch(0)));
+    }
+}
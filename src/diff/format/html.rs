@@ -0,0 +1,192 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Result,Write};
+use crate::util::Region;
+use crate::diff::{Chunk,Diff,StrDelta,diff_chunks};
+
+/// Options controlling how a string delta is rendered as HTML.  By
+/// default, the text is rendered _inline_ with deleted text enclosed
+/// in `<del>` and inserted text in `<ins>` (which is suitable for
+/// placing within a `<pre>` element).  For example:
+///
+/// ```
+/// use delta_inc::diff::Diff;
+/// use delta_inc::diff::format::html::HtmlFormat;
+///
+/// let text = HtmlFormat::new().render("a < b", &"a < b".diff("a <= b"));
+/// assert_eq!(text, "a &lt;<ins>=</ins> b");
+/// ```
+///
+/// Alternatively, the text can be rendered as a side-by-side `<table>`
+/// of lines, where each row holds the number and content of a line in
+/// the original text followed by those of the updated text.  Cells of
+/// deleted and inserted lines have the classes `del` and `ins`
+/// respectively, and the characters which differ between replaced
+/// lines are marked using `<del>` and `<ins>`.  All text is escaped.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct HtmlFormat {
+    /// Whether or not to render a side-by-side table.
+    table: bool
+}
+
+impl HtmlFormat {
+    /// Construct the default format, which renders inline.
+    pub const fn new() -> Self { HtmlFormat{table: false} }
+
+    /// Set whether or not a side-by-side table is rendered.
+    pub fn table(mut self, enable: bool) -> Self {
+        self.table = enable;
+        self
+    }
+
+    /// Render a delta against the string it applies to.  This
+    /// operation will `panic` if the delta is malformed with respect
+    /// to `before`.
+    pub fn render(&self, before: &str, delta: &StrDelta) -> String {
+        let mut out = String::new();
+        self.write(&mut out, before, delta).unwrap();
+        out
+    }
+
+    /// Render a delta against the string it applies to, writing the
+    /// result to a given output (see `render()`).
+    pub fn write<W:Write>(&self, out: &mut W, before: &str, delta: &StrDelta) -> Result {
+        if self.table { return write_table(out, before, &delta.apply(before)); }
+        let mut cursor = 0;
+        for (r,text) in delta.iter_source() {
+            write_escaped(out,&before[cursor..r.offset])?;
+            write_marked(out,"del",&before[r.as_range()])?;
+            write_marked(out,"ins",text)?;
+            cursor = r.offset + r.length;
+        }
+        write_escaped(out,&before[cursor..])
+    }
+}
+
+/// Render the lines of two strings as a side-by-side table.
+fn write_table<W:Write>(out: &mut W, before: &str, after: &str) -> Result {
+    let old : Vec<&str> = before.split_inclusive('\n').collect();
+    let new : Vec<&str> = after.split_inclusive('\n').collect();
+    writeln!(out,"<table class=\"diff\">")?;
+    let (mut i, mut j) = (0,0);
+    let mut chunks = diff_chunks(&old,&new).peekable();
+    while let Some(chunk) = chunks.next() {
+        match chunk {
+            Chunk::Equal(lines) => {
+                for l in lines {
+                    i += 1;
+                    j += 1;
+                    write_row(out,Some((i,"",l,&[])),Some((j,"",l,&[])))?;
+                }
+            }
+            Chunk::Deleted(deleted) => {
+                // Pair deleted lines with any inserted lines after
+                let inserted = match chunks.peek() {
+                    Some(Chunk::Inserted(lines)) => { let lines = *lines; chunks.next(); lines }
+                    _ => &[]
+                };
+                for k in 0..deleted.len().max(inserted.len()) {
+                    let (l,r) = (deleted.get(k),inserted.get(k));
+                    let (mut ls, mut rs) = (Vec::new(),Vec::new());
+                    if let (Some(l),Some(r)) = (l,r) {
+                        let d = l.diff(r);
+                        ls = d.iter_source().map(|(r,_)| r).collect();
+                        rs = d.iter().map(|(r,t)| Region::new(r.offset,t.len())).collect();
+                    }
+                    let left = l.map(|l| { i += 1; (i,"del",*l,ls.as_slice()) });
+                    let right = r.map(|r| { j += 1; (j,"ins",*r,rs.as_slice()) });
+                    write_row(out,left,right)?;
+                }
+            }
+            Chunk::Inserted(lines) => {
+                for r in lines {
+                    j += 1;
+                    write_row(out,None,Some((j,"ins",r,&[])))?;
+                }
+            }
+        }
+    }
+    writeln!(out,"</table>")
+}
+
+/// A cell of the table, consisting of a line number, a class, the line
+/// itself and the regions of bytes within it to mark.
+type Cell<'a> = Option<(usize,&'a str,&'a str,&'a [Region])>;
+
+/// Write a single row of the table.
+fn write_row<W:Write>(out: &mut W, left: Cell<'_>, right: Cell<'_>) -> Result {
+    out.write_str("<tr>")?;
+    for cell in [left,right] {
+        match cell {
+            Some((n,class,line,marks)) => {
+                let tag = if class == "ins" { "ins" } else { "del" };
+                if class.is_empty() { write!(out,"<th>{n}</th><td>")?; } else { write!(out,"<th>{n}</th><td class=\"{class}\">")?; }
+                let line = line.strip_suffix('\n').unwrap_or(line);
+                let mut cursor = 0;
+                for r in marks {
+                    let (start,finish) = (r.offset.min(line.len()),(r.offset + r.length).min(line.len()));
+                    write_escaped(out,&line[cursor..start])?;
+                    write_marked(out,tag,&line[start..finish])?;
+                    cursor = finish;
+                }
+                write_escaped(out,&line[cursor..])?;
+                out.write_str("</td>")?;
+            }
+            None => out.write_str("<th></th><td></td>")?
+        }
+    }
+    writeln!(out,"</tr>")
+}
+
+/// Write some (non-empty) text enclosed in a given tag.
+fn write_marked<W:Write>(out: &mut W, tag: &str, text: &str) -> Result {
+    if text.is_empty() { return Ok(()); }
+    write!(out,"<{tag}>")?;
+    write_escaped(out,text)?;
+    write!(out,"</{tag}>")
+}
+
+/// Write some text, escaping any characters which are special in HTML.
+fn write_escaped<W:Write>(out: &mut W, text: &str) -> Result {
+    for c in text.chars() {
+        match c {
+            '&' => out.write_str("&amp;")?,
+            '<' => out.write_str("&lt;")?,
+            '>' => out.write_str("&gt;")?,
+            '"' => out.write_str("&quot;")?,
+            '\'' => out.write_str("&#39;")?,
+            c => out.write_char(c)?
+        }
+    }
+    Ok(())
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod html_tests {
+    use crate::diff::{Diff,StrDelta};
+    use super::HtmlFormat;
+
+    #[test]
+    fn html_test_01() {
+        let (before,after) = ("the cat sat","the dog sat down");
+        let text = HtmlFormat::new().render(before,&before.diff(after));
+        assert_eq!(text,"the <del>cat</del><ins>dog</ins> sat<ins> down</ins>");
+        assert_eq!(HtmlFormat::new().render("<&>",&StrDelta::new()),"&lt;&amp;&gt;");
+    }
+
+    #[test]
+    fn html_test_02() {
+        let (before,after) = ("a\nb = 1\nc\n","a\nb = 2\nd\n\"e\"");
+        let text = HtmlFormat::new().table(true).render(before,&before.diff(after));
+        assert_eq!(text,"<table class=\"diff\">\n\
+                         <tr><th>1</th><td>a</td><th>1</th><td>a</td></tr>\n\
+                         <tr><th>2</th><td class=\"del\">b = <del>1</del></td><th>2</th><td class=\"ins\">b = <ins>2</ins></td></tr>\n\
+                         <tr><th>3</th><td class=\"del\"><del>c</del></td><th>3</th><td class=\"ins\"><ins>d</ins></td></tr>\n\
+                         <tr><th></th><td></td><th>4</th><td class=\"ins\">&quot;e&quot;</td></tr>\n\
+                         </table>\n");
+    }
+}
@@ -0,0 +1,343 @@
+use alloc::format;
+use alloc::string::{String,ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize,Serialize};
+use serde_json::Value;
+use crate::diff::{TryTransform,VecDelta};
+
+/// An individual operation within a JSON Patch document, where each
+/// `path` is a JSON Pointer (RFC 6901).  Operations are serialised as
+/// described in RFC 6902 (e.g. `{"op":"add","path":"/a/0","value":1}`).
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    /// Insert a value into an array (or set a member of an object).
+    Add{path: String, value: Value},
+    /// Remove the value at the given location.
+    Remove{path: String},
+    /// Replace the value at the given location.
+    Replace{path: String, value: Value},
+    /// Remove the value at one location and add it at another.
+    Move{from: String, path: String},
+    /// Add a copy of the value at one location to another.
+    Copy{from: String, path: String},
+    /// Check the value at the given location is equal to a given value.
+    Test{path: String, value: Value}
+}
+
+/// A JSON Patch document (RFC 6902) is a sequence of operations which
+/// are applied in order to a JSON value.  A `VecDelta` can be converted
+/// into a JSON Patch which performs the same rewrites on a JSON array
+/// (see `from_delta()`).  For example, transforming `[1,2,3]` into
+/// `[1,4]` gives:
+///
+/// ```txt
+///  [{"op":"replace","path":"/1","value":4},{"op":"remove","path":"/2"}]
+/// ```
+///
+/// Since rewrites are given in terms of the _target sequence_ (i.e.
+/// where earlier rewrites have already been applied), array indices
+/// within the patch follow directly from their regions.  Conversely, a
+/// JSON Patch document can be deserialised and applied to a JSON value
+/// using `TryTransform`:
+///
+/// ```
+/// use delta_inc::diff::TryTransform;
+/// use delta_inc::diff::format::json_patch::JsonPatch;
+/// use serde_json::json;
+///
+/// let patch : JsonPatch = serde_json::from_str(r#"[
+///    {"op":"test","path":"/a","value":1},
+///    {"op":"move","from":"/a","path":"/b/0"}
+/// ]"#).unwrap();
+/// let mut doc = json!({"a":1,"b":[2]});
+/// doc.try_transform(&patch).unwrap();
+/// assert_eq!(doc,json!({"b":[1,2]}));
+/// ```
+#[derive(Clone,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(transparent)]
+pub struct JsonPatch {
+    ops: Vec<PatchOp>
+}
+
+impl JsonPatch {
+    /// Construct an empty `JsonPatch`.
+    pub const fn new() -> Self { JsonPatch{ops: Vec::new()} }
+
+    /// Construct a patch which applies a given delta to the array at
+    /// a given location (e.g. `""` for the root, or `"/items"`).
+    /// Each element of replacement data is converted into a JSON value,
+    /// and an error is returned if this fails.
+    pub fn from_delta<T:Serialize>(delta: &VecDelta<T>, path: &str) -> Result<Self,serde_json::Error> {
+        let mut patch = JsonPatch::new();
+        patch.push_delta(delta, path)?;
+        Ok(patch)
+    }
+
+    /// Get the number of operations in this patch.
+    pub fn len(&self) -> usize { self.ops.len() }
+
+    /// Check whether this patch contains any operations or not.
+    pub fn is_empty(&self) -> bool { self.ops.is_empty() }
+
+    /// Get the `ith` operation in this patch.
+    pub fn get(&self, ith: usize) -> Option<&PatchOp> { self.ops.get(ith) }
+
+    /// Iterate the operations in this patch, in order.
+    pub fn iter(&self) -> core::slice::Iter<'_,PatchOp> { self.ops.iter() }
+
+    /// Append an operation onto the end of this patch.
+    pub fn push(&mut self, op: PatchOp) { self.ops.push(op) }
+
+    /// Append operations which apply a given delta to the array at a
+    /// given location (see `from_delta()`).  Each rewrite replaces
+    /// elements in place as far as possible, and then either removes
+    /// or adds the remainder.
+    pub fn push_delta<T:Serialize>(&mut self, delta: &VecDelta<T>, path: &str) -> Result<(),serde_json::Error> {
+        for rw in delta.iter() {
+            let (r,data) = (rw.region(),rw.data());
+            let n = r.length.min(data.len());
+            for (i,item) in data.iter().enumerate() {
+                let path = format!("{path}/{}",r.offset + i);
+                let value = serde_json::to_value(item)?;
+                if i < n {
+                    self.ops.push(PatchOp::Replace{path,value});
+                } else {
+                    self.ops.push(PatchOp::Add{path,value});
+                }
+            }
+            for _ in n..r.length {
+                self.ops.push(PatchOp::Remove{path: format!("{path}/{}",r.offset + n)});
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply this patch to a given JSON value.  As required by RFC
+    /// 6902, operations are applied in order and, if any fails, an
+    /// error is returned and the value is left unchanged.
+    pub fn apply(&self, doc: &mut Value) -> Result<(),JsonPatchError> {
+        let mut result = doc.clone();
+        for (i,op) in self.ops.iter().enumerate() {
+            apply_op(&mut result, op).map_err(|kind| JsonPatchError{op: i, kind})?;
+        }
+        *doc = result;
+        Ok(())
+    }
+}
+
+impl TryTransform for Value {
+    type Delta = JsonPatch;
+    type Error = JsonPatchError;
+
+    fn try_transform(&mut self, d: &JsonPatch) -> Result<(),JsonPatchError> {
+        d.apply(self)
+    }
+}
+
+/// An error arising from applying a JSON Patch, which identifies the
+/// operation (by its position in the patch) which failed.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct JsonPatchError {
+    /// Index of the failing operation within the patch.
+    pub op: usize,
+    /// The reason the operation failed.
+    pub kind: JsonPatchErrorKind
+}
+
+/// Describes why an operation within a JSON Patch failed.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum JsonPatchErrorKind {
+    /// The given location does not exist (or, for an array, is not a
+    /// valid index).
+    NotFound(String),
+    /// The value at the given location differs from that expected by a
+    /// `test` operation.
+    TestFailed(String),
+    /// A value cannot be moved into one of its own children.
+    InvalidMove{from: String, path: String}
+}
+
+impl core::fmt::Display for JsonPatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let op = self.op;
+        match &self.kind {
+            JsonPatchErrorKind::NotFound(path) => {
+                write!(f,"operation {op}: location \"{path}\" not found")
+            }
+            JsonPatchErrorKind::TestFailed(path) => {
+                write!(f,"operation {op}: test of \"{path}\" failed")
+            }
+            JsonPatchErrorKind::InvalidMove{from,path} => {
+                write!(f,"operation {op}: cannot move \"{from}\" into \"{path}\"")
+            }
+        }
+    }
+}
+
+impl core::error::Error for JsonPatchError {}
+
+/// Apply a single operation to a given JSON value.
+fn apply_op(doc: &mut Value, op: &PatchOp) -> Result<(),JsonPatchErrorKind> {
+    let not_found = |path: &String| JsonPatchErrorKind::NotFound(path.clone());
+    match op {
+        PatchOp::Add{path,value} => add(doc, path, value.clone()),
+        PatchOp::Remove{path} => remove(doc, path).map(|_| ()),
+        PatchOp::Replace{path,value} => {
+            *doc.pointer_mut(path).ok_or_else(|| not_found(path))? = value.clone();
+            Ok(())
+        }
+        PatchOp::Move{from,path} => {
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(JsonPatchErrorKind::InvalidMove{from: from.clone(), path: path.clone()});
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        PatchOp::Copy{from,path} => {
+            let value = doc.pointer(from).ok_or_else(|| not_found(from))?.clone();
+            add(doc, path, value)
+        }
+        PatchOp::Test{path,value} => {
+            match doc.pointer(path) {
+                Some(v) if v == value => Ok(()),
+                Some(_) => Err(JsonPatchErrorKind::TestFailed(path.clone())),
+                None => Err(not_found(path))
+            }
+        }
+    }
+}
+
+/// Add a value at a given location.  For an array, the value is
+/// inserted before the given index (or appended for `-`), whilst for
+/// an object the given member is set.
+fn add(doc: &mut Value, path: &str, value: Value) -> Result<(),JsonPatchErrorKind> {
+    if path.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let not_found = || JsonPatchErrorKind::NotFound(path.to_string());
+    let (parent,key) = split(path).ok_or_else(not_found)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => { map.insert(key,value); }
+        Some(Value::Array(vec)) if key == "-" => vec.push(value),
+        Some(Value::Array(vec)) => {
+            let i = index(&key).filter(|&i| i <= vec.len()).ok_or_else(not_found)?;
+            vec.insert(i,value);
+        }
+        _ => return Err(not_found())
+    }
+    Ok(())
+}
+
+/// Remove the value at a given location, returning it.
+fn remove(doc: &mut Value, path: &str) -> Result<Value,JsonPatchErrorKind> {
+    let not_found = || JsonPatchErrorKind::NotFound(path.to_string());
+    let (parent,key) = split(path).ok_or_else(not_found)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&key).ok_or_else(not_found),
+        Some(Value::Array(vec)) => {
+            let i = index(&key).filter(|&i| i < vec.len()).ok_or_else(not_found)?;
+            Ok(vec.remove(i))
+        }
+        _ => Err(not_found())
+    }
+}
+
+/// Split a (non-empty) JSON Pointer into the pointer to its parent and
+/// its final (unescaped) reference token.
+fn split(path: &str) -> Option<(&str,String)> {
+    let (parent,key) = path.rsplit_once('/')?;
+    Some((parent,key.replace("~1","/").replace("~0","~")))
+}
+
+/// Parse an array index, which cannot have leading zeros.
+fn index(key: &str) -> Option<usize> {
+    if key.starts_with('0') && key != "0" { return None; }
+    if !key.bytes().all(|b| b.is_ascii_digit()) { return None; }
+    key.parse().ok()
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod json_patch_tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use serde_json::{Value,json};
+    use crate::diff::{Diff,TryTransform};
+    use super::{JsonPatch,JsonPatchError,JsonPatchErrorKind};
+
+    #[test]
+    fn json_patch_test_01() {
+        let patch = JsonPatch::from_delta(&[1,2,3].diff(&[1,4]),"").unwrap();
+        assert_eq!(serde_json::to_value(&patch).unwrap(),
+                   json!([{"op":"replace","path":"/1","value":4},{"op":"remove","path":"/2"}]));
+        let patch = JsonPatch::from_delta(&["a"].diff(&["b","a","c"]),"/items").unwrap();
+        assert_eq!(serde_json::to_value(&patch).unwrap(),
+                   json!([{"op":"add","path":"/items/0","value":"b"},{"op":"add","path":"/items/2","value":"c"}]));
+    }
+
+    #[test]
+    fn json_patch_test_02() {
+        // Applying the patch reproduces the target
+        let seqs : Vec<Vec<usize>> = (0..10).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let patch = JsonPatch::from_delta(&a.diff(b),"").unwrap();
+                let vec : Vec<Value> = a.iter().map(|&i| json!(i)).collect();
+                let mut doc = Value::from(vec);
+                assert_eq!(doc.try_transform(&patch),Ok(()));
+                assert_eq!(doc,json!(b));
+            }
+        }
+    }
+
+    #[test]
+    fn json_patch_test_03() {
+        // Examples from RFC 6902 (Appendix A)
+        check(json!({"foo":"bar"}),json!([{"op":"add","path":"/baz","value":"qux"}]),json!({"baz":"qux","foo":"bar"}));
+        check(json!({"foo":["bar","baz"]}),json!([{"op":"add","path":"/foo/1","value":"qux"}]),json!({"foo":["bar","qux","baz"]}));
+        check(json!({"baz":"qux","foo":"bar"}),json!([{"op":"remove","path":"/baz"}]),json!({"foo":"bar"}));
+        check(json!({"foo":["bar","qux","baz"]}),json!([{"op":"remove","path":"/foo/1"}]),json!({"foo":["bar","baz"]}));
+        check(json!({"baz":"qux","foo":"bar"}),json!([{"op":"replace","path":"/baz","value":"boo"}]),json!({"baz":"boo","foo":"bar"}));
+        check(json!({"foo":{"bar":"baz","waldo":"fred"},"qux":{"corge":"grault"}}),
+              json!([{"op":"move","from":"/foo/waldo","path":"/qux/thud"}]),
+              json!({"foo":{"bar":"baz"},"qux":{"corge":"grault","thud":"fred"}}));
+        check(json!({"foo":["all","grass","cows","eat"]}),json!([{"op":"move","from":"/foo/1","path":"/foo/3"}]),
+              json!({"foo":["all","cows","eat","grass"]}));
+        check(json!({"foo":["bar"]}),json!([{"op":"add","path":"/foo/-","value":["abc","def"]}]),json!({"foo":["bar",["abc","def"]]}));
+        check(json!({"/":9,"~1":10}),json!([{"op":"test","path":"/~01","value":10},{"op":"copy","from":"/~1","path":"/x"}]),
+              json!({"/":9,"~1":10,"x":9}));
+    }
+
+    #[test]
+    fn json_patch_test_04() {
+        // Failures leave the document unchanged
+        let doc = json!({"baz":"qux","foo":["a",2,"c"]});
+        fail(&doc,json!([{"op":"remove","path":"/baz"},{"op":"test","path":"/foo/1","value":"2"}]),
+             1,JsonPatchErrorKind::TestFailed("/foo/1".to_string()));
+        fail(&doc,json!([{"op":"add","path":"/baz/bat","value":"qux"}]),0,JsonPatchErrorKind::NotFound("/baz/bat".to_string()));
+        fail(&doc,json!([{"op":"add","path":"/foo/4","value":1}]),0,JsonPatchErrorKind::NotFound("/foo/4".to_string()));
+        fail(&doc,json!([{"op":"remove","path":"/foo/01"}]),0,JsonPatchErrorKind::NotFound("/foo/01".to_string()));
+        fail(&doc,json!([{"op":"move","from":"/foo","path":"/foo/0"}]),0,
+             JsonPatchErrorKind::InvalidMove{from: "/foo".to_string(), path: "/foo/0".to_string()});
+        // Unknown operations are rejected when parsing
+        assert!(serde_json::from_value::<JsonPatch>(json!([{"op":"frob","path":""}])).is_err());
+    }
+
+    fn check(mut doc: Value, patch: Value, expected: Value) {
+        let patch : JsonPatch = serde_json::from_value(patch).unwrap();
+        assert_eq!(doc.try_transform(&patch),Ok(()));
+        assert_eq!(doc,expected);
+    }
+
+    fn fail(doc: &Value, patch: Value, op: usize, kind: JsonPatchErrorKind) {
+        let patch : JsonPatch = serde_json::from_value(patch).unwrap();
+        let mut d = doc.clone();
+        assert_eq!(d.try_transform(&patch),Err(JsonPatchError{op,kind}));
+        assert_eq!(&d,doc);
+    }
+}
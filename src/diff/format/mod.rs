@@ -0,0 +1,20 @@
+/// Rendering of line-based deltas with ANSI colours for terminals.
+pub mod ansi;
+/// Rendering of line-based diffs as context diffs (i.e. as produced
+/// by `diff -c`).
+pub mod context;
+/// Rendering and parsing of string deltas in the patch format of
+/// diff-match-patch.
+pub mod dmp;
+/// Rendering of string deltas as HTML, either inline or as a
+/// side-by-side table.
+pub mod html;
+/// Rendering of line-based diffs as two columns side-by-side (i.e. as
+/// produced by `diff -y`).
+pub mod side_by_side;
+/// Rendering of line-based deltas as unified diffs (i.e. as produced
+/// by `diff -u`).
+pub mod unified;
+/// Conversion of deltas to and from JSON Patch (RFC 6902) documents.
+#[cfg(feature = "json")]
+pub mod json_patch;
@@ -0,0 +1,137 @@
+use alloc::string::String;
+use core::fmt::{Result,Write};
+use crate::diff::Chunk;
+
+/// Options controlling how a line-based diff is rendered as two
+/// columns side-by-side, such as produced by `diff -y`.  This is driven
+/// from the chunks produced by `diff_chunks()`.  Each row shows a line
+/// of the original file on the left and of the updated file on the
+/// right, separated by a gutter which is `|` for a replaced line, `<`
+/// for a deleted line and `>` for an inserted line.  For example:
+///
+/// ```
+/// use delta_inc::diff::diff_chunks;
+/// use delta_inc::diff::format::side_by_side::SideBySideFormat;
+///
+/// let before = ["a\n", "b\n", "c\n"];
+/// let after = ["a\n", "x\n"];
+/// let text = SideBySideFormat::new().width(11).render(diff_chunks(&before, &after));
+/// assert_eq!(text, "a      a\nb    | x\nc    <\n");
+/// ```
+///
+/// Line terminators are not shown, and lines which are too long for
+/// their column are truncated (where every `char` is assumed to occupy
+/// one column).
+#[derive(Clone,Copy,Debug)]
+pub struct SideBySideFormat {
+    /// Total width of each row.
+    width: usize
+}
+
+impl SideBySideFormat {
+    /// Construct the default format, which is 130 columns wide (as for
+    /// `diff -y`).
+    pub const fn new() -> Self { SideBySideFormat{width: 130} }
+
+    /// Set the total width of each row, which is split evenly between
+    /// the two columns and the gutter.
+    pub fn width(mut self, columns: usize) -> Self {
+        self.width = columns;
+        self
+    }
+
+    /// Render a sequence of chunks.
+    pub fn render<'c,T,I>(&self, chunks: I) -> String
+    where T:AsRef<str>+'c, I:IntoIterator<Item=Chunk<'c,T>> {
+        let mut out = String::new();
+        self.write(&mut out, chunks).unwrap();
+        out
+    }
+
+    /// Render a sequence of chunks, writing the result to a given
+    /// output (see `render()`).
+    pub fn write<'c,T,I,W>(&self, out: &mut W, chunks: I) -> Result
+    where T:AsRef<str>+'c, I:IntoIterator<Item=Chunk<'c,T>>, W:Write {
+        let mut chunks = chunks.into_iter().peekable();
+        while let Some(chunk) = chunks.next() {
+            match chunk {
+                Chunk::Equal(lines) => {
+                    for l in lines { self.write_row(out,l.as_ref(),' ',l.as_ref())?; }
+                }
+                Chunk::Deleted(deleted) => {
+                    // Pair deleted lines with any inserted lines after
+                    let inserted = match chunks.peek() {
+                        Some(Chunk::Inserted(lines)) => { let lines = *lines; chunks.next(); lines }
+                        _ => &[]
+                    };
+                    for i in 0..deleted.len().max(inserted.len()) {
+                        match (deleted.get(i),inserted.get(i)) {
+                            (Some(l),Some(r)) => self.write_row(out,l.as_ref(),'|',r.as_ref())?,
+                            (Some(l),None) => self.write_row(out,l.as_ref(),'<',"")?,
+                            (None,Some(r)) => self.write_row(out,"",'>',r.as_ref())?,
+                            (None,None) => unreachable!()
+                        }
+                    }
+                }
+                Chunk::Inserted(lines) => {
+                    for r in lines { self.write_row(out,"",'>',r.as_ref())?; }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a single row, with trailing whitespace removed.
+    fn write_row<W:Write>(&self, out: &mut W, left: &str, gutter: char, right: &str) -> Result {
+        let column = self.width.saturating_sub(3) / 2;
+        let mut row = String::new();
+        row.extend(column_of(left,column).chars().chain(core::iter::repeat(' ')).take(column));
+        row.push(' ');
+        row.push(gutter);
+        row.push(' ');
+        row.push_str(column_of(right,column));
+        writeln!(out,"{}",row.trim_end())
+    }
+}
+
+impl Default for SideBySideFormat {
+    fn default() -> Self { Self::new() }
+}
+
+/// Determine the portion of a line shown in a column of a given width,
+/// excluding its terminator.
+fn column_of(line: &str, width: usize) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    match line.char_indices().nth(width) {
+        Some((i,_)) => &line[..i],
+        None => line
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod side_by_side_tests {
+    use alloc::vec::Vec;
+    use crate::diff::diff_chunks;
+    use super::SideBySideFormat;
+
+    fn lines(text: &str) -> Vec<&str> { text.split_inclusive('\n').collect() }
+
+    #[test]
+    fn side_by_side_test_01() {
+        let before = lines("apple\nbanana\ncherry\ndate\n");
+        let after = lines("apple\nblueberry\ndate\nelderberry\nfig");
+        let text = SideBySideFormat::new().width(17).render(diff_chunks(&before,&after));
+        assert_eq!(text,"apple     apple\nbanana  | blueber\ncherry  <\ndate      date\n        > elderbe\n        > fig\n");
+        // Identical inputs
+        let text = SideBySideFormat::new().width(9).render(diff_chunks(&before,&before));
+        assert_eq!(text,"app   app\nban   ban\nche   che\ndat   dat\n");
+        // Very narrow
+        let text = SideBySideFormat::new().width(0).render(diff_chunks(&before[..1],&[]));
+        assert_eq!(text," <\n");
+    }
+}
@@ -0,0 +1,385 @@
+use alloc::string::{String,ToString};
+use alloc::vec::Vec;
+use core::fmt::{Result,Write};
+use crate::util::Region;
+use crate::diff::VecDelta;
+
+/// Options controlling how a line-based delta is rendered as a
+/// _unified diff_, such as produced by `diff -u` and consumed by
+/// `patch`.  Each line is expected to include its terminator (e.g. as
+/// produced by `stream::lines()`), and a line without one (i.e. the
+/// last line of a file) is marked with `\ No newline at end of file`.
+/// For example:
+///
+/// ```
+/// use delta_inc::diff::Diff;
+/// use delta_inc::diff::format::unified::UnifiedFormat;
+///
+/// let before = ["a\n", "b\n", "c\n"];
+/// let after = ["a\n", "x\n", "c\n"];
+/// let text = UnifiedFormat::new().context(1).render(&before, &before.diff(&after));
+/// assert_eq!(text, "@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n");
+/// ```
+///
+/// Rewrites separated by no more than twice the number of context
+/// lines are combined into the same hunk.
+#[derive(Clone,Copy,Debug)]
+pub struct UnifiedFormat<'a> {
+    /// Number of unchanged lines shown around each change.
+    context: usize,
+    /// File names shown in the `---` and `+++` header lines (if any).
+    header: Option<(&'a str,&'a str)>
+}
+
+impl<'a> UnifiedFormat<'a> {
+    /// Construct the default format, which shows three lines of context
+    /// and no header.
+    pub const fn new() -> Self { UnifiedFormat{context: 3, header: None} }
+
+    /// Set the number of unchanged lines shown around each change.
+    pub fn context(mut self, lines: usize) -> Self {
+        self.context = lines;
+        self
+    }
+
+    /// Include a header naming the original and updated files.
+    pub fn header(mut self, from: &'a str, to: &'a str) -> Self {
+        self.header = Some((from,to));
+        self
+    }
+
+    /// Render a delta against the `before` lines it applies to.  If
+    /// the delta is empty then nothing is rendered (not even the
+    /// header).  This operation will `panic` if the delta is malformed
+    /// with respect to `before`.
+    pub fn render<T:AsRef<str>>(&self, before: &[T], delta: &VecDelta<T>) -> String {
+        let mut out = String::new();
+        self.write(&mut out, before, delta).unwrap();
+        out
+    }
+
+    /// Render a delta against the `before` lines it applies to, writing
+    /// the result to a given output (see `render()`).
+    pub fn write<T:AsRef<str>,W:Write>(&self, out: &mut W, before: &[T], delta: &VecDelta<T>) -> Result {
+        let hunks = delta.hunks(before.len(),self.context);
+        if hunks.is_empty() { return Ok(()); }
+        if let Some((from,to)) = self.header {
+            writeln!(out,"--- {from}")?;
+            writeln!(out,"+++ {to}")?;
+        }
+        for h in &hunks {
+            let (from,to) = (h.before(),h.after());
+            writeln!(out,"@@ -{} +{} @@",Range(from.offset,from.length),Range(to.offset,to.length))?;
+            let mut cursor = from.offset;
+            for (r,data) in h.rewrites() {
+                write_lines(out,' ',&before[cursor..r.offset])?;
+                write_lines(out,'-',&before[r.as_range()])?;
+                write_lines(out,'+',data)?;
+                cursor = end(r);
+            }
+            write_lines(out,' ',&before[cursor..end(&from)])?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for UnifiedFormat<'_> {
+    fn default() -> Self { Self::new() }
+}
+
+/// Determine the end of a given region.
+fn end(r: &Region) -> usize { r.offset + r.length }
+
+/// A range of lines as shown in a hunk header, given as a (zero-based)
+/// start and a length.  Following `diff`, lines are numbered from one,
+/// an empty range is identified by the line before it, and a length of
+/// one is omitted.
+pub(super) struct Range(pub(super) usize,pub(super) usize);
+
+impl core::fmt::Display for Range {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result {
+        match self.1 {
+            0 => write!(f,"{},0",self.0),
+            1 => write!(f,"{}",self.0 + 1),
+            n => write!(f,"{},{n}",self.0 + 1)
+        }
+    }
+}
+
+/// Write a block of lines, each with a given prefix.
+fn write_lines<T:AsRef<str>,W:Write>(out: &mut W, prefix: char, lines: &[T]) -> Result {
+    for line in lines {
+        let line = line.as_ref();
+        out.write_char(prefix)?;
+        out.write_str(line)?;
+        if !line.ends_with('\n') {
+            out.write_str("\n\\ No newline at end of file\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// A patch read from a unified diff (see `parse()`).  This consists
+/// of a delta, along with the lines of the original file which each
+/// hunk expects to find (i.e. its context and removed lines).  The
+/// latter allow a patch to be checked before it is applied, as
+/// `patch` does.
+#[derive(Clone,Debug,PartialEq)]
+pub struct UnifiedPatch {
+    /// The delta described by this patch.
+    delta: VecDelta<String>,
+    /// The region of the original file covered by each hunk, along
+    /// with the lines expected there.
+    expected: Vec<(Region,Vec<String>)>
+}
+
+impl UnifiedPatch {
+    /// Get the delta described by this patch.
+    pub fn delta(&self) -> &VecDelta<String> { &self.delta }
+
+    /// Consume this patch, returning the delta it describes.
+    pub fn into_delta(self) -> VecDelta<String> { self.delta }
+
+    /// Check the lines of a given file match those expected by every
+    /// hunk of this patch.  If not, the first mismatch is reported.  A
+    /// hunk which extends beyond the end of the file is reported as a
+    /// mismatch on the line following the last.
+    pub fn check<T:AsRef<str>>(&self, before: &[T]) -> core::result::Result<(),PatchError> {
+        for (region,lines) in &self.expected {
+            if region.offset + region.length > before.len() {
+                return Err(PatchError::ContextMismatch(before.len() + 1));
+            }
+            for (i,line) in lines.iter().enumerate() {
+                let n = region.offset + i;
+                if before.get(n).map(|l| l.as_ref()) != Some(line.as_str()) {
+                    return Err(PatchError::ContextMismatch(n + 1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply this patch to the lines of a given file, having first
+    /// checked they match those expected (see `check()`).
+    pub fn apply<T:AsRef<str>>(&self, before: &[T]) -> core::result::Result<Vec<String>,PatchError> {
+        self.check(before)?;
+        let mut lines : Vec<String> = before.iter().map(|l| l.as_ref().to_string()).collect();
+        self.delta.transform(&mut lines);
+        Ok(lines)
+    }
+}
+
+/// Errors arising from parsing or applying a unified diff.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PatchError {
+    /// The given line of the patch is malformed (e.g. an invalid hunk
+    /// header, or a hunk which is shorter than its header states).
+    Malformed(usize),
+    /// The given line of the file being patched does not match the
+    /// line expected by the patch.
+    ContextMismatch(usize)
+}
+
+impl core::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result {
+        match self {
+            PatchError::Malformed(n) => write!(f,"malformed patch at line {n}"),
+            PatchError::ContextMismatch(n) => write!(f,"patch does not match file at line {n}")
+        }
+    }
+}
+
+impl core::error::Error for PatchError {}
+
+/// Parse a unified diff, such as produced by `diff -u` or `git diff`,
+/// into a patch.  As for `UnifiedFormat`, each line of the resulting
+/// delta includes its terminator unless marked with `\ No newline at
+/// end of file`.  Lines outside of hunks (e.g. the `---` and `+++`
+/// header lines) are ignored.  Only a single file is supported, and
+/// hunks must be given in order.  Line numbers reported in errors
+/// start from one.
+pub fn parse(text: &str) -> core::result::Result<UnifiedPatch,PatchError> {
+    let lines : Vec<&str> = text.split_inclusive('\n').collect();
+    let mut hunks : Vec<(Region,Vec<String>)> = Vec::new();
+    let mut expected : Vec<(Region,Vec<String>)> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !lines[i].starts_with("@@ ") { i += 1; continue; }
+        let (start,mut old,mut new) = parse_header(lines[i]).ok_or(PatchError::Malformed(i + 1))?;
+        let previous = expected.last().map_or(0,|(r,_)| r.offset + r.length);
+        if start < previous { return Err(PatchError::Malformed(i + 1)); }
+        let mut context = Vec::new();
+        // Kind of the last line, to account for a missing newline
+        let mut last = None;
+        let mut open = false;
+        i += 1;
+        while old > 0 || new > 0 || (i < lines.len() && lines[i].starts_with('\\')) {
+            let line = *lines.get(i).ok_or(PatchError::Malformed(i + 1))?;
+            let (kind,content) = match line.chars().next() {
+                // Some tools strip the space from empty context lines
+                Some('\n') => (' ',line),
+                Some(c) => (c,&line[c.len_utf8()..]),
+                None => (' ',line)
+            };
+            let pos = start + context.len();
+            match kind {
+                ' ' if old > 0 && new > 0 => {
+                    old -= 1;
+                    new -= 1;
+                    open = false;
+                    context.push(content.to_string());
+                }
+                '-' if old > 0 => {
+                    old -= 1;
+                    if !open { hunks.push((Region::new(pos,0),Vec::new())); open = true; }
+                    let (r,_) = hunks.last_mut().unwrap();
+                    r.length += 1;
+                    context.push(content.to_string());
+                }
+                '+' if new > 0 => {
+                    new -= 1;
+                    if !open { hunks.push((Region::new(pos,0),Vec::new())); open = true; }
+                    let (_,data) = hunks.last_mut().unwrap();
+                    data.push(content.to_string());
+                }
+                '\\' => {
+                    let line = match last.take() {
+                        Some('+') => hunks.last_mut().and_then(|(_,d)| d.last_mut()),
+                        Some(_) => context.last_mut(),
+                        None => None
+                    };
+                    match line {
+                        Some(l) if l.ends_with('\n') => { l.pop(); }
+                        _ => return Err(PatchError::Malformed(i + 1))
+                    }
+                    i += 1;
+                    continue;
+                }
+                _ => return Err(PatchError::Malformed(i + 1))
+            }
+            last = Some(kind);
+            i += 1;
+        }
+        expected.push((Region::new(start,context.len()),context));
+    }
+    let delta = VecDelta::from_source(hunks.iter().map(|(r,d)| (*r,d.as_slice())));
+    Ok(UnifiedPatch{delta,expected})
+}
+
+/// Parse a hunk header (e.g. `@@ -1,3 +1,4 @@`), returning the
+/// (zero-based) start of the hunk in the original file, and the number
+/// of lines it covers in the original and updated files.
+pub(super) fn parse_header(line: &str) -> Option<(usize,usize,usize)> {
+    let mut parts = line.strip_prefix("@@ ")?.split(' ');
+    let (start,old) = parse_range(parts.next()?.strip_prefix('-')?)?;
+    let (_,new) = parse_range(parts.next()?.strip_prefix('+')?)?;
+    if parts.next()?.trim_end() != "@@" { return None; }
+    // An empty range is identified by the line before it
+    let start = if old == 0 { start } else { start.checked_sub(1)? };
+    Some((start,old,new))
+}
+
+/// Parse a range within a hunk header (e.g. `1,3`), where a missing
+/// length means one.
+fn parse_range(range: &str) -> Option<(usize,usize)> {
+    match range.split_once(',') {
+        Some((start,len)) => Some((start.parse().ok()?,len.parse().ok()?)),
+        None => Some((range.parse().ok()?,1))
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod unified_tests {
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,VecDelta};
+    use super::{PatchError,UnifiedFormat,parse};
+
+    fn lines(text: &str) -> Vec<&str> { text.split_inclusive('\n').collect() }
+
+    #[test]
+    fn unified_test_01() {
+        let before = lines("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n");
+        let after = lines("a\nB\nc\nd\ne\nf\ng\nh\nj\nk\n");
+        let text = UnifiedFormat::new().header("a/x","b/x").render(&before,&before.diff(&after));
+        assert_eq!(text,"--- a/x\n+++ b/x\n@@ -1,10 +1,10 @@\n a\n-b\n+B\n c\n d\n e\n f\n g\n h\n-i\n j\n+k\n");
+        // Less context splits hunks
+        let text = UnifiedFormat::new().context(1).render(&before,&before.diff(&after));
+        assert_eq!(text,"@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -8,3 +8,3 @@\n h\n-i\n j\n+k\n");
+    }
+
+    #[test]
+    fn unified_test_02() {
+        // Insertion into, and deletion of, an entire file
+        let after = lines("a\nb\n");
+        let text = UnifiedFormat::new().render(&[],&[].diff(&after));
+        assert_eq!(text,"@@ -0,0 +1,2 @@\n+a\n+b\n");
+        let text = UnifiedFormat::new().render(&after,&after.diff(&[]));
+        assert_eq!(text,"@@ -1,2 +0,0 @@\n-a\n-b\n");
+        // Nothing to render
+        assert_eq!(UnifiedFormat::new().render(&after,&VecDelta::new()),"");
+    }
+
+    #[test]
+    fn unified_test_03() {
+        // Missing newline at end of file
+        let before = lines("a\nb");
+        let after = lines("a\nb\n");
+        let text = UnifiedFormat::new().render(&before,&before.diff(&after));
+        assert_eq!(text,"@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n");
+    }
+
+    #[test]
+    fn unified_test_04() {
+        // Parse output of git diff
+        let text = "diff --git a/x b/x\nindex 1234567..89abcde 100644\n--- a/x\n+++ b/x\n\
+                    @@ -1,3 +1,3 @@ fn main\n a\n-b\n+B\n c\n@@ -8,3 +8,3 @@\n h\n-i\n j\n+k\n";
+        let patch = parse(text).unwrap();
+        let before = lines("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n");
+        let after = lines("a\nB\nc\nd\ne\nf\ng\nh\nj\nk\n");
+        assert_eq!(patch.delta().len(),3);
+        assert_eq!(patch.apply(&before).unwrap(),after);
+        // Context must match
+        let other = lines("a\nb\nc\nd\ne\nf\ng\nX\ni\nj\n");
+        assert_eq!(patch.apply(&other),Err(PatchError::ContextMismatch(8)));
+        assert_eq!(patch.check(&before[..9]),Err(PatchError::ContextMismatch(10)));
+        // Hunk beyond the end of the file
+        let patch = parse("@@ -10,0 +1 @@\n+3\n").unwrap();
+        assert_eq!(patch.apply(&["1"]),Err(PatchError::ContextMismatch(2)));
+        // Hunk line starting with a multibyte character
+        assert_eq!(parse("@@ -1 +1 @@\n\u{e9}\n"),Err(PatchError::Malformed(2)));
+    }
+
+    #[test]
+    fn unified_test_05() {
+        // Round trip for a range of inputs
+        let texts = ["","a\n","a\nb","a\nb\n","b\na\nb\n","a\nc\nb\nc\na\n","c\nc\nc\nb\na\na\nb\n"];
+        for a in texts {
+            for b in texts {
+                let (before,after) = (lines(a),lines(b));
+                let delta = before.diff(&after);
+                for n in 0..3 {
+                    let text = UnifiedFormat::new().context(n).header("a","b").render(&before,&delta);
+                    let patch = parse(&text).unwrap();
+                    for (rw1,rw2) in patch.delta().iter().zip(delta.iter()) {
+                        assert_eq!(rw1.region(),rw2.region());
+                        assert_eq!(rw1.data(),rw2.data());
+                    }
+                    assert_eq!(patch.delta().len(),delta.len());
+                    assert_eq!(patch.apply(&before).unwrap(),after);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unified_test_06() {
+        assert_eq!(parse("@@ -1,2 +1 @@\n a\n"),Err(PatchError::Malformed(3)));
+        assert_eq!(parse("@@ -1,x +1 @@\n a\n"),Err(PatchError::Malformed(1)));
+        assert_eq!(parse("@@ -1 +1 @@\n?a\n"),Err(PatchError::Malformed(2)));
+        assert_eq!(parse("@@ -3 +3 @@\n-a\n+b\n@@ -1 +1 @@\n-a\n+b\n"),Err(PatchError::Malformed(4)));
+    }
+}
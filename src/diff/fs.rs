@@ -0,0 +1,269 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path,PathBuf};
+use super::{DeltaError,Diff,TryTransform,VecDelta};
+
+/// A delta between two directory trees, described in terms of the
+/// (relative) paths of files which are _added_, _removed_ or
+/// _modified_.  The contents of modified files are not diffed until
+/// requested (see `content_delta()`), since this is relatively
+/// expensive and is not always needed (e.g. when only listing what has
+/// changed).  Only regular files are considered, such that symbolic
+/// links and empty directories are ignored.  For example:
+///
+/// ```no_run
+/// use delta_inc::diff::TryTransform;
+/// use delta_inc::diff::fs::{Directory,diff_dirs};
+///
+/// let delta = diff_dirs("v1","v2").unwrap();
+/// for path in delta.modified() {
+///     println!("{}: {} rewrites",path.display(),delta.content_delta(path).unwrap().len());
+/// }
+/// // Bring a copy of v1 up to date
+/// Directory::new("copy").try_transform(&delta).unwrap();
+/// ```
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct DirDelta {
+    /// Root of the original tree.
+    source: PathBuf,
+    /// Root of the updated tree.
+    target: PathBuf,
+    /// Files present only in the updated tree.
+    added: Vec<PathBuf>,
+    /// Files present only in the original tree.
+    removed: Vec<PathBuf>,
+    /// Files present in both trees, but with different contents.
+    modified: Vec<PathBuf>
+}
+
+impl DirDelta {
+    /// Get the root of the original tree.
+    pub fn source(&self) -> &Path { &self.source }
+
+    /// Get the root of the updated tree.
+    pub fn target(&self) -> &Path { &self.target }
+
+    /// Get the total number of files affected by this delta.
+    pub fn len(&self) -> usize { self.added.len() + self.removed.len() + self.modified.len() }
+
+    /// Check whether this delta affects any files or not.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Get the paths (relative to the root) of files which are added.
+    pub fn added(&self) -> &[PathBuf] { &self.added }
+
+    /// Get the paths (relative to the root) of files which are removed.
+    pub fn removed(&self) -> &[PathBuf] { &self.removed }
+
+    /// Get the paths (relative to the root) of files which are modified.
+    pub fn modified(&self) -> &[PathBuf] { &self.modified }
+
+    /// Compute the delta between the original and updated contents of
+    /// a given file, whose path is relative to the root.  Both versions
+    /// of the file are read afresh on every call.
+    pub fn content_delta<P:AsRef<Path>>(&self, path: P) -> io::Result<VecDelta<u8>> {
+        let path = path.as_ref();
+        let before = fs::read(self.source.join(path))?;
+        let after = fs::read(self.target.join(path))?;
+        Ok(before.diff(&after))
+    }
+
+    /// Iterate the modified files, along with their content deltas
+    /// (which are computed as the iterator advances).
+    pub fn content_deltas(&self) -> impl Iterator<Item=(&Path,io::Result<VecDelta<u8>>)> {
+        self.modified.iter().map(|p| (p.as_path(),self.content_delta(p)))
+    }
+}
+
+/// Compute the delta between two directory trees, by walking both and
+/// comparing the contents of the files they have in common.
+pub fn diff_dirs<P:AsRef<Path>,Q:AsRef<Path>>(lhs: P, rhs: Q) -> io::Result<DirDelta> {
+    let (source,target) = (lhs.as_ref().to_path_buf(),rhs.as_ref().to_path_buf());
+    let (before,after) = (files_of(&source)?,files_of(&target)?);
+    let mut delta = DirDelta{source, target, added: Vec::new(), removed: Vec::new(), modified: Vec::new()};
+    let (mut lhs,mut rhs) = (before.into_iter().peekable(),after.into_iter().peekable());
+    loop {
+        let ord = match (lhs.peek(),rhs.peek()) {
+            (None,None) => break,
+            (Some(_),None) => Ordering::Less,
+            (None,Some(_)) => Ordering::Greater,
+            (Some(l),Some(r)) => l.cmp(r)
+        };
+        match ord {
+            Ordering::Less => delta.removed.extend(lhs.next()),
+            Ordering::Greater => delta.added.extend(rhs.next()),
+            Ordering::Equal => {
+                let path = lhs.next().unwrap();
+                rhs.next();
+                if !same_contents(&delta.source.join(&path),&delta.target.join(&path))? {
+                    delta.modified.push(path);
+                }
+            }
+        }
+    }
+    Ok(delta)
+}
+
+// ===================================================================
+// Directory
+// ===================================================================
+
+/// A directory on disk to which a `DirDelta` can be applied.  Applying
+/// a delta removes the removed files, copies the added files from the
+/// updated tree, and patches the modified files using their content
+/// deltas.  Directories are created as needed, and removed when they
+/// are emptied.  Note that the directory is expected to match the
+/// original tree and, if an error arises, it may be left partially
+/// updated.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Directory {
+    path: PathBuf
+}
+
+impl Directory {
+    /// Construct a `Directory` for a given path.
+    pub fn new<P:AsRef<Path>>(path: P) -> Self { Directory{path: path.as_ref().to_path_buf()} }
+
+    /// Get the path of this directory.
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+impl TryTransform for Directory {
+    type Delta = DirDelta;
+    type Error = FsError;
+
+    fn try_transform(&mut self, d: &DirDelta) -> Result<(),FsError> {
+        for path in &d.removed {
+            let file = self.path.join(path);
+            fs::remove_file(&file)?;
+            // Remove any directories which are now empty
+            for dir in file.ancestors().skip(1).take_while(|&a| a != self.path) {
+                if fs::read_dir(dir)?.next().is_some() { break; }
+                fs::remove_dir(dir)?;
+            }
+        }
+        for path in &d.modified {
+            let file = self.path.join(path);
+            let mut bytes = fs::read(&file)?;
+            bytes.try_transform(&d.content_delta(path)?).map_err(|e| FsError::Delta(path.clone(),e))?;
+            fs::write(&file,bytes)?;
+        }
+        for path in &d.added {
+            let file = self.path.join(path);
+            if let Some(dir) = file.parent() { fs::create_dir_all(dir)?; }
+            fs::copy(d.target.join(path),&file)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error arising when applying a `DirDelta` to a directory.
+#[derive(Debug)]
+pub enum FsError {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The content delta of a file (at the given relative path) could
+    /// not be applied.
+    Delta(PathBuf,DeltaError)
+}
+
+impl core::fmt::Display for FsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FsError::Io(e) => write!(f,"{e}"),
+            FsError::Delta(p,e) => write!(f,"{}: {e}",p.display())
+        }
+    }
+}
+
+impl core::error::Error for FsError {}
+
+impl From<io::Error> for FsError {
+    fn from(e: io::Error) -> Self { FsError::Io(e) }
+}
+
+// ===================================================================
+// Helpers
+// ===================================================================
+
+/// Determine the (relative) paths of all regular files within a given
+/// directory tree.
+fn files_of(root: &Path) -> io::Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    let mut dirs = alloc::vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(root.join(&dir))? {
+            let entry = entry?;
+            let kind = entry.file_type()?;
+            let path = dir.join(entry.file_name());
+            if kind.is_dir() { dirs.push(path); } else if kind.is_file() { files.insert(path); }
+        }
+    }
+    Ok(files)
+}
+
+/// Check whether two files have the same contents.
+fn same_contents(lhs: &Path, rhs: &Path) -> io::Result<bool> {
+    if fs::metadata(lhs)?.len() != fs::metadata(rhs)?.len() { return Ok(false); }
+    Ok(fs::read(lhs)? == fs::read(rhs)?)
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod fs_tests {
+    use alloc::vec::Vec;
+    use std::fs;
+    use std::path::{Path,PathBuf};
+    use crate::diff::TryTransform;
+    use super::{Directory,diff_dirs};
+
+    /// Create a fresh directory tree containing the given files.
+    fn tree(name: &str, files: &[(&str,&str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(std::format!("delta_inc_fs_{}_{name}",std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        for (path,contents) in files {
+            let file = root.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file,contents).unwrap();
+        }
+        root
+    }
+
+    fn paths(ps: &[&str]) -> Vec<PathBuf> { ps.iter().map(|p| Path::new(p).to_path_buf()).collect() }
+
+    #[test]
+    fn fs_test_01() {
+        let v1 = tree("01a",&[("a.txt","hello world"),("src/b.rs","fn b() {}"),("src/c.rs","fn c() {}"),("old/d","d")]);
+        let v2 = tree("01b",&[("a.txt","hello there world"),("src/b.rs","fn b() {}"),("src/e/f.rs","fn f() {}")]);
+        let delta = diff_dirs(&v1,&v2).unwrap();
+        assert_eq!(delta.added(),paths(&["src/e/f.rs"]));
+        assert_eq!(delta.removed(),paths(&["old/d","src/c.rs"]));
+        assert_eq!(delta.modified(),paths(&["a.txt"]));
+        assert_eq!(delta.content_delta("a.txt").unwrap().len(),1);
+        // Apply to a copy of the original
+        let copy = tree("01c",&[("a.txt","hello world"),("src/b.rs","fn b() {}"),("src/c.rs","fn c() {}"),("old/d","d")]);
+        Directory::new(&copy).try_transform(&delta).unwrap();
+        assert!(diff_dirs(&copy,&v2).unwrap().is_empty());
+        assert!(!copy.join("old").exists());
+        for dir in [v1,v2,copy] { fs::remove_dir_all(dir).unwrap(); }
+    }
+
+    #[test]
+    fn fs_test_02() {
+        let v1 = tree("02a",&[("a","abc")]);
+        let v2 = tree("02b",&[("a","abcdef")]);
+        let delta = diff_dirs(&v1,&v2).unwrap();
+        assert_eq!(delta.content_deltas().count(),1);
+        // Applying to a directory which doesn't match the original
+        let copy = tree("02c",&[("a","")]);
+        assert!(Directory::new(&copy).try_transform(&delta).is_err());
+        for dir in [v1,v2,copy] { fs::remove_dir_all(dir).unwrap(); }
+    }
+}
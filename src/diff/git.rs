@@ -0,0 +1,216 @@
+use alloc::vec::Vec;
+use crate::util::Region;
+use super::VecDelta;
+use super::vcdiff::Builder;
+
+/// Largest size of a single copy instruction emitted by the encoder.
+/// Larger sizes are permitted by the format, but this limit matches
+/// that used by git itself.
+const MAX_COPY : usize = 0x10000;
+
+/// Largest size of a single insert instruction.
+const MAX_INSERT : usize = 0x7F;
+
+/// Errors arising from decoding a git delta.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum GitDeltaError {
+    /// The delta ended unexpectedly.
+    Truncated,
+    /// The delta was computed against a base of the given size, which
+    /// differs from that supplied.
+    BaseMismatch(usize),
+    /// The delta is malformed at the given byte offset (e.g. it uses
+    /// the reserved instruction, or a copy is out of range).
+    Malformed(usize)
+}
+
+impl core::fmt::Display for GitDeltaError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            GitDeltaError::Truncated => write!(f, "unexpected end of delta"),
+            GitDeltaError::BaseMismatch(n) => write!(f, "delta expects base of {n} bytes"),
+            GitDeltaError::Malformed(pos) => write!(f, "malformed delta at byte {pos}")
+        }
+    }
+}
+
+impl core::error::Error for GitDeltaError {}
+
+/// Encode a delta over bytes in git's packfile delta format, given the
+/// length of the base it applies to.  Unchanged bytes become copy
+/// instructions from the base, and replacement data becomes insert
+/// instructions.  This will `panic` if the delta extends beyond the
+/// base, or if the base is larger than 4GiB.
+pub fn encode_git_delta(delta: &VecDelta<u8>, base_len: usize) -> Vec<u8> {
+    assert!(u32::try_from(base_len).is_ok(), "base too large");
+    let mut out = Vec::new();
+    let mut insts = Vec::new();
+    let mut cursor = 0;
+    let mut target_len = 0;
+    for (r,bytes) in delta.iter_source().chain([(Region::new(base_len,0),&[][..])]) {
+        assert!(r.offset >= cursor && r.offset + r.length <= base_len,"delta out of bounds");
+        let mut pos = cursor;
+        while pos < r.offset {
+            let size = MAX_COPY.min(r.offset - pos);
+            push_copy(&mut insts, pos, size);
+            pos += size;
+        }
+        for chunk in bytes.chunks(MAX_INSERT) {
+            insts.push(chunk.len() as u8);
+            insts.extend_from_slice(chunk);
+        }
+        target_len += r.offset - cursor + bytes.len();
+        cursor = r.offset + r.length;
+    }
+    write_size(&mut out, base_len);
+    write_size(&mut out, target_len);
+    out.extend(insts);
+    out
+}
+
+/// Append a copy instruction, where only the non-zero bytes of the
+/// offset and size are included (as indicated by the low and high
+/// bits of the opcode respectively).  A size of `0x10000` is encoded
+/// as zero.
+fn push_copy(insts: &mut Vec<u8>, offset: usize, size: usize) {
+    let at = insts.len();
+    let mut op = 0x80;
+    insts.push(op);
+    for i in 0..4 {
+        let b = (offset >> (8 * i)) as u8;
+        if b != 0 { op |= 1 << i; insts.push(b); }
+    }
+    for i in 0..3 {
+        let b = ((size & 0xFFFF) >> (8 * i)) as u8;
+        if b != 0 { op |= 0x10 << i; insts.push(b); }
+    }
+    insts[at] = op;
+}
+
+/// Decode a git packfile delta against the base it was computed from,
+/// producing the equivalent delta.  This is obtained by treating copy
+/// instructions which read the base in order as unchanged bytes, and
+/// everything else as replacement data.
+pub fn decode_git_delta(base: &[u8], data: &[u8]) -> Result<VecDelta<u8>,GitDeltaError> {
+    let mut pos = 0;
+    let base_len = read_size(data, &mut pos)?;
+    if base_len != base.len() { return Err(GitDeltaError::BaseMismatch(base_len)); }
+    let target_len = read_size(data, &mut pos)?;
+    let mut builder = Builder::new(base);
+    while pos < data.len() {
+        let start = pos;
+        let op = data[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            let mut offset = 0;
+            for i in 0..4 {
+                if op & (1 << i) != 0 { offset |= (byte(data, &mut pos)? as usize) << (8 * i); }
+            }
+            let mut size = 0;
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 { size |= (byte(data, &mut pos)? as usize) << (8 * i); }
+            }
+            if size == 0 { size = MAX_COPY; }
+            if offset + size > base.len() { return Err(GitDeltaError::Malformed(start)); }
+            builder.copy_source(offset, size);
+        } else if op != 0 {
+            let bytes = data.get(pos..pos + op as usize).ok_or(GitDeltaError::Truncated)?;
+            builder.literal(bytes);
+            pos += op as usize;
+        } else {
+            return Err(GitDeltaError::Malformed(start));
+        }
+    }
+    if builder.target().len() != target_len { return Err(GitDeltaError::Malformed(pos)); }
+    Ok(builder.finish())
+}
+
+/// Write a size as a little-endian sequence of 7-bit groups, where the
+/// high bit indicates whether another group follows.
+fn write_size(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push((n & 0x7F) as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn read_size(data: &[u8], pos: &mut usize) -> Result<usize,GitDeltaError> {
+    let start = *pos;
+    let mut n = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let b = byte(data, pos)?;
+        n |= ((b & 0x7F) as usize) << shift;
+        if b & 0x80 == 0 { return Ok(n); }
+    }
+    Err(GitDeltaError::Malformed(start))
+}
+
+fn byte(data: &[u8], pos: &mut usize) -> Result<u8,GitDeltaError> {
+    let b = *data.get(*pos).ok_or(GitDeltaError::Truncated)?;
+    *pos += 1;
+    Ok(b)
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod git_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::Diff;
+    use super::{GitDeltaError,decode_git_delta,encode_git_delta};
+
+    #[test]
+    fn git_test_01() {
+        let (base,target) = (b"hello world",b"hello there world");
+        let delta = base.diff(target);
+        let data = encode_git_delta(&delta,base.len());
+        // Copy 6 from 0, insert "there " and copy 5 from 6
+        let mut expected = vec![0x0B,0x11,0x90,0x06,0x06];
+        expected.extend(b"there ".iter().chain(&[0x91,0x06,0x05]));
+        assert_eq!(data,expected);
+        assert_eq!(decode_git_delta(base,&data),Ok(delta));
+    }
+
+    #[test]
+    fn git_test_02() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<u8>> = (0..40).map(|i| (0..i).map(|j| ((j * i + 3) % 5) as u8).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let delta = a.diff(b);
+                let data = encode_git_delta(&delta,a.len());
+                assert_eq!(decode_git_delta(a,&data).unwrap(),delta);
+            }
+        }
+    }
+
+    #[test]
+    fn git_test_03() {
+        // Large copies and inserts are split
+        let base : Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut target = base.clone();
+        target.splice(100..100, [7u8;300]);
+        let delta = base.diff(&target);
+        let data = encode_git_delta(&delta,base.len());
+        assert_eq!(decode_git_delta(&base,&data).unwrap().apply(&base),target);
+        // Copies out of order become replacement data
+        let data = [0x04,0x06,0x91,0x02,0x02,0x02,b'X',b'Y',0x90,0x02];
+        let delta = decode_git_delta(b"abcd",&data).unwrap();
+        assert_eq!(delta.apply(b"abcd"),b"cdXYab");
+    }
+
+    #[test]
+    fn git_test_04() {
+        assert_eq!(decode_git_delta(b"abc",&[0x04,0x00]),Err(GitDeltaError::BaseMismatch(4)));
+        assert_eq!(decode_git_delta(b"",&[0x00,0x02,0x02,b'X']),Err(GitDeltaError::Truncated));
+        assert_eq!(decode_git_delta(b"",&[0x00,0x00,0x00]),Err(GitDeltaError::Malformed(2)));
+        // Copy beyond end of base
+        assert_eq!(decode_git_delta(b"ab",&[0x02,0x02,0x91,0x01,0x02]),Err(GitDeltaError::Malformed(2)));
+        // Result size mismatch
+        assert_eq!(decode_git_delta(b"ab",&[0x02,0x03,0x90,0x02]),Err(GitDeltaError::Malformed(4)));
+    }
+}
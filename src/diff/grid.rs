@@ -0,0 +1,269 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use super::{Diff,Transform};
+
+/// A two-dimensional grid of cells (e.g. a tile map or an image),
+/// stored in row-major order.  Diffing two grids identifies the
+/// _rectangular_ regions which have changed, rather than treating the
+/// grid as a flat sequence (where a change to a single column affects
+/// every row, leading to large and fragmented deltas).  For example:
+///
+/// ```
+/// use delta_inc::diff::{Diff,Grid,Transform};
+///
+/// let before = Grid::new(4,3,'.');
+/// let mut after = before.clone();
+/// after.set(1,0,'#');
+/// after.set(1,1,'#');
+/// let delta = before.diff(&after);
+/// assert_eq!(delta.regions().len(),1);
+/// let mut grid = before.clone();
+/// grid.transform(&delta);
+/// assert_eq!(grid,after);
+/// ```
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>
+}
+
+impl<T> Grid<T> {
+    /// Construct a grid of given dimensions, where every cell holds a
+    /// given value.
+    pub fn new(width: usize, height: usize, fill: T) -> Self where T:Clone {
+        Grid{width, height, cells: vec![fill;width*height]}
+    }
+
+    /// Construct a grid of a given width from cells in row-major
+    /// order.  This will `panic` if the number of cells is not a
+    /// multiple of the width.
+    pub fn from_vec(width: usize, cells: Vec<T>) -> Self {
+        let height = cells.len().checked_div(width).unwrap_or(0);
+        assert_eq!(width*height,cells.len(),"cells do not form a grid of width {width}");
+        Grid{width, height, cells}
+    }
+
+    /// Get the number of columns in this grid.
+    pub fn width(&self) -> usize { self.width }
+
+    /// Get the number of rows in this grid.
+    pub fn height(&self) -> usize { self.height }
+
+    /// Get the cell at a given column and row (if it exists).
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if x < self.width && y < self.height { Some(&self.cells[y*self.width+x]) } else { None }
+    }
+
+    /// Update the cell at a given column and row.  This will `panic` if
+    /// the cell does not exist.
+    pub fn set(&mut self, x: usize, y: usize, item: T) {
+        assert!(x < self.width && y < self.height,"cell ({x},{y}) out of bounds");
+        self.cells[y*self.width+x] = item;
+    }
+
+    /// Get the cells of a given row.
+    pub fn row(&self, y: usize) -> &[T] { &self.cells[y*self.width..(y+1)*self.width] }
+
+    /// Get all cells of this grid in row-major order.
+    pub fn as_slice(&self) -> &[T] { &self.cells }
+}
+
+// ===================================================================
+// GridDelta
+// ===================================================================
+
+/// A rectangular block of cells within a grid, whose top-left corner is
+/// at a given column and row.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct GridRegion<T> {
+    /// Column of the top-left cell.
+    pub x: usize,
+    /// Row of the top-left cell.
+    pub y: usize,
+    /// Number of columns covered by this region.
+    pub width: usize,
+    /// Cells of this region in row-major order.
+    pub cells: Vec<T>
+}
+
+impl<T> GridRegion<T> {
+    /// Get the number of rows covered by this region.
+    pub fn height(&self) -> usize { self.cells.len().checked_div(self.width).unwrap_or(0) }
+}
+
+/// A delta between two grids, consisting of the dimensions of the
+/// updated grid and a set of disjoint rectangular regions which are
+/// overwritten.  When the dimensions change, the cells which remain
+/// keep their column and row, and any new cells are covered by the
+/// regions.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct GridDelta<T> {
+    width: usize,
+    height: usize,
+    regions: Vec<GridRegion<T>>
+}
+
+impl<T> GridDelta<T> {
+    /// Get the dimensions (i.e. width and height) of the grid after
+    /// this delta is applied.
+    pub fn dimensions(&self) -> (usize,usize) { (self.width,self.height) }
+
+    /// Get the regions overwritten by this delta.
+    pub fn regions(&self) -> &[GridRegion<T>] { &self.regions }
+
+    /// Get the total number of cells overwritten by this delta.
+    pub fn len(&self) -> usize { self.regions.iter().map(|r| r.cells.len()).sum() }
+
+    /// Check whether this delta overwrites any cells or not.  Note that
+    /// an empty delta may still change the dimensions of a grid (e.g.
+    /// by removing rows).
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+}
+
+/// Diffing of grids determines the changed regions by finding groups of
+/// adjacent changed cells (including diagonally), and then merging the
+/// bounding rectangles of these groups until none overlap.
+impl<T:Clone+PartialEq> Diff for Grid<T> {
+    type Delta = GridDelta<T>;
+
+    fn diff(&self, other: &Grid<T>) -> GridDelta<T> {
+        let (w,h) = (other.width,other.height);
+        let changed : Vec<bool> = (0..w*h).map(|i| self.get(i%w,i/w) != Some(&other.cells[i])).collect();
+        let regions = changed_rectangles(w,h,changed).into_iter().map(|[x0,y0,x1,y1]| {
+            let cells = (y0..y1).flat_map(|y| other.row(y)[x0..x1].iter().cloned()).collect();
+            GridRegion{x: x0, y: y0, width: x1-x0, cells}
+        }).collect();
+        GridDelta{width: w, height: h, regions}
+    }
+}
+
+/// Applying a `GridDelta` will `panic` if it is malformed with respect
+/// to the grid (e.g. a region lies outside the updated grid, or a new
+/// cell is not covered by any region).
+impl<T:Clone> Transform for Grid<T> {
+    type Delta = GridDelta<T>;
+
+    fn transform(&mut self, d: &GridDelta<T>) {
+        let (w,h) = (d.width,d.height);
+        let mut cells : Vec<Option<T>> = if (w,h) == (self.width,self.height) {
+            core::mem::take(&mut self.cells).into_iter().map(Some).collect()
+        } else {
+            (0..w*h).map(|i| self.get(i%w,i/w).cloned()).collect()
+        };
+        for r in &d.regions {
+            assert!(r.x + r.width <= w && r.y + r.height() <= h,"region out of bounds");
+            for (i,c) in r.cells.iter().enumerate() {
+                cells[(r.y + i/r.width)*w + r.x + i%r.width] = Some(c.clone());
+            }
+        }
+        self.width = w;
+        self.height = h;
+        self.cells = cells.into_iter().map(|c| c.expect("cell not covered by delta")).collect();
+    }
+}
+
+// ===================================================================
+// Helpers
+// ===================================================================
+
+/// Determine a set of disjoint rectangles (as `[x0,y0,x1,y1]` with
+/// exclusive upper bounds) which cover every changed cell of a grid.
+fn changed_rectangles(width: usize, height: usize, mut changed: Vec<bool>) -> Vec<[usize;4]> {
+    let mut rects : Vec<[usize;4]> = Vec::new();
+    // Bounding rectangles of groups of adjacent cells
+    for start in 0..changed.len() {
+        if !changed[start] { continue; }
+        changed[start] = false;
+        let mut rect = [start%width,start/width,start%width+1,start/width+1];
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            let (x,y) = (i%width,i/width);
+            rect = union(rect,[x,y,x+1,y+1]);
+            for ny in y.saturating_sub(1)..(y+2).min(height) {
+                for nx in x.saturating_sub(1)..(x+2).min(width) {
+                    let j = ny*width+nx;
+                    if changed[j] { changed[j] = false; stack.push(j); }
+                }
+            }
+        }
+        rects.push(rect);
+    }
+    // Merge overlapping rectangles until none remain
+    let mut merged = true;
+    while merged {
+        merged = false;
+        let mut i = 0;
+        while i < rects.len() {
+            if let Some(j) = (i+1..rects.len()).find(|&j| overlaps(rects[i],rects[j])) {
+                let r = rects.swap_remove(j);
+                rects[i] = union(rects[i],r);
+                merged = true;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    rects.sort();
+    rects
+}
+
+fn union(a: [usize;4], b: [usize;4]) -> [usize;4] {
+    [a[0].min(b[0]),a[1].min(b[1]),a[2].max(b[2]),a[3].max(b[3])]
+}
+
+fn overlaps(a: [usize;4], b: [usize;4]) -> bool {
+    a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod grid_tests {
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,Transform};
+    use super::Grid;
+
+    fn grids() -> Vec<Grid<usize>> {
+        (0..12).map(|i| {
+            let w = i % 4 + 1;
+            Grid::from_vec(w,(0..w*(i%3+1)).map(|j| (j*i+3)%5).collect())
+        }).collect()
+    }
+
+    #[test]
+    fn grid_test_01() {
+        for a in grids() {
+            for b in grids() {
+                let delta = a.diff(&b);
+                if a == b { assert!(delta.is_empty()); }
+                let mut g = a.clone();
+                g.transform(&delta);
+                assert_eq!(g,b);
+            }
+        }
+    }
+
+    #[test]
+    fn grid_test_02() {
+        // Changing a column gives a single narrow region
+        let before = Grid::new(8,8,0);
+        let mut after = before.clone();
+        for y in 0..8 { after.set(5,y,1); }
+        let delta = before.diff(&after);
+        assert_eq!(delta.len(),8);
+        assert_eq!((delta.regions()[0].x,delta.regions()[0].width),(5,1));
+        // Separate changes give separate regions
+        let mut after = before.clone();
+        after.set(0,0,1);
+        after.set(7,7,1);
+        after.set(6,6,1);
+        let delta = before.diff(&after);
+        assert_eq!(delta.regions().iter().map(|r| (r.x,r.y,r.width,r.height())).collect::<Vec<_>>(),[(0,0,1,1),(6,6,2,2)]);
+        // Growing the grid covers the new cells
+        let delta = Grid::new(2,2,0).diff(&Grid::new(3,2,0));
+        assert_eq!(delta.len(),2);
+    }
+}
@@ -0,0 +1,153 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use super::{VecDelta,diff_trimmed};
+
+/// Compute a delta between two slices using Hirschberg's linear space
+/// algorithm for the _longest common subsequence_ (see
+/// `hirschberg_subsequence()`).
+pub fn diff_hirschberg<T:Clone+PartialEq>(lhs: &[T], rhs: &[T]) -> VecDelta<T> {
+    diff_trimmed(lhs, rhs, hirschberg_subsequence)
+}
+
+/// Determine a longest common subsequence of two slices using
+/// Hirschberg's divide-and-conquer algorithm.  This produces a mapping
+/// from elements in `lhs` to elements in `rhs` in the same form as
+/// `longest_common_subsequence()`.  However, rather than constructing
+/// the full `m*n` table, it repeatedly splits `lhs` in half and uses
+/// two rows of the table (computed forwards and backwards) to find
+/// where the optimal path crosses the split.  Thus, whilst the time
+/// taken remains `O(mn)`, the working space required (beyond the
+/// mapping itself) is only `O(min(m,n))`.
+///
+/// # References
+///
+/// * _A Linear Space Algorithm for Computing Maximal Common
+///   Subsequences_, D. S. Hirschberg.  Communications of the ACM,
+///   1975.
+pub fn hirschberg_subsequence<T:PartialEq>(lhs: &[T], rhs: &[T]) -> Vec<Option<usize>> {
+    if rhs.len() > lhs.len() {
+        // Rows are sized by the right-hand side, so flip the problem
+        // to keep these as short as possible.
+        let flipped = hirschberg_subsequence(rhs,lhs);
+        let mut res = vec![None;lhs.len()];
+        for (j,m) in flipped.into_iter().enumerate() {
+            if let Some(i) = m { res[i] = Some(j); }
+        }
+        res
+    } else {
+        let mut res = vec![None;lhs.len()];
+        split(lhs, rhs, 0, 0, &mut res);
+        res
+    }
+}
+
+/// Solve the subproblem for `lhs` and `rhs`, which begin at the given
+/// offsets within the original slices.
+fn split<T:PartialEq>(lhs: &[T], rhs: &[T], loff: usize, roff: usize, res: &mut [Option<usize>]) {
+    if lhs.is_empty() || rhs.is_empty() {
+        // Nothing to match
+    } else if lhs.len() == 1 {
+        if let Some(j) = rhs.iter().position(|r| r == &lhs[0]) {
+            res[loff] = Some(roff + j);
+        }
+    } else {
+        let mid = lhs.len() / 2;
+        let fwd = lcs_row(&lhs[..mid], rhs, false);
+        let bwd = lcs_row(&lhs[mid..], rhs, true);
+        // Find split point for rhs
+        let n = rhs.len();
+        let k = (0..=n).max_by_key(|&k| (fwd[k] + bwd[n-k], core::cmp::Reverse(k))).unwrap();
+        split(&lhs[..mid], &rhs[..k], loff, roff, res);
+        split(&lhs[mid..], &rhs[k..], loff + mid, roff + k, res);
+    }
+}
+
+/// Compute the last row of the _longest common subsequence_ table for
+/// two slices (or their reversals).  That is, the `jth` element of the
+/// result holds the length of the longest common subsequence of `lhs`
+/// and the first `j` elements of `rhs`.
+pub(crate) fn lcs_row<T:PartialEq>(lhs: &[T], rhs: &[T], reverse: bool) -> Vec<usize> {
+    let (m,n) = (lhs.len(), rhs.len());
+    let mut prev = vec![0;n + 1];
+    let mut curr = vec![0;n + 1];
+    for i in 0..m {
+        let l = if reverse { &lhs[m - 1 - i] } else { &lhs[i] };
+        for j in 0..n {
+            let r = if reverse { &rhs[n - 1 - j] } else { &rhs[j] };
+            curr[j+1] = if l == r { prev[j] + 1 } else { curr[j].max(prev[j+1]) };
+        }
+        core::mem::swap(&mut prev,&mut curr);
+    }
+    prev
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod hirschberg_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::{diff_hirschberg,hirschberg_subsequence,longest_common_subsequence};
+
+    #[test]
+    fn hirschberg_test_01() {
+        let v = hirschberg_subsequence::<usize>(&[],&[]);
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn hirschberg_test_02() {
+        let v = hirschberg_subsequence(&[0],&[]);
+        assert_eq!(v,vec![None]);
+    }
+
+    #[test]
+    fn hirschberg_test_03() {
+        let v = hirschberg_subsequence(&[],&[0]);
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn hirschberg_test_04() {
+        let v = hirschberg_subsequence(&[0,1],&[0]);
+        assert_eq!(v,vec![Some(0),None]);
+    }
+
+    #[test]
+    fn hirschberg_test_05() {
+        let (a,b) = (['a','b','b','c','b','c','d'],['b','b','e','c','d','e']);
+        let v = hirschberg_subsequence(&a,&b);
+        assert_eq!(matched(&a,&b,&v),4);
+        assert_eq!(matched(&a,&b,&longest_common_subsequence(&a,&b)),4);
+        let mut vec = a.to_vec();
+        diff_hirschberg(&a,&b).transform(&mut vec);
+        assert_eq!(vec,b);
+    }
+
+    #[test]
+    fn hirschberg_test_06() {
+        // Same length as LCS for a range of inputs
+        let seqs : Vec<Vec<u8>> = (0..20).map(|i| (0..i).map(|j| ((j * i + 3) % 5) as u8).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let v1 = hirschberg_subsequence(a,b);
+                let v2 = longest_common_subsequence(a,b);
+                assert_eq!(matched(a,b,&v1),matched(a,b,&v2));
+                let mut vec = a.clone();
+                diff_hirschberg(a,b).transform(&mut vec);
+                assert_eq!(&vec,b);
+            }
+        }
+    }
+
+    fn matched<T:PartialEq>(lhs: &[T], rhs: &[T], mapping: &[Option<usize>]) -> usize {
+        // Check mapping is strictly increasing, and only matches equal
+        // elements
+        let ms : Vec<usize> = mapping.iter().flatten().copied().collect();
+        assert!(ms.windows(2).all(|w| w[0] < w[1]));
+        assert!(mapping.iter().enumerate().all(|(i,m)| m.is_none_or(|j| lhs[i] == rhs[j])));
+        ms.len()
+    }
+}
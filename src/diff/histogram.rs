@@ -0,0 +1,228 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::Hash;
+use core::ops::Range;
+use crate::util::hash_of;
+use super::{VecDelta,common_prefix_suffix,diff_trimmed,myers_subsequence};
+
+/// The maximum number of occurrences an element may have in `lhs` for
+/// it to be considered as the starting point for a match.  Elements
+/// occurring more frequently than this are (in practice) unimportant
+/// and, furthermore, considering them is expensive.
+const MAX_CHAIN : usize = 64;
+
+/// Compute a delta between two slices using the _histogram diff_
+/// algorithm (see `histogram_subsequence()`).
+pub fn diff_histogram<T:Clone+Hash+Eq>(lhs: &[T], rhs: &[T]) -> VecDelta<T> {
+    diff_trimmed(lhs, rhs, histogram_subsequence)
+}
+
+/// The maximum number of consecutive splits of a subproblem which fail
+/// to (at least) halve it, before falling back to `myers_subsequence()`.
+/// Without this, inputs where every region found is small (e.g. many
+/// interleaved insertions) would take quadratic time.
+pub(crate) const MAX_STALLS : usize = 16;
+
+/// Determine a common subsequence of two slices using the _histogram
+/// diff_ algorithm (as found in `git`).  This produces a mapping from
+/// elements in `lhs` to elements in `rhs` in the same form as
+/// `longest_common_subsequence()`.  This is an extension of patience
+/// diff which, rather than requiring anchors to be unique, looks for
+/// the longest common region built from the _least frequently
+/// occurring_ elements.  The problem is then split around this region
+/// and each side solved in turn.  When no suitable region exists, or
+/// splitting repeatedly fails to shrink a subproblem, this falls back
+/// to `myers_subsequence()`.
+pub fn histogram_subsequence<T:Hash+Eq>(lhs: &[T], rhs: &[T]) -> Vec<Option<usize>> {
+    let mut res = vec![None;lhs.len()];
+    let index = Histogram::new(lhs,rhs);
+    // Subproblems are given as (lstart,lend,rstart,rend,stalls)
+    let mut work = vec![(0,lhs.len(),0,rhs.len(),0)];
+    while let Some((ls,le,rs,re,stalls)) = work.pop() {
+        // Match common prefix and suffix
+        let (pre,suf) = common_prefix_suffix(&lhs[ls..le],&rhs[rs..re]);
+        for k in 0..pre { res[ls + k] = Some(rs + k); }
+        for k in 1..=suf { res[le - k] = Some(re - k); }
+        let (ls,le,rs,re) = (ls + pre, le - suf, rs + pre, re - suf);
+        if ls == le || rs == re { continue; }
+        //
+        match index.longest_rare_region(ls..le,rs..re) {
+            Some((i,j,n)) if stalls < MAX_STALLS => {
+                for k in 0..n { res[i + k] = Some(j + k); }
+                let before = (i - ls) + (j - rs);
+                let after = (le - i - n) + (re - j - n);
+                let stalls = if 2 * before.max(after) > (le - ls) + (re - rs) { stalls + 1 } else { 0 };
+                work.push((ls,i,rs,j,stalls));
+                work.push((i + n,le,j + n,re,stalls));
+            }
+            _ => {
+                let mapping = myers_subsequence(&lhs[ls..le],&rhs[rs..re]);
+                for (k,m) in mapping.into_iter().enumerate() {
+                    res[ls + k] = m.map(|v| v + rs);
+                }
+            }
+        }
+    }
+    res
+}
+
+/// An index of the elements in `lhs`, which is constructed once and
+/// then narrowed to each subproblem.  Equal elements are identified by
+/// the same _class_, and the positions of each class in `lhs` are kept
+/// in order (so the occurrences within a range can be found by binary
+/// search).
+struct Histogram {
+    /// Class of each element in `lhs`.
+    lclasses: Vec<usize>,
+    /// Class of each element in `rhs`, or `None` if it is not in `lhs`.
+    rclasses: Vec<Option<usize>>,
+    /// Positions in `lhs` of each class.
+    positions: Vec<Vec<usize>>
+}
+
+impl Histogram {
+    fn new<T:Hash+Eq>(lhs: &[T], rhs: &[T]) -> Self {
+        // Classes with a given hash
+        let mut buckets : BTreeMap<u64,Vec<usize>> = BTreeMap::new();
+        let mut positions : Vec<Vec<usize>> = Vec::new();
+        let mut lclasses = Vec::with_capacity(lhs.len());
+        for (i,l) in lhs.iter().enumerate() {
+            let bucket = buckets.entry(hash_of(l)).or_default();
+            let c = match bucket.iter().find(|&&c| &lhs[positions[c][0]] == l) {
+                Some(&c) => c,
+                None => {
+                    bucket.push(positions.len());
+                    positions.push(Vec::new());
+                    positions.len() - 1
+                }
+            };
+            positions[c].push(i);
+            lclasses.push(c);
+        }
+        let rclasses = rhs.iter().map(|r| {
+            buckets.get(&hash_of(r))?.iter().find(|&&c| &lhs[positions[c][0]] == r).copied()
+        }).collect();
+        Histogram{lclasses, rclasses, positions}
+    }
+
+    /// Get the positions of a given class within a given range of `lhs`.
+    fn within(&self, c: usize, range: &Range<usize>) -> &[usize] {
+        let ps = &self.positions[c];
+        &ps[ps.partition_point(|&i| i < range.start)..ps.partition_point(|&i| i < range.end)]
+    }
+
+    /// Find the best common region `(i,j,n)` within the given ranges
+    /// such that `lhs[i..i+n] == rhs[j..j+n]`.  Regions whose rarest
+    /// element occurs least often in the range of `lhs` are preferred,
+    /// with ties broken by length.
+    fn longest_rare_region(&self, lr: Range<usize>, rr: Range<usize>) -> Option<(usize,usize,usize)> {
+        let (lc,rc) = (&self.lclasses,&self.rclasses);
+        // Best region so far, along with its rarity.
+        let mut best : Option<(usize,usize,usize,usize)> = None;
+        let mut j = rr.start;
+        while j < rr.end {
+            let mut next = j + 1;
+            if let Some(c) = rc[j] {
+                let occurrences = self.within(c,&lr);
+                if occurrences.len() <= MAX_CHAIN {
+                    for &i in occurrences {
+                        // Extend region in both directions
+                        let (mut s, mut e) = (0,1);
+                        while i - s > lr.start && j - s > rr.start && rc[j-s-1] == Some(lc[i-s-1]) { s += 1; }
+                        while i + e < lr.end && j + e < rr.end && rc[j+e] == Some(lc[i+e]) { e += 1; }
+                        let (ri,rj,n) = (i - s, j - s, s + e);
+                        let rarity = (ri..ri+n).map(|k| self.within(lc[k],&lr).len()).min().unwrap();
+                        let better = match best {
+                            None => true,
+                            Some((_,_,bn,br)) => rarity < br || (rarity == br && n > bn)
+                        };
+                        if better { best = Some((ri,rj,n,rarity)); }
+                        // Skip over the remainder of this region
+                        next = next.max(rj + n);
+                    }
+                }
+            }
+            j = next;
+        }
+        best.map(|(i,j,n,_)| (i,j,n))
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod histogram_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::{diff_histogram,histogram_subsequence,longest_common_subsequence};
+
+    #[test]
+    fn histogram_test_01() {
+        let v = histogram_subsequence::<usize>(&[],&[]);
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn histogram_test_02() {
+        let v = histogram_subsequence(&[0,1],&[0]);
+        assert_eq!(v,vec![Some(0),None]);
+    }
+
+    #[test]
+    fn histogram_test_03() {
+        // Rare elements anchor the match
+        let v = histogram_subsequence(&['x','a','x','b','x'],&['a','x','x','b']);
+        assert_eq!(v[1],Some(0));
+        assert_eq!(matched(&v),3);
+    }
+
+    #[test]
+    fn histogram_test_04() {
+        // Inserting a function between two others
+        let before = ["fn f() {","  x","}","","fn h() {","  z","}"];
+        let after = ["fn f() {","  x","}","","fn g() {","  y","}","","fn h() {","  z","}"];
+        let mut vec = before.to_vec();
+        let delta = diff_histogram(&before,&after);
+        assert_eq!(delta.len(),1);
+        delta.transform(&mut vec);
+        assert_eq!(vec,after);
+    }
+
+    #[test]
+    fn histogram_test_05() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..20).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let v1 = histogram_subsequence(a,b);
+                let v2 = longest_common_subsequence(a,b);
+                assert!(matched(&v1) <= matched(&v2));
+                let mut vec = a.clone();
+                diff_histogram(a,b).transform(&mut vec);
+                assert_eq!(&vec,b);
+            }
+        }
+    }
+
+    #[test]
+    fn histogram_test_06() {
+        // Many interleaved insertions
+        let a : Vec<usize> = (0..5000).collect();
+        let b : Vec<usize> = (0..10000).map(|i| if i % 2 == 0 { i / 2 } else { 5000 + i }).collect();
+        let v = histogram_subsequence(&a,&b);
+        assert_eq!(matched(&v),5000);
+        let mut vec = a.clone();
+        diff_histogram(&a,&b).transform(&mut vec);
+        assert_eq!(vec,b);
+    }
+
+    fn matched(mapping: &[Option<usize>]) -> usize {
+        // Check mapping is strictly increasing
+        let ms : Vec<usize> = mapping.iter().flatten().copied().collect();
+        assert!(ms.windows(2).all(|w| w[0] < w[1]));
+        ms.len()
+    }
+}
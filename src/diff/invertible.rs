@@ -0,0 +1,102 @@
+use alloc::vec::Vec;
+use crate::util::Region;
+use super::VecDelta;
+
+/// An `InvertibleDelta` is a `VecDelta` which additionally records
+/// the elements replaced by each rewrite.  This means it can be
+/// inverted to give a delta which undoes its effect.  For example,
+/// consider the delta `(1;2;"xyz")` which was generated from the
+/// sequence `"abcd"`.  This is inverted by the delta `(1;3;"bc")`.
+/// The cost of this is that the replaced elements must be stored, and
+/// an `InvertibleDelta` must be constructed from the sequence it
+/// applies to.
+#[derive(Clone,Debug,PartialEq)]
+pub struct InvertibleDelta<T> {
+    forward: VecDelta<T>,
+    backward: VecDelta<T>
+}
+
+impl<T> InvertibleDelta<T> {
+    /// Get the underlying delta.
+    pub fn delta(&self) -> &VecDelta<T> { &self.forward }
+
+    /// Get the delta which undoes the effect of this delta.
+    pub fn inverse(&self) -> &VecDelta<T> { &self.backward }
+
+    /// Get the number of rewrites in this delta.
+    pub fn len(&self) -> usize { self.forward.len() }
+
+    /// Check whether this delta contains any rewrites or not.
+    pub fn is_empty(&self) -> bool { self.forward.is_empty() }
+}
+
+impl<T:Clone> InvertibleDelta<T> {
+    /// Construct an invertible delta from a `VecDelta` and the
+    /// `before` sequence it applies to.  This will `panic` if the
+    /// delta is malformed with respect to the given sequence.
+    pub fn new(delta: VecDelta<T>, before: &[T]) -> Self {
+        // Every rewrite is inverted by a rewrite covering its data in
+        // the final sequence, which restores the replaced elements.
+        let removed : Vec<(Region,&[T])> = delta.iter().zip(delta.iter_source()).map(|(rw,(r,_))| {
+            (Region::new(rw.region().offset,rw.data().len()),&before[r.as_range()])
+        }).collect();
+        let backward = VecDelta::from_source(removed);
+        InvertibleDelta{forward: delta, backward}
+    }
+
+    /// Construct the inverse of this delta.  Applying this delta and
+    /// then its inverse restores the original sequence.
+    pub fn invert(&self) -> Self {
+        InvertibleDelta{forward: self.backward.clone(), backward: self.forward.clone()}
+    }
+
+    /// Apply this delta to a given `Vec`, thus transforming it.
+    pub fn transform(&self, vec: &mut Vec<T>) {
+        self.forward.transform(vec)
+    }
+}
+
+impl<T> From<InvertibleDelta<T>> for VecDelta<T> {
+    fn from(delta: InvertibleDelta<T>) -> Self { delta.forward }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod invertible_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,VecDelta};
+    use super::InvertibleDelta;
+
+    #[test]
+    fn invertible_test_01() {
+        let mut d = VecDelta::new();
+        unsafe { d.push_raw(1..3, &['x','y','z']); }
+        let d = InvertibleDelta::new(d,&['a','b','c','d']);
+        let mut vec = vec!['a','b','c','d'];
+        d.transform(&mut vec);
+        assert_eq!(vec,['a','x','y','z','d']);
+        d.invert().transform(&mut vec);
+        assert_eq!(vec,['a','b','c','d']);
+        assert_eq!(d.invert().invert(),d);
+    }
+
+    #[test]
+    fn invertible_test_02() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let d = InvertibleDelta::new(a.diff(b),a);
+                let mut vec = a.clone();
+                d.transform(&mut vec);
+                assert_eq!(&vec,b);
+                d.invert().transform(&mut vec);
+                assert_eq!(&vec,a);
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use alloc::format;
+use alloc::string::String;
+use serde_json::{Map,Value};
+use super::{Diff,MapDelta,MutableMap,Transform,VecDelta};
+use super::format::json_patch::{JsonPatch,PatchOp};
+
+/// A structural delta between two JSON values.  Objects are diffed
+/// key-wise, such that the value of each changed key is itself
+/// described by a `JsonDelta`.  Arrays are diffed as sequences of
+/// values (i.e. elements are either kept or replaced wholesale).  Any
+/// other change (e.g. of a number, or from an array to an object)
+/// replaces the value.  For example:
+///
+/// ```
+/// use delta_inc::diff::{Diff,Transform};
+/// use serde_json::json;
+///
+/// let before = json!({"name":"delta","tags":[1,2,3],"meta":{"v":1}});
+/// let after = json!({"name":"delta","tags":[1,3],"meta":{"v":2,"w":true}});
+/// let delta = before.diff(&after);
+/// let mut doc = before.clone();
+/// doc.transform(&delta);
+/// assert_eq!(doc,after);
+/// ```
+#[derive(Clone,Debug,PartialEq)]
+pub enum JsonDelta {
+    /// Replace the value wholesale.
+    Replace(Value),
+    /// Update the members of an object.
+    Object(MapDelta<String,Value,JsonDelta>),
+    /// Update the elements of an array.
+    Array(VecDelta<Value>)
+}
+
+impl JsonDelta {
+    /// Check whether this delta leaves the value it applies to
+    /// unchanged or not.  A delta which replaces a value is never
+    /// considered empty.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            JsonDelta::Replace(_) => false,
+            JsonDelta::Object(d) => d.is_empty(),
+            JsonDelta::Array(d) => d.is_empty()
+        }
+    }
+
+    /// Convert this delta into an equivalent JSON Patch document, which
+    /// applies it to the value at a given location (e.g. `""` for the
+    /// root).
+    pub fn to_patch(&self, path: &str) -> JsonPatch {
+        let mut patch = JsonPatch::new();
+        push_ops(&mut patch, self, path);
+        patch
+    }
+}
+
+/// Diffing of JSON values is structural (see `JsonDelta`).  When two
+/// values are equal, the resulting delta is empty if they are objects
+/// or arrays, and otherwise replaces the value with itself.
+impl Diff for Value {
+    type Delta = JsonDelta;
+
+    fn diff(&self, other: &Value) -> JsonDelta {
+        match (self,other) {
+            (Value::Object(m1),Value::Object(m2)) => {
+                let mut delta = MapDelta::new();
+                for (k,v1) in m1 {
+                    match m2.get(k) {
+                        None => delta.remove(k.clone()),
+                        Some(v2) if v1 != v2 => delta.change(k.clone(),v1.diff(v2)),
+                        Some(_) => {}
+                    }
+                }
+                for (k,v2) in m2 {
+                    if !m1.contains_key(k) { delta.insert(k.clone(),v2.clone()); }
+                }
+                JsonDelta::Object(delta)
+            }
+            (Value::Array(a1),Value::Array(a2)) => JsonDelta::Array(a1.diff(a2)),
+            (_,v) => JsonDelta::Replace(v.clone())
+        }
+    }
+}
+
+/// Applying a `JsonDelta` will `panic` if it is malformed with respect
+/// to the value (e.g. it updates the members of an object, but the
+/// value is an array).
+impl Transform for Value {
+    type Delta = JsonDelta;
+
+    fn transform(&mut self, d: &JsonDelta) {
+        match (self,d) {
+            (v,JsonDelta::Replace(w)) => *v = w.clone(),
+            (Value::Object(m),JsonDelta::Object(d)) => d.transform_nested(m),
+            (Value::Array(a),JsonDelta::Array(d)) => d.transform(a),
+            (v,_) => panic!("delta cannot be applied to {v}")
+        }
+    }
+}
+
+impl MutableMap for Map<String,Value> {
+    type Key = String;
+    type Value = Value;
+    fn get_mut(&mut self, key: &String) -> Option<&mut Value> { Map::get_mut(self,key) }
+    fn insert(&mut self, key: String, value: Value) { Map::insert(self,key,value); }
+    fn remove(&mut self, key: &String) { Map::remove(self,key); }
+}
+
+/// Append the operations of a given delta onto a JSON Patch.
+fn push_ops(patch: &mut JsonPatch, delta: &JsonDelta, path: &str) {
+    match delta {
+        JsonDelta::Replace(v) => patch.push(PatchOp::Replace{path: path.into(), value: v.clone()}),
+        JsonDelta::Array(d) => patch.push_delta(d,path).unwrap(),
+        JsonDelta::Object(d) => {
+            let member = |k: &str| format!("{path}/{}",k.replace('~',"~0").replace('/',"~1"));
+            for k in d.removed() { patch.push(PatchOp::Remove{path: member(k)}); }
+            for (k,v) in d.inserted() { patch.push(PatchOp::Add{path: member(k), value: v.clone()}); }
+            for (k,d) in d.changed() { push_ops(patch,d,&member(k)); }
+        }
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod json_tests {
+    use alloc::vec::Vec;
+    use serde_json::{Value,json};
+    use crate::diff::{Diff,Transform,TryTransform};
+    use super::JsonDelta;
+
+    fn values() -> Vec<Value> {
+        [json!(null),json!(1),json!("a"),json!([]),json!([1,2,3]),json!([3,{"a":1}]),json!({}),
+         json!({"a":1}),json!({"a":2,"b":[1]}),json!({"a":{"x":[1,2],"y":null},"a/b~":true}),
+         json!({"a":{"x":[2],"z":"q"},"a/b~":false})].into()
+    }
+
+    #[test]
+    fn json_test_01() {
+        for a in values() {
+            for b in values() {
+                let delta = a.diff(&b);
+                let mut v = a.clone();
+                v.transform(&delta);
+                assert_eq!(v,b);
+                // Patch gives the same result
+                let mut v = a.clone();
+                v.try_transform(&delta.to_patch("")).unwrap();
+                assert_eq!(v,b);
+            }
+        }
+    }
+
+    #[test]
+    fn json_test_02() {
+        let delta = json!({"a":{"x":1,"y":2}}).diff(&json!({"a":{"x":1,"y":3}}));
+        let JsonDelta::Object(d) = &delta else { panic!() };
+        let JsonDelta::Object(inner) = &d.changed()[0].1 else { panic!() };
+        assert_eq!(inner.changed(),[("y".into(),JsonDelta::Replace(json!(3)))]);
+        assert_eq!(serde_json::to_value(delta.to_patch("")).unwrap(),json!([{"op":"replace","path":"/a/y","value":3}]));
+        assert!(json!([1,{}]).diff(&json!([1,{}])).is_empty());
+        assert!(!json!(1).diff(&json!(1)).is_empty());
+    }
+}
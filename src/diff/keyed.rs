@@ -0,0 +1,153 @@
+use alloc::vec::Vec;
+use super::{Diff,Transform,VecDelta,common_prefix_suffix,myers_subsequence};
+use super::slice::extract_delta;
+
+/// A `KeyedDelta` describes the differences between two sequences whose
+/// elements have an _identity_ given by some key (e.g. the `id` of an
+/// entity, or of a UI component).  Elements whose keys are matched are
+/// _updated_ in place when their contents differ, where each update is
+/// described by some type `D`.  Elements which are not matched are
+/// rewritten as for a `VecDelta`.  By default, `D` is the element type
+/// itself (i.e. the new element replaces the old element).
+/// Alternatively, when elements can themselves be diffed, `D` can be
+/// their delta type (see `diff_keyed_nested()`).
+#[derive(Clone,Debug,PartialEq)]
+pub struct KeyedDelta<T,D=T> {
+    /// Rewrites of unmatched elements.
+    rewrites: VecDelta<T>,
+    /// Updates of matched elements, given by their position in the
+    /// original sequence.
+    updates: Vec<(usize,D)>
+}
+
+impl<T,D> KeyedDelta<T,D> {
+    /// Get the rewrites of elements which are not matched.
+    pub fn rewrites(&self) -> &VecDelta<T> { &self.rewrites }
+
+    /// Get the updates of elements which are matched, in order of their
+    /// position in the original sequence.
+    pub fn updates(&self) -> &[(usize,D)] { &self.updates }
+
+    /// Get the total number of rewrites and updates in this delta.
+    pub fn len(&self) -> usize { self.rewrites.len() + self.updates.len() }
+
+    /// Check whether this delta contains any rewrites or updates.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+}
+
+impl<T:Clone> KeyedDelta<T> {
+    /// Apply this delta to a given `Vec`, thus transforming it.  This
+    /// operation will `panic` if this delta is malformed with respect
+    /// to the given `Vec`.
+    pub fn transform(&self, vec: &mut Vec<T>) {
+        self.transform_with(vec, |item,update| *item = update.clone());
+    }
+}
+
+impl<T:Clone,D> KeyedDelta<T,D> {
+    /// Apply this delta to a given `Vec`, where each matched element is
+    /// transformed in place by its nested delta.  This operation will
+    /// `panic` if this delta is malformed with respect to the given
+    /// `Vec`.
+    pub fn transform_nested(&self, vec: &mut Vec<T>) where T:Transform<Delta=D> {
+        self.transform_with(vec, |item,update| item.transform(update));
+    }
+
+    /// Apply this delta to a given `Vec`, using a given function to
+    /// apply each update.  Updates are applied first, since they are
+    /// positioned in the original sequence, and never overlap the
+    /// rewrites.
+    fn transform_with<F:FnMut(&mut T,&D)>(&self, vec: &mut Vec<T>, mut f: F) {
+        for (i,d) in &self.updates { f(&mut vec[*i],d); }
+        self.rewrites.transform(vec);
+    }
+}
+
+/// Compute a delta between two slices where elements are matched by a
+/// key extracted using a given function, such that matched elements
+/// keep their identity even when their contents change.  Unlike
+/// `diff_by_key()`, a matched element which differs is not ignored but,
+/// instead, is replaced by an update.  And, unlike `diff_myers()`, it
+/// is not deleted and reinserted.  For example:
+///
+/// ```
+/// use delta_inc::diff::diff_keyed;
+///
+/// let before = [(1,"a"),(2,"b"),(3,"c")];
+/// let after = [(1,"a"),(4,"d"),(2,"B")];
+/// let delta = diff_keyed(&before,&after,|e| e.0);
+/// assert_eq!(delta.updates(),[(1,(2,"B"))]);
+/// let mut vec = before.to_vec();
+/// delta.transform(&mut vec);
+/// assert_eq!(vec,after);
+/// ```
+pub fn diff_keyed<T,K,F>(lhs: &[T], rhs: &[T], key: F) -> KeyedDelta<T>
+where T:Clone+PartialEq, K:PartialEq, F:Fn(&T)->K {
+    diff_keyed_with(lhs, rhs, key, |l,r| (l != r).then(|| r.clone()))
+}
+
+/// Compute a delta between two slices where elements are matched by a
+/// key (as for `diff_keyed()`), and where each matched element which
+/// differs is updated by its own delta.
+pub fn diff_keyed_nested<T,K,F>(lhs: &[T], rhs: &[T], key: F) -> KeyedDelta<T,T::Delta>
+where T:Clone+PartialEq+Diff, K:PartialEq, F:Fn(&T)->K {
+    diff_keyed_with(lhs, rhs, key, |l,r| (l != r).then(|| l.diff(r)))
+}
+
+/// Compute a delta between two slices where elements are matched by a
+/// key, using a given function to determine the update (if any) of
+/// each matched pair.
+fn diff_keyed_with<T,K,D,F,G>(lhs: &[T], rhs: &[T], key: F, update: G) -> KeyedDelta<T,D>
+where T:Clone, K:PartialEq, F:Fn(&T)->K, G:Fn(&T,&T)->Option<D> {
+    let lkeys : Vec<K> = lhs.iter().map(&key).collect();
+    let rkeys : Vec<K> = rhs.iter().map(&key).collect();
+    let (pre,suf) = common_prefix_suffix(&lkeys,&rkeys);
+    let mapping = myers_subsequence(&lkeys[pre..lhs.len()-suf],&rkeys[pre..rhs.len()-suf]);
+    // Determine matched pairs in order
+    let prefix = (0..pre).map(|i| (i,i));
+    let middle = mapping.iter().enumerate().filter_map(|(i,m)| m.map(|j| (pre+i,pre+j)));
+    let suffix = (0..suf).map(|k| (lhs.len()-suf+k,rhs.len()-suf+k));
+    let updates = prefix.chain(middle).chain(suffix).filter_map(|(i,j)| update(&lhs[i],&rhs[j]).map(|d| (i,d))).collect();
+    let rewrites = extract_delta(pre, &mapping, &rhs[pre..rhs.len()-suf]);
+    KeyedDelta{rewrites, updates}
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod keyed_tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use super::{diff_keyed,diff_keyed_nested};
+
+    fn seqs() -> Vec<Vec<(usize,usize)>> {
+        (0..12).map(|i| (0..i).map(|j| ((j*i+3)%5,(j+i)%3)).collect()).collect()
+    }
+
+    #[test]
+    fn keyed_test_01() {
+        for a in seqs() {
+            for b in seqs() {
+                let delta = diff_keyed(&a,&b,|e| e.0);
+                let mut v = a.clone();
+                delta.transform(&mut v);
+                assert_eq!(v,b);
+                assert_eq!(delta.is_empty(),a == b);
+            }
+        }
+    }
+
+    #[test]
+    fn keyed_test_02() {
+        // Nested deltas of matched elements
+        let a : Vec<String> = ["apple","banana","cherry"].map(String::from).into();
+        let b : Vec<String> = ["banana split","cherry","apricot"].map(String::from).into();
+        let delta = diff_keyed_nested(&a,&b,|e| e.chars().next());
+        assert_eq!(delta.updates().len(),1);
+        let mut v = a.clone();
+        delta.transform_nested(&mut v);
+        assert_eq!(v,b);
+    }
+}
@@ -0,0 +1,391 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher,Hash};
+use core::ops::RangeBounds;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+use super::{Diff,Transform};
+
+/// A `MapDelta` describes the differences between two maps, in terms
+/// of the keys which are _removed_, _inserted_ and _changed_.  The
+/// values of inserted keys are stored in full, whilst those of changed
+/// keys are described by some type `D`.  By default, `D` is the value
+/// type itself (i.e. the new value replaces the old value).
+/// Alternatively, when values can themselves be diffed, `D` can be
+/// their delta type (see `diff_map_nested()`).
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct MapDelta<K,V,D=V> {
+    /// Keys which are removed.
+    removed: Vec<K>,
+    /// Keys which are inserted, along with their values.
+    inserted: Vec<(K,V)>,
+    /// Keys whose values change, along with a description of how.
+    changed: Vec<(K,D)>
+}
+
+impl<K,V,D> MapDelta<K,V,D> {
+    /// Construct an empty `MapDelta`.
+    pub const fn new() -> Self {
+        MapDelta{removed: Vec::new(), inserted: Vec::new(), changed: Vec::new()}
+    }
+
+    /// Get the total number of keys affected by this delta.
+    pub fn len(&self) -> usize { self.removed.len() + self.inserted.len() + self.changed.len() }
+
+    /// Check whether this delta affects any keys or not.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Get the keys removed by this delta.
+    pub fn removed(&self) -> &[K] { &self.removed }
+
+    /// Get the keys inserted by this delta, along with their values.
+    pub fn inserted(&self) -> &[(K,V)] { &self.inserted }
+
+    /// Get the keys whose values are changed by this delta, along with
+    /// how they change.
+    pub fn changed(&self) -> &[(K,D)] { &self.changed }
+
+    /// Record that a given key is removed.
+    pub fn remove(&mut self, key: K) { self.removed.push(key); }
+
+    /// Record that a given key is inserted with a given value.
+    pub fn insert(&mut self, key: K, value: V) { self.inserted.push((key,value)); }
+
+    /// Record that the value of a given key changes.
+    pub fn change(&mut self, key: K, delta: D) { self.changed.push((key,delta)); }
+}
+
+impl<K:Clone,V:Clone> MapDelta<K,V> {
+    /// Apply this delta to a given map, thus transforming it.  Keys
+    /// which are removed but not present are ignored, and the values of
+    /// changed keys are replaced wholesale.
+    pub fn transform<M:MutableMap<Key=K,Value=V>>(&self, map: &mut M) {
+        self.transform_with(map, |v,d| *v = d.clone());
+        for (k,v) in &self.changed {
+            if map.get_mut(k).is_none() { map.insert(k.clone(),v.clone()); }
+        }
+    }
+}
+
+impl<K:Clone,V:Clone,D> MapDelta<K,V,D> {
+    /// Apply this delta to a given map, where the value of each changed
+    /// key is transformed in place by its nested delta.  This operation
+    /// will `panic` if a changed key is not present in the map.
+    pub fn transform_nested<M:MutableMap<Key=K,Value=V>>(&self, map: &mut M)
+    where V:Transform<Delta=D> {
+        self.transform_with(map, |v,d| v.transform(d));
+    }
+
+    /// Apply this delta to a given map, using a given function to apply
+    /// the change of each changed key which is present.
+    fn transform_with<M,F>(&self, map: &mut M, mut f: F)
+    where M:MutableMap<Key=K,Value=V>, F:FnMut(&mut V,&D) {
+        for k in &self.removed { map.remove(k); }
+        for (k,v) in &self.inserted { map.insert(k.clone(),v.clone()); }
+        for (k,d) in &self.changed {
+            if let Some(v) = map.get_mut(k) { f(v,d); }
+        }
+    }
+}
+
+impl<K,V,D> Default for MapDelta<K,V,D> {
+    fn default() -> Self { Self::new() }
+}
+
+// ===================================================================
+// MutableMap
+// ===================================================================
+
+/// A trait describing a map which can be modified by inserting and
+/// removing keys, and by updating values in place.  Any such map can
+/// be transformed by a `MapDelta`.
+pub trait MutableMap {
+    /// The type of keys held in this map.
+    type Key;
+    /// The type of values held in this map.
+    type Value;
+    /// Get the value of a given key, if it is present.
+    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value>;
+    /// Insert a given key with a given value, replacing any existing
+    /// value.
+    fn insert(&mut self, key: Self::Key, value: Self::Value);
+    /// Remove a given key, if it is present.
+    fn remove(&mut self, key: &Self::Key);
+}
+
+// ===================================================================
+// HashMap
+// ===================================================================
+
+#[cfg(feature = "std")]
+impl<K:Hash+Eq,V,S:BuildHasher> MutableMap for HashMap<K,V,S> {
+    type Key = K;
+    type Value = V;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> { HashMap::get_mut(self,key) }
+    fn insert(&mut self, key: K, value: V) { HashMap::insert(self,key,value); }
+    fn remove(&mut self, key: &K) { HashMap::remove(self,key); }
+}
+
+/// Diffing of hash maps replaces the value of each changed key
+/// wholesale (see `diff_map_nested()` otherwise).  The order of keys
+/// within the resulting delta is unspecified.  For example:
+///
+/// ```
+/// use std::collections::HashMap;
+/// use delta_inc::diff::{Diff,Transform};
+///
+/// let before = HashMap::from([("a",1),("b",2),("c",3)]);
+/// let after = HashMap::from([("a",1),("b",5),("d",4)]);
+/// let delta = before.diff(&after);
+/// assert_eq!((delta.removed().len(),delta.inserted().len(),delta.changed().len()),(1,1,1));
+/// let mut map = before.clone();
+/// map.transform(&delta);
+/// assert_eq!(map,after);
+/// ```
+#[cfg(feature = "std")]
+impl<K:Hash+Eq+Clone,V:Clone+PartialEq,S:BuildHasher> Diff for HashMap<K,V,S> {
+    type Delta = MapDelta<K,V>;
+
+    fn diff(&self, other: &Self) -> MapDelta<K,V> {
+        diff_hash_maps(self, other, |v1,v2| (v1 != v2).then(|| v2.clone()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K:Hash+Eq+Clone,V:Clone,S:BuildHasher> Transform for HashMap<K,V,S> {
+    type Delta = MapDelta<K,V>;
+
+    fn transform(&mut self, d: &MapDelta<K,V>) {
+        d.transform(self)
+    }
+}
+
+/// Compute a delta between two hash maps, where the change in value of
+/// each changed key is described by diffing its values.  For example:
+///
+/// ```
+/// use std::collections::HashMap;
+/// use delta_inc::diff::diff_map_nested;
+///
+/// let before = HashMap::from([("name","Dave".to_string())]);
+/// let after = HashMap::from([("name","David".to_string())]);
+/// let delta = diff_map_nested(&before,&after);
+/// let mut map = before.clone();
+/// delta.transform_nested(&mut map);
+/// assert_eq!(map,after);
+/// ```
+#[cfg(feature = "std")]
+pub fn diff_map_nested<K,V,S>(lhs: &HashMap<K,V,S>, rhs: &HashMap<K,V,S>) -> MapDelta<K,V,V::Delta>
+where K:Hash+Eq+Clone, V:Clone+PartialEq+Diff, S:BuildHasher {
+    diff_hash_maps(lhs, rhs, |v1,v2| (v1 != v2).then(|| v1.diff(v2)))
+}
+
+/// Compute a delta between two hash maps, using a given function to
+/// describe the change (if any) between the values of each key.
+#[cfg(feature = "std")]
+fn diff_hash_maps<K,V,D,S,F>(lhs: &HashMap<K,V,S>, rhs: &HashMap<K,V,S>, mut change: F) -> MapDelta<K,V,D>
+where K:Hash+Eq+Clone, V:Clone, S:BuildHasher, F:FnMut(&V,&V)->Option<D> {
+    let mut delta = MapDelta::new();
+    for (k,v1) in lhs {
+        match rhs.get(k) {
+            None => delta.remove(k.clone()),
+            Some(v2) => if let Some(d) = change(v1,v2) { delta.change(k.clone(),d); }
+        }
+    }
+    for (k,v2) in rhs {
+        if !lhs.contains_key(k) { delta.insert(k.clone(),v2.clone()); }
+    }
+    delta
+}
+
+// ===================================================================
+// BTreeMap
+// ===================================================================
+
+impl<K:Ord,V> MutableMap for BTreeMap<K,V> {
+    type Key = K;
+    type Value = V;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> { BTreeMap::get_mut(self,key) }
+    fn insert(&mut self, key: K, value: V) { BTreeMap::insert(self,key,value); }
+    fn remove(&mut self, key: &K) { BTreeMap::remove(self,key); }
+}
+
+/// Diffing of ordered maps exploits their ordering by scanning both
+/// maps together (in the manner of merging two sorted lists) and,
+/// hence, takes `O(n+m)` time without hashing.  The keys within the
+/// resulting delta are sorted.  As for `HashMap`, the value of each
+/// changed key is replaced wholesale (see `diff_btree_nested()`
+/// otherwise).
+impl<K:Ord+Clone,V:Clone+PartialEq> Diff for BTreeMap<K,V> {
+    type Delta = MapDelta<K,V>;
+
+    fn diff(&self, other: &Self) -> MapDelta<K,V> {
+        merge_scan(self.iter(), other.iter(), |v1,v2| (v1 != v2).then(|| v2.clone()))
+    }
+}
+
+impl<K:Ord+Clone,V:Clone> Transform for BTreeMap<K,V> {
+    type Delta = MapDelta<K,V>;
+
+    fn transform(&mut self, d: &MapDelta<K,V>) {
+        d.transform(self)
+    }
+}
+
+/// Compute a delta between two ordered maps, where the change in value
+/// of each changed key is described by diffing its values (see
+/// `diff_map_nested()`).
+pub fn diff_btree_nested<K,V>(lhs: &BTreeMap<K,V>, rhs: &BTreeMap<K,V>) -> MapDelta<K,V,V::Delta>
+where K:Ord+Clone, V:Clone+PartialEq+Diff {
+    merge_scan(lhs.iter(), rhs.iter(), |v1,v2| (v1 != v2).then(|| v1.diff(v2)))
+}
+
+/// Compute a delta between the sub-maps of two ordered maps whose keys
+/// lie within a given range.  Keys outside this range are ignored and,
+/// hence, the resulting delta leaves them untouched.  This takes time
+/// proportional to the size of the sub-maps (plus `O(log n)` to locate
+/// them).  For example:
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use delta_inc::diff::diff_btree_range;
+///
+/// let before = BTreeMap::from([(1,'a'),(2,'b'),(3,'c')]);
+/// let after = BTreeMap::from([(1,'x'),(2,'b'),(4,'d')]);
+/// let delta = diff_btree_range(&before, &after, 2..);
+/// assert_eq!(delta.removed(), [3]);
+/// assert_eq!(delta.inserted(), [(4,'d')]);
+/// assert!(delta.changed().is_empty());
+/// ```
+pub fn diff_btree_range<K,V,R>(lhs: &BTreeMap<K,V>, rhs: &BTreeMap<K,V>, range: R) -> MapDelta<K,V>
+where K:Ord+Clone, V:Clone+PartialEq, R:RangeBounds<K>+Clone {
+    merge_scan(lhs.range(range.clone()), rhs.range(range), |v1,v2| (v1 != v2).then(|| v2.clone()))
+}
+
+/// Compute a delta between two sequences of key-value pairs which are
+/// sorted by key, using a given function to describe the change (if
+/// any) between the values of each key.
+fn merge_scan<'a,K,V,D,I,F>(lhs: I, rhs: I, mut change: F) -> MapDelta<K,V,D>
+where K:'a+Ord+Clone, V:'a+Clone, I:Iterator<Item=(&'a K,&'a V)>, F:FnMut(&V,&V)->Option<D> {
+    let mut delta = MapDelta::new();
+    let (mut lhs, mut rhs) = (lhs.peekable(), rhs.peekable());
+    loop {
+        let ord = match (lhs.peek(),rhs.peek()) {
+            (None,None) => return delta,
+            (Some(_),None) => Ordering::Less,
+            (None,Some(_)) => Ordering::Greater,
+            (Some((k1,_)),Some((k2,_))) => k1.cmp(k2)
+        };
+        match ord {
+            Ordering::Less => { let (k,_) = lhs.next().unwrap(); delta.remove(k.clone()); }
+            Ordering::Greater => { let (k,v) = rhs.next().unwrap(); delta.insert(k.clone(),v.clone()); }
+            Ordering::Equal => {
+                let ((k,v1),(_,v2)) = (lhs.next().unwrap(),rhs.next().unwrap());
+                if let Some(d) = change(v1,v2) { delta.change(k.clone(),d); }
+            }
+        }
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod map_delta_tests {
+    use alloc::collections::BTreeMap;
+    use alloc::string::ToString;
+    #[cfg(feature = "std")]
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    #[cfg(feature = "std")]
+    use std::collections::HashMap;
+    use crate::diff::{Diff,Transform};
+    use super::{diff_btree_nested,diff_btree_range};
+    #[cfg(feature = "std")]
+    use super::{MapDelta,diff_map_nested};
+
+    #[cfg(feature = "std")]
+    fn maps() -> Vec<HashMap<usize,usize>> {
+        (0..10).map(|i| (0..i).map(|j| ((j*i+3)%7,(j*i)%5)).collect()).collect()
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn map_delta_test_01() {
+        for a in &maps() {
+            for b in &maps() {
+                let delta = a.diff(b);
+                assert_eq!(delta.is_empty(),a == b);
+                let mut m = a.clone();
+                m.transform(&delta);
+                assert_eq!(m,*b);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn map_delta_test_02() {
+        let before : HashMap<&str,String> = [("a","hello"),("b","world")].map(|(k,v)| (k,v.to_string())).into();
+        let after : HashMap<&str,String> = [("a","help"),("c","!")].map(|(k,v)| (k,v.to_string())).into();
+        let delta = diff_map_nested(&before,&after);
+        assert_eq!(delta.removed(),["b"]);
+        assert_eq!(delta.changed()[0].0,"a");
+        assert_eq!(delta.changed()[0].1,"hello".diff("help"));
+        let mut map = before.clone();
+        delta.transform_nested(&mut map);
+        assert_eq!(map,after);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn map_delta_test_03() {
+        // Changing a missing key inserts it, removing one is ignored
+        let mut delta = MapDelta::new();
+        delta.change(1,'a');
+        delta.remove(2);
+        let mut map = HashMap::from([(3,'c')]);
+        map.transform(&delta);
+        assert_eq!(map,HashMap::from([(1,'a'),(3,'c')]));
+    }
+
+    fn btree_maps() -> Vec<BTreeMap<usize,usize>> {
+        (0..10).map(|i| (0..i).map(|j| ((j*i+3)%7,(j*i)%5)).collect()).collect()
+    }
+
+    #[test]
+    fn map_delta_test_04() {
+        for a in &btree_maps() {
+            for b in &btree_maps() {
+                let delta = a.diff(b);
+                assert_eq!(delta.is_empty(),a == b);
+                assert!(delta.removed().is_sorted() && delta.inserted().is_sorted() && delta.changed().is_sorted());
+                let mut m = a.clone();
+                m.transform(&delta);
+                assert_eq!(m,*b);
+                // Range-restricted diffs only affect keys in range
+                let mut m = a.clone();
+                m.transform(&diff_btree_range(a,b,2..5));
+                for (k,v) in &m {
+                    let expected = if (2..5).contains(k) { b.get(k) } else { a.get(k) };
+                    assert_eq!(Some(v),expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn map_delta_test_05() {
+        let before = BTreeMap::from([(1,"abc".to_string()),(2,"def".to_string())]);
+        let after = BTreeMap::from([(0,"x".to_string()),(1,"abd".to_string()),(2,"def".to_string())]);
+        let delta = diff_btree_nested(&before,&after);
+        assert_eq!(delta.inserted(),[(0,"x".to_string())]);
+        assert_eq!(delta.changed(),[(1,"abc".diff("abd"))]);
+        let mut map = before.clone();
+        delta.transform_nested(&mut map);
+        assert_eq!(map,after);
+    }
+}
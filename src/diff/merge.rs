@@ -0,0 +1,202 @@
+use alloc::vec::Vec;
+use crate::util::Region;
+use super::VecDelta;
+
+/// Describes a conflict arising when merging two deltas (see
+/// `merge()`).  That is, a region of the base sequence which both
+/// deltas modify in different ways.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Conflict {
+    /// The region of the base sequence affected by the conflicting
+    /// rewrites from both sides.
+    pub base: Region,
+    /// The region of the base sequence rewritten by the first delta.
+    pub left: Region,
+    /// The region of the base sequence rewritten by the second delta.
+    pub right: Region
+}
+
+/// Perform a _three-way merge_ of two deltas computed independently
+/// against the same `base` sequence.  If successful, this returns a
+/// single delta combining the rewrites of both.  Rewrites from either
+/// side are combined freely when they affect disjoint regions of the
+/// base sequence.  However, when they overlap (or insert at the same
+/// position) then either: **(a)** both sides have the same effect on
+/// the region in question, in which case it is rewritten once; or
+/// **(b)** there is a conflict.  In the latter case, all conflicts
+/// found are returned instead.  For example:
+///
+/// ```txt
+///  base:   a b c d e f
+///  left:   a X c d e f    (1;1;"X")
+///  right:  a b c d Y Z f  (4;1;"YZ")
+///  merged: a X c d Y Z f
+/// ```
+pub fn merge<T:Clone+PartialEq>(base: &[T], d1: &VecDelta<T>, d2: &VecDelta<T>) -> Result<VecDelta<T>,Vec<Conflict>> {
+    let left : Vec<(Region,&[T])> = d1.iter_source().collect();
+    let right : Vec<(Region,&[T])> = d2.iter_source().collect();
+    let mut rewrites : Vec<(Region,Vec<T>)> = Vec::new();
+    let mut conflicts = Vec::new();
+    let (mut i, mut j) = (0,0);
+    while i < left.len() || j < right.len() {
+        // Start a new cluster from whichever hunk comes first
+        let (mut ci, mut cj) = (i,j);
+        let first = if j == right.len() || (i < left.len() && (left[i].0.offset,left[i].0.length) <= (right[j].0.offset,right[j].0.length)) {
+            ci += 1;
+            left[i].0
+        } else {
+            cj += 1;
+            right[j].0
+        };
+        let mut cluster = first;
+        // Extend cluster with overlapping hunks from either side
+        loop {
+            if ci < left.len() && overlaps(&cluster,&left[ci].0) {
+                cluster = union(&cluster,&left[ci].0);
+                ci += 1;
+            } else if cj < right.len() && overlaps(&cluster,&right[cj].0) {
+                cluster = union(&cluster,&right[cj].0);
+                cj += 1;
+            } else {
+                break;
+            }
+        }
+        // Resolve cluster
+        let (ls,rs) = (&left[i..ci], &right[j..cj]);
+        if rs.is_empty() || ls.is_empty() {
+            for (r,d) in ls.iter().chain(rs) {
+                rewrites.push((*r,d.to_vec()));
+            }
+        } else {
+            let lhs = expand(base,&cluster,ls);
+            let rhs = expand(base,&cluster,rs);
+            if lhs == rhs {
+                rewrites.push((cluster,lhs));
+            } else {
+                let left = union(&ls[0].0,&ls[ls.len()-1].0);
+                let right = union(&rs[0].0,&rs[rs.len()-1].0);
+                conflicts.push(Conflict{base: cluster, left, right});
+            }
+        }
+        i = ci;
+        j = cj;
+    }
+    //
+    if conflicts.is_empty() {
+        Ok(VecDelta::from_source(rewrites.iter().map(|(r,d)| (*r,d.as_slice()))))
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// Check whether a given hunk (which does not start before a given
+/// cluster) must be resolved together with it.  This is the case when
+/// the hunk starts within the cluster or, since the order of
+/// application would otherwise be ambiguous, when the cluster is an
+/// insertion at the point where the hunk starts.
+fn overlaps(cluster: &Region, hunk: &Region) -> bool {
+    let c = cluster.as_range();
+    hunk.offset < c.end || (hunk.offset == c.end && c.is_empty())
+}
+
+/// Determine the smallest region enclosing two regions.
+fn union(r1: &Region, r2: &Region) -> Region {
+    let start = r1.offset.min(r2.offset);
+    let end = r1.as_range().end.max(r2.as_range().end);
+    Region::new(start,end - start)
+}
+
+/// Determine the contents of a region of the base sequence after
+/// applying a given set of hunks (all of which lie within it).
+fn expand<T:Clone>(base: &[T], region: &Region, hunks: &[(Region,&[T])]) -> Vec<T> {
+    let mut out = Vec::new();
+    let mut cursor = region.offset;
+    for (r,d) in hunks {
+        out.extend_from_slice(&base[cursor..r.offset]);
+        out.extend_from_slice(d);
+        cursor = r.as_range().end;
+    }
+    out.extend_from_slice(&base[cursor..region.as_range().end]);
+    out
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod merge_tests {
+    use alloc::vec::Vec;
+    use crate::diff::{Conflict,Diff,VecDelta,merge};
+    use crate::util::Region;
+
+    #[test]
+    fn test_merge_01() {
+        // Disjoint edits
+        check("abcdef","aXcdef","abcdYZf",Ok("aXcdYZf"));
+    }
+
+    #[test]
+    fn test_merge_02() {
+        // No edits on one side
+        check("abcdef","abcdef","abXYef",Ok("abXYef"));
+        check("abcdef","abXYef","abcdef",Ok("abXYef"));
+    }
+
+    #[test]
+    fn test_merge_03() {
+        // Same edit on both sides
+        check("abcdef","abXdef","abXdef",Ok("abXdef"));
+    }
+
+    #[test]
+    fn test_merge_04() {
+        // Adjacent edits
+        check("abcdef","aXcdef","abYdef",Ok("aXYdef"));
+    }
+
+    #[test]
+    fn test_merge_05() {
+        // Overlapping edits
+        let c = Conflict{base: Region::new(1,3), left: Region::new(1,2), right: Region::new(2,2)};
+        check("abcdef","aXYdef","abZWef",Err(&[c]));
+    }
+
+    #[test]
+    fn test_merge_06() {
+        // Insertions at the same point
+        let c = Conflict{base: Region::new(2,0), left: Region::new(2,0), right: Region::new(2,0)};
+        check("abcdef","abXcdef","abYcdef",Err(&[c]));
+    }
+
+    #[test]
+    fn test_merge_07() {
+        // Convergent overlapping edits
+        let base : Vec<char> = "abcdef".chars().collect();
+        let after : Vec<char> = "abXYef".chars().collect();
+        // Rewrites "bcd" rather than just "cd"
+        let d1 = VecDelta::from_source([(Region::new(1,3),&['b','X','Y'][..])]);
+        let d2 = base.diff(&after);
+        assert_ne!(d1,d2);
+        let mut vec = base.clone();
+        merge(&base,&d1,&d2).unwrap().transform(&mut vec);
+        assert_eq!(vec,after);
+    }
+
+    fn check(base: &str, left: &str, right: &str, expected: Result<&str,&[Conflict]>) {
+        let base : Vec<char> = base.chars().collect();
+        let left : Vec<char> = left.chars().collect();
+        let right : Vec<char> = right.chars().collect();
+        let d1 = base.diff(&left);
+        let d2 = base.diff(&right);
+        match (merge(&base,&d1,&d2),expected) {
+            (Ok(d),Ok(e)) => {
+                let mut vec = base.clone();
+                d.transform(&mut vec);
+                assert_eq!(vec,e.chars().collect::<Vec<_>>());
+            }
+            (Err(cs),Err(es)) => assert_eq!(cs,es),
+            (r,e) => panic!("unexpected merge result {r:?} (expected {e:?})")
+        }
+    }
+}
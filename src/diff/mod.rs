@@ -1,12 +1,96 @@
+mod algorithm;
+mod anchored;
+mod bit_vec;
+mod bsdiff;
+mod builder;
+mod checked;
+mod chunk;
+/// Pluggable compression of binary deltas.
+pub mod compress;
+mod edit_script;
+mod encoding;
+/// Rendering and parsing of deltas in standard textual formats.
+pub mod format;
+/// Diffing of directory trees on disk.
+#[cfg(feature = "std")]
+pub mod fs;
+/// Encoding and decoding of git packfile deltas.
+pub mod git;
+mod grid;
+mod histogram;
+mod hirschberg;
+mod invertible;
+mod keyed;
+/// Structural diffing of JSON values.
+#[cfg(feature = "json")]
+pub mod json;
+mod map_delta;
+mod merge;
+mod multiset;
+mod myers;
+mod op_delta;
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "im")]
+mod persistent;
 mod slice;
+mod slice_delta;
+/// Diffing of line-oriented inputs read via `BufRead`.
+#[cfg(feature = "std")]
+pub mod stream;
 mod rewrite;
+#[cfg(feature = "ropey")]
+mod rope;
+/// Remote synchronisation of byte sequences in the style of `rsync`.
+pub mod rolling;
+mod set_delta;
+mod str_delta;
+mod table;
+/// Diffing of text by lines or grapheme clusters.
+pub mod text;
+/// Diffing of ordered trees, such as abstract syntax trees.
+pub mod tree;
+/// Encoding and decoding of VCDIFF (RFC 3284) binary deltas.
+pub mod vcdiff;
 mod vec_delta;
+mod wrapper;
 
-use std::result::Result;
+use core::ops::Range;
+use core::result::Result;
 
+pub use algorithm::*;
+pub use anchored::{diff_anchored,anchored_subsequence,diff_patience,patience_subsequence};
+pub use bit_vec::*;
+pub use bsdiff::*;
+pub use builder::*;
+pub use checked::*;
+pub use chunk::*;
+pub use edit_script::*;
+pub use encoding::*;
+pub use grid::*;
+pub use histogram::*;
+pub use hirschberg::*;
+pub use invertible::*;
+pub use keyed::*;
+pub use map_delta::*;
+pub use merge::*;
+pub use multiset::*;
+pub use myers::*;
+pub use op_delta::*;
+pub use options::*;
+#[cfg(feature = "rayon")]
+pub use parallel::*;
 pub use rewrite::*;
+pub use set_delta::*;
 pub use vec_delta::*;
+pub use wrapper::*;
+#[cfg(feature = "derive")]
+pub use delta_inc_derive::{Diff,Transform};
 pub use slice::*;
+pub use slice_delta::*;
+pub use str_delta::*;
+pub use table::*;
 
 // ===================================================================
 // Diff
@@ -59,3 +143,23 @@ pub trait TryTransform : Sized {
     /// potentially updated version of this item.
     fn try_transform(&mut self,d: &Self::Delta) -> Result<(),Self::Error>;
 }
+
+// ===================================================================
+// MutableSequence
+// ===================================================================
+
+/// A trait describing a sequence of items which can be modified by
+/// replacing a contiguous range of items (e.g. a rope or a gap
+/// buffer).  Any such sequence can be transformed by a `VecDelta`,
+/// where each rewrite is applied in turn as a single `splice()`.
+pub trait MutableSequence {
+    /// The type of items held in this sequence.
+    type Item;
+    /// Get the number of items in this sequence.
+    fn len(&self) -> usize;
+    /// Check whether this sequence contains any items or not.
+    fn is_empty(&self) -> bool { self.len() == 0 }
+    /// Replace a given range of items in this sequence with a given
+    /// set of items.  This may `panic` if the range is out of bounds.
+    fn splice(&mut self, range: Range<usize>, items: &[Self::Item]);
+}
@@ -0,0 +1,171 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// A `MultisetDelta` describes the differences between two _multisets_
+/// (a.k.a. bags), where the order of items is irrelevant but the number
+/// of occurrences of each item is not.  This is given by the number of
+/// occurrences of each item which are _added_ and _removed_, where no
+/// item is both added and removed.  For example:
+///
+/// ```
+/// use delta_inc::diff::diff_multiset;
+///
+/// let before = ["a","b","b","c"];
+/// let after = ["b","c","c","a","d"];
+/// let delta = diff_multiset(&before,&after);
+/// assert_eq!(delta.added(),[("c",1),("d",1)]);
+/// assert_eq!(delta.removed(),[("b",1)]);
+/// let mut items = before.to_vec();
+/// delta.transform(&mut items);
+/// assert_eq!(items,["a","b","c","c","d"]);
+/// ```
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct MultisetDelta<T> {
+    /// Items which are added, along with the number of occurrences.
+    added: Vec<(T,usize)>,
+    /// Items which are removed, along with the number of occurrences.
+    removed: Vec<(T,usize)>
+}
+
+impl<T> MultisetDelta<T> {
+    /// Construct an empty `MultisetDelta`.
+    pub const fn new() -> Self { MultisetDelta{added: Vec::new(), removed: Vec::new()} }
+
+    /// Get the total number of occurrences added or removed by this
+    /// delta.
+    pub fn len(&self) -> usize { self.added.iter().chain(&self.removed).map(|(_,n)| n).sum() }
+
+    /// Check whether this delta adds or removes anything.
+    pub fn is_empty(&self) -> bool { self.added.is_empty() && self.removed.is_empty() }
+
+    /// Get the items added by this delta (in sorted order), along with
+    /// the number of occurrences of each.
+    pub fn added(&self) -> &[(T,usize)] { &self.added }
+
+    /// Get the items removed by this delta (in sorted order), along
+    /// with the number of occurrences of each.
+    pub fn removed(&self) -> &[(T,usize)] { &self.removed }
+
+    /// Construct the inverse of this delta (i.e. which adds the items
+    /// this removes, and vice versa).
+    pub fn invert(self) -> Self { MultisetDelta{added: self.removed, removed: self.added} }
+}
+
+impl<T:Ord+Clone> MultisetDelta<T> {
+    /// Apply this delta to a multiset represented by the number of
+    /// occurrences of each item.  Items whose count falls to zero are
+    /// removed.  This will `panic` if more occurrences of an item are
+    /// removed than are present.
+    pub fn transform_counts(&self, counts: &mut BTreeMap<T,usize>) {
+        for (item,n) in &self.removed {
+            let count = counts.get_mut(item).filter(|c| **c >= *n).expect("too many occurrences removed");
+            *count -= n;
+            if *count == 0 { counts.remove(item); }
+        }
+        for (item,n) in &self.added { *counts.entry(item.clone()).or_default() += n; }
+    }
+
+    /// Apply this delta to a multiset represented by a sequence of
+    /// items.  The last occurrences of removed items are dropped, and
+    /// added items are appended (in sorted order).  This will `panic`
+    /// if more occurrences of an item are removed than are present.
+    pub fn transform(&self, items: &mut Vec<T>) {
+        let mut remaining : BTreeMap<&T,usize> = self.removed.iter().map(|(t,n)| (t,*n)).collect();
+        let mut kept = Vec::with_capacity(items.len());
+        for item in core::mem::take(items).into_iter().rev() {
+            match remaining.get_mut(&item) {
+                Some(n) if *n > 0 => *n -= 1,
+                _ => kept.push(item)
+            }
+        }
+        assert!(remaining.values().all(|&n| n == 0),"too many occurrences removed");
+        kept.reverse();
+        kept.extend(self.added.iter().flat_map(|(t,n)| core::iter::repeat_n(t,*n).cloned()));
+        *items = kept;
+    }
+}
+
+impl<T> Default for MultisetDelta<T> {
+    fn default() -> Self { Self::new() }
+}
+
+/// Count the occurrences of each item in a given sequence.
+pub fn counts_of<T:Ord+Clone>(items: &[T]) -> BTreeMap<T,usize> {
+    let mut counts = BTreeMap::new();
+    for item in items { *counts.entry(item.clone()).or_default() += 1; }
+    counts
+}
+
+/// Compute a delta between two sequences treated as multisets (i.e.
+/// ignoring the order of items), in terms of the number of occurrences
+/// of each item added or removed.
+pub fn diff_multiset<T:Ord+Clone>(lhs: &[T], rhs: &[T]) -> MultisetDelta<T> {
+    diff_counts(&counts_of(lhs),&counts_of(rhs))
+}
+
+/// Compute a delta between two multisets represented by the number of
+/// occurrences of each item (see `counts_of()`).  Items with a count of
+/// zero are considered absent.
+pub fn diff_counts<T:Ord+Clone>(lhs: &BTreeMap<T,usize>, rhs: &BTreeMap<T,usize>) -> MultisetDelta<T> {
+    let mut delta = MultisetDelta::new();
+    let (mut l,mut r) = (lhs.iter().peekable(),rhs.iter().peekable());
+    loop {
+        let (item,before,after) = match (l.peek(),r.peek()) {
+            (None,None) => break,
+            (Some(&(t,&n)),None) => { l.next(); (t,n,0) }
+            (None,Some(&(t,&n))) => { r.next(); (t,0,n) }
+            (Some(&(t1,&n1)),Some(&(t2,&n2))) => match t1.cmp(t2) {
+                Ordering::Less => { l.next(); (t1,n1,0) }
+                Ordering::Greater => { r.next(); (t2,0,n2) }
+                Ordering::Equal => { l.next(); r.next(); (t1,n1,n2) }
+            }
+        };
+        match before.cmp(&after) {
+            Ordering::Less => delta.added.push((item.clone(),after-before)),
+            Ordering::Greater => delta.removed.push((item.clone(),before-after)),
+            Ordering::Equal => {}
+        }
+    }
+    delta
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod multiset_tests {
+    use alloc::vec::Vec;
+    use super::{counts_of,diff_multiset};
+
+    fn seqs() -> Vec<Vec<usize>> {
+        (0..16).map(|i| (0..i).map(|j| (j*i+3)%5).collect()).collect()
+    }
+
+    #[test]
+    fn multiset_test_01() {
+        for a in seqs() {
+            for b in seqs() {
+                let delta = diff_multiset(&a,&b);
+                let mut counts = counts_of(&a);
+                delta.transform_counts(&mut counts);
+                assert_eq!(counts,counts_of(&b));
+                let mut items = a.clone();
+                delta.transform(&mut items);
+                assert_eq!(counts_of(&items),counts_of(&b));
+                let mut items = b.clone();
+                delta.clone().invert().transform(&mut items);
+                assert_eq!(counts_of(&items),counts_of(&a));
+            }
+        }
+    }
+
+    #[test]
+    fn multiset_test_02() {
+        // Reordering gives an empty delta
+        assert!(diff_multiset(&[1,2,2,3],&[2,3,1,2]).is_empty());
+        let delta = diff_multiset(&[1,1,1,2],&[2,2,3]);
+        assert_eq!((delta.added(),delta.removed(),delta.len()),(&[(2,1),(3,1)][..],&[(1,3)][..],5));
+    }
+}
@@ -0,0 +1,340 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use super::{VecDelta,common_prefix_suffix,diff_trimmed};
+use super::slice::{common_prefix_len,common_suffix_len,extract_delta};
+
+/// Compute a delta between two slices using Myers' `O(ND)` difference
+/// algorithm, where `N` is the combined length of both slices and `D`
+/// is the size of the minimal edit script between them.  Since the
+/// common prefix and suffix are stripped first, and typical edits are
+/// small, this is much faster than the quadratic
+/// `longest_common_subsequence()`.  Furthermore, only linear working
+/// space is required (see `myers_subsequence()`).
+pub fn diff_myers<T:Clone+PartialEq>(lhs: &[T], rhs: &[T]) -> VecDelta<T> {
+    diff_trimmed(lhs, rhs, myers_subsequence)
+}
+
+/// Compute a delta between two slices using Myers' algorithm (see
+/// `diff_myers()`), but giving up once the cost of the edit script
+/// being sought exceeds `max_cost` (see
+/// `myers_subsequence_bounded()`).  In such case, a single rewrite
+/// replacing everything between the common prefix and suffix is
+/// returned instead.  This allows interactive tools to bound the time
+/// spent diffing, whilst still obtaining minimal deltas when the
+/// inputs are similar.
+pub fn diff_bounded<T:Clone+PartialEq>(lhs: &[T], rhs: &[T], max_cost: usize) -> VecDelta<T> {
+    let (pre,suf) = common_prefix_suffix(lhs,rhs);
+    let lhs = &lhs[pre..lhs.len()-suf];
+    let rhs = &rhs[pre..rhs.len()-suf];
+    match myers_subsequence_bounded(lhs,rhs,max_cost) {
+        Some(mapping) => extract_delta(pre, &mapping, rhs),
+        None => {
+            let mut delta = VecDelta::new();
+            unsafe { delta.push_raw(pre .. pre + lhs.len(), rhs); }
+            delta
+        }
+    }
+}
+
+/// Determine the _edit distance_ between two slices (see
+/// `edit_distance()`), provided it is at most `max_cost`.  Otherwise,
+/// `None` is returned.  This uses the forward pass of Myers' algorithm
+/// only, without recording the frontier for each round.  Thus, it
+/// requires `O(max_cost)` space and `O((N+max_cost)*max_cost)` time,
+/// which makes it ideal for cheaply checking whether two sequences
+/// are within some distance of each other.
+pub fn edit_distance_bounded<T:PartialEq>(lhs: &[T], rhs: &[T], max_cost: usize) -> Option<usize> {
+    let (pre,suf) = common_prefix_suffix(lhs,rhs);
+    let lhs = &lhs[pre..lhs.len()-suf];
+    let rhs = &rhs[pre..rhs.len()-suf];
+    let (n,m) = (lhs.len() as isize, rhs.len() as isize);
+    let limit = max_cost.min(lhs.len() + rhs.len()) as isize;
+    // Furthest reaching x on each diagonal, offset by limit.
+    let mut v = vec![0isize; (2 * limit + 3) as usize];
+    for d in 0..=limit {
+        for k in (-d..=d).step_by(2) {
+            let kk = (k + limit + 1) as usize;
+            let mut x = if k == -d || (k != d && v[kk-1] < v[kk+1]) {
+                v[kk+1]
+            } else {
+                v[kk-1] + 1
+            };
+            let mut y = x - k;
+            if x < n && y < m {
+                let snake = common_prefix_len(&lhs[x as usize..],&rhs[y as usize..]) as isize;
+                x += snake;
+                y += snake;
+            }
+            v[kk] = x;
+            if x >= n && y >= m { return Some(d as usize); }
+        }
+    }
+    None
+}
+
+/// Determine a longest common subsequence of two slices using Myers'
+/// greedy algorithm.  This produces a mapping from elements in `lhs`
+/// to elements in `rhs` in the same form as
+/// `longest_common_subsequence()`.  The algorithm explores
+/// _diagonals_ `k = x - y` of the edit graph, recording for each
+/// number of edits `d` the furthest reaching point on each diagonal.
+/// Rather than recording these frontiers to recover the path (which
+/// requires `O(D^2)` space), the _linear space refinement_ is used.
+/// That is, the search proceeds from both ends at once until the two
+/// meet at the _middle snake_ of an optimal path, and then each half
+/// is solved independently.  Thus, the time taken remains `O(ND)`
+/// whilst the working space is only `O(N)`.
+///
+/// # References
+///
+/// * _An O(ND) Difference Algorithm and Its Variations_, E. W. Myers.
+///   Algorithmica, 1986.
+pub fn myers_subsequence<T:PartialEq>(lhs: &[T], rhs: &[T]) -> Vec<Option<usize>> {
+    let mut res = vec![None;lhs.len()];
+    // Subproblems are given as (lstart,lend,rstart,rend)
+    let mut work = vec![(0,lhs.len(),0,rhs.len())];
+    while let Some((ls,le,rs,re)) = work.pop() {
+        let (l,r) = (&lhs[ls..le],&rhs[rs..re]);
+        // Matches in the common prefix and suffix are part of some
+        // optimal path.
+        let pre = common_prefix_len(l,r);
+        let suf = common_suffix_len(&l[pre..],&r[pre..]);
+        for k in 0..pre { res[ls+k] = Some(rs+k); }
+        for k in 1..=suf { res[le-k] = Some(re-k); }
+        let (ls,le,rs,re) = (ls+pre,le-suf,rs+pre,re-suf);
+        if ls == le || rs == re { continue; }
+        // Split around the middle snake (if any)
+        if let Some((x,y)) = middle_snake(&lhs[ls..le],&rhs[rs..re]) {
+            work.push((ls,ls+x,rs,rs+y));
+            work.push((ls+x,le,rs+y,re));
+        }
+    }
+    res
+}
+
+/// Find a point on an optimal path through the edit graph of two
+/// slices, by searching forwards from the start and backwards from the
+/// end until the two searches overlap.  Both slices are non-empty, and
+/// differ in their first and last elements.  The point returned splits
+/// the problem such that each half has (roughly) half of the edits.
+/// When no elements can be matched, `None` is returned.
+fn middle_snake<T:PartialEq>(lhs: &[T], rhs: &[T]) -> Option<(usize,usize)> {
+    let (n,m) = (lhs.len() as isize, rhs.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d + 1;
+    // Furthest reaching x on each diagonal (offset), where -1 means
+    // the diagonal has not been reached.
+    let mut fwd = vec![-1isize; (2 * offset + 1) as usize];
+    let mut bwd = vec![-1isize; (2 * offset + 1) as usize];
+    fwd[(offset+1) as usize] = 0;
+    bwd[(offset+1) as usize] = 0;
+    let delta = n - m;
+    // When delta is odd, the searches meet during a forward round.
+    let front = delta % 2 != 0;
+    // Diagonals which have run off the edit graph are skipped
+    let (mut fstart,mut fend,mut bstart,mut bend) = (0,0,0,0);
+    for d in 0..max_d {
+        for k in (-d+fstart..=d-fend).step_by(2) {
+            let kk = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && fwd[kk-1] < fwd[kk+1]) { fwd[kk+1] } else { fwd[kk-1] + 1 };
+            let y = x - k;
+            if x < n && y < m { x += common_prefix_len(&lhs[x as usize..],&rhs[y as usize..]) as isize; }
+            fwd[kk] = x;
+            if x > n {
+                fend += 2;
+            } else if x - k > m {
+                fstart += 2;
+            } else if front {
+                let bk = offset + delta - k;
+                if bk >= 0 && bk < bwd.len() as isize && bwd[bk as usize] != -1 && x >= n - bwd[bk as usize] {
+                    return Some((x as usize,(x - k) as usize));
+                }
+            }
+        }
+        for k in (-d+bstart..=d-bend).step_by(2) {
+            let kk = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && bwd[kk-1] < bwd[kk+1]) { bwd[kk+1] } else { bwd[kk-1] + 1 };
+            let y = x - k;
+            if x < n && y < m { x += common_suffix_len(&lhs[..(n-x) as usize],&rhs[..(m-y) as usize]) as isize; }
+            bwd[kk] = x;
+            if x > n {
+                bend += 2;
+            } else if x - k > m {
+                bstart += 2;
+            } else if !front {
+                let fk = offset + delta - k;
+                if fk >= 0 && fk < fwd.len() as isize && fwd[fk as usize] != -1 {
+                    let fx = fwd[fk as usize];
+                    if fx >= n - x { return Some((fx as usize,(fx - (fk - offset)) as usize)); }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Determine a longest common subsequence of two slices using Myers'
+/// greedy algorithm (see `myers_subsequence()`), provided the minimal
+/// edit script between them has a cost of at most `max_cost`.  Here,
+/// the cost is the number of elements deleted plus the number
+/// inserted.  Otherwise, the search is abandoned and `None` returned.
+/// The cost is first checked using `edit_distance_bounded()`, which
+/// bounds the time taken to `O((N+max_cost)*max_cost)` when the cost is
+/// exceeded.
+pub fn myers_subsequence_bounded<T:PartialEq>(lhs: &[T], rhs: &[T], max_cost: usize) -> Option<Vec<Option<usize>>> {
+    edit_distance_bounded(lhs,rhs,max_cost)?;
+    Some(myers_subsequence(lhs,rhs))
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod myers_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::{diff_bounded,diff_myers,edit_distance,edit_distance_bounded,longest_common_subsequence,myers_subsequence,myers_subsequence_bounded};
+
+    #[test]
+    fn myers_test_01() {
+        let v = myers_subsequence::<usize>(&[],&[]);
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn myers_test_02() {
+        let v = myers_subsequence(&[0],&[]);
+        assert_eq!(v,vec![None]);
+    }
+
+    #[test]
+    fn myers_test_03() {
+        let v = myers_subsequence(&[0],&[0]);
+        assert_eq!(v,vec![Some(0)]);
+    }
+
+    #[test]
+    fn myers_test_04() {
+        let v = myers_subsequence(&[0,1],&[1,0]);
+        assert_eq!(matched(&v),1);
+    }
+
+    #[test]
+    fn myers_test_05() {
+        let v = myers_subsequence(&['a','b','b','c','b','c','d'],&['b','b','e','c','d','e']);
+        assert_eq!(matched(&v),4);
+    }
+
+    #[test]
+    fn myers_test_06() {
+        // Same length as LCS for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..20).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let v1 = myers_subsequence(a,b);
+                let v2 = longest_common_subsequence(a,b);
+                assert_eq!(matched(&v1),matched(&v2));
+                let mut vec = a.clone();
+                diff_myers(a,b).transform(&mut vec);
+                assert_eq!(&vec,b);
+            }
+        }
+    }
+
+    #[test]
+    fn myers_test_07() {
+        // Large input with small edit
+        let from : Vec<usize> = (0..100_000).collect();
+        let mut to = from.clone();
+        to.insert(500,0);
+        to.remove(70_000);
+        let mut vec = from.clone();
+        let delta = diff_myers(&from,&to);
+        assert_eq!(delta.len(),2);
+        delta.transform(&mut vec);
+        assert_eq!(vec,to);
+    }
+
+    #[test]
+    fn myers_test_08() {
+        assert!(myers_subsequence_bounded(&[1,2,3],&[1,2,3],0).is_some());
+        assert!(myers_subsequence_bounded(&[1,2,3],&[1,3],0).is_none());
+        assert!(myers_subsequence_bounded(&[1,2,3],&[1,3],1).is_some());
+        assert!(myers_subsequence_bounded(&[1,2,3],&[1,4,3],1).is_none());
+        assert!(myers_subsequence_bounded(&[1,2,3],&[1,4,3],2).is_some());
+    }
+
+    #[test]
+    fn myers_test_09() {
+        // Within budget gives minimal delta
+        let delta = diff_bounded(&[1,2,3,4,5],&[1,6,3,4,7],4);
+        assert_eq!(delta.len(),2);
+        // Exceeding budget gives single rewrite
+        let mut vec = vec![1,2,3,4,5];
+        let delta = diff_bounded(&[1,2,3,4,5],&[1,6,3,4,7],3);
+        assert_eq!(delta.len(),1);
+        assert_eq!(delta.get(0).unwrap().data(),&[6,3,4,7]);
+        delta.transform(&mut vec);
+        assert_eq!(vec,vec![1,6,3,4,7]);
+        // Unbounded budget gives minimal delta
+        let seqs : Vec<Vec<usize>> = (0..12).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                assert_eq!(diff_bounded(a,b,usize::MAX),diff_myers(a,b));
+                assert_eq!(myers_subsequence_bounded(a,b,usize::MAX),Some(myers_subsequence(a,b)));
+            }
+        }
+    }
+
+    #[test]
+    fn myers_test_10() {
+        assert_eq!(edit_distance_bounded::<usize>(&[],&[],0),Some(0));
+        assert_eq!(edit_distance_bounded(&[1,2,3],&[1,4,3],1),None);
+        assert_eq!(edit_distance_bounded(&[1,2,3],&[1,4,3],2),Some(2));
+        assert_eq!(edit_distance_bounded(&[1,2,3],&[1,4,3],10),Some(2));
+        assert_eq!(edit_distance_bounded(&[1,2,3],&[],10),Some(3));
+        assert_eq!(edit_distance_bounded(&[],&[1,2,3],10),Some(3));
+    }
+
+    #[test]
+    fn myers_test_11() {
+        // Agrees with unbounded edit distance
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let d = edit_distance(a,b);
+                assert_eq!(edit_distance_bounded(a,b,d),Some(d));
+                assert_eq!(edit_distance_bounded(a,b,a.len()+b.len()),Some(d));
+                if d > 0 { assert_eq!(edit_distance_bounded(a,b,d-1),None); }
+            }
+        }
+        // Budgets beyond isize::MAX
+        let x = [1,2,3];
+        assert_eq!(edit_distance_bounded(&x,&x,usize::MAX),Some(0));
+        assert_eq!(edit_distance_bounded(&x,&[2,3],usize::MAX),Some(1));
+        assert_eq!(edit_distance_bounded(&x,&[4],isize::MAX as usize + 1),Some(4));
+    }
+
+    #[test]
+    fn myers_test_12() {
+        // Large dissimilar inputs (which require linear space)
+        let a : Vec<usize> = (0..2000).collect();
+        let b : Vec<usize> = (2000..4000).collect();
+        assert_eq!(matched(&myers_subsequence(&a,&b)),0);
+        let b : Vec<usize> = (0..8000).map(|i| if i % 2 == 0 { i / 2 } else { 4000 + i }).collect();
+        let a : Vec<usize> = (0..4000).collect();
+        let mapping = myers_subsequence(&a,&b);
+        assert_eq!(matched(&mapping),4000);
+        assert!(mapping.iter().enumerate().all(|(i,m)| m.map(|j| b[j]) == Some(a[i])));
+        assert_eq!(myers_subsequence_bounded(&a[..1000],&b[..2000],1000).map(|m| matched(&m)),Some(1000));
+        assert_eq!(myers_subsequence_bounded(&a[..1000],&b[..2000],999),None);
+    }
+
+    fn matched(mapping: &[Option<usize>]) -> usize {
+        // Check mapping is strictly increasing
+        let ms : Vec<usize> = mapping.iter().flatten().copied().collect();
+        assert!(ms.windows(2).all(|w| w[0] < w[1]));
+        ms.len()
+    }
+}
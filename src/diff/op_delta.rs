@@ -0,0 +1,401 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+use crate::util::Region;
+use super::{RunRewrite,VecDelta,VecRewrite,common_prefix_suffix,myers_subsequence};
+use super::slice::extract_rewrites;
+
+/// An individual operation within an `OpDelta`.  Observe that all
+/// regions and positions are given in terms of the _original_
+/// sequence.
+#[derive(Clone,Debug,PartialEq)]
+pub enum Op<T> {
+    /// Replace a region of the original sequence with new data, as
+    /// for a single rewrite within a `VecDelta`.
+    Rewrite(VecRewrite<T>),
+    /// Move a block of elements from one place to another.  The
+    /// block is removed from region `from`, and reinserted
+    /// immediately before the element at position `to`.  A move
+    /// carries no data, since the elements involved already exist in
+    /// the original sequence.
+    Move{from: Region, to: usize},
+    /// Swap the two adjacent elements at positions `i` and `i+1`.
+    /// Like a move, a swap carries no data.
+    Swap(usize),
+    /// Replace a region of the original sequence with a run of copies
+    /// of a given item.  This is a _run-length encoded_ rewrite, which
+    /// avoids materialising a run of identical elements (e.g. when
+    /// filling with spaces or zeros).
+    Fill(RunRewrite<T>)
+}
+
+/// An `OpDelta` is a delta which, in addition to rewrites, can
+/// include richer operations such as moving a block of elements.
+/// Unlike `VecDelta`, all operations are expressed in terms of the
+/// _original sequence_ and are applied simultaneously.  Consider the
+/// following:
+///
+/// ```txt
+///  0 1 2 3 4 5 6 7
+/// +-+-+-+-+-+-+-+-+
+/// |a|b|c|d|e|f|g|h|
+/// +-+-+-+-+-+-+-+-+
+///   | |       ^
+///   +-+-------+
+/// ```
+///
+/// Here, the block `"bc"` is moved to just before `g`, giving
+/// `"adefbcgh"`.  This is encoded as `Move{from: (1;2), to: 6}`.
+/// As a `VecDelta` this would be encoded as `(1;2;""),(4;0;"bc")`,
+/// which duplicates the data being moved.  Operations in an `OpDelta`
+/// must not overlap, and no operation may insert data strictly within
+/// the region removed by another.
+#[derive(Clone,Debug,PartialEq)]
+pub struct OpDelta<T> {
+    ops: Vec<Op<T>>
+}
+
+impl<T> OpDelta<T> {
+    /// Construct an empty `OpDelta`.
+    pub const fn new() -> Self { OpDelta{ops: Vec::new()} }
+
+    /// Get the number of operations in this delta.
+    pub fn len(&self) -> usize { self.ops.len() }
+
+    /// Check whether this delta contains any operations or not.
+    pub fn is_empty(&self) -> bool { self.ops.is_empty() }
+
+    /// Get the `ith` operation in this delta.
+    pub fn get(&self, ith: usize) -> Option<&Op<T>> { self.ops.get(ith) }
+
+    /// Iterate the operations in this delta.
+    pub fn iter(&self) -> core::slice::Iter<'_,Op<T>> { self.ops.iter() }
+
+    /// Append an operation onto this delta.
+    pub fn push(&mut self, op: Op<T>) { self.ops.push(op); }
+
+    /// Append an operation onto this delta which replaces a given range
+    /// of the original sequence with `count` copies of an item (see
+    /// `Op::Fill`).
+    pub fn push_fill(&mut self, range: Range<usize>, item: T, count: usize) {
+        self.ops.push(Op::Fill(RunRewrite::new(range.into(),item,count)));
+    }
+}
+
+impl<T> Default for OpDelta<T> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<T:Clone+PartialEq> OpDelta<T> {
+    /// Construct an `OpDelta` from a `VecDelta` which was generated
+    /// from a given `before` sequence.  Any block of at least
+    /// `min_len` elements which is deleted by one rewrite and inserted
+    /// verbatim by another is encoded as a single `Op::Move`.  All
+    /// other rewrites are retained as they are (though their regions
+    /// are converted into source coordinates).
+    pub fn detect_moves(delta: &VecDelta<T>, before: &[T], min_len: usize) -> Self {
+        let min_len = min_len.max(1);
+        // Convert rewrites into source coordinates
+        let rewrites : Vec<(Region,&[T])> = delta.iter_source().collect();
+        // Pair up pure deletions with identical pure insertions
+        let mut paired : Vec<Option<usize>> = alloc::vec![None;rewrites.len()];
+        let mut used = alloc::vec![false;rewrites.len()];
+        for (j,&(rj,dj)) in rewrites.iter().enumerate() {
+            if rj.length != 0 || dj.len() < min_len { continue; }
+            let found = rewrites.iter().enumerate().position(|(i,&(ri,di))| {
+                !used[i] && di.is_empty() && ri.length == dj.len() && &before[ri.as_range()] == dj
+            });
+            if let Some(i) = found {
+                used[i] = true;
+                used[j] = true;
+                paired[j] = Some(i);
+            }
+        }
+        // Construct operations
+        let mut ops = Vec::new();
+        for (j,&(rj,dj)) in rewrites.iter().enumerate() {
+            if let Some(i) = paired[j] {
+                ops.push(Op::Move{from: rewrites[i].0, to: rj.offset});
+            } else if !used[j] {
+                ops.push(Op::Rewrite(VecRewrite::new(rj,dj.to_vec())));
+            }
+        }
+        OpDelta{ops}
+    }
+
+    /// Construct an `OpDelta` from a `VecDelta` which was generated
+    /// from a given `before` sequence, such that any transposition of
+    /// two adjacent elements is encoded as a single `Op::Swap` (in
+    /// the spirit of the Damerau-Levenshtein distance).  Such a
+    /// transposition typically appears in a `VecDelta` as either a
+    /// single rewrite (e.g. `(0;2;"ba")`), or as a pair of rewrites
+    /// separated by at most one element (e.g. `(0;0;"b"),(1;1;"")`).
+    /// All other rewrites are retained as they are (though their
+    /// regions are converted into source coordinates).
+    pub fn detect_swaps(delta: &VecDelta<T>, before: &[T]) -> Self {
+        let rewrites : Vec<(Region,&[T])> = delta.iter_source().collect();
+        let mut ops = Vec::new();
+        let mut i = 0;
+        while i < rewrites.len() {
+            let (ri,di) = rewrites[i];
+            // Try this rewrite on its own
+            if let Some(p) = find_swap(&before[ri.as_range()],&[di]) {
+                ops.push(Op::Swap(ri.offset + p));
+                i += 1;
+                continue;
+            }
+            // Try this rewrite combined with the next
+            if let Some(&(rj,dj)) = rewrites.get(i+1) {
+                let end = ri.offset + ri.length;
+                if rj.offset - end <= 1 {
+                    let old = &before[ri.offset..rj.offset + rj.length];
+                    if let Some(p) = find_swap(old,&[di,&before[end..rj.offset],dj]) {
+                        ops.push(Op::Swap(ri.offset + p));
+                        i += 2;
+                        continue;
+                    }
+                }
+            }
+            ops.push(Op::Rewrite(VecRewrite::new(ri,di.to_vec())));
+            i += 1;
+        }
+        OpDelta{ops}
+    }
+
+    /// Compute an `OpDelta` between two slices (using Myers' algorithm,
+    /// as for `diff_myers()`), such that any rewrite whose data
+    /// consists of at least `min_len` copies of the same element is
+    /// encoded directly as an `Op::Fill`.  Such runs are never
+    /// materialised, since the data of each rewrite is borrowed from
+    /// `after` until its kind is known.
+    pub fn diff_runs(before: &[T], after: &[T], min_len: usize) -> Self {
+        let min_len = min_len.max(1);
+        let (pre,suf) = common_prefix_suffix(before,after);
+        let rhs = &after[pre..after.len()-suf];
+        let mapping = myers_subsequence(&before[pre..before.len()-suf],rhs);
+        let mut ops = Vec::new();
+        // Convert rewrites into source coordinates
+        let mut shift = 0isize;
+        extract_rewrites(pre, &mapping, rhs, |range,data| {
+            let region = Region::new((range.start as isize - shift) as usize,range.len());
+            shift += data.len() as isize - range.len() as isize;
+            ops.push(match data.first() {
+                Some(item) if data.len() >= min_len && data.iter().all(|d| d == item) => {
+                    Op::Fill(RunRewrite::new(region,item.clone(),data.len()))
+                }
+                _ => Op::Rewrite(VecRewrite::new(region,data.to_vec()))
+            });
+        });
+        OpDelta{ops}
+    }
+}
+
+/// Determine whether replacing `old` with the concatenation of
+/// `parts` amounts to swapping exactly two adjacent (and distinct)
+/// elements and, if so, return the position of the first.
+fn find_swap<T:PartialEq>(old: &[T], parts: &[&[T]]) -> Option<usize> {
+    let new : Vec<&T> = parts.iter().flat_map(|p| p.iter()).collect();
+    if old.len() != new.len() { return None; }
+    let pre = old.iter().zip(&new).take_while(|(l,r)| *l == **r).count();
+    let suf = old.iter().rev().zip(new.iter().rev()).take_while(|(l,r)| *l == **r).count();
+    if pre + suf + 2 != old.len() { return None; }
+    let (a,b) = (&old[pre],&old[pre+1]);
+    (a != b && new[pre] == b && new[pre+1] == a).then_some(pre)
+}
+
+impl<T:Clone> OpDelta<T> {
+    /// Apply this delta to a given `Vec`, thus transforming it.  This
+    /// operation will `panic` if this delta is malformed with respect
+    /// to the given `Vec`.
+    pub fn transform(&self, vec: &mut Vec<T>) {
+        // Events are (position,kind,op) where moves into a position
+        // come before anything removing from that position.
+        let mut events : Vec<(usize,u8,usize)> = Vec::new();
+        for (i,op) in self.ops.iter().enumerate() {
+            match op {
+                Op::Rewrite(rw) => events.push((rw.region().offset,1,i)),
+                Op::Move{from,to} => {
+                    events.push((*to,0,i));
+                    events.push((from.offset,1,i));
+                }
+                Op::Swap(p) => events.push((*p,1,i)),
+                Op::Fill(rw) => events.push((rw.region().offset,1,i))
+            }
+        }
+        events.sort();
+        //
+        let mut out = Vec::with_capacity(vec.len());
+        let mut cursor = 0;
+        for (pos,kind,i) in events {
+            out.extend_from_slice(&vec[cursor..pos.max(cursor)]);
+            cursor = cursor.max(pos);
+            match (&self.ops[i],kind) {
+                (Op::Rewrite(rw),_) => {
+                    out.extend_from_slice(rw.data());
+                    cursor = rw.region().as_range().end;
+                }
+                (Op::Move{from,..},0) => out.extend_from_slice(&vec[from.as_range()]),
+                (Op::Move{from,..},_) => cursor = from.as_range().end,
+                (Op::Swap(p),_) => {
+                    out.push(vec[p+1].clone());
+                    out.push(vec[*p].clone());
+                    cursor = p + 2;
+                }
+                (Op::Fill(rw),_) => {
+                    out.extend(rw.data().cloned());
+                    cursor = rw.region().as_range().end;
+                }
+            }
+        }
+        out.extend_from_slice(&vec[cursor..]);
+        *vec = out;
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod op_delta_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,Op,OpDelta,RunRewrite,VecRewrite};
+    use crate::util::Region;
+
+    #[test]
+    fn test_op_delta_01() {
+        // Move forwards
+        let mut vec = vec!['a','b','c','d','e','f','g','h'];
+        let mut d = OpDelta::new();
+        d.push(Op::Move{from: Region::new(1,2), to: 6});
+        d.transform(&mut vec);
+        assert_eq!(vec,['a','d','e','f','b','c','g','h']);
+    }
+
+    #[test]
+    fn test_op_delta_02() {
+        // Move backwards
+        let mut vec = vec!['a','b','c','d','e','f','g','h'];
+        let mut d = OpDelta::new();
+        d.push(Op::Move{from: Region::new(5,2), to: 0});
+        d.transform(&mut vec);
+        assert_eq!(vec,['f','g','a','b','c','d','e','h']);
+    }
+
+    #[test]
+    fn test_op_delta_03() {
+        // Move with rewrite
+        let mut vec = vec!['a','b','c','d','e','f','g','h'];
+        let mut d = OpDelta::new();
+        d.push(Op::Move{from: Region::new(0,2), to: 8});
+        d.push(Op::Rewrite(VecRewrite::new(Region::new(3,1),vec!['x','y'])));
+        d.transform(&mut vec);
+        assert_eq!(vec,['c','x','y','e','f','g','h','a','b']);
+    }
+
+    #[test]
+    fn test_op_delta_04() {
+        check(&[1,2,3,4,5,6],&[4,5,6,1,2,3],2,1);
+    }
+
+    #[test]
+    fn test_op_delta_05() {
+        check(&[1,2,3,4,5,6,7,8,9],&[1,7,8,2,3,4,5,6,0,9],2,2);
+    }
+
+    #[test]
+    fn test_op_delta_06() {
+        // Moves below minimum length are not detected
+        let d = check(&[1,2,3,4,5,6],&[4,5,6,1,2,3],4,2);
+        assert!(d.iter().all(|op| matches!(op,Op::Rewrite(_))));
+    }
+
+    #[test]
+    fn test_op_delta_07() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let mut vec = a.clone();
+                OpDelta::detect_moves(&a.diff(b),a,1).transform(&mut vec);
+                assert_eq!(&vec,b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_op_delta_08() {
+        // Single swap
+        let d = check_swaps(&[1,2],&[2,1],1);
+        assert_eq!(d.get(0),Some(&Op::Swap(0)));
+        let d = check_swaps(&[0,1,2,3],&[0,2,1,3],1);
+        assert_eq!(d.get(0),Some(&Op::Swap(1)));
+    }
+
+    #[test]
+    fn test_op_delta_09() {
+        // Multiple swaps, and non-swaps
+        let d = check_swaps(&[1,2,3,4,5,6,7,8],&[2,1,3,4,6,5,7,0],3);
+        assert_eq!(d.iter().filter(|op| matches!(op,Op::Swap(_))).count(),2);
+        check_swaps(&[1,1],&[1,1],0);
+        check_swaps(&[1,2,3],&[3,2,1],2);
+    }
+
+    #[test]
+    fn test_op_delta_10() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let mut vec = a.clone();
+                OpDelta::detect_swaps(&a.diff(b),a).transform(&mut vec);
+                assert_eq!(&vec,b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_op_delta_11() {
+        let (from,to) = ([1,2,3,4,5,6],[1,0,0,0,0,6,7,7]);
+        let delta = OpDelta::diff_runs(&from,&to,3);
+        assert_eq!(delta.get(0),Some(&Op::Fill(RunRewrite::new(Region::new(1,4),0,4))));
+        assert!(matches!(delta.get(1),Some(Op::Rewrite(_))));
+        let mut vec = from.to_vec();
+        delta.transform(&mut vec);
+        assert_eq!(vec,to);
+        // Fills can be constructed directly
+        let mut delta = OpDelta::new();
+        delta.push_fill(1..5,0,4);
+        delta.push(Op::Rewrite(VecRewrite::new(Region::new(6,0),vec![7,7])));
+        let mut vec = from.to_vec();
+        delta.transform(&mut vec);
+        assert_eq!(vec,to);
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5 / 2).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let mut vec = a.clone();
+                OpDelta::diff_runs(a,b,2).transform(&mut vec);
+                assert_eq!(&vec,b);
+            }
+        }
+    }
+
+    fn check_swaps(from: &[usize], to: &[usize], num: usize) -> OpDelta<usize> {
+        let mut vec = from.to_vec();
+        let delta = OpDelta::detect_swaps(&from.diff(to),from);
+        assert_eq!(delta.len(),num);
+        delta.transform(&mut vec);
+        assert_eq!(vec,to);
+        delta
+    }
+
+    fn check(from: &[usize], to: &[usize], min_len: usize, num: usize) -> OpDelta<usize> {
+        let mut vec = from.to_vec();
+        let delta = OpDelta::detect_moves(&from.diff(to),from,min_len);
+        assert_eq!(delta.len(),num);
+        delta.transform(&mut vec);
+        assert_eq!(vec,to);
+        delta
+    }
+}
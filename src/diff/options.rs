@@ -0,0 +1,146 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::hash::Hash;
+use super::{DiffAlgorithm,VecDelta,common_prefix_suffix};
+use super::slice::extract_delta;
+
+/// Options controlling how two slices are diffed (see `diff_with()`).
+/// This determines the algorithm used and, furthermore, allows
+/// elements to be _normalised_ before being compared.  For example,
+/// when diffing lines of text we may wish to ignore differences in
+/// whitespace or case.  Normalisation maps each element to a _key_
+/// and only keys are compared.  However, the resulting delta is always
+/// expressed in terms of the original elements.  For example:
+///
+/// ```
+/// use delta_inc::diff::{DiffOptions,diff_with};
+///
+/// let before = ["Hello World", "abc"];
+/// let after = ["hello  world", "def"];
+/// let opts = DiffOptions::text().ignore_whitespace().ignore_case();
+/// let delta = diff_with(&before,&after,&opts);
+/// let mut lines = before.to_vec();
+/// delta.transform(&mut lines);
+/// assert_eq!(lines,["Hello World", "def"]);
+/// ```
+pub struct DiffOptions<'a,T,K> {
+    /// The algorithm used to match keys.
+    algorithm: DiffAlgorithm,
+    /// Determines the key for each element.
+    key: Box<dyn Fn(&T)->K + 'a>
+}
+
+impl<'a,T:Clone+'a> DiffOptions<'a,T,T> {
+    /// Construct options which compare elements directly using the
+    /// default algorithm.
+    pub fn new() -> Self {
+        DiffOptions{algorithm: DiffAlgorithm::default(), key: Box::new(|t: &T| t.clone())}
+    }
+}
+
+impl<'a,T:Clone+'a> Default for DiffOptions<'a,T,T> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<'a,T:AsRef<str>+'a> DiffOptions<'a,T,String> {
+    /// Construct options for comparing textual elements (e.g. lines),
+    /// which can then be normalised using `ignore_whitespace()` and
+    /// `ignore_case()`.
+    pub fn text() -> Self {
+        DiffOptions{algorithm: DiffAlgorithm::default(), key: Box::new(|t: &T| String::from(t.as_ref()))}
+    }
+
+    /// Ignore all whitespace when comparing elements.
+    pub fn ignore_whitespace(self) -> Self {
+        self.normalize_key(|k| k.chars().filter(|c| !c.is_whitespace()).collect())
+    }
+
+    /// Ignore differences in case when comparing elements.
+    pub fn ignore_case(self) -> Self {
+        self.normalize_key(|k| k.to_lowercase())
+    }
+}
+
+impl<'a,T:'a,K:'a> DiffOptions<'a,T,K> {
+    /// Set the algorithm used for matching elements.
+    pub fn algorithm(mut self, algorithm: DiffAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Replace the normalisation of elements with a given function,
+    /// which maps each element to the key used to compare it.
+    pub fn normalize<L,F:Fn(&T)->L+'a>(self, f: F) -> DiffOptions<'a,T,L> {
+        DiffOptions{algorithm: self.algorithm, key: Box::new(f)}
+    }
+
+    /// Apply a given function to further normalise the keys produced
+    /// by the existing normalisation.
+    pub fn normalize_key<F:Fn(K)->K+'a>(self, f: F) -> Self {
+        let key = self.key;
+        DiffOptions{algorithm: self.algorithm, key: Box::new(move |t| f(key(t)))}
+    }
+}
+
+/// Compute a delta between two slices according to a given set of
+/// options.  Elements are compared by their normalised keys, and the
+/// configured algorithm is run over whatever remains after stripping
+/// the common prefix and suffix.  Elements whose keys match are left
+/// untouched by the delta.
+pub fn diff_with<T:Clone,K:Hash+Eq>(lhs: &[T], rhs: &[T], opts: &DiffOptions<'_,T,K>) -> VecDelta<T> {
+    let lkeys : Vec<K> = lhs.iter().map(&opts.key).collect();
+    let rkeys : Vec<K> = rhs.iter().map(&opts.key).collect();
+    let (pre,suf) = common_prefix_suffix(&lkeys,&rkeys);
+    let mapping = opts.algorithm.subsequence(&lkeys[pre..lhs.len()-suf],&rkeys[pre..rhs.len()-suf]);
+    extract_delta(pre, &mapping, &rhs[pre..rhs.len()-suf])
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod options_tests {
+    use alloc::string::String;
+    use crate::diff::{DiffAlgorithm,DiffOptions,diff_with};
+
+    #[test]
+    fn test_options_01() {
+        let opts = DiffOptions::new();
+        let delta = diff_with(&[1,2,3],&[1,4,3],&opts);
+        assert_eq!(delta.len(),1);
+    }
+
+    #[test]
+    fn test_options_02() {
+        // Normalise by absolute value
+        let opts = DiffOptions::new().normalize(|x: &i32| x.abs()).algorithm(DiffAlgorithm::Patience);
+        let mut vec = [1,-2,3].to_vec();
+        let delta = diff_with(&vec.clone(),&[-1,2,4],&opts);
+        assert_eq!(delta.len(),1);
+        delta.transform(&mut vec);
+        assert_eq!(vec,[1,-2,4]);
+    }
+
+    #[test]
+    fn test_options_03() {
+        let before = ["a b","C"];
+        let after = ["ab","c"];
+        assert_eq!(diff_with(&before,&after,&DiffOptions::text()).len(),1);
+        assert_eq!(diff_with(&before,&after,&DiffOptions::text().ignore_whitespace()).len(),1);
+        assert_eq!(diff_with(&before,&after,&DiffOptions::text().ignore_case()).len(),1);
+        assert!(diff_with(&before,&after,&DiffOptions::text().ignore_case().ignore_whitespace()).is_empty());
+    }
+
+    #[test]
+    fn test_options_04() {
+        // Owned strings with custom normalisation
+        let before = [String::from("x = 1;  // one"),String::from("y = 2;")];
+        let after = [String::from("x = 1;"),String::from("y = 3;")];
+        let opts = DiffOptions::text().normalize_key(|k| String::from(k.split("//").next().unwrap().trim_end()));
+        let delta = diff_with(&before,&after,&opts);
+        assert_eq!(delta.len(),1);
+        assert_eq!(delta.get(0).unwrap().region().offset,1);
+    }
+}
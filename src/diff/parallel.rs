@@ -0,0 +1,97 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::Hash;
+use rayon::prelude::*;
+use super::{VecDelta,diff_trimmed,myers_subsequence};
+use super::anchored::{longest_increasing,unique_matches};
+
+/// Compute a delta between two (potentially very large) slices in
+/// parallel.  After stripping the common prefix and suffix, elements
+/// unique to both sides are used as anchors (as for patience diff) to
+/// split the inputs into independent segments.  These segments are
+/// then diffed in parallel using `myers_subsequence()`, and the
+/// results stitched together.  This mirrors `diff_anchored()`, except
+/// that Myers' algorithm is used between anchors.
+pub fn diff_parallel<T:Clone+Hash+Eq+Send+Sync>(lhs: &[T], rhs: &[T]) -> VecDelta<T> {
+    diff_trimmed(lhs, rhs, parallel_subsequence)
+}
+
+/// Determine a common subsequence of two slices by splitting them at
+/// unique anchors, and solving the segments between anchors in
+/// parallel.  This produces a mapping from elements in `lhs` to
+/// elements in `rhs` in the same form as
+/// `longest_common_subsequence()`.
+pub fn parallel_subsequence<T:Hash+Eq+Send+Sync>(lhs: &[T], rhs: &[T]) -> Vec<Option<usize>> {
+    let anchors = longest_increasing(&unique_matches(lhs,rhs));
+    // Determine segments between anchors
+    let mut segments = Vec::with_capacity(anchors.len() + 1);
+    let (mut i, mut j) = (0,0);
+    for &(ai,aj) in &anchors {
+        segments.push((i..ai,j..aj));
+        i = ai + 1;
+        j = aj + 1;
+    }
+    segments.push((i..lhs.len(),j..rhs.len()));
+    // Solve segments in parallel
+    let mappings : Vec<Vec<Option<usize>>> = segments.par_iter().map(|(l,r)| {
+        myers_subsequence(&lhs[l.clone()],&rhs[r.clone()])
+    }).collect();
+    // Stitch together
+    let mut res = vec![None;lhs.len()];
+    for ((l,r),mapping) in segments.iter().zip(mappings) {
+        for (k,m) in mapping.into_iter().enumerate() {
+            res[l.start + k] = m.map(|v| v + r.start);
+        }
+    }
+    for (ai,aj) in anchors {
+        res[ai] = Some(aj);
+    }
+    res
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod parallel_tests {
+    use alloc::vec::Vec;
+    use crate::diff::{diff_parallel,edit_distance};
+
+    #[test]
+    fn test_parallel_01() {
+        let mut vec = Vec::from([1,2,3]);
+        let delta = diff_parallel(&[1,2,3],&[4,1,2,5,6]);
+        assert_eq!(delta.len(),2);
+        delta.transform(&mut vec);
+        assert_eq!(vec,[4,1,2,5,6]);
+    }
+
+    #[test]
+    fn test_parallel_02() {
+        // Large input with scattered edits
+        let from : Vec<usize> = (0..200_000).collect();
+        let mut to = from.clone();
+        for i in (5..200_000).step_by(10_000) { to[i] = 0; }
+        let mut vec = from.clone();
+        let delta = diff_parallel(&from,&to);
+        assert_eq!(delta.len(),20);
+        delta.transform(&mut vec);
+        assert_eq!(vec,to);
+    }
+
+    #[test]
+    fn test_parallel_03() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let mut vec = a.clone();
+                let delta = diff_parallel(a,b);
+                delta.transform(&mut vec);
+                assert_eq!(&vec,b);
+                assert!(edit_distance(a,b) <= delta.iter().map(|r| r.region().length + r.data().len()).sum());
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+use im::Vector;
+use super::{DeltaError,Diff,MutableSequence,TryTransform,VecDelta,common_prefix_suffix,myers_subsequence};
+use super::slice::extract_delta;
+
+/// A persistent `Vector` is spliced by splitting and concatenating its
+/// underlying tree in `O(log n)` time.  Since untouched parts of the
+/// tree are shared, applying a delta to a clone is cheap and leaves the
+/// original intact (e.g. for an undo history).  For example:
+///
+/// ```
+/// use im::vector;
+/// use delta_inc::diff::{Diff,Transform};
+///
+/// let v1 = vector![1,2,3,4,5];
+/// let mut v2 = v1.clone();
+/// v2.transform(&v1.diff(&vector![1,3,4,6,5]));
+/// assert_eq!((v1,v2),(vector![1,2,3,4,5],vector![1,3,4,6,5]));
+/// ```
+impl<T:Clone> MutableSequence for Vector<T> {
+    type Item = T;
+
+    fn len(&self) -> usize { Vector::len(self) }
+
+    fn splice(&mut self, range: Range<usize>, items: &[T]) {
+        let mut rest = self.split_off(range.start);
+        let rest = rest.split_off(range.end - range.start);
+        self.extend(items.iter().cloned());
+        self.append(rest);
+    }
+}
+
+/// Diffing of persistent vectors compares elements by reference, such
+/// that only the data of rewrites is cloned.  Furthermore, vectors
+/// which share the same tree are identified as equal immediately.
+impl<T:Clone+PartialEq> Diff for Vector<T> {
+    type Delta = VecDelta<T>;
+
+    fn diff(&self, other: &Vector<T>) -> VecDelta<T> {
+        if self.ptr_eq(other) { return VecDelta::new(); }
+        let lhs : Vec<&T> = self.iter().collect();
+        let rhs : Vec<&T> = other.iter().collect();
+        let (pre,suf) = common_prefix_suffix(&lhs,&rhs);
+        let mapping = myers_subsequence(&lhs[pre..lhs.len()-suf],&rhs[pre..rhs.len()-suf]);
+        let after : Vec<T> = rhs[pre..rhs.len()-suf].iter().map(|&t| t.clone()).collect();
+        extract_delta(pre, &mapping, &after)
+    }
+}
+
+/// Applying a delta to a persistent vector fails if any rewrite lies
+/// beyond the end of the vector (at the point it is applied).  In such
+/// case, any preceding rewrites have already been applied.
+impl<T:Clone> TryTransform for Vector<T> {
+    type Delta = VecDelta<T>;
+    type Error = DeltaError;
+
+    fn try_transform(&mut self, d: &VecDelta<T>) -> Result<(),DeltaError> {
+        for rw in d.iter() {
+            let (region,len) = (rw.region(),self.len());
+            if region.offset + region.length > len {
+                return Err(DeltaError::RegionOutOfBounds{region,len});
+            }
+            self.splice(region.as_range(),rw.data());
+        }
+        Ok(())
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod persistent_tests {
+    use alloc::vec::Vec;
+    use im::Vector;
+    use crate::diff::{Diff,Transform,TryTransform,VecDelta};
+
+    #[test]
+    fn persistent_test_01() {
+        let seqs : Vec<Vector<usize>> = (0..16).map(|i| (0..i).map(|j| (j*i+3)%5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let delta = a.diff(b);
+                let mut v = a.clone();
+                v.transform(&delta);
+                assert_eq!(v,*b);
+                let mut v = a.clone();
+                v.try_transform(&delta).unwrap();
+                assert_eq!(v,*b);
+                // Same as diffing plain vectors
+                let (l,r) : (Vec<usize>,Vec<usize>) = (a.iter().copied().collect(),b.iter().copied().collect());
+                assert_eq!(delta,l.diff(&r));
+            }
+        }
+    }
+
+    #[test]
+    fn persistent_test_02() {
+        let v : Vector<usize> = (0..10).collect();
+        assert!(v.diff(&v.clone()).is_empty());
+        let mut delta = VecDelta::new();
+        delta.try_push(8..12,&[0]).unwrap();
+        assert!(v.clone().try_transform(&delta).is_err());
+    }
+}
@@ -1,4 +1,6 @@
-use std::marker::PhantomData;
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 use crate::util::Region;
 
 /// Describes an _atomic rewrite_ of some source array (slice, `Vec`,
@@ -26,13 +28,18 @@ use crate::util::Region;
 /// `(2;4;"llo")` which indicates the replacement begins at position
 /// `2`, replaces `4` items from the original array with a given
 /// sequence of zero or more items.
+///
+/// When the `serde` feature is enabled, a rewrite is serialised as a
+/// `region` (i.e. an `offset` and `length`) together with its `data`.
 #[derive(Clone,Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize,serde::Deserialize))]
 pub struct Rewrite<S,T:AsRef<[S]>> {
     /// Portion of `Vec<T>` being replaced.
     region: Region,
     /// Data being used for replacement
     data: T,
     // dummy field
+    #[cfg_attr(feature = "serde", serde(skip))]
     dummy: PhantomData<S>
 }
 
@@ -41,6 +48,16 @@ impl<S,T:AsRef<[S]>> Rewrite<S,T> {
         let dummy = PhantomData;
 	Self{region,data,dummy}
     }
+
+    /// Get the region of the source sequence being replaced.
+    pub fn region(&self) -> Region { self.region }
+
+    /// Get the data used to replace the region.
+    pub fn data(&self) -> &[S] { self.data.as_ref() }
+
+    /// Consume this rewrite, returning the data used to replace the
+    /// region.
+    pub fn into_data(self) -> T { self.data }
 }
 
 impl<S,T:AsRef<[S]>+PartialEq> PartialEq for Rewrite<S,T> {
@@ -62,12 +79,88 @@ pub type VecRewrite<T> = Rewrite<T,Vec<T>>;
 /// (e.g. to iterate over them).
 pub type SliceRewrite<'a,T> = Rewrite<T,&'a [T]>;
 
+/// A rewrite whose data is either borrowed (e.g. from the sequence
+/// being diffed against) or owned.  This allows rewrites which are
+/// borrowed and rewrites which are owned to be mixed together (e.g.
+/// within a `CowDelta`), without copying the borrowed data until it
+/// needs to outlive the sequence it was borrowed from.
+pub type CowRewrite<'a,T> = Rewrite<T,Cow<'a,[T]>>;
+
+impl<'a,T:Clone> From<SliceRewrite<'a,T>> for CowRewrite<'a,T> {
+    fn from(rw: SliceRewrite<'a,T>) -> Self {
+        Rewrite::new(rw.region, Cow::Borrowed(rw.data))
+    }
+}
+
+impl<T:Clone> From<VecRewrite<T>> for CowRewrite<'_,T> {
+    fn from(rw: VecRewrite<T>) -> Self {
+        Rewrite::new(rw.region, Cow::Owned(rw.data))
+    }
+}
+
+impl<T:Clone> CowRewrite<'_,T> {
+    /// Convert this rewrite into one which owns its data, copying it
+    /// if necessary.
+    pub fn into_owned(self) -> VecRewrite<T> {
+        Rewrite::new(self.region, self.data.into_owned())
+    }
+}
+
+// ===================================================================
+// Run-Length Encoded Rewrites
+// ===================================================================
+
+/// Describes an atomic rewrite whose data is _run-length encoded_.
+/// That is, a region in the source array is replaced by `count` copies
+/// of a single item (e.g. when filling with spaces or zeros).  This is
+/// denoted as the triple `(2;4;'x'*3)`, which indicates the
+/// replacement begins at position `2` and replaces `4` items from the
+/// original array with three copies of `'x'`.  Unlike a `VecRewrite`,
+/// the run is not materialised until the rewrite is applied.
+#[derive(Clone,Debug,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize,serde::Deserialize))]
+pub struct RunRewrite<T> {
+    /// Portion of the source sequence being replaced.
+    region: Region,
+    /// Item being repeated.
+    item: T,
+    /// Number of copies of the item.
+    count: usize
+}
+
+impl<T> RunRewrite<T> {
+    pub fn new(region: Region, item: T, count: usize) -> Self {
+        Self{region,item,count}
+    }
+
+    /// Get the region of the source sequence being replaced.
+    pub fn region(&self) -> Region { self.region }
+
+    /// Get the item being repeated.
+    pub fn item(&self) -> &T { &self.item }
+
+    /// Get the number of copies of the item used to replace the region.
+    pub fn count(&self) -> usize { self.count }
+
+    /// Iterate the data used to replace the region (i.e. `count`
+    /// copies of the item).
+    pub fn data(&self) -> impl Iterator<Item=&T> { core::iter::repeat_n(&self.item,self.count) }
+}
+
+impl<T:Clone> RunRewrite<T> {
+    /// Expand this rewrite into one whose data is materialised.
+    pub fn expand(&self) -> VecRewrite<T> {
+        Rewrite::new(self.region, self.data().cloned().collect())
+    }
+}
+
 // ===================================================================
 // Tests
 // ===================================================================
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
     use crate::diff::rewrite::*;
 
     #[test]
@@ -83,4 +176,10 @@ mod tests {
         let rw = Rewrite::new(Region::new(0,1), &items);
         assert_eq!(rw.region.offset,0);
     }
+
+    #[test]
+    fn test_run_01() {
+        let rw = RunRewrite::new(Region::new(1,2), 'x', 3);
+        assert_eq!(rw.expand(),Rewrite::new(Region::new(1,2),vec!['x','x','x']));
+    }
 }
@@ -0,0 +1,342 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use crate::util::{Region,checksum};
+use super::VecDelta;
+
+/// The signature of a single block of the old file, consisting of a
+/// weak (rolling) checksum and a strong hash.  The strong hash is the
+/// FNV-1a hash of the block's bytes (see `checksum()`) and, like the
+/// weak checksum, is the same on every platform.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize,serde::Deserialize))]
+pub struct BlockSignature {
+    /// Weak checksum, which can be updated cheaply as a window slides
+    /// over the new file.
+    pub weak: u32,
+    /// Strong hash, which confirms a match of the weak checksum.
+    pub strong: u64
+}
+
+/// The signature of an old file, as used in the style of `rsync`.  The
+/// old file is split into fixed-size blocks (where the last may be
+/// shorter), and each is summarised by a `BlockSignature`.  Since a
+/// signature is much smaller than the file itself, it can be sent to
+/// whoever holds the new file, who then computes a `RemoteDelta`
+/// without needing access to the old file.
+///
+/// When the `serde` feature is enabled, signatures (and the resulting
+/// deltas) can be serialised for sending between machines.  A
+/// signature whose blocks are inconsistent with its block size and
+/// file length is rejected when deserialised.  Note the strong hash
+/// is not cryptographic and, hence, a signature should not be relied
+/// upon in an adversarial setting.
+#[derive(Clone,Debug,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize,serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "SignatureData"))]
+pub struct Signature {
+    block_size: usize,
+    len: usize,
+    blocks: Vec<BlockSignature>
+}
+
+impl Signature {
+    /// Compute the signature of an old file using blocks of a given
+    /// size.  This will `panic` if the block size is zero.
+    pub fn new(old: &[u8], block_size: usize) -> Self {
+        assert!(block_size > 0, "block size must be non-zero");
+        let blocks = old.chunks(block_size).map(|b| BlockSignature{weak: Rolling::new(b).digest(), strong: checksum(b)}).collect();
+        Signature{block_size, len: old.len(), blocks}
+    }
+
+    /// Get the size of the blocks in this signature.
+    pub fn block_size(&self) -> usize { self.block_size }
+
+    /// Get the length of the file from which this signature was
+    /// computed.
+    pub fn file_len(&self) -> usize { self.len }
+
+    /// Get the signatures of each block.
+    pub fn blocks(&self) -> &[BlockSignature] { &self.blocks }
+
+    /// Compute the delta which transforms the old file (as summarised
+    /// by this signature) into a given new file.  This slides a window
+    /// over the new file one byte at a time, emitting a reference to
+    /// any block of the old file found at that point, and otherwise
+    /// literal data.
+    pub fn delta(&self, new: &[u8]) -> RemoteDelta {
+        let bs = self.block_size;
+        // Index full blocks by weak checksum
+        let mut index : BTreeMap<u32,Vec<usize>> = BTreeMap::new();
+        for (i,b) in self.blocks.iter().enumerate() {
+            if self.block_len(i) == bs { index.entry(b.weak).or_default().push(i); }
+        }
+        let find = |window: &[u8], weak: u32| -> Option<usize> {
+            let ids = index.get(&weak)?;
+            let strong = checksum(window);
+            ids.iter().copied().find(|&i| self.blocks[i].strong == strong)
+        };
+        let mut delta = RemoteDelta{ops: Vec::new()};
+        let (mut start, mut pos) = (0,0);
+        let mut rolling = None;
+        while pos + bs <= new.len() {
+            let window = &new[pos..pos + bs];
+            let r = rolling.get_or_insert_with(|| Rolling::new(window));
+            if let Some(i) = find(window, r.digest()) {
+                delta.push_literal(&new[start..pos]);
+                delta.ops.push(RemoteOp::Block(i));
+                pos += bs;
+                start = pos;
+                rolling = None;
+            } else {
+                if pos + bs < new.len() { r.roll(new[pos], new[pos + bs]); }
+                pos += 1;
+            }
+        }
+        // Check whether the (shorter) final block matches the tail
+        let last = self.blocks.len().saturating_sub(1);
+        let tail = if self.blocks.is_empty() { bs } else { self.block_len(last) };
+        if tail < bs && tail <= new.len() - start {
+            let window = &new[new.len() - tail..];
+            if self.blocks[last] == (BlockSignature{weak: Rolling::new(window).digest(), strong: checksum(window)}) {
+                delta.push_literal(&new[start..new.len() - tail]);
+                delta.ops.push(RemoteOp::Block(last));
+                start = new.len();
+            }
+        }
+        delta.push_literal(&new[start..]);
+        delta
+    }
+
+    /// Determine the length of the `ith` block.
+    fn block_len(&self, ith: usize) -> usize {
+        if ith + 1 == self.blocks.len() { self.len - (ith * self.block_size) } else { self.block_size }
+    }
+}
+
+/// The unchecked contents of a serialised `Signature`.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SignatureData {
+    block_size: usize,
+    len: usize,
+    blocks: Vec<BlockSignature>
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SignatureData> for Signature {
+    type Error = &'static str;
+
+    fn try_from(data: SignatureData) -> Result<Self,Self::Error> {
+        if data.block_size == 0 { return Err("block size must be non-zero"); }
+        if data.blocks.len() != data.len.div_ceil(data.block_size) { return Err("blocks do not match file length"); }
+        Ok(Signature{block_size: data.block_size, len: data.len, blocks: data.blocks})
+    }
+}
+
+/// An operation within a `RemoteDelta`.
+#[derive(Clone,Debug,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize,serde::Deserialize))]
+pub enum RemoteOp {
+    /// Copy a given block of the old file.
+    Block(usize),
+    /// Insert some literal data.
+    Literal(Vec<u8>)
+}
+
+/// A delta computed against a `Signature`, rather than against the old
+/// file itself.  This consists of a sequence of operations which
+/// either copy a block of the old file, or insert literal data.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize,serde::Deserialize))]
+pub struct RemoteDelta {
+    ops: Vec<RemoteOp>
+}
+
+/// Errors arising from applying a `RemoteDelta` to the wrong file.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum RemoteError {
+    /// The delta refers to a block which lies beyond the end of the
+    /// old file.
+    BlockOutOfRange(usize)
+}
+
+impl core::fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RemoteError::BlockOutOfRange(i) => write!(f, "block {i} out of range")
+        }
+    }
+}
+
+impl core::error::Error for RemoteError {}
+
+impl RemoteDelta {
+    /// Get the operations which make up this delta.
+    pub fn ops(&self) -> &[RemoteOp] { &self.ops }
+
+    /// Apply this delta to the old file using the block size of the
+    /// signature from which it was computed, producing the new file.
+    pub fn apply(&self, old: &[u8], block_size: usize) -> Result<Vec<u8>,RemoteError> {
+        let mut new = Vec::new();
+        for op in &self.ops {
+            match op {
+                RemoteOp::Block(i) => new.extend_from_slice(block(old, block_size, *i)?),
+                RemoteOp::Literal(data) => new.extend_from_slice(data)
+            }
+        }
+        Ok(new)
+    }
+
+    /// Convert this delta into a `VecDelta` over the old file, using
+    /// the block size of the signature from which it was computed.
+    /// Blocks copied in order are unchanged by the resulting delta,
+    /// whilst everything else becomes replacement data.
+    pub fn to_delta(&self, old: &[u8], block_size: usize) -> Result<VecDelta<u8>,RemoteError> {
+        let mut hunks = Vec::new();
+        let (mut cursor, mut data) = (0, Vec::new());
+        for op in &self.ops {
+            match op {
+                RemoteOp::Block(i) => {
+                    let bytes = block(old, block_size, *i)?;
+                    let offset = i * block_size;
+                    if offset >= cursor {
+                        hunks.push((Region::new(cursor,offset - cursor),core::mem::take(&mut data)));
+                        cursor = offset + bytes.len();
+                    } else {
+                        data.extend_from_slice(bytes);
+                    }
+                }
+                RemoteOp::Literal(bytes) => data.extend_from_slice(bytes)
+            }
+        }
+        hunks.push((Region::new(cursor,old.len() - cursor),data));
+        let hunks = hunks.iter().filter(|(r,d)| r.length > 0 || !d.is_empty());
+        Ok(VecDelta::from_source(hunks.map(|(r,d)| (*r,d.as_slice()))))
+    }
+
+    /// Append some literal data, merging with any literal immediately
+    /// before.
+    fn push_literal(&mut self, data: &[u8]) {
+        if data.is_empty() { return; }
+        match self.ops.last_mut() {
+            Some(RemoteOp::Literal(bytes)) => bytes.extend_from_slice(data),
+            _ => self.ops.push(RemoteOp::Literal(data.to_vec()))
+        }
+    }
+}
+
+/// Get the `ith` block of a given file.
+fn block(old: &[u8], block_size: usize, ith: usize) -> Result<&[u8],RemoteError> {
+    let start = ith.checked_mul(block_size).filter(|&s| s < old.len());
+    let start = start.ok_or(RemoteError::BlockOutOfRange(ith))?;
+    Ok(&old[start..old.len().min(start + block_size)])
+}
+
+/// The rolling checksum used by `rsync`, which is a variant of
+/// Adler-32.  This can be updated in constant time as the window
+/// slides forward by one byte.
+struct Rolling { a: u16, b: u16, len: u16 }
+
+impl Rolling {
+    fn new(window: &[u8]) -> Self {
+        let len = window.len() as u16;
+        let mut r = Rolling{a: 0, b: 0, len};
+        for (i,&x) in window.iter().enumerate() {
+            r.a = r.a.wrapping_add(x as u16);
+            r.b = r.b.wrapping_add((len.wrapping_sub(i as u16)).wrapping_mul(x as u16));
+        }
+        r
+    }
+
+    /// Slide the window forward, removing one byte and adding another.
+    fn roll(&mut self, out: u8, inp: u8) {
+        self.a = self.a.wrapping_sub(out as u16).wrapping_add(inp as u16);
+        self.b = self.b.wrapping_sub(self.len.wrapping_mul(out as u16)).wrapping_add(self.a);
+    }
+
+    fn digest(&self) -> u32 { ((self.b as u32) << 16) | self.a as u32 }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod rolling_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use super::{Rolling,RemoteError,RemoteOp,Signature};
+
+    #[test]
+    fn rolling_test_01() {
+        // Rolling matches recomputing from scratch
+        let data : Vec<u8> = (0..200u32).map(|i| (i * 37 % 251) as u8).collect();
+        let mut r = Rolling::new(&data[0..16]);
+        for i in 1..data.len() - 16 {
+            r.roll(data[i - 1], data[i + 15]);
+            assert_eq!(r.digest(), Rolling::new(&data[i..i + 16]).digest());
+        }
+    }
+
+    #[test]
+    fn rolling_test_02() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<u8>> = (0..30).map(|i| (0..i).map(|j| ((j * i + 3) % 5) as u8).collect()).collect();
+        for bs in [1,2,3,7] {
+            for a in &seqs {
+                let sig = Signature::new(a, bs);
+                for b in &seqs {
+                    let d = sig.delta(b);
+                    assert_eq!(&d.apply(a, bs).unwrap(), b);
+                    assert_eq!(&d.to_delta(a, bs).unwrap().apply(a), b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rolling_test_03() {
+        let old : Vec<u8> = (0..1000u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+        let mut new = old.clone();
+        new.splice(500..500, *b"hello");
+        let sig = Signature::new(&old, 100);
+        assert_eq!(sig.blocks().len(), 10);
+        let d = sig.delta(&new);
+        // Only the inserted bytes are sent literally
+        let literal : usize = d.ops().iter().map(|op| match op { RemoteOp::Literal(b) => b.len(), _ => 0 }).sum();
+        assert_eq!(literal, 5);
+        assert_eq!(d.apply(&old, 100).unwrap(), new);
+        assert_eq!(d.to_delta(&old, 100).unwrap().len(), 1);
+        // Applying to the wrong file is detected
+        assert_eq!(d.apply(&old[..50], 100), Err(RemoteError::BlockOutOfRange(1)));
+        // Shorter final block is matched
+        let sig = Signature::new(&old[..950], 100);
+        let d = sig.delta(&[b"xyz".as_slice(), &old[..950]].concat());
+        assert_eq!(d.ops()[0], RemoteOp::Literal(vec![b'x',b'y',b'z']));
+        assert_eq!(d.ops().len(), 11);
+    }
+
+    #[test]
+    fn rolling_test_04() {
+        // Strong hashes are fixed
+        let sig = Signature::new(b"foobar", 3);
+        assert_eq!(sig.blocks()[0].strong, 0xdcb2_7518_fed9_d577);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn rolling_test_05() {
+        let old : Vec<u8> = (0..100u8).collect();
+        let new = [b"abc".as_slice(), &old[..60], b"xyz", &old[70..]].concat();
+        let sig = Signature::new(&old, 8);
+        let json = serde_json::to_string(&sig).unwrap();
+        let sig2 : Signature = serde_json::from_str(&json).unwrap();
+        assert_eq!(sig2, sig);
+        let d = sig2.delta(&new);
+        let d2 : super::RemoteDelta = serde_json::from_str(&serde_json::to_string(&d).unwrap()).unwrap();
+        assert_eq!(d2.apply(&old, 8).unwrap(), new);
+        // Inconsistent signatures are rejected
+        assert!(serde_json::from_str::<Signature>(r#"{"block_size":8,"len":100,"blocks":[]}"#).is_err());
+        assert!(serde_json::from_str::<Signature>(r#"{"block_size":0,"len":0,"blocks":[]}"#).is_err());
+    }
+}
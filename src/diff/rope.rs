@@ -0,0 +1,88 @@
+use alloc::string::String;
+use core::ops::Range;
+use ropey::Rope;
+use super::{DeltaError,MutableSequence,TryTransform,VecDelta};
+
+/// A `Rope` is a sequence of `char`s which can be spliced in `O(log n)`
+/// time and, hence, applying a `VecDelta<char>` to a large document
+/// costs `O(log n)` per rewrite (plus the size of the rewrite) rather
+/// than moving the rest of the document each time.  For example:
+///
+/// ```
+/// use ropey::Rope;
+/// use delta_inc::diff::{Diff,Transform};
+///
+/// let before : Vec<char> = "hello world".chars().collect();
+/// let after : Vec<char> = "hello there world".chars().collect();
+/// let mut rope = Rope::from_str("hello world");
+/// rope.transform(&before.diff(&after));
+/// assert_eq!(rope,"hello there world");
+/// ```
+impl MutableSequence for Rope {
+    type Item = char;
+
+    fn len(&self) -> usize { self.len_chars() }
+
+    fn splice(&mut self, range: Range<usize>, items: &[char]) {
+        let start = range.start;
+        self.remove(range);
+        self.insert(start,&items.iter().collect::<String>());
+    }
+}
+
+/// Applying a delta to a `Rope` fails if any rewrite lies beyond the
+/// end of the rope (at the point it is applied).  In such case, any
+/// preceding rewrites have already been applied.
+impl TryTransform for Rope {
+    type Delta = VecDelta<char>;
+    type Error = DeltaError;
+
+    fn try_transform(&mut self, d: &VecDelta<char>) -> Result<(),DeltaError> {
+        for rw in d.iter() {
+            let (region,len) = (rw.region(),self.len_chars());
+            if region.offset + region.length > len {
+                return Err(DeltaError::RegionOutOfBounds{region,len});
+            }
+            self.splice(region.as_range(),rw.data());
+        }
+        Ok(())
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod rope_tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use ropey::Rope;
+    use crate::diff::{Diff,Transform,TryTransform,VecDelta};
+
+    #[test]
+    fn rope_test_01() {
+        let seqs : Vec<String> = (0..12).map(|i| (0..i).map(|j| ['a','β','c','\n','€'][(j*i+3)%5]).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let (l,r) : (Vec<char>,Vec<char>) = (a.chars().collect(),b.chars().collect());
+                let delta = l.diff(&r);
+                let mut rope = Rope::from_str(a);
+                rope.transform(&delta);
+                assert_eq!(rope,b.as_str());
+                let mut rope = Rope::from_str(a);
+                rope.try_transform(&delta).unwrap();
+                assert_eq!(rope,b.as_str());
+            }
+        }
+    }
+
+    #[test]
+    fn rope_test_02() {
+        let mut delta = VecDelta::new();
+        delta.try_push(3..5,&['x']).unwrap();
+        let mut rope = Rope::from_str("abcd");
+        assert!(rope.try_transform(&delta).is_err());
+        assert_eq!(rope,"abcd");
+    }
+}
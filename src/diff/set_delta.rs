@@ -0,0 +1,157 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher,Hash};
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+use super::{Diff,Transform};
+
+/// A `SetDelta` describes the differences between two sets, in terms of
+/// the items which are _added_ and _removed_.  Applying it removes the
+/// latter and then adds the former.  For example:
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use delta_inc::diff::{Diff,Transform};
+///
+/// let before = BTreeSet::from([1,2,3]);
+/// let after = BTreeSet::from([2,3,4]);
+/// let delta = before.diff(&after);
+/// assert_eq!((delta.added(),delta.removed()),(&[4][..],&[1][..]));
+/// let mut set = before.clone();
+/// set.transform(&delta);
+/// assert_eq!(set,after);
+/// ```
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct SetDelta<T> {
+    /// Items which are added.
+    added: Vec<T>,
+    /// Items which are removed.
+    removed: Vec<T>
+}
+
+impl<T> SetDelta<T> {
+    /// Construct an empty `SetDelta`.
+    pub const fn new() -> Self { SetDelta{added: Vec::new(), removed: Vec::new()} }
+
+    /// Get the total number of items affected by this delta.
+    pub fn len(&self) -> usize { self.added.len() + self.removed.len() }
+
+    /// Check whether this delta affects any items or not.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Get the items added by this delta.
+    pub fn added(&self) -> &[T] { &self.added }
+
+    /// Get the items removed by this delta.
+    pub fn removed(&self) -> &[T] { &self.removed }
+
+    /// Record that a given item is added.
+    pub fn add(&mut self, item: T) { self.added.push(item); }
+
+    /// Record that a given item is removed.
+    pub fn remove(&mut self, item: T) { self.removed.push(item); }
+
+    /// Construct the inverse of this delta (i.e. which adds the items
+    /// this removes, and vice versa).
+    pub fn invert(self) -> Self { SetDelta{added: self.removed, removed: self.added} }
+}
+
+impl<T> Default for SetDelta<T> {
+    fn default() -> Self { Self::new() }
+}
+
+// ===================================================================
+// BTreeSet
+// ===================================================================
+
+/// Diffing of ordered sets produces a delta whose items are sorted.
+impl<T:Ord+Clone> Diff for BTreeSet<T> {
+    type Delta = SetDelta<T>;
+
+    fn diff(&self, other: &Self) -> SetDelta<T> {
+        SetDelta{added: other.difference(self).cloned().collect(), removed: self.difference(other).cloned().collect()}
+    }
+}
+
+impl<T:Ord+Clone> Transform for BTreeSet<T> {
+    type Delta = SetDelta<T>;
+
+    fn transform(&mut self, d: &SetDelta<T>) {
+        for item in &d.removed { self.remove(item); }
+        self.extend(d.added.iter().cloned());
+    }
+}
+
+// ===================================================================
+// HashSet
+// ===================================================================
+
+/// Diffing of hash sets produces a delta whose items are in an
+/// unspecified order.
+#[cfg(feature = "std")]
+impl<T:Hash+Eq+Clone,S:BuildHasher> Diff for HashSet<T,S> {
+    type Delta = SetDelta<T>;
+
+    fn diff(&self, other: &Self) -> SetDelta<T> {
+        SetDelta{added: other.difference(self).cloned().collect(), removed: self.difference(other).cloned().collect()}
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T:Hash+Eq+Clone,S:BuildHasher> Transform for HashSet<T,S> {
+    type Delta = SetDelta<T>;
+
+    fn transform(&mut self, d: &SetDelta<T>) {
+        for item in &d.removed { self.remove(item); }
+        self.extend(d.added.iter().cloned());
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod set_delta_tests {
+    use alloc::collections::BTreeSet;
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,Transform};
+
+    fn sets() -> Vec<Vec<usize>> {
+        (0..10).map(|i| (0..i).map(|j| (j*i+3)%7).collect()).collect()
+    }
+
+    #[test]
+    fn set_delta_test_01() {
+        for a in sets() {
+            for b in sets() {
+                let (a,b) : (BTreeSet<usize>,BTreeSet<usize>) = (a.iter().copied().collect(),b.into_iter().collect());
+                let delta = a.diff(&b);
+                assert_eq!(delta.is_empty(),a == b);
+                assert!(delta.added().is_sorted() && delta.removed().is_sorted());
+                let mut s = a.clone();
+                s.transform(&delta);
+                assert_eq!(s,b);
+                s.transform(&delta.invert());
+                assert_eq!(s,a);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn set_delta_test_02() {
+        use std::collections::HashSet;
+        for a in sets() {
+            for b in sets() {
+                let (a,b) : (HashSet<usize>,HashSet<usize>) = (a.iter().copied().collect(),b.into_iter().collect());
+                let delta = a.diff(&b);
+                assert_eq!(delta.len(),a.symmetric_difference(&b).count());
+                let mut s = a.clone();
+                s.transform(&delta);
+                assert_eq!(s,b);
+            }
+        }
+    }
+}
@@ -1,19 +1,242 @@
-use super::{Diff,VecDelta};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::hash::Hash;
+use core::ops::Range;
+use crate::util::hash_of;
+use super::{Diff,VecDelta,diff_myers,myers_subsequence};
+use super::hirschberg::lcs_row;
 
 /// An implementation of the `Diff` trait for arbritrary slices.  This
-/// is implemented using the well-known _longest common subsequence_
-/// algorithm.
+/// is implemented using Myers' `O(ND)` difference algorithm (see
+/// `diff_myers()`), which computes a _longest common subsequence_
+/// without the quadratic cost of `diff_lcs()`.
 impl<T:Clone+PartialEq> Diff for [T] {
     type Delta = VecDelta<T>;
 
     fn diff(&self, other: &[T]) -> Self::Delta {
-        // FIXME: reduce number of allocations!
-        let mapping = longest_common_subsequence(self,other);
-        // Convert mapping to rewrites
-        extract_delta(&mapping, other)
+        diff_myers(self,other)
     }
 }
 
+/// Compute a delta between two slices using the classical dynamic
+/// programming algorithm for the _longest common subsequence_ (see
+/// `longest_common_subsequence()`).  This requires `O(mn)` time and
+/// space and, hence, is mostly useful for testing.
+pub fn diff_lcs<T:Clone+PartialEq>(lhs: &[T], rhs: &[T]) -> VecDelta<T> {
+    // FIXME: reduce number of allocations!
+    diff_trimmed(lhs, rhs, longest_common_subsequence)
+}
+
+/// Compute a delta between two slices using a given algorithm for
+/// determining a common subsequence.  This algorithm must return a
+/// mapping from elements in `lhs` to elements in `rhs` in the same
+/// form as `longest_common_subsequence()`.  As a preprocessing step,
+/// the common prefix and suffix of both slices are stripped off (see
+/// `common_prefix_suffix()`) and the algorithm is only applied to
+/// what remains.  The regions of the resulting delta are then offset
+/// accordingly.  Since edits are typically localised, this alone
+/// makes diffing a single edit close to `O(edit size)` regardless of
+/// the algorithm used.
+pub fn diff_trimmed<T,F>(lhs: &[T], rhs: &[T], subsequence: F) -> VecDelta<T>
+where T:Clone+PartialEq, F:FnOnce(&[T],&[T])->Vec<Option<usize>> {
+    let (pre,suf) = common_prefix_suffix(lhs,rhs);
+    let rhs = &rhs[pre..rhs.len()-suf];
+    let mapping = subsequence(&lhs[pre..lhs.len()-suf],rhs);
+    extract_delta(pre, &mapping, rhs)
+}
+
+/// Compute a delta between two slices where elements are compared
+/// using a given equality function, rather than `PartialEq`.  For
+/// example, records might be matched on their `id` field alone.
+/// **NOTE:** elements considered equal are not rewritten and, hence,
+/// applying the resulting delta to `lhs` produces a sequence which
+/// equals `rhs` only according to the given equality function.
+pub fn diff_by<T:Clone,F:Fn(&T,&T)->bool>(lhs: &[T], rhs: &[T], eq: F) -> VecDelta<T> {
+    let lhs_by : Vec<By<T,F>> = lhs.iter().map(|item| By{item,eq: &eq}).collect();
+    let rhs_by : Vec<By<T,F>> = rhs.iter().map(|item| By{item,eq: &eq}).collect();
+    let (pre,suf) = common_prefix_suffix(&lhs_by,&rhs_by);
+    let mapping = myers_subsequence(&lhs_by[pre..lhs.len()-suf],&rhs_by[pre..rhs.len()-suf]);
+    extract_delta(pre, &mapping, &rhs[pre..rhs.len()-suf])
+}
+
+/// Compute a delta between two slices where elements are compared by
+/// a key extracted using a given function.  As for `diff_by()`,
+/// elements with equal keys are not rewritten.
+pub fn diff_by_key<T:Clone,K:PartialEq,F:Fn(&T)->K>(lhs: &[T], rhs: &[T], key: F) -> VecDelta<T> {
+    let lkeys : Vec<K> = lhs.iter().map(&key).collect();
+    let rkeys : Vec<K> = rhs.iter().map(&key).collect();
+    let (pre,suf) = common_prefix_suffix(&lkeys,&rkeys);
+    let mapping = myers_subsequence(&lkeys[pre..lhs.len()-suf],&rkeys[pre..rhs.len()-suf]);
+    extract_delta(pre, &mapping, &rhs[pre..rhs.len()-suf])
+}
+
+/// Pairs an item with an equality function, such that the algorithms
+/// defined over `PartialEq` can be used with a custom notion of
+/// equality.
+struct By<'a,T,F:Fn(&T,&T)->bool> {
+    item: &'a T,
+    eq: &'a F
+}
+
+impl<T,F:Fn(&T,&T)->bool> PartialEq for By<'_,T,F> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.eq)(self.item,other.item)
+    }
+}
+
+/// Determine the _edit distance_ between two slices.  That is, the
+/// minimum number of elements which must be deleted from `lhs` and
+/// inserted into it to produce `rhs`.  Equivalently, this is `m + n -
+/// 2*L` where `L` is the length of their longest common subsequence.
+/// This avoids constructing a delta altogether and, beyond stripping
+/// the common prefix and suffix, uses only a single row of the
+/// _longest common subsequence_ table.  Hence, it requires `O(min(m,n))`
+/// space.
+pub fn edit_distance<T:PartialEq>(lhs: &[T], rhs: &[T]) -> usize {
+    let (pre,suf) = common_prefix_suffix(lhs,rhs);
+    let lhs = &lhs[pre..lhs.len()-suf];
+    let rhs = &rhs[pre..rhs.len()-suf];
+    // Keep row as short as possible
+    let (lhs,rhs) = if lhs.len() < rhs.len() { (rhs,lhs) } else { (lhs,rhs) };
+    let lcs = lcs_row(lhs,rhs,false)[rhs.len()];
+    lhs.len() + rhs.len() - (2 * lcs)
+}
+
+/// Determine how similar two slices are, as a value between `0.0`
+/// (nothing in common) and `1.0` (identical).  Following Python's
+/// `difflib`, this is computed as `2*L / (m+n)` where `L` is the
+/// length of their longest common subsequence.  Two empty slices are
+/// considered identical.  See also `similarity_upper_bound()` for a
+/// much cheaper estimate.
+pub fn similarity<T:PartialEq>(lhs: &[T], rhs: &[T]) -> f64 {
+    let total = lhs.len() + rhs.len();
+    if total == 0 { return 1.0; }
+    let common = total - edit_distance(lhs,rhs);
+    common as f64 / total as f64
+}
+
+/// Cheaply compute an upper bound on `similarity()`.  This ignores
+/// the order of elements and, instead, counts how many could possibly
+/// be matched (i.e. the size of the multiset intersection of both
+/// slices).  This requires only linear time and, hence, allows
+/// callers to quickly rule out pairs of sequences which are
+/// unrelated before computing a full delta.
+pub fn similarity_upper_bound<T:Hash>(lhs: &[T], rhs: &[T]) -> f64 {
+    let total = lhs.len() + rhs.len();
+    if total == 0 { return 1.0; }
+    // Count occurrences on the left.  Observe hash collisions can only
+    // increase the count, hence this remains an upper bound.
+    let mut counts : BTreeMap<u64,usize> = BTreeMap::new();
+    for l in lhs { *counts.entry(hash_of(l)).or_insert(0) += 1; }
+    let mut matched = 0;
+    for r in rhs {
+        if let Some(c) = counts.get_mut(&hash_of(r)) {
+            if *c > 0 {
+                *c -= 1;
+                matched += 1;
+            }
+        }
+    }
+    (2 * matched) as f64 / total as f64
+}
+
+/// Determine the length of the longest common prefix and suffix of
+/// two slices.  For example, given `lhs=[a,b,c,d]` and
+/// `rhs=[a,e,d]`, this returns `(1,1)`.  The prefix and suffix never
+/// overlap and, hence, their combined length never exceeds the
+/// length of either slice.  Since edits are typically localised,
+/// stripping the common prefix and suffix before computing a diff is
+/// cheap and can dramatically reduce the work required.
+///
+/// Long matches are compared in blocks using slice equality, which the
+/// standard library lowers to `memcmp` for bytes (and other primitive
+/// types).  Hence, trimming multi-megabyte byte buffers is limited by
+/// memory bandwidth rather than by comparing one element at a time.
+pub fn common_prefix_suffix<T:PartialEq>(lhs: &[T], rhs: &[T]) -> (usize,usize) {
+    let pre = common_prefix_len(lhs,rhs);
+    let suf = common_suffix_len(&lhs[pre..],&rhs[pre..]);
+    (pre,suf)
+}
+
+/// Number of elements compared at a time when matching long runs.
+const BLOCK : usize = 64;
+
+/// Determine the length of the longest common prefix of two slices.
+/// The first block is compared element by element, such that short
+/// matches (e.g. the snakes of `myers_subsequence()`) incur no
+/// overhead.  Thereafter, whole blocks are compared at once until one
+/// differs.
+pub(crate) fn common_prefix_len<T:PartialEq>(lhs: &[T], rhs: &[T]) -> usize {
+    let n = lhs.len().min(rhs.len());
+    let mut i = lhs.iter().zip(rhs).take(BLOCK).take_while(|(l,r)| l == r).count();
+    if i < BLOCK { return i; }
+    while i + BLOCK <= n && lhs[i..i+BLOCK] == rhs[i..i+BLOCK] { i += BLOCK; }
+    i + lhs[i..n].iter().zip(&rhs[i..n]).take_while(|(l,r)| l == r).count()
+}
+
+/// Determine the length of the longest common suffix of two slices,
+/// in the same manner as `common_prefix_len()`.
+pub(crate) fn common_suffix_len<T:PartialEq>(lhs: &[T], rhs: &[T]) -> usize {
+    let n = lhs.len().min(rhs.len());
+    let (lhs,rhs) = (&lhs[lhs.len()-n..],&rhs[rhs.len()-n..]);
+    let mut i = lhs.iter().rev().zip(rhs.iter().rev()).take(BLOCK).take_while(|(l,r)| l == r).count();
+    if i < BLOCK { return i; }
+    while i + BLOCK <= n && lhs[n-i-BLOCK..n-i] == rhs[n-i-BLOCK..n-i] { i += BLOCK; }
+    i + lhs[..n-i].iter().rev().zip(rhs[..n-i].iter().rev()).take_while(|(l,r)| l == r).count()
+}
+
+/// Compute a delta between two (potentially very large) slices by
+/// diffing them in aligned _windows_.  Specifically, the common
+/// prefix and suffix are first stripped off.  If what remains fits
+/// within the window size, it is diffed directly.  Otherwise, both
+/// sides are split into the same number of windows (each of at most
+/// `window` elements), and corresponding windows are diffed
+/// independently.  This bounds the memory used by the _longest common
+/// subsequence_ algorithm to `O(window^2)`.  The resulting delta
+/// always transforms `lhs` into `rhs`, though it may not be minimal
+/// across window boundaries.
+pub fn diff_chunked<T:Clone+PartialEq>(lhs: &[T], rhs: &[T], window: usize) -> VecDelta<T> {
+    assert!(window > 0);
+    diff_trimmed(lhs, rhs, |lhs,rhs| {
+        // Determine number of windows required
+        let n = lhs.len().max(rhs.len()).div_ceil(window).max(1);
+        let mut mapping = Vec::with_capacity(lhs.len());
+        for k in 0..n {
+            let (ls,le) = (k * lhs.len() / n, (k+1) * lhs.len() / n);
+            let (rs,re) = (k * rhs.len() / n, (k+1) * rhs.len() / n);
+            let m = longest_common_subsequence(&lhs[ls..le],&rhs[rs..re]);
+            mapping.extend(m.into_iter().map(|v| v.map(|j| j + rs)));
+        }
+        mapping
+    })
+}
+
+/// Compute a delta between two slices which are both known to be
+/// sorted (in ascending order).  In this case, the longest common
+/// subsequence is simply the (multiset) intersection of the two
+/// slices, and can be determined in linear time by a merge-style scan
+/// (rather than using a general purpose algorithm).  The result is
+/// unspecified (though will not `panic`) if either slice is not
+/// sorted.
+pub fn diff_sorted<T:Clone+Ord>(lhs: &[T], rhs: &[T]) -> VecDelta<T> {
+    let mut mapping = vec![None; lhs.len()];
+    let (mut i, mut j) = (0,0);
+    while i < lhs.len() && j < rhs.len() {
+        match lhs[i].cmp(&rhs[j]) {
+            Ordering::Less => { i += 1; }
+            Ordering::Greater => { j += 1; }
+            Ordering::Equal => {
+                mapping[i] = Some(j);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    extract_delta(0, &mapping, rhs)
+}
+
 /// Determine the longest common subsequence of two slices. For
 /// example, suppose `lhs=[a,b,b,c,b,c,d]` and `rhs=[b,b,e,c,d,e]` then a
 /// *common subsequence* is `[b,b]` and another is `[b,c,d]`. However,
@@ -32,8 +255,8 @@ impl<T:Clone+PartialEq> Diff for [T] {
 /// # References
 ///
 /// * _Introduction to Algorithms_, T.H Cormen, C.E. Leiserson,
-/// R.L. Rivert and C. Stein, 2nd ed.  Chapter 15.
-pub fn longest_common_subsequence<T:Clone+PartialEq>(lhs: &[T], rhs: &[T]) -> Vec<Option<usize>> {
+///   R.L. Rivert and C. Stein, 2nd ed.  Chapter 15.
+pub fn longest_common_subsequence<T:PartialEq>(lhs: &[T], rhs: &[T]) -> Vec<Option<usize>> {
     let m = lhs.len() + 1;
     let n = rhs.len() + 1;
     let mut c = vec![0; m * n];
@@ -103,9 +326,25 @@ fn extract_subsequence<T:PartialEq>(c: &[T], res: &mut [Option<usize>], i: usize
 /// it can generate lots of small delta's when a single large one
 /// would be more sensible. Potentially, some form of post processing
 /// could coalesce delta's as necessary.
-fn extract_delta<T:Clone>(mapping: &[Option<usize>], after: &[T]) -> VecDelta<T> {
+///
+/// Finally, all regions in the resulting delta are offset by a given
+/// amount.  This allows a delta to be extracted for a mapping which
+/// was computed between subslices (e.g. after stripping a common
+/// prefix).
+pub(crate) fn extract_delta<T:Clone>(offset: usize, mapping: &[Option<usize>], after: &[T]) -> VecDelta<T> {
     let mut delta = VecDelta::new();
-    println!("MAPPING: {mapping:?}");
+    extract_rewrites(offset, mapping, after, |range,data| {
+        delta.try_push(range, data).expect("invalid mapping");
+    });
+    delta
+}
+
+/// Extract rewrites using a given mapping from the before sequence to
+/// the after sequence (see `extract_delta()`).  Each rewrite is passed
+/// to a given function, in order, with its range expressed in terms
+/// of the _target sequence_ and its data borrowed from `after`.
+pub(crate) fn extract_rewrites<'a,T,F>(offset: usize, mapping: &[Option<usize>], after: &'a [T], mut push: F)
+where F:FnMut(Range<usize>,&'a [T]) {
     // Initialise after markers
     let (mut a_start, mut a_pos) = (0,0);
     // Initialise before markers
@@ -129,9 +368,8 @@ fn extract_delta<T:Clone>(mapping: &[Option<usize>], after: &[T]) -> VecDelta<T>
 		// Matching case. Flush buffers and advance
 		if b_start < b_pos || a_start < a_pos {
 		    let n = b_pos - b_start;
-		    println!("ADDING: {a_start} ==> {n}");		    
 		    // Extract the difference
-		    unsafe { delta.push_raw(a_start .. a_start + n, &after[a_start .. a_pos]); }
+		    push(offset + a_start .. offset + a_start + n, &after[a_start .. a_pos]);
 		}
 		a_pos += 1;
 		b_pos += 1;		
@@ -144,11 +382,8 @@ fn extract_delta<T:Clone>(mapping: &[Option<usize>], after: &[T]) -> VecDelta<T>
     if b_start < mapping.len() || a_start < after.len() {
         // Terminating case. Flush buffers and end.
 	let n = mapping.len() - b_start;
-	println!("ADDING2: {n}");	
-	unsafe { delta.push_raw(a_start .. a_start + n, &after[a_start .. ]); }	
+	push(offset + a_start .. offset + a_start + n, &after[a_start .. ]);
     }
-    //
-    delta
 }
 
 // ===================================================================
@@ -157,8 +392,10 @@ fn extract_delta<T:Clone>(mapping: &[Option<usize>], after: &[T]) -> VecDelta<T>
 
 #[cfg(test)]
 mod diff_tests {
-    use std::fmt::Debug;
-    use crate::diff::{Diff};
+    use alloc::vec::Vec;
+    use core::fmt::Debug;
+    use alloc::vec;
+    use crate::diff::{Diff,common_prefix_suffix,diff_by,diff_by_key,diff_chunked,diff_sorted,diff_trimmed,edit_distance,similarity,similarity_upper_bound};
     
     #[test]
     fn test_01() {
@@ -313,17 +550,170 @@ mod diff_tests {
     }
 
     // Triple rewrites
-    
-    
+
+    // Large rewrites
+
+    #[test]
+    fn test_60() {
+        // Only last element differs
+        let from : Vec<usize> = (0..1_000_000).collect();
+        let mut to = from.clone();
+        to[999_999] = 0;
+        check(&from,&to,1);
+    }
+
+    #[test]
+    fn test_61() {
+        // Only first element differs
+        let from : Vec<usize> = (0..1_000_000).collect();
+        let mut to = from.clone();
+        to[0] = 1;
+        check(&from,&to,1);
+    }
+
+    #[test]
+    fn test_62() {
+        // Chunked diff of large sequence
+        let from : Vec<usize> = (0..1_000_000).collect();
+        let mut to = from.clone();
+        to[999_999] = 0;
+        let delta = diff_chunked(&from,&to,100);
+        assert_eq!(delta.len(),1);
+        assert_eq!(delta.get(0).unwrap().data(),&[0]);
+    }
+
+    #[test]
+    fn test_63() {
+        // Chunked diff across several windows
+        let from : Vec<usize> = (0..100).map(|i| i % 7).collect();
+        let to : Vec<usize> = (0..130).map(|i| (i * 3) % 11).collect();
+        for w in [1,2,5,10,50,200] {
+            let mut vec = from.clone();
+            diff_chunked(&from,&to,w).transform(&mut vec);
+            assert_eq!(vec,to);
+        }
+    }
+
+    #[test]
+    fn test_64() {
+        assert_eq!(common_prefix_suffix(&[1,2,3,4],&[1,5,4]),(1,1));
+        assert_eq!(common_prefix_suffix(&[1,1],&[1,1,1]),(2,0));
+        assert_eq!(common_prefix_suffix::<usize>(&[],&[]),(0,0));
+        // Long matches compared in blocks
+        let lhs : Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        for (i,j) in [(0,999),(63,64),(64,65),(500,700),(998,999),(999,999)] {
+            let mut rhs = lhs.clone();
+            rhs.splice(i..j,[255,255]);
+            assert_eq!(common_prefix_suffix(&lhs,&rhs),(i,1000-j));
+            assert_eq!(common_prefix_suffix(&rhs,&lhs),(i,1000-j));
+        }
+    }
+
+    #[test]
+    fn test_65() {
+        // Compare on first component only
+        let from = [(1,'a'),(2,'b'),(3,'c')];
+        let to = [(1,'x'),(4,'d'),(3,'y')];
+        let mut vec = from.to_vec();
+        let delta = diff_by(&from,&to,|l,r| l.0 == r.0);
+        assert_eq!(delta.len(),1);
+        delta.transform(&mut vec);
+        assert_eq!(vec,[(1,'a'),(4,'d'),(3,'c')]);
+    }
+
+    #[test]
+    fn test_66() {
+        // Case insensitive comparison
+        let from = ["Hello","World","!"];
+        let to = ["hello","there","world"];
+        let mut vec = from.to_vec();
+        let delta = diff_by_key(&from,&to,|s| s.to_lowercase());
+        assert_eq!(delta.len(),2);
+        delta.transform(&mut vec);
+        assert_eq!(vec,["Hello","there","World"]);
+    }
+
+    #[test]
+    fn test_67() {
+        // Algorithm only sees the middle
+        let from = [1,2,3,4,5];
+        let to = [1,2,6,4,5];
+        let mut vec = from.to_vec();
+        let delta = diff_trimmed(&from,&to,|l,r| {
+            assert_eq!((l,r),(&[3][..],&[6][..]));
+            vec![None]
+        });
+        assert_eq!(delta.len(),1);
+        assert_eq!(delta.get(0).unwrap().region().offset,2);
+        delta.transform(&mut vec);
+        assert_eq!(vec,to);
+    }
+
+    #[test]
+    fn test_68() {
+        assert_eq!(edit_distance::<usize>(&[],&[]),0);
+        assert_eq!(edit_distance(&[1,2,3],&[1,2,3]),0);
+        assert_eq!(edit_distance(&[1,2,3],&[]),3);
+        assert_eq!(edit_distance(&[],&[1,2]),2);
+        assert_eq!(edit_distance(&[1,2,3],&[1,4,3]),2);
+        assert_eq!(edit_distance(&['a','b','b','c','b','c','d'],&['b','b','e','c','d','e']),5);
+    }
+
+    #[test]
+    fn test_69() {
+        assert_eq!(similarity::<usize>(&[],&[]),1.0);
+        assert_eq!(similarity(&[1,2,3],&[1,2,3]),1.0);
+        assert_eq!(similarity(&[1,2,3],&[4,5,6]),0.0);
+        assert_eq!(similarity(&[1,2,3,4],&[1,2,5,6]),0.5);
+        assert_eq!(similarity(&['a','b','c','d'],&['b','c','d','e']),0.75);
+    }
+
+    #[test]
+    fn test_70() {
+        assert_eq!(similarity_upper_bound::<usize>(&[],&[]),1.0);
+        assert_eq!(similarity_upper_bound(&[1,2,3],&[3,2,1]),1.0);
+        assert_eq!(similarity_upper_bound(&[1,2,3],&[4,5,6]),0.0);
+        assert_eq!(similarity_upper_bound(&[1,1,2],&[1,2,2]),2.0/3.0);
+        // Always an upper bound
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                assert!(similarity(a,b) <= similarity_upper_bound(a,b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_71() {
+        let lhs = [1,2,2,4,7,9];
+        let rhs = [0,2,3,4,4,9,10];
+        let delta = diff_sorted(&lhs,&rhs);
+        let mut vec = lhs.to_vec();
+        delta.transform(&mut vec);
+        assert_eq!(vec,rhs);
+        // Same size as a general purpose diff
+        assert_eq!(delta.len(),lhs.diff(&rhs).len());
+    }
+
+    #[test]
+    fn test_72() {
+        let seqs = [vec![],vec![1],vec![1,2,3],vec![1,1,2,2],vec![0,2,4],vec![3,3,3]];
+        for lhs in &seqs {
+            for rhs in &seqs {
+                let mut vec = lhs.clone();
+                diff_sorted(lhs,rhs).transform(&mut vec);
+                assert_eq!(&vec,rhs);
+            }
+        }
+    }
+
     // Construct diff between `from` and `to`, which is expected to
     // produce a delta with a given number of rewrites.  Check that
     // applying this delta to `from` produces `to`.
     fn check<T:Clone+Debug+PartialEq>(from: &[T], to: &[T], num: usize) {
 	let mut vec = from.to_vec();
 	// Generatre diff between `from` and `to`.
-	let delta = from.diff(&to);
-	//
-	println!("GOT: {delta:?}");
+	let delta = from.diff(to);
 	// Check number of rewrites matches expected
 	assert_eq!(delta.len(),num);
 	// Apply delta to original sequence
@@ -339,6 +729,7 @@ mod diff_tests {
 
 #[cfg(test)]
 mod lcs_tests {
+    use alloc::vec;
     use crate::diff::slice::*;
 
     #[test]
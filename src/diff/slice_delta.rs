@@ -0,0 +1,211 @@
+use alloc::vec::Vec;
+use crate::util::Region;
+use super::{CowRewrite,DeltaError,SliceRewrite,VecDelta,common_prefix_suffix,myers_subsequence};
+use super::slice::extract_rewrites;
+use super::vec_delta::check_follows;
+
+/// A `SliceDelta` is a _zero-copy_ variant of `VecDelta` whose
+/// replacement data is borrowed from the sequence being diffed
+/// against (i.e. the _after_ sequence), rather than copied.  As such,
+/// constructing a `SliceDelta` does not require elements to be
+/// cloneable, and allocates only the rewrites themselves.  As for
+/// `VecDelta`, rewrites are sorted, disjoint and their offsets are
+/// given in terms of the _target sequence_.  When a delta needs to
+/// outlive the sequence it borrows from, it can be converted into a
+/// `VecDelta` using `into_owned()`.
+#[derive(Clone,Debug)]
+pub struct SliceDelta<'a,T> {
+    rewrites: Vec<SliceRewrite<'a,T>>
+}
+
+impl<'a,T> SliceDelta<'a,T> {
+    /// Construct an empty `SliceDelta`.
+    pub const fn new() -> Self { SliceDelta{rewrites: Vec::new()} }
+
+    /// Get the number of rewrites in this delta.
+    pub fn len(&self) -> usize { self.rewrites.len() }
+
+    /// Check whether this delta contains any rewrites or not.
+    pub fn is_empty(&self) -> bool { self.rewrites.is_empty() }
+
+    /// Get the `ith` rewrite in this delta.
+    pub fn get(&self, ith: usize) -> Option<&SliceRewrite<'a,T>> { self.rewrites.get(ith) }
+
+    /// Iterate the rewrites contained within this `SliceDelta`, in
+    /// order.
+    pub fn iter(&self) -> core::slice::Iter<'_,SliceRewrite<'a,T>> { self.rewrites.iter() }
+}
+
+impl<T> Default for SliceDelta<'_,T> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<T:PartialEq> PartialEq for SliceDelta<'_,T> {
+    fn eq(&self, other: &Self) -> bool { self.rewrites == other.rewrites }
+}
+
+impl<T:Clone> SliceDelta<'_,T> {
+    /// Convert this delta into a `VecDelta` by copying its replacement
+    /// data, such that it no longer borrows from the after sequence.
+    pub fn into_owned(self) -> VecDelta<T> {
+        let mut delta = VecDelta::new();
+        for rw in self.rewrites {
+            delta.try_push(rw.region().as_range(), rw.into_data()).expect("malformed delta");
+        }
+        delta
+    }
+
+    /// Apply this delta to a given `Vec`, thus transforming it.  This
+    /// operation will `panic` if this delta is malformed with respect
+    /// to the given `Vec`.
+    pub fn transform(&self, vec: &mut Vec<T>) {
+        for rw in &self.rewrites {
+            vec.splice(rw.region().as_range(), rw.data().iter().cloned());
+        }
+    }
+}
+
+/// A `CowDelta` is a variant of `VecDelta` where the data for each
+/// rewrite is either borrowed or owned (see `CowRewrite`).  Thus, a
+/// delta obtained by diffing (see `diff_borrowed()`) can be converted
+/// into a `CowDelta` and then extended with owned rewrites, without
+/// copying the data already borrowed.  As for `VecDelta`, rewrites are
+/// sorted, disjoint and their offsets are given in terms of the
+/// _target sequence_.
+#[derive(Clone,Debug,PartialEq)]
+pub struct CowDelta<'a,T:Clone> {
+    rewrites: Vec<CowRewrite<'a,T>>
+}
+
+impl<'a,T:Clone> CowDelta<'a,T> {
+    /// Construct an empty `CowDelta`.
+    pub const fn new() -> Self { CowDelta{rewrites: Vec::new()} }
+
+    /// Get the number of rewrites in this delta.
+    pub fn len(&self) -> usize { self.rewrites.len() }
+
+    /// Check whether this delta contains any rewrites or not.
+    pub fn is_empty(&self) -> bool { self.rewrites.is_empty() }
+
+    /// Get the `ith` rewrite in this delta.
+    pub fn get(&self, ith: usize) -> Option<&CowRewrite<'a,T>> { self.rewrites.get(ith) }
+
+    /// Iterate the rewrites contained within this `CowDelta`, in
+    /// order.
+    pub fn iter(&self) -> core::slice::Iter<'_,CowRewrite<'a,T>> { self.rewrites.iter() }
+
+    /// Append a new (borrowed or owned) rewrite onto the end of this
+    /// delta, whose region is in terms of the _target sequence_.  This
+    /// requires that the rewrite logically follows all other rewrites,
+    /// and is disjoint from them (see `VecDelta::try_push()`).
+    pub fn try_push<R:Into<CowRewrite<'a,T>>>(&mut self, rewrite: R) -> Result<(),DeltaError> {
+        let rewrite = rewrite.into();
+        if let Some(last) = self.rewrites.last() {
+            let r = last.region();
+            check_follows(Region::new(r.offset,last.data().len()),rewrite.region())?;
+        }
+        self.rewrites.push(rewrite);
+        Ok(())
+    }
+
+    /// Convert this delta into a `VecDelta`, copying any borrowed
+    /// data.
+    pub fn into_owned(self) -> VecDelta<T> {
+        let mut delta = VecDelta::new();
+        for rw in self.rewrites {
+            delta.try_push(rw.region().as_range(), rw.data()).expect("malformed delta");
+        }
+        delta
+    }
+
+    /// Apply this delta to a given `Vec`, thus transforming it.  This
+    /// operation will `panic` if this delta is malformed with respect
+    /// to the given `Vec`.
+    pub fn transform(&self, vec: &mut Vec<T>) {
+        for rw in &self.rewrites {
+            vec.splice(rw.region().as_range(), rw.data().iter().cloned());
+        }
+    }
+}
+
+impl<T:Clone> Default for CowDelta<'_,T> {
+    fn default() -> Self { Self::new() }
+}
+
+impl<'a,T:Clone> From<SliceDelta<'a,T>> for CowDelta<'a,T> {
+    fn from(delta: SliceDelta<'a,T>) -> Self {
+        CowDelta{rewrites: delta.rewrites.into_iter().map(CowRewrite::from).collect()}
+    }
+}
+
+/// Compute a _zero-copy_ delta between two slices, whose replacement
+/// data is borrowed from `rhs` (see `SliceDelta`).  This uses Myers'
+/// algorithm (see `diff_myers()`) and, hence, produces the same
+/// rewrites as `lhs.diff(rhs)`.
+pub fn diff_borrowed<'a,T:PartialEq>(lhs: &[T], rhs: &'a [T]) -> SliceDelta<'a,T> {
+    let (pre,suf) = common_prefix_suffix(lhs,rhs);
+    let after = &rhs[pre..rhs.len()-suf];
+    let mapping = myers_subsequence(&lhs[pre..lhs.len()-suf],after);
+    let mut rewrites = Vec::new();
+    extract_rewrites(pre, &mapping, after, |range,data| {
+        rewrites.push(SliceRewrite::new(range.into(),data));
+    });
+    SliceDelta{rewrites}
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod slice_delta_tests {
+    use alloc::borrow::Cow;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,VecRewrite};
+    use crate::util::Region;
+    use super::{CowDelta,diff_borrowed};
+
+    #[test]
+    fn slice_delta_test_01() {
+        // Elements need not be cloneable
+        #[derive(Debug,PartialEq)]
+        struct Node(usize);
+        let lhs = [Node(1),Node(2),Node(3)];
+        let rhs = [Node(1),Node(4),Node(5),Node(3)];
+        let d = diff_borrowed(&lhs,&rhs);
+        assert_eq!(d.len(),1);
+        assert_eq!(d.get(0).unwrap().region(),Region::new(1,1));
+        assert!(core::ptr::eq(d.get(0).unwrap().data(),&rhs[1..3]));
+    }
+
+    #[test]
+    fn slice_delta_test_02() {
+        // Agrees with owned diff
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let d = diff_borrowed(a,b);
+                let mut vec = a.clone();
+                d.transform(&mut vec);
+                assert_eq!(&vec,b);
+                assert_eq!(d.clone().into_owned(),a.diff(b));
+                assert_eq!(CowDelta::from(d).into_owned(),a.diff(b));
+            }
+        }
+    }
+
+    #[test]
+    fn slice_delta_test_03() {
+        // Mixing borrowed and owned rewrites
+        let (lhs,rhs) = ([1,2,3,4,5],[1,6,3,4,5]);
+        let mut d = CowDelta::from(diff_borrowed(&lhs,&rhs));
+        assert_eq!(d.try_push(VecRewrite::new(Region::new(3,2),vec![7])),Ok(()));
+        assert!(matches!(d.get(0).unwrap().clone().into_data(),Cow::Borrowed(_)));
+        assert!(matches!(d.get(1).unwrap().clone().into_data(),Cow::Owned(_)));
+        assert!(d.try_push(VecRewrite::new(Region::new(0,1),vec![])).is_err());
+        let mut vec = lhs.to_vec();
+        d.transform(&mut vec);
+        assert_eq!(vec,[1,6,3,7]);
+    }
+}
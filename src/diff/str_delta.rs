@@ -0,0 +1,289 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+use crate::util::Region;
+use super::{DeltaError,Diff,Transform,TryTransform};
+use super::vec_delta::check_follows;
+
+/// A `StrDelta` is the analogue of a `VecDelta` for strings.  That
+/// is, a sequence of zero (or more) rewrites which can be applied to a
+/// `String` to generate another `String`.  As for `VecDelta`, the
+/// rewrites are sorted, disjoint and their offsets are given in terms
+/// of the _target_ string (reading left-to-right).  However, all
+/// offsets are _byte offsets_ and every rewrite must begin and end on
+/// a `char` boundary.  The replacement text for all rewrites is stored
+/// together in a single `String`.  For example, transforming `"héllo"`
+/// into `"hèlp"` gives `(1;2;"è"),(4;2;"p")`.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct StrDelta {
+    /// Regions of the target string being replaced, along with the
+    /// corresponding region of `data` being used for replacement.
+    regions: Vec<(Region,Region)>,
+    /// Replacement text for all rewrites.
+    data: String
+}
+
+impl StrDelta {
+    /// Construct an empty `StrDelta`.
+    pub const fn new() -> Self { StrDelta{regions: Vec::new(), data: String::new()} }
+
+    /// Get the number of rewrites in this delta.
+    pub fn len(&self) -> usize { self.regions.len() }
+
+    /// Check whether this delta contains any rewrites or not.
+    pub fn is_empty(&self) -> bool { self.regions.is_empty() }
+
+    /// Get the `ith` rewrite in this delta, as a region of the target
+    /// string and its replacement text.
+    pub fn get(&self, ith: usize) -> Option<(Region,&str)> {
+        self.regions.get(ith).map(|(r1,r2)| (*r1,&self.data[r2.as_range()]))
+    }
+
+    /// Iterate the rewrites contained within this `StrDelta`, in
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item=(Region,&str)> {
+        self.regions.iter().map(|(r1,r2)| (*r1,&self.data[r2.as_range()]))
+    }
+
+    /// Append a new rewrite onto the end of this delta, where the
+    /// given range is expressed as byte offsets in terms of the
+    /// _target string_.  This requires that the rewrite logically
+    /// follows all other rewrites, and is disjoint from them (see
+    /// `VecDelta::try_push()`).
+    pub fn try_push(&mut self, range: Range<usize>, text: &str) -> Result<(),DeltaError> {
+        let region : Region = range.into();
+        if let Some((r1,r2)) = self.regions.last() {
+            check_follows(Region::new(r1.offset,r2.length),region)?;
+        }
+        let data_start = self.data.len();
+        self.data.push_str(text);
+        self.regions.push((region,Region::new(data_start,text.len())));
+        Ok(())
+    }
+
+    /// Apply this delta to a given `String`, thus transforming it.
+    /// This operation will `panic` if this delta is malformed with
+    /// respect to the given string (including when a rewrite does not
+    /// begin or end on a `char` boundary).
+    pub fn transform(&self, string: &mut String) {
+        for (r1,r2) in &self.regions {
+            string.replace_range(r1.as_range(), &self.data[r2.as_range()]);
+        }
+    }
+
+    /// Apply this delta to a given `String`, thus transforming it.
+    /// Unlike `transform()`, this first checks every rewrite lies
+    /// within bounds, and begins and ends on a `char` boundary.  If
+    /// not, an error is returned and the given `String` is left
+    /// unchanged.
+    pub fn try_transform(&self, string: &mut String) -> Result<(),DeltaError> {
+        let mut len = string.len();
+        for ((r1,r2),(r,_)) in self.regions.iter().zip(self.iter_source()) {
+            if r1.offset + r1.length > len {
+                return Err(DeltaError::RegionOutOfBounds{region: *r1, len});
+            }
+            // Since rewrites are disjoint, boundaries can be checked
+            // against the original string.
+            for i in [r.offset, r.offset + r.length] {
+                if !string.is_char_boundary(i) {
+                    return Err(DeltaError::NotCharBoundary(i));
+                }
+            }
+            len = len - r1.length + r2.length;
+        }
+        self.transform(string);
+        Ok(())
+    }
+
+    /// Apply this delta to a given string, producing a new `String`
+    /// (see `VecDelta::apply()`).
+    pub fn apply(&self, src: &str) -> String {
+        let mut out = String::with_capacity(src.len() + self.data.len());
+        let mut cursor = 0;
+        for (r,text) in self.iter_source() {
+            out.push_str(&src[cursor..r.offset]);
+            out.push_str(text);
+            cursor = r.offset + r.length;
+        }
+        out.push_str(&src[cursor..]);
+        out
+    }
+
+    /// Iterate the rewrites contained within this delta, with their
+    /// regions given in terms of the _original string_.
+    pub(crate) fn iter_source(&self) -> impl Iterator<Item=(Region,&str)> {
+        let mut shift = 0isize;
+        self.regions.iter().map(move |(r1,r2)| {
+            let offset = (r1.offset as isize - shift) as usize;
+            shift += r2.length as isize - r1.length as isize;
+            (Region::new(offset,r1.length),&self.data[r2.as_range()])
+        })
+    }
+
+    /// Construct a delta from a sequence of rewrites whose regions are
+    /// given in terms of the _original string_.  These must be sorted
+    /// and disjoint.
+    pub(crate) fn from_source<'a,I:IntoIterator<Item=(Region,&'a str)>>(rewrites: I) -> Self {
+        let mut delta = StrDelta::new();
+        let mut shift = 0isize;
+        for (r,text) in rewrites {
+            let offset = (r.offset as isize + shift) as usize;
+            delta.try_push(offset .. offset + r.length, text).unwrap();
+            shift += text.len() as isize - r.length as isize;
+        }
+        delta
+    }
+}
+
+/// Diffing of strings operates at the level of `char`s, rather than
+/// bytes.  Thus, every rewrite in the resulting delta is guaranteed to
+/// begin and end on a `char` boundary.
+impl Diff for str {
+    type Delta = StrDelta;
+
+    fn diff(&self, other: &str) -> StrDelta {
+        let lhs : Vec<char> = self.chars().collect();
+        let rhs : Vec<char> = other.chars().collect();
+        // Byte offset of each char (plus the end)
+        let offsets : Vec<usize> = self.char_indices().map(|(i,_)| i).chain([self.len()]).collect();
+        let hunks : Vec<(Region,String)> = lhs.diff(&rhs).iter_source().map(|(r,data)| {
+            let start = offsets[r.offset];
+            let end = offsets[r.offset + r.length];
+            (Region::new(start,end - start),data.iter().collect())
+        }).collect();
+        StrDelta::from_source(hunks.iter().map(|(r,d)| (*r,d.as_str())))
+    }
+}
+
+impl Diff for String {
+    type Delta = StrDelta;
+
+    fn diff(&self, other: &String) -> StrDelta {
+        self.as_str().diff(other.as_str())
+    }
+}
+
+/// Compute a delta between two strings at the level of bytes, rather
+/// than `char`s.  This is typically faster than diffing `char`s (and
+/// can produce finer grained deltas) though, for text which is mostly
+/// ASCII, the results are similar.  Any rewrite found which does not
+/// begin or end on a `char` boundary (i.e. because only part of the
+/// encoding of a `char` differs) is widened accordingly.  For example,
+/// `é` and `è` differ only in their last byte, but a rewrite of the
+/// whole `char` is produced.
+pub fn diff_str_bytes(lhs: &str, rhs: &str) -> StrDelta {
+    // Determine hunks as (lhs start, lhs end, rhs start, rhs end)
+    let mut hunks = Vec::new();
+    let mut shift = 0isize;
+    for (r,data) in lhs.as_bytes().diff(rhs.as_bytes()).iter_source() {
+        let start = (r.offset as isize + shift) as usize;
+        hunks.push((r.offset,r.offset + r.length,start,start + data.len()));
+        shift += data.len() as isize - r.length as isize;
+    }
+    // Widen hunks to char boundaries.  Since the bytes between hunks
+    // are the same on both sides, both sides are widened together.
+    // Hunks which become adjacent are merged.
+    let boundary = |l: usize, r: usize| lhs.is_char_boundary(l) && rhs.is_char_boundary(r);
+    let mut widened : Vec<(usize,usize,usize,usize)> = Vec::new();
+    let mut i = 0;
+    while i < hunks.len() {
+        let (mut s,mut e,mut t,mut u) = hunks[i];
+        i += 1;
+        while !boundary(s,t) { s -= 1; t -= 1; }
+        loop {
+            while !boundary(e,u) && (i == hunks.len() || e < hunks[i].0) { e += 1; u += 1; }
+            if i < hunks.len() && e == hunks[i].0 {
+                (e,u) = (hunks[i].1,hunks[i].3);
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        match widened.last_mut() {
+            Some(last) if last.1 == s => { last.1 = e; last.3 = u; }
+            _ => widened.push((s,e,t,u))
+        }
+    }
+    StrDelta::from_source(widened.iter().map(|&(s,e,t,u)| (Region::new(s,e - s),&rhs[t..u])))
+}
+
+impl Transform for String {
+    type Delta = StrDelta;
+
+    fn transform(&mut self, d: &StrDelta) {
+        d.transform(self)
+    }
+}
+
+impl TryTransform for String {
+    type Delta = StrDelta;
+    type Error = DeltaError;
+
+    fn try_transform(&mut self, d: &StrDelta) -> Result<(),DeltaError> {
+        d.try_transform(self)
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod str_delta_tests {
+    use alloc::string::{String,ToString};
+    use crate::diff::{DeltaError,Diff,Transform,TryTransform};
+    use crate::util::Region;
+    use super::{StrDelta,diff_str_bytes};
+
+    const STRS : [&str;8] = ["","hello","héllo","hèlp","héllo wörld","ünïcödé","日本語","日本人"];
+
+    #[test]
+    fn str_delta_test_01() {
+        let d = "héllo".diff("hèlp");
+        assert_eq!(d.len(),2);
+        assert_eq!(d.get(0),Some((Region::new(1,2),"è")));
+        assert_eq!(d.get(1),Some((Region::new(4,2),"p")));
+    }
+
+    #[test]
+    fn str_delta_test_02() {
+        // Round trip for chars and bytes
+        for lhs in STRS {
+            for rhs in STRS {
+                for d in [lhs.diff(rhs),diff_str_bytes(lhs,rhs)] {
+                    let mut s = lhs.to_string();
+                    s.transform(&d);
+                    assert_eq!(s,rhs);
+                    assert_eq!(d.apply(lhs),rhs);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn str_delta_test_03() {
+        // Byte diffs widened to char boundaries
+        let d = diff_str_bytes("héllo","hèllo");
+        assert_eq!(d.len(),1);
+        assert_eq!(d.get(0),Some((Region::new(1,2),"è")));
+        let d = diff_str_bytes("日本語","日本人");
+        assert_eq!(d.get(0),Some((Region::new(6,3),"人")));
+    }
+
+    #[test]
+    fn str_delta_test_04() {
+        let mut d = StrDelta::new();
+        d.try_push(1..2,"x").unwrap();
+        // Not char boundary
+        let mut s = String::from("héllo");
+        assert_eq!(s.try_transform(&d),Err(DeltaError::NotCharBoundary(2)));
+        assert_eq!(s,"héllo");
+        // Out of bounds
+        let mut s = String::new();
+        assert!(s.try_transform(&d).is_err());
+        // Ok
+        let mut s = String::from("hello");
+        assert_eq!(s.try_transform(&d),Ok(()));
+        assert_eq!(s,"hxllo");
+    }
+}
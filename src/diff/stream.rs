@@ -0,0 +1,92 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::io::{BufRead,Result};
+use crate::util::hash_of;
+use super::{VecDelta,common_prefix_suffix,myers_subsequence};
+use super::slice::extract_delta;
+
+/// Compute a delta between the lines of two inputs, which are read
+/// incrementally.  Each line (including its terminator, if any) is
+/// one element of the resulting delta and, hence, applying it to the
+/// lines of `lhs` reproduces `rhs` exactly.  Only the hash of each
+/// line in `lhs` is retained, whilst lines of `rhs` are retained since
+/// they provide the replacement data.  Lines are then matched using
+/// Myers' algorithm over their hashes.
+///
+/// **NOTE:** since lines of `lhs` are not retained, two distinct lines
+/// whose 64-bit hashes collide are treated as equal.  Whilst this is
+/// extremely unlikely in practice, callers requiring certainty should
+/// use `Diff` directly.
+pub fn diff_lines<L:BufRead,R:BufRead>(lhs: L, rhs: R) -> Result<VecDelta<String>> {
+    let mut lhashes = Vec::new();
+    for_each_line(lhs, |line| lhashes.push(hash_of(&line)))?;
+    let mut lines = Vec::new();
+    for_each_line(rhs, |line| lines.push(line))?;
+    let rhashes : Vec<u64> = lines.iter().map(hash_of).collect();
+    // Compute mapping over hashes
+    let (pre,suf) = common_prefix_suffix(&lhashes,&rhashes);
+    let mapping = myers_subsequence(&lhashes[pre..lhashes.len()-suf],&rhashes[pre..rhashes.len()-suf]);
+    Ok(extract_delta(pre, &mapping, &lines[pre..lines.len()-suf]))
+}
+
+/// Split a given input into lines, including their terminators.
+pub fn lines<B:BufRead>(input: B) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for_each_line(input, |line| lines.push(line))?;
+    Ok(lines)
+}
+
+/// Read each line of a given input in turn (including its terminator)
+/// and pass it to a given function.
+fn for_each_line<B:BufRead,F:FnMut(String)>(mut input: B, mut f: F) -> Result<()> {
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 { return Ok(()); }
+        f(line);
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod stream_tests {
+    use std::io::Cursor;
+    use super::{diff_lines,lines};
+
+    #[test]
+    fn test_stream_01() {
+        check("a\nb\nc\n","a\nb\nc\n",0);
+    }
+
+    #[test]
+    fn test_stream_02() {
+        check("a\nb\nc\n","a\nx\nc\n",1);
+    }
+
+    #[test]
+    fn test_stream_03() {
+        // Missing final newline
+        check("a\nb\nc\n","a\nb\nc",1);
+    }
+
+    #[test]
+    fn test_stream_04() {
+        check("","a\r\nb\r\n",1);
+        check("a\r\nb\r\n","",1);
+    }
+
+    #[test]
+    fn test_stream_05() {
+        check("one\ntwo\nthree\nfour\n","zero\none\nthree\nfour\nfive\n",3);
+    }
+
+    fn check(before: &str, after: &str, num: usize) {
+        let delta = diff_lines(Cursor::new(before),Cursor::new(after)).unwrap();
+        assert_eq!(delta.len(),num);
+        let mut ls = lines(Cursor::new(before)).unwrap();
+        delta.transform(&mut ls);
+        assert_eq!(ls.concat(),after);
+    }
+}
@@ -0,0 +1,114 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use super::{Diff,MapDelta,VecDelta};
+
+/// A delta between two tables (i.e. sequences of rows, each being a
+/// sequence of cells) where rows are identified by a _key_, rather than
+/// by their position.  Rows are removed or inserted wholesale, whilst
+/// the cells of a changed row are described by a `VecDelta`.
+pub type TableDelta<K,T> = MapDelta<K,Vec<T>,VecDelta<T>>;
+
+/// Compute a delta between two tables, where rows are matched by a key
+/// extracted using a given function (e.g. the value of an `id`
+/// column).  Unlike a positional diff, the order of rows is irrelevant
+/// and, hence, sorting a table or inserting rows does not cause
+/// unrelated rows to be mismatched.  For example:
+///
+/// ```
+/// use delta_inc::diff::{diff_table,transform_table};
+///
+/// let before = vec![vec!["1","alice","london"],vec!["2","bob","paris"],vec!["3","carol","rome"]];
+/// let after = vec![vec!["3","carol","rome"],vec!["2","bob","berlin"],vec!["4","dave","oslo"]];
+/// let delta = diff_table(&before,&after,|row| row[0]);
+/// assert_eq!(delta.removed(),["1"]);
+/// assert_eq!(delta.changed()[0].0,"2");
+/// let mut table = before.clone();
+/// transform_table(&mut table,&delta,|row| row[0]);
+/// assert_eq!(table,[vec!["2","bob","berlin"],vec!["3","carol","rome"],vec!["4","dave","oslo"]]);
+/// ```
+///
+/// Keys are expected to be unique within each table.  The removed and
+/// changed rows are given in their order within `lhs`, and the
+/// inserted rows in their order within `rhs`.
+pub fn diff_table<K,T,F>(lhs: &[Vec<T>], rhs: &[Vec<T>], key: F) -> TableDelta<K,T>
+where K:Ord+Clone, T:Clone+PartialEq, F:Fn(&[T])->K {
+    let before : BTreeMap<K,&Vec<T>> = lhs.iter().map(|r| (key(r),r)).collect();
+    let after : BTreeMap<K,&Vec<T>> = rhs.iter().map(|r| (key(r),r)).collect();
+    let mut delta = MapDelta::new();
+    for row in lhs {
+        let k = key(row);
+        match after.get(&k) {
+            None => delta.remove(k),
+            Some(r) if row != *r => delta.change(k,row.diff(r)),
+            Some(_) => {}
+        }
+    }
+    for row in rhs {
+        let k = key(row);
+        if !before.contains_key(&k) { delta.insert(k,row.clone()); }
+    }
+    delta
+}
+
+/// Apply a `TableDelta` to a table, where rows are identified using a
+/// given function (which should be that used to compute the delta).
+/// Changed rows are updated in place, removed rows are dropped and
+/// inserted rows are appended (in order).  This will `panic` if a
+/// changed row is not present, or its delta cannot be applied.
+pub fn transform_table<K,T,F>(rows: &mut Vec<Vec<T>>, delta: &TableDelta<K,T>, key: F)
+where K:Ord, T:Clone, F:Fn(&[T])->K {
+    let index : BTreeMap<K,usize> = rows.iter().enumerate().map(|(i,r)| (key(r),i)).collect();
+    for (k,d) in delta.changed() {
+        let i = *index.get(k).expect("changed row not present");
+        d.transform(&mut rows[i]);
+    }
+    let removed : BTreeMap<&K,()> = delta.removed().iter().map(|k| (k,())).collect();
+    rows.retain(|r| !removed.contains_key(&key(r)));
+    rows.extend(delta.inserted().iter().map(|(_,r)| r.clone()));
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod table_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use super::{diff_table,transform_table};
+
+    fn tables() -> Vec<Vec<Vec<usize>>> {
+        (0..12).map(|i| (0..i).map(|j| vec![(j*7+i)%12,(j*i+3)%5,j%2]).collect()).collect()
+    }
+
+    #[test]
+    fn table_test_01() {
+        for a in tables() {
+            for b in tables() {
+                let delta = diff_table(&a,&b,|r| r[0]);
+                let mut t = a.clone();
+                transform_table(&mut t,&delta,|r| r[0]);
+                // Rows are equal irrespective of order
+                t.sort();
+                let mut b = b.clone();
+                b.sort();
+                assert_eq!(t,b);
+            }
+        }
+    }
+
+    #[test]
+    fn table_test_02() {
+        // Reordering rows gives an empty delta
+        let a = vec![vec![1,2],vec![2,3],vec![3,4]];
+        let mut b = a.clone();
+        b.reverse();
+        assert!(diff_table(&a,&b,|r| r[0]).is_empty());
+        // Changing a cell gives a delta for that row only
+        b[1][1] = 7;
+        let delta = diff_table(&a,&b,|r| r[0]);
+        assert_eq!(delta.len(),1);
+        assert_eq!(delta.changed()[0].0,2);
+        assert_eq!(delta.changed()[0].1.len(),1);
+    }
+}
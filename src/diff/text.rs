@@ -0,0 +1,180 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::util::Region;
+use super::{StrDelta,VecDelta,common_prefix_suffix,myers_subsequence};
+use super::slice::extract_delta;
+
+/// Split a given string into lines, where each line includes its
+/// terminator (either `"\n"` or `"\r\n"`).  Thus, concatenating the
+/// lines reproduces the string exactly.  The final line has no
+/// terminator if the string does not end with one, whilst an empty
+/// string has no lines.  A lone `'\r'` is not treated as a terminator.
+pub fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Compute a delta between the lines of two strings (see `lines()`).
+/// Each line is one element of the resulting delta, whose replacement
+/// data borrows from `rhs`.  Thus, applying it to the lines of `lhs`
+/// reproduces `rhs` exactly.  For example:
+///
+/// ```
+/// use delta_inc::diff::text::{diff_lines,lines};
+///
+/// let delta = diff_lines("a\nb\nc\n", "a\nx\nc\n");
+/// assert_eq!(delta.apply(&lines("a\nb\nc\n")).concat(), "a\nx\nc\n");
+/// ```
+///
+/// Since lines retain their terminators, a line whose terminator
+/// differs (e.g. `"\r\n"` versus `"\n"`), or the final line when only
+/// one side ends with a terminator, is considered changed.  This
+/// matches the behaviour of line-based tools such as `diff` and
+/// `git`.  Internally, each distinct line is assigned an identifier,
+/// and lines are then matched using Myers' algorithm over these.
+pub fn diff_lines<'b>(lhs: &str, rhs: &'b str) -> VecDelta<&'b str> {
+    let (llines,rlines) = (lines(lhs),lines(rhs));
+    let mut ids = BTreeMap::new();
+    let mut all = Vec::with_capacity(llines.len() + rlines.len());
+    for &l in llines.iter().chain(&rlines) {
+        let n = ids.len();
+        all.push(*ids.entry(l).or_insert(n));
+    }
+    let (lids,rids) = all.split_at(llines.len());
+    let (pre,suf) = common_prefix_suffix(lids,rids);
+    let mapping = myers_subsequence(&lids[pre..lids.len()-suf],&rids[pre..rids.len()-suf]);
+    extract_delta(pre, &mapping, &rlines[pre..rlines.len()-suf])
+}
+
+/// Convert a delta over the lines of a given string (see
+/// `diff_lines()`) into an equivalent delta over the string itself.
+/// Each rewrite replaces whole lines and, hence, begins and ends on a
+/// line boundary.  For example:
+///
+/// ```
+/// use delta_inc::diff::text::{diff_lines,to_str_delta};
+///
+/// let delta = to_str_delta("a\nb\n", &diff_lines("a\nb\n", "a\r\nb\n"));
+/// assert_eq!(delta.apply("a\nb\n"), "a\r\nb\n");
+/// ```
+///
+/// This operation will `panic` if the delta is malformed with respect
+/// to the lines of `before`.
+pub fn to_str_delta<S:AsRef<str>>(before: &str, delta: &VecDelta<S>) -> StrDelta {
+    join(&lines(before), delta)
+}
+
+/// Compute a delta between two strings at the level of (extended)
+/// grapheme clusters, rather than `char`s.  Thus, every rewrite in the
+/// resulting delta begins and ends on a grapheme boundary and, hence,
+/// never splits a user-perceived character (e.g. a letter followed by
+/// a combining accent, or an emoji sequence joined by `U+200D`).  For
+/// example:
+///
+/// ```
+/// use delta_inc::diff::Diff;
+/// use delta_inc::diff::text::diff_graphemes;
+///
+/// let (before,after) = ("cafe\u{301}", "cafe\u{300}");
+/// // Diffing chars replaces only the accent
+/// assert_eq!(before.diff(after).iter().next().unwrap().1, "\u{300}");
+/// // Diffing graphemes replaces the whole accented letter
+/// assert_eq!(diff_graphemes(before, after).iter().next().unwrap().1, "e\u{300}");
+/// ```
+#[cfg(feature = "graphemes")]
+pub fn diff_graphemes(lhs: &str, rhs: &str) -> StrDelta {
+    use unicode_segmentation::UnicodeSegmentation;
+    let lgraphemes : Vec<&str> = lhs.graphemes(true).collect();
+    let rgraphemes : Vec<&str> = rhs.graphemes(true).collect();
+    let (pre,suf) = common_prefix_suffix(&lgraphemes,&rgraphemes);
+    let mapping = myers_subsequence(&lgraphemes[pre..lgraphemes.len()-suf],&rgraphemes[pre..rgraphemes.len()-suf]);
+    join(&lgraphemes, &extract_delta(pre, &mapping, &rgraphemes[pre..rgraphemes.len()-suf]))
+}
+
+/// Convert a delta over the pieces of a given string (e.g. its lines)
+/// into an equivalent delta over the string itself.
+fn join<S:AsRef<str>>(pieces: &[&str], delta: &VecDelta<S>) -> StrDelta {
+    // Byte offset of each piece (plus the end)
+    let mut offsets = Vec::with_capacity(pieces.len() + 1);
+    let mut n = 0;
+    for p in pieces { offsets.push(n); n += p.len(); }
+    offsets.push(n);
+    let hunks : Vec<_> = delta.iter_source().map(|(r,data)| {
+        let (start,end) = (offsets[r.offset],offsets[r.offset + r.length]);
+        (Region::new(start,end - start),data.iter().map(|p| p.as_ref()).collect::<String>())
+    }).collect();
+    StrDelta::from_source(hunks.iter().map(|(r,d)| (*r,d.as_str())))
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod text_tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use super::{diff_lines,lines,to_str_delta};
+
+    fn check(before: &str, after: &str, rewrites: usize) {
+        let delta = diff_lines(before,after);
+        assert_eq!(delta.len(),rewrites);
+        assert_eq!(delta.apply(&lines(before)).concat(),after);
+        assert_eq!(to_str_delta(before,&delta).apply(before),after);
+    }
+
+    #[test]
+    fn text_test_01() {
+        check("","",0);
+        check("a\nb\nc\n","a\nb\nc\n",0);
+        check("a\nb\nc\n","a\nx\nc\n",1);
+        check("a\nb\nc\n","c\nb\na\n",2);
+        // Missing final newline
+        check("a\nb\nc\n","a\nb\nc",1);
+        check("a\nb","a\nb\n",1);
+        // Carriage returns
+        check("a\r\nb\r\n","a\nb\r\n",1);
+        check("a\rb\n","a\nb\n",1);
+        assert_eq!(lines("a\r\nb\rc\n\nd"),["a\r\n","b\rc\n","\n","d"]);
+    }
+
+    #[test]
+    fn text_test_02() {
+        let words = ["a\n","b\n","c\r\n","d"];
+        let seqs : Vec<String> = (0..15).map(|i| (0..i).map(|j| words[(j*i+3)%words.len()]).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let delta = diff_lines(a,b);
+                assert_eq!(delta.apply(&lines(a)).concat(),*b);
+                assert_eq!(to_str_delta(a,&delta).apply(a),*b);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "graphemes")]
+    fn text_test_03() {
+        use super::diff_graphemes;
+        let texts = ["","e\u{301}","e\u{300}x","👨\u{200D}👩\u{200D}👧","👨\u{200D}👩","a👨b","éé"];
+        for a in texts {
+            for b in texts {
+                let delta = diff_graphemes(a,b);
+                assert_eq!(delta.apply(a),b);
+                // Every rewrite begins and ends on a grapheme boundary
+                let mut shift = 0isize;
+                for (r,text) in delta.iter() {
+                    let (s,e) = ((r.offset as isize - shift) as usize,(r.offset as isize - shift) as usize + r.length);
+                    assert!(boundary(a,s) && boundary(a,e));
+                    shift += text.len() as isize - r.length as isize;
+                }
+            }
+        }
+        assert_eq!(diff_graphemes("👨\u{200D}👩","👨\u{200D}👩\u{200D}👧").len(),1);
+    }
+
+    #[cfg(feature = "graphemes")]
+    fn boundary(text: &str, offset: usize) -> bool {
+        use unicode_segmentation::UnicodeSegmentation;
+        offset == text.len() || text.grapheme_indices(true).any(|(i,_)| i == offset)
+    }
+}
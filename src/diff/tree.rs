@@ -0,0 +1,782 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use core::hash::Hash;
+use crate::util::hash_of;
+use super::{Diff,Transform,longest_common_subsequence};
+
+// ===================================================================
+// Trees
+// ===================================================================
+
+/// A trait describing a node of an ordered tree (e.g. an abstract
+/// syntax tree), which has a label and a sequence of children.  Two
+/// trees can be diffed using `diff_trees()`.
+pub trait TreeNode : Sized {
+    /// The type of labels held by nodes.
+    type Label : Clone+PartialEq;
+    /// Get the label of this node.
+    fn label(&self) -> &Self::Label;
+    /// Get the children of this node, in order.
+    fn children(&self) -> &[Self];
+}
+
+/// A simple ordered tree, where each node holds a label and a sequence
+/// of children.  Any `TreeNode` can be converted into a `Tree` (see
+/// `Tree::of()`).
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Tree<L> {
+    /// The label of this node.
+    pub label: L,
+    /// The children of this node.
+    pub children: Vec<Tree<L>>
+}
+
+impl<L> Tree<L> {
+    /// Construct a tree from a label and some children.
+    pub fn new(label: L, children: Vec<Tree<L>>) -> Self { Tree{label, children} }
+
+    /// Construct a tree with a single node.
+    pub fn leaf(label: L) -> Self { Tree{label, children: Vec::new()} }
+
+    /// Get the number of nodes in this tree.
+    pub fn size(&self) -> usize { 1 + self.children.iter().map(Tree::size).sum::<usize>() }
+}
+
+impl<L:Clone> Tree<L> {
+    /// Construct a copy of an arbitrary tree.
+    pub fn of<N:TreeNode<Label=L>>(node: &N) -> Self {
+        Tree{label: node.label().clone(), children: node.children().iter().map(Tree::of).collect()}
+    }
+}
+
+impl<L:Clone+PartialEq> TreeNode for Tree<L> {
+    type Label = L;
+    fn label(&self) -> &L { &self.label }
+    fn children(&self) -> &[Self] { &self.children }
+}
+
+impl<L:Clone+PartialEq> Diff for Tree<L> {
+    type Delta = TreeDelta<L>;
+
+    fn diff(&self, other: &Self) -> TreeDelta<L> {
+        diff_trees(self, other)
+    }
+}
+
+impl<L:Clone> Transform for Tree<L> {
+    type Delta = TreeDelta<L>;
+
+    fn transform(&mut self, d: &TreeDelta<L>) {
+        d.transform(self)
+    }
+}
+
+// ===================================================================
+// Tree Delta
+// ===================================================================
+
+/// An individual operation within a `TreeDelta`.  Nodes of the original
+/// tree are identified by their position in a _postorder_ traversal
+/// (i.e. where children precede their parent).  Thus, for an original
+/// tree of `n` nodes, the root is `n-1`.  Nodes which are inserted are
+/// identified by `n`, `n+1`, etc in the order they are inserted.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum TreeOp<L> {
+    /// Change the label of a node.
+    Relabel(usize,L),
+    /// Delete a node, such that its children take its place (in order)
+    /// amongst the children of its parent.
+    Delete(usize),
+    /// Insert a new node with a given label as a child of a given
+    /// parent (or as a root, when `None`).  The new node is placed at
+    /// the given position amongst the children of the parent, and
+    /// adopts the `count` children which were previously at that
+    /// position.
+    Insert{parent: Option<usize>, position: usize, count: usize, label: L},
+    /// Move the subtree rooted at a given node, such that it becomes a
+    /// child of a given parent (or a root, when `None`) at the given
+    /// position amongst its children.  The position is determined after
+    /// the subtree is removed from its original location.
+    Move{node: usize, parent: Option<usize>, position: usize}
+}
+
+/// A `TreeDelta` is a sequence of operations which transform one
+/// ordered tree into another, where operations are applied in order.
+/// During application, the tree may temporarily become a _forest_
+/// (e.g. after deleting the root) though, once every operation is
+/// applied, exactly one root must remain.  For example:
+///
+/// ```
+/// use delta_inc::diff::{Diff,Transform};
+/// use delta_inc::diff::tree::Tree;
+///
+/// // f(a,b) becomes f(g(a),c)
+/// let before = Tree::new('f',vec![Tree::leaf('a'),Tree::leaf('b')]);
+/// let after = Tree::new('f',vec![Tree::new('g',vec![Tree::leaf('a')]),Tree::leaf('c')]);
+/// let delta = before.diff(&after);
+/// assert_eq!(delta.len(),2);
+/// let mut tree = before.clone();
+/// tree.transform(&delta);
+/// assert_eq!(tree,after);
+/// ```
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct TreeDelta<L> {
+    ops: Vec<TreeOp<L>>
+}
+
+impl<L> TreeDelta<L> {
+    /// Construct an empty `TreeDelta`.
+    pub const fn new() -> Self { TreeDelta{ops: Vec::new()} }
+
+    /// Get the number of operations in this delta.
+    pub fn len(&self) -> usize { self.ops.len() }
+
+    /// Check whether this delta contains any operations or not.
+    pub fn is_empty(&self) -> bool { self.ops.is_empty() }
+
+    /// Get the operations of this delta, in order.
+    pub fn ops(&self) -> &[TreeOp<L>] { &self.ops }
+
+    /// Append an operation onto the end of this delta.
+    pub fn push(&mut self, op: TreeOp<L>) { self.ops.push(op) }
+}
+
+impl<L:Clone> TreeDelta<L> {
+    /// Apply this delta to a given tree, thus transforming it.  This
+    /// operation will `panic` if the delta is malformed with respect to
+    /// the tree (e.g. it refers to a node which does not exist, or
+    /// leaves other than one root).
+    pub fn transform(&self, tree: &mut Tree<L>) {
+        // Flatten tree in postorder
+        let mut labels = Vec::new();
+        let mut forest = Forest::new();
+        let root = flatten(core::mem::take(&mut tree.children), tree.label.clone(), &mut labels, &mut forest);
+        forest.roots.push(root);
+        // Apply operations
+        for op in &self.ops {
+            match op {
+                TreeOp::Relabel(n,l) => labels[*n] = Some(l.clone()),
+                TreeOp::Delete(n) => forest.delete(*n),
+                TreeOp::Insert{parent,position,count,label} => {
+                    forest.insert(*parent,*position,*count);
+                    labels.push(Some(label.clone()));
+                }
+                TreeOp::Move{node,parent,position} => forest.relocate(*node,*parent,*position)
+            }
+        }
+        assert!(forest.roots.len() == 1,"delta leaves {} roots",forest.roots.len());
+        *tree = rebuild(forest.roots[0], &forest, &mut labels);
+    }
+}
+
+impl<L> Default for TreeDelta<L> {
+    fn default() -> Self { Self::new() }
+}
+
+/// Flatten a node (given as its label and children) into postorder,
+/// returning its identifier.
+fn flatten<L>(children: Vec<Tree<L>>, label: L, labels: &mut Vec<Option<L>>, forest: &mut Forest) -> usize {
+    let ids : Vec<usize> = children.into_iter().map(|c| flatten(c.children,c.label,labels,forest)).collect();
+    labels.push(Some(label));
+    forest.node(ids)
+}
+
+/// Reconstruct the tree rooted at a given node of a forest.
+fn rebuild<L>(id: usize, forest: &Forest, labels: &mut [Option<L>]) -> Tree<L> {
+    let children = forest.children[id].iter().map(|&c| rebuild(c,forest,labels)).collect();
+    Tree{label: labels[id].take().unwrap(), children}
+}
+
+/// The structure of a forest of nodes (without labels), as it is
+/// transformed by a sequence of operations.
+struct Forest {
+    parent: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    roots: Vec<usize>
+}
+
+impl Forest {
+    fn new() -> Self { Forest{parent: Vec::new(), children: Vec::new(), roots: Vec::new()} }
+
+    /// Add a node with given children (which must be roots), returning
+    /// its identifier.  The new node is not itself added as a root.
+    fn node(&mut self, children: Vec<usize>) -> usize {
+        let id = self.parent.len();
+        for &c in &children { self.parent[c] = Some(id); }
+        self.parent.push(None);
+        self.children.push(children);
+        id
+    }
+
+    /// Get the children of a given node (or the roots).
+    fn siblings_mut(&mut self, parent: Option<usize>) -> &mut Vec<usize> {
+        match parent {
+            Some(p) => &mut self.children[p],
+            None => &mut self.roots
+        }
+    }
+
+    fn delete(&mut self, id: usize) {
+        let p = self.parent[id];
+        let children = core::mem::take(&mut self.children[id]);
+        for &c in &children { self.parent[c] = p; }
+        let siblings = self.siblings_mut(p);
+        let i = siblings.iter().position(|&s| s == id).expect("node does not exist");
+        siblings.splice(i..=i,children);
+        self.parent[id] = None;
+    }
+
+    fn relocate(&mut self, id: usize, parent: Option<usize>, position: usize) {
+        let old = self.parent[id];
+        let siblings = self.siblings_mut(old);
+        let i = siblings.iter().position(|&s| s == id).expect("node does not exist");
+        siblings.remove(i);
+        self.siblings_mut(parent).insert(position,id);
+        self.parent[id] = parent;
+    }
+
+    fn insert(&mut self, parent: Option<usize>, position: usize, count: usize) -> usize {
+        let adopted : Vec<usize> = self.siblings_mut(parent).drain(position..position+count).collect();
+        let id = self.node(adopted);
+        self.parent[id] = parent;
+        self.siblings_mut(parent).insert(position,id);
+        id
+    }
+}
+
+// ===================================================================
+// Zhang-Shasha
+// ===================================================================
+
+/// A tree flattened in postorder, where `lmd[i]` is the leftmost
+/// (leaf) descendant of node `i`.
+struct Postorder<'a,L> {
+    labels: Vec<&'a L>,
+    lmd: Vec<usize>,
+    parent: Vec<Option<usize>>,
+    keyroots: Vec<usize>
+}
+
+impl<'a,L> Postorder<'a,L> {
+    fn new<N:TreeNode<Label=L>>(root: &'a N) -> Self {
+        let mut p = Postorder{labels: Vec::new(), lmd: Vec::new(), parent: Vec::new(), keyroots: Vec::new()};
+        p.visit(root);
+        // Keyroots are those nodes with no ancestor sharing their
+        // leftmost descendant (i.e. the root and all left siblings).
+        let mut seen = vec![false;p.len()];
+        for i in (0..p.len()).rev() {
+            if !seen[p.lmd[i]] { seen[p.lmd[i]] = true; p.keyroots.push(i); }
+        }
+        p.keyroots.reverse();
+        p
+    }
+
+    fn visit<N:TreeNode<Label=L>>(&mut self, node: &'a N) -> usize {
+        let ids : Vec<usize> = node.children().iter().map(|c| self.visit(c)).collect();
+        let id = self.labels.len();
+        self.labels.push(node.label());
+        self.lmd.push(ids.first().map_or(id,|&c| self.lmd[c]));
+        self.parent.push(None);
+        for c in ids { self.parent[c] = Some(id); }
+        id
+    }
+
+    fn len(&self) -> usize { self.labels.len() }
+}
+
+/// Compute the _tree edit distance_ between two ordered trees, which
+/// is the minimum number of node deletions, insertions and relabellings
+/// required to transform one into the other.  This uses the algorithm
+/// of Zhang and Shasha, which takes `O(n^2 m^2)` time in the worst case
+/// (though much less for balanced trees) and `O(nm)` space.
+pub fn tree_edit_distance<N:TreeNode>(lhs: &N, rhs: &N) -> usize {
+    let (t1,t2) = (Postorder::new(lhs),Postorder::new(rhs));
+    let td = tree_distances(&t1,&t2);
+    td[t1.len()-1][t2.len()-1]
+}
+
+/// Compute a delta between two ordered trees, consisting of a minimal
+/// number of operations (see `tree_edit_distance()`).  Relabellings
+/// come first, followed by deletions and then insertions.
+pub fn diff_trees<N:TreeNode>(lhs: &N, rhs: &N) -> TreeDelta<N::Label> {
+    let (t1,t2) = (Postorder::new(lhs),Postorder::new(rhs));
+    let td = tree_distances(&t1,&t2);
+    let mapping = tree_mapping(&t1,&t2,&td);
+    delta_of(&t1,&t2,&mapping)
+}
+
+/// Compute the distance between every pair of subtrees rooted at
+/// keyroots (and, by extension, at nodes on their leftmost paths).
+fn tree_distances<L:PartialEq>(t1: &Postorder<L>, t2: &Postorder<L>) -> Vec<Vec<usize>> {
+    let mut td = vec![vec![0;t2.len()];t1.len()];
+    for &i in &t1.keyroots {
+        for &j in &t2.keyroots {
+            forest_distances(t1,t2,i,j,&mut td);
+        }
+    }
+    td
+}
+
+/// Compute the distances between every pair of prefix forests of the
+/// subtrees rooted at `i` and `j`, recording the distances between
+/// subtrees as they are found.  The result is indexed from `lmd(i)-1`
+/// and `lmd(j)-1` (i.e. where `[0][0]` is two empty forests).
+fn forest_distances<L:PartialEq>(t1: &Postorder<L>, t2: &Postorder<L>, i: usize, j: usize, td: &mut [Vec<usize>]) -> Vec<Vec<usize>> {
+    let (li,lj) = (t1.lmd[i],t2.lmd[j]);
+    let mut fd = vec![vec![0;j-lj+2];i-li+2];
+    for (x,row) in fd.iter_mut().enumerate() { row[0] = x; }
+    for (y,d) in fd[0].iter_mut().enumerate() { *d = y; }
+    for x in 1..fd.len() {
+        for y in 1..fd[0].len() {
+            let (i1,j1) = (li+x-1,lj+y-1);
+            let del = fd[x-1][y] + 1;
+            let ins = fd[x][y-1] + 1;
+            if t1.lmd[i1] == li && t2.lmd[j1] == lj {
+                let ren = fd[x-1][y-1] + usize::from(t1.labels[i1] != t2.labels[j1]);
+                fd[x][y] = del.min(ins).min(ren);
+                td[i1][j1] = fd[x][y];
+            } else {
+                let (x1,y1) = (t1.lmd[i1]-li,t2.lmd[j1]-lj);
+                fd[x][y] = del.min(ins).min(fd[x1][y1] + td[i1][j1]);
+            }
+        }
+    }
+    fd
+}
+
+/// Recover an optimal mapping between the nodes of two trees, from the
+/// distances between their subtrees.
+fn tree_mapping<L:PartialEq>(t1: &Postorder<L>, t2: &Postorder<L>, td: &[Vec<usize>]) -> Vec<Option<usize>> {
+    let mut mapping = vec![None;t1.len()];
+    let mut td = td.to_vec();
+    let mut stack = vec![(t1.len()-1,t2.len()-1)];
+    while let Some((i,j)) = stack.pop() {
+        let (li,lj) = (t1.lmd[i],t2.lmd[j]);
+        let fd = forest_distances(t1,t2,i,j,&mut td);
+        let (mut x, mut y) = (i-li+1,j-lj+1);
+        while x > 0 || y > 0 {
+            if x == 0 { y -= 1; continue; }
+            if y == 0 { x -= 1; continue; }
+            let (i1,j1) = (li+x-1,lj+y-1);
+            if fd[x][y] == fd[x-1][y] + 1 {
+                x -= 1;
+            } else if fd[x][y] == fd[x][y-1] + 1 {
+                y -= 1;
+            } else if t1.lmd[i1] == li && t2.lmd[j1] == lj {
+                mapping[i1] = Some(j1);
+                x -= 1;
+                y -= 1;
+            } else {
+                stack.push((i1,j1));
+                x = t1.lmd[i1] - li;
+                y = t2.lmd[j1] - lj;
+            }
+        }
+    }
+    mapping
+}
+
+/// Construct a delta from a mapping between the nodes of two trees.
+fn delta_of<L:Clone+PartialEq>(t1: &Postorder<L>, t2: &Postorder<L>, mapping: &[Option<usize>]) -> TreeDelta<L> {
+    let mut delta = TreeDelta::new();
+    let mut forest = Forest::new();
+    // Node of target corresponding to each node in forest
+    let mut target = Vec::with_capacity(t1.len());
+    // Node of forest corresponding to each node of target
+    let mut ids = vec![None;t2.len()];
+    let mut children = vec![Vec::new();t1.len()];
+    for (c,p) in t1.parent.iter().enumerate() {
+        if let Some(p) = p { children[*p].push(c); }
+    }
+    for i in 0..t1.len() {
+        forest.node(core::mem::take(&mut children[i]));
+        target.push(mapping[i].unwrap_or(usize::MAX));
+        match mapping[i] {
+            Some(j) if t1.labels[i] != t2.labels[j] => delta.push(TreeOp::Relabel(i,t2.labels[j].clone())),
+            _ => {}
+        }
+        if let Some(j) = mapping[i] { ids[j] = Some(i); }
+    }
+    forest.roots.push(t1.len()-1);
+    for (i,m) in mapping.iter().enumerate() {
+        if m.is_none() {
+            forest.delete(i);
+            delta.push(TreeOp::Delete(i));
+        }
+    }
+    // Insert unmapped nodes of target, parents first
+    for j in (0..t2.len()).rev() {
+        if ids[j].is_some() { continue; }
+        let parent = t2.parent[j].map(|p| ids[p].unwrap());
+        let siblings = match parent { Some(p) => &forest.children[p], None => &forest.roots };
+        let position = siblings.iter().filter(|&&s| target[s] < t2.lmd[j]).count();
+        let count = siblings.iter().filter(|&&s| t2.lmd[j] <= target[s] && target[s] < j).count();
+        ids[j] = Some(forest.insert(parent,position,count));
+        target.push(j);
+        delta.push(TreeOp::Insert{parent, position, count, label: t2.labels[j].clone()});
+    }
+    delta
+}
+
+// ===================================================================
+// GumTree
+// ===================================================================
+
+/// Compute a delta between two ordered trees which can include moves of
+/// whole subtrees, by matching nodes in the style of _GumTree_.  Whilst
+/// the result is not guaranteed to be minimal (unlike `diff_trees()`),
+/// it is typically much smaller and more meaningful when subtrees are
+/// rearranged (e.g. when refactoring code).  For example:
+///
+/// ```
+/// use delta_inc::diff::Transform;
+/// use delta_inc::diff::tree::{Tree,TreeOp,diff_trees_with_moves};
+///
+/// // f(g(a,b),c) becomes f(c,g(a,b))
+/// let g = Tree::new('g',vec![Tree::leaf('a'),Tree::leaf('b')]);
+/// let before = Tree::new('f',vec![g.clone(),Tree::leaf('c')]);
+/// let after = Tree::new('f',vec![Tree::leaf('c'),g]);
+/// let delta = diff_trees_with_moves(&before,&after);
+/// assert_eq!(delta.ops(),[TreeOp::Move{node: 2, parent: Some(4), position: 1}]);
+/// let mut tree = before.clone();
+/// tree.transform(&delta);
+/// assert_eq!(tree,after);
+/// ```
+///
+/// Matching proceeds in two phases.  Firstly, identical subtrees are
+/// matched _top-down_, from the tallest to those of height two.  Where
+/// a subtree has several identical candidates, they are paired in
+/// order.  Secondly, unmatched nodes are matched _bottom-up_ with a
+/// node of the same label whose descendants share at least half of
+/// their matches (by the Dice coefficient).  When two nodes are
+/// matched this way, their unmatched children are then matched by
+/// label where possible.  The delta relabels matched nodes, deletes
+/// unmatched nodes of the original tree, and then places every node of
+/// the updated tree by inserting or moving it as necessary.
+///
+/// # References
+///
+/// * _Fine-grained and Accurate Source Code Differencing_,
+///   J.R. Falleri, F. Morandat, X. Blanc, M. Martinez and M. Monperrus.
+///   In Proceedings of ASE, 2014.
+pub fn diff_trees_with_moves<N:TreeNode>(lhs: &N, rhs: &N) -> TreeDelta<N::Label>
+where N::Label : Hash {
+    let (t1,t2) = (Postorder::new(lhs),Postorder::new(rhs));
+    let (mut m1, mut m2) = (vec![None;t1.len()],vec![None;t2.len()]);
+    match_top_down(&t1,&t2,&mut m1,&mut m2);
+    match_bottom_up(&t1,&t2,&mut m1,&mut m2);
+    delta_with_moves(&t1,&t2,&m1)
+}
+
+/// Minimum height of subtrees matched top-down.
+const MIN_HEIGHT : usize = 2;
+
+/// Minimum Dice coefficient for nodes matched bottom-up.
+const MIN_DICE : f64 = 0.5;
+
+impl<L> Postorder<'_,L> {
+    /// Get the number of nodes in the subtree rooted at a given node.
+    fn size(&self, i: usize) -> usize { i - self.lmd[i] + 1 }
+
+    /// Check whether one node is a descendant of another (or the same).
+    fn within(&self, i: usize, ancestor: usize) -> bool { self.lmd[ancestor] <= i && i <= ancestor }
+
+    /// Get the children of every node, in order.
+    fn child_lists(&self) -> Vec<Vec<usize>> {
+        let mut children = vec![Vec::new();self.len()];
+        for (c,p) in self.parent.iter().enumerate() {
+            if let Some(p) = p { children[*p].push(c); }
+        }
+        children
+    }
+
+    /// Compute the height of every node, where leaves have height one.
+    fn heights(&self) -> Vec<usize> {
+        let mut heights = vec![1;self.len()];
+        for i in 0..self.len() {
+            if let Some(p) = self.parent[i] { heights[p] = heights[p].max(heights[i] + 1); }
+        }
+        heights
+    }
+}
+
+impl<L:Hash> Postorder<'_,L> {
+    /// Compute a hash of every subtree, such that identical subtrees
+    /// have identical hashes.
+    fn hashes(&self) -> Vec<u64> {
+        let children = self.child_lists();
+        let mut hashes = vec![0;self.len()];
+        for i in 0..self.len() {
+            let hs : Vec<u64> = children[i].iter().map(|&c| hashes[c]).collect();
+            hashes[i] = hash_of(&(self.labels[i],hs));
+        }
+        hashes
+    }
+}
+
+/// Check whether the subtrees rooted at two nodes are identical.
+fn isomorphic<L:PartialEq>(t1: &Postorder<L>, i: usize, t2: &Postorder<L>, j: usize) -> bool {
+    let (l1,l2) = (t1.lmd[i],t2.lmd[j]);
+    t1.size(i) == t2.size(j) && (0..t1.size(i)).all(|k| {
+        t1.labels[l1+k] == t2.labels[l2+k] && t1.lmd[l1+k] - l1 == t2.lmd[l2+k] - l2
+    })
+}
+
+/// Match identical subtrees, from the tallest downwards.
+fn match_top_down<L:Hash+PartialEq>(t1: &Postorder<L>, t2: &Postorder<L>, m1: &mut [Option<usize>], m2: &mut [Option<usize>]) {
+    let (h1,h2) = (t1.heights(),t2.heights());
+    let (x1,x2) = (t1.hashes(),t2.hashes());
+    let max = h1.iter().chain(&h2).copied().max().unwrap_or(0);
+    for h in (MIN_HEIGHT..=max).rev() {
+        // Group unmatched subtrees of this height by hash
+        let mut groups : BTreeMap<u64,(Vec<usize>,Vec<usize>)> = BTreeMap::new();
+        for i in (0..t1.len()).filter(|&i| h1[i] == h && m1[i].is_none()) { groups.entry(x1[i]).or_default().0.push(i); }
+        for j in (0..t2.len()).filter(|&j| h2[j] == h && m2[j].is_none()) { groups.entry(x2[j]).or_default().1.push(j); }
+        for (is,js) in groups.values() {
+            let mut js = js.clone();
+            for &i in is {
+                if let Some(k) = js.iter().position(|&j| isomorphic(t1,i,t2,j)) {
+                    let j = js.remove(k);
+                    for k in 0..t1.size(i) {
+                        m1[t1.lmd[i]+k] = Some(t2.lmd[j]+k);
+                        m2[t2.lmd[j]+k] = Some(t1.lmd[i]+k);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Match nodes whose descendants are largely matched, along with their
+/// children where possible.
+fn match_bottom_up<L:PartialEq>(t1: &Postorder<L>, t2: &Postorder<L>, m1: &mut [Option<usize>], m2: &mut [Option<usize>]) {
+    let (c1,c2) = (t1.child_lists(),t2.child_lists());
+    let (r1,r2) = (t1.len()-1,t2.len()-1);
+    for i in 0..t1.len() {
+        if m1[i].is_some() || c1[i].is_empty() { continue; }
+        // Candidates are unmatched ancestors of the matches of descendants
+        let mut best : Option<(usize,f64)> = None;
+        for d in t1.lmd[i]..i {
+            let Some(mut j) = m1[d] else { continue; };
+            while let Some(p) = t2.parent[j] {
+                j = p;
+                if m2[j].is_some() || t2.labels[j] != t1.labels[i] { continue; }
+                let common = (t1.lmd[i]..i).filter(|&x| m1[x].is_some_and(|y| y != j && t2.within(y,j))).count();
+                let dice = 2.0 * common as f64 / (t1.size(i) + t2.size(j) - 2) as f64;
+                if best.is_none_or(|(_,b)| dice > b) { best = Some((j,dice)); }
+            }
+        }
+        match best {
+            Some((j,dice)) if dice >= MIN_DICE => recover(t1,t2,&c1,&c2,i,j,m1,m2),
+            _ if i == r1 && m2[r2].is_none() => recover(t1,t2,&c1,&c2,r1,r2,m1,m2),
+            _ => {}
+        }
+    }
+}
+
+/// Match two nodes, along with those of their unmatched children (and,
+/// recursively, their children) which have the same labels.
+#[allow(clippy::too_many_arguments)]
+fn recover<L:PartialEq>(t1: &Postorder<L>, t2: &Postorder<L>, c1: &[Vec<usize>], c2: &[Vec<usize>], i: usize, j: usize, m1: &mut [Option<usize>], m2: &mut [Option<usize>]) {
+    m1[i] = Some(j);
+    m2[j] = Some(i);
+    let is : Vec<usize> = c1[i].iter().copied().filter(|&x| m1[x].is_none()).collect();
+    let js : Vec<usize> = c2[j].iter().copied().filter(|&y| m2[y].is_none()).collect();
+    let ls : Vec<&L> = is.iter().map(|&x| t1.labels[x]).collect();
+    let rs : Vec<&L> = js.iter().map(|&y| t2.labels[y]).collect();
+    for (a,b) in longest_common_subsequence(&ls,&rs).into_iter().enumerate() {
+        if let Some(b) = b { recover(t1,t2,c1,c2,is[a],js[b],m1,m2); }
+    }
+}
+
+/// Construct a delta from an arbitrary (partial) matching between the
+/// nodes of two trees, using moves where necessary.
+fn delta_with_moves<L:Clone+PartialEq>(t1: &Postorder<L>, t2: &Postorder<L>, mapping: &[Option<usize>]) -> TreeDelta<L> {
+    let mut delta = TreeDelta::new();
+    let mut forest = Forest::new();
+    let mut children = t1.child_lists();
+    for (i,m) in mapping.iter().enumerate() {
+        forest.node(core::mem::take(&mut children[i]));
+        if let Some(j) = m {
+            if t1.labels[i] != t2.labels[*j] { delta.push(TreeOp::Relabel(i,t2.labels[*j].clone())); }
+        }
+    }
+    forest.roots.push(t1.len()-1);
+    for (i,m) in mapping.iter().enumerate() {
+        if m.is_none() {
+            forest.delete(i);
+            delta.push(TreeOp::Delete(i));
+        }
+    }
+    // Node of forest corresponding to each node of target
+    let mut ids = vec![None;t2.len()];
+    for (i,m) in mapping.iter().enumerate() {
+        if let Some(j) = m { ids[*j] = Some(i); }
+    }
+    // Place the root of the target, leaving any other roots to be moved
+    // away later.
+    let root = t2.len()-1;
+    match ids[root] {
+        None => {
+            ids[root] = Some(forest.insert(None,0,0));
+            delta.push(TreeOp::Insert{parent: None, position: 0, count: 0, label: t2.labels[root].clone()});
+        }
+        Some(id) if forest.parent[id].is_some() => {
+            forest.relocate(id,None,0);
+            delta.push(TreeOp::Move{node: id, parent: None, position: 0});
+        }
+        Some(_) => {}
+    }
+    // Place the children of each node of the target in preorder.  Those
+    // children which are already in the right order (i.e. form a longest
+    // common subsequence with the current children) stay put, whilst
+    // others are inserted or moved after their preceding sibling.
+    let c2 = t2.child_lists();
+    let mut stack = vec![root];
+    while let Some(j) = stack.pop() {
+        let p = ids[j].unwrap();
+        let targets : Vec<Option<usize>> = c2[j].iter().map(|&c| ids[c]).collect();
+        let current : Vec<Option<usize>> = forest.children[p].iter().map(|&c| Some(c)).collect();
+        let stays = longest_common_subsequence(&targets,&current);
+        for (k,&c) in c2[j].iter().enumerate() {
+            let position = match k {
+                0 => 0,
+                _ => forest.children[p].iter().position(|&s| Some(s) == ids[c2[j][k-1]]).unwrap() + 1
+            };
+            match ids[c] {
+                None => {
+                    ids[c] = Some(forest.insert(Some(p),position,0));
+                    delta.push(TreeOp::Insert{parent: Some(p), position, count: 0, label: t2.labels[c].clone()});
+                }
+                Some(id) if stays[k].is_none() && forest.children[p].get(position) != Some(&id) => {
+                    // Account for removing the node from before its position
+                    let position = match forest.children[p].iter().position(|&s| s == id) {
+                        Some(i) if i < position => position - 1,
+                        _ => position
+                    };
+                    forest.relocate(id,Some(p),position);
+                    delta.push(TreeOp::Move{node: id, parent: Some(p), position});
+                }
+                Some(_) => {}
+            }
+        }
+        stack.extend(c2[j].iter().rev());
+    }
+    delta
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod tree_tests {
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,Transform};
+    use super::{Tree,TreeOp,diff_trees_with_moves,tree_edit_distance};
+
+    /// Parse a tree written as (e.g.) `"f(d(a,c(b)),e)"`.
+    fn parse(text: &str) -> Tree<char> {
+        fn node(chars: &mut core::iter::Peekable<core::str::Chars<'_>>) -> Tree<char> {
+            let label = chars.next().unwrap();
+            let mut children = Vec::new();
+            if chars.peek() == Some(&'(') {
+                chars.next();
+                loop {
+                    children.push(node(chars));
+                    if chars.next() == Some(')') { break; }
+                }
+            }
+            Tree::new(label,children)
+        }
+        node(&mut text.chars().peekable())
+    }
+
+    fn check(t1: &str, t2: &str, distance: usize) {
+        let (t1,t2) = (parse(t1),parse(t2));
+        assert_eq!(tree_edit_distance(&t1,&t2),distance);
+        let delta = t1.diff(&t2);
+        assert_eq!(delta.len(),distance);
+        let mut t = t1.clone();
+        t.transform(&delta);
+        assert_eq!(t,t2);
+    }
+
+    #[test]
+    fn tree_test_01() {
+        check("a","a",0);
+        check("a","b",1);
+        check("f(a,b)","f(a,b)",0);
+        check("f(a,b)","f(b)",1);
+        check("f(a,b)","g(f(a,b))",1);
+        check("f(a,b)","a",2);
+        // Classic example from Zhang and Shasha
+        check("f(d(a,c(b)),e)","f(c(d(a,b)),e)",2);
+        check("a(b(c,d),e(f,g))","a(c,d,f,g)",2);
+    }
+
+    fn trees() -> Vec<Tree<char>> {
+        let labels = ['a','b','c'];
+        (0..12).map(|i| {
+            // Build a tree by attaching each node to an earlier one
+            let mut nodes : Vec<Tree<char>> = (0..i).map(|j| Tree::leaf(labels[(j*i+3)%3])).collect();
+            let mut root = Tree::leaf('r');
+            for j in (0..nodes.len()).rev() {
+                let node = nodes.pop().unwrap();
+                let p = (j*7+i) % (j+1);
+                if p == j { root.children.insert(0,node); } else { nodes[p].children.push(node); }
+            }
+            root
+        }).collect()
+    }
+
+    #[test]
+    fn tree_test_02() {
+        for a in &trees() {
+            for b in &trees() {
+                let delta = a.diff(b);
+                assert!(delta.len() <= a.size() + b.size());
+                let mut t = a.clone();
+                t.transform(&delta);
+                assert_eq!(t,*b);
+            }
+        }
+    }
+
+    #[test]
+    fn tree_test_03() {
+        let delta = parse("f(a,b)").diff(&parse("f(g(a,b))"));
+        assert_eq!(delta.ops(),[TreeOp::Insert{parent: Some(2), position: 0, count: 2, label: 'g'}]);
+        let delta = parse("f(a,b)").diff(&parse("g(b)"));
+        assert_eq!(delta.ops(),[TreeOp::Relabel(2,'g'),TreeOp::Delete(0)]);
+    }
+
+    #[test]
+    fn tree_test_04() {
+        for (t1,t2) in [("a","a"),("a","b"),("f(a,b)","f(b,a)"),("f(g(a,b),c)","f(c,g(a,b))"),
+                        ("f(d(a,c(b)),e)","f(c(d(a,b)),e)"),("f(a(x,y),b(z))","f(b(z,a(x,y)))"),
+                        ("f(a,b)","g(f(a,b))"),("g(f(a,b))","f(a,b)"),("f(a(b,c),d(e,f))","d(e,f,a(b,c))")] {
+            let (t1,t2) = (parse(t1),parse(t2));
+            let delta = diff_trees_with_moves(&t1,&t2);
+            let mut t = t1.clone();
+            t.transform(&delta);
+            assert_eq!(t,t2);
+        }
+        // Moving a subtree takes a single operation
+        let delta = diff_trees_with_moves(&parse("f(a(x,y),b(z))"),&parse("f(b(z,a(x,y)))"));
+        assert_eq!(delta.ops(),[TreeOp::Move{node: 2, parent: Some(4), position: 1}]);
+    }
+
+    #[test]
+    fn tree_test_05() {
+        for a in &trees() {
+            for b in &trees() {
+                let delta = diff_trees_with_moves(a,b);
+                let mut t = a.clone();
+                t.transform(&delta);
+                assert_eq!(t,*b);
+            }
+        }
+    }
+}
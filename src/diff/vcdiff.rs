@@ -0,0 +1,436 @@
+use alloc::vec::Vec;
+use crate::util::Region;
+use super::VecDelta;
+
+/// Magic bytes identifying a VCDIFF file (`"VCD"` with the high bits
+/// set, followed by the version).
+const MAGIC : [u8;4] = [0xD6,0xC3,0xC4,0x00];
+
+// Header and window indicators
+const VCD_DECOMPRESS : u8 = 0x01;
+const VCD_CODETABLE : u8 = 0x02;
+const VCD_APPHEADER : u8 = 0x04;
+const VCD_SOURCE : u8 = 0x01;
+const VCD_TARGET : u8 = 0x02;
+const VCD_ADLER32 : u8 = 0x04;
+
+// Instruction types within the code table
+const NOOP : u8 = 0;
+const ADD : u8 = 1;
+const RUN : u8 = 2;
+const COPY : u8 = 3;
+
+/// Sizes of the near and same address caches in the default
+/// configuration.
+const NEAR : usize = 4;
+const SAME : usize = 3;
+
+/// Errors arising from decoding a VCDIFF file.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum VcdiffError {
+    /// The file does not begin with the VCDIFF magic bytes.
+    InvalidHeader,
+    /// The file ended unexpectedly.
+    Truncated,
+    /// The file uses a feature which is not supported (e.g. secondary
+    /// compression or an application-defined code table).
+    Unsupported(&'static str),
+    /// The file is malformed at the given byte offset (e.g. a section
+    /// length is inconsistent, or an address is out of range).
+    Malformed(usize)
+}
+
+impl core::fmt::Display for VcdiffError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            VcdiffError::InvalidHeader => write!(f,"invalid VCDIFF header"),
+            VcdiffError::Truncated => write!(f,"truncated VCDIFF file"),
+            VcdiffError::Unsupported(s) => write!(f,"unsupported VCDIFF feature ({s})"),
+            VcdiffError::Malformed(i) => write!(f,"malformed VCDIFF file at byte {i}")
+        }
+    }
+}
+
+impl core::error::Error for VcdiffError {}
+
+/// Encode a delta over bytes as a VCDIFF file (RFC 3284), given the
+/// length of the source it applies to.  The result consists of a
+/// single window using the default code table, where unchanged bytes
+/// become `COPY` instructions from the source and replacement data
+/// becomes `ADD` (or `RUN`) instructions.  This will `panic` if the
+/// delta extends beyond the source.
+pub fn encode_vcdiff(delta: &VecDelta<u8>, source_len: usize) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut insts = Vec::new();
+    let mut addrs = Vec::new();
+    let mut cursor = 0;
+    let mut target_len = 0;
+    for (r,bytes) in delta.iter_source().chain([(Region::new(source_len,0),&[][..])]) {
+        assert!(r.offset >= cursor && r.offset + r.length <= source_len,"delta out of bounds");
+        if r.offset > cursor {
+            // Mode 0 (VCD_SELF) gives the address directly.
+            push_inst(&mut insts, COPY_BASE, r.offset - cursor, 4..=18);
+            write_varint(&mut addrs, cursor);
+        }
+        if bytes.len() > 2 && bytes.iter().all(|&b| b == bytes[0]) {
+            insts.push(0);
+            write_varint(&mut insts, bytes.len());
+            data.push(bytes[0]);
+        } else if !bytes.is_empty() {
+            push_inst(&mut insts, ADD_BASE, bytes.len(), 1..=17);
+            data.extend_from_slice(bytes);
+        }
+        target_len += r.offset - cursor + bytes.len();
+        cursor = r.offset + r.length;
+    }
+    // Delta encoding
+    let mut encoding = Vec::new();
+    write_varint(&mut encoding, target_len);
+    encoding.push(0);
+    write_varint(&mut encoding, data.len());
+    write_varint(&mut encoding, insts.len());
+    write_varint(&mut encoding, addrs.len());
+    encoding.extend(data.into_iter().chain(insts).chain(addrs));
+    // File
+    let mut out = MAGIC.to_vec();
+    out.push(0);
+    if source_len > 0 {
+        out.push(VCD_SOURCE);
+        write_varint(&mut out, source_len);
+        write_varint(&mut out, 0);
+    } else {
+        out.push(0);
+    }
+    write_varint(&mut out, encoding.len());
+    out.extend(encoding);
+    out
+}
+
+/// Index of the first `ADD` instruction (of size `0`) in the default
+/// code table.
+const ADD_BASE : u8 = 1;
+/// Index of the first `COPY` instruction (of size `0` and mode `0`)
+/// in the default code table.
+const COPY_BASE : u8 = 19;
+
+/// Append an `ADD` or `COPY` instruction of a given size, using the
+/// code for that size if it is within a given range (or an explicit
+/// size otherwise).
+fn push_inst(insts: &mut Vec<u8>, base: u8, size: usize, sizes: core::ops::RangeInclusive<usize>) {
+    if sizes.contains(&size) {
+        insts.push(base + (size - sizes.start() + 1) as u8);
+    } else {
+        insts.push(base);
+        write_varint(insts, size);
+    }
+}
+
+/// Decode a VCDIFF file (RFC 3284) against the source it was computed
+/// from, producing the equivalent delta.  Files using the default code
+/// table, any number of windows and any addressing modes are supported
+/// (though not secondary compression).  The delta is obtained by
+/// treating `COPY` instructions which read the source in order as
+/// unchanged bytes, and everything else as replacement data.
+pub fn decode_vcdiff(source: &[u8], file: &[u8]) -> Result<VecDelta<u8>,VcdiffError> {
+    let table = default_code_table();
+    let mut rd = Reader{bytes: file, pos: 0};
+    if rd.take(4)? != MAGIC { return Err(VcdiffError::InvalidHeader); }
+    let indicator = rd.byte()?;
+    if indicator & VCD_DECOMPRESS != 0 { return Err(VcdiffError::Unsupported("secondary compression")); }
+    if indicator & VCD_CODETABLE != 0 { return Err(VcdiffError::Unsupported("application-defined code table")); }
+    if indicator & VCD_APPHEADER != 0 {
+        let n = rd.varint()?;
+        rd.take(n)?;
+    }
+    let mut builder = Builder::new(source);
+    while rd.pos < file.len() {
+        decode_window(&mut rd, &table, &mut builder)?;
+    }
+    Ok(builder.finish())
+}
+
+/// Decode a single window, appending its target bytes to the builder.
+fn decode_window(rd: &mut Reader<'_>, table: &[[(u8,u8,u8);2]], b: &mut Builder<'_>) -> Result<(),VcdiffError> {
+    let indicator = rd.byte()?;
+    // Determine the segment (if any) copied from, as a position within
+    // the source or the target, along with its length.
+    let (segment,seg_len,seg_pos) = if indicator & (VCD_SOURCE|VCD_TARGET) != 0 {
+        let pos = rd.pos;
+        let len = rd.varint()?;
+        let offset = rd.varint()?;
+        let limit = if indicator & VCD_SOURCE != 0 { b.source.len() } else { b.target.len() };
+        if offset.checked_add(len).is_none_or(|end| end > limit) { return Err(VcdiffError::Malformed(pos)); }
+        (indicator & VCD_SOURCE,len,offset)
+    } else {
+        (0,0,0)
+    };
+    let length = rd.varint()?;
+    let encoding = rd.pos;
+    let target_len = rd.varint()?;
+    if rd.byte()? != 0 { return Err(VcdiffError::Unsupported("secondary compression")); }
+    let data_len = rd.varint()?;
+    let inst_len = rd.varint()?;
+    let addr_len = rd.varint()?;
+    if indicator & VCD_ADLER32 != 0 { rd.take(4)?; }
+    let mut data = Reader{bytes: rd.take(data_len)?, pos: 0};
+    let mut insts = Reader{bytes: rd.take(inst_len)?, pos: 0};
+    let mut addrs = Reader{bytes: rd.take(addr_len)?, pos: 0};
+    if rd.pos - encoding != length { return Err(VcdiffError::Malformed(encoding)); }
+    let start = b.target.len();
+    let mut cache = AddressCache::new();
+    while insts.pos < inst_len {
+        let code = insts.byte()? as usize;
+        for &(inst,size,mode) in &table[code] {
+            if inst == NOOP { continue; }
+            let size = if size == 0 { insts.varint()? } else { size as usize };
+            let here = b.target.len() - start;
+            if here + size > target_len { return Err(VcdiffError::Malformed(insts.pos)); }
+            match inst {
+                ADD => {
+                    let bytes = data.take(size)?;
+                    b.literal(bytes);
+                }
+                RUN => {
+                    let byte = data.byte()?;
+                    for _ in 0..size { b.literal(&[byte]); }
+                }
+                _ => {
+                    let addr = cache.decode(&mut addrs, here + seg_len, mode)?;
+                    if addr >= here + seg_len { return Err(VcdiffError::Malformed(addrs.pos)); }
+                    if addr < seg_len && addr + size <= seg_len && segment == VCD_SOURCE {
+                        b.copy_source(seg_pos + addr, size);
+                    } else {
+                        // Copy byte by byte, since the copied region can
+                        // overlap the bytes being written.
+                        for i in addr .. addr + size {
+                            let byte = if i < seg_len {
+                                if segment == VCD_SOURCE { b.source[seg_pos + i] } else { b.target[seg_pos + i] }
+                            } else {
+                                b.target[start + i - seg_len]
+                            };
+                            b.literal(&[byte]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if b.target.len() - start != target_len { return Err(VcdiffError::Malformed(rd.pos)); }
+    Ok(())
+}
+
+/// Incrementally constructs both the target and the delta producing
+/// it, where `source[..cursor]` has been accounted for and `pending`
+/// holds replacement data not yet assigned to a rewrite.
+pub(super) struct Builder<'a> {
+    source: &'a [u8],
+    cursor: usize,
+    pending: Vec<u8>,
+    target: Vec<u8>,
+    hunks: Vec<(Region,Vec<u8>)>
+}
+
+impl<'a> Builder<'a> {
+    pub(super) fn new(source: &'a [u8]) -> Self {
+        Builder{source, cursor: 0, pending: Vec::new(), target: Vec::new(), hunks: Vec::new()}
+    }
+
+    /// Get the target bytes constructed so far.
+    pub(super) fn target(&self) -> &[u8] { &self.target }
+
+    /// Append bytes which do not correspond to the source.
+    pub(super) fn literal(&mut self, bytes: &[u8]) {
+        self.target.extend_from_slice(bytes);
+        self.pending.extend_from_slice(bytes);
+    }
+
+    /// Append bytes copied from a given position of the source.  These
+    /// are unchanged if they follow the bytes already accounted for
+    /// and, otherwise, are treated as literal bytes.
+    pub(super) fn copy_source(&mut self, pos: usize, size: usize) {
+        let source = self.source;
+        if pos < self.cursor || size == 0 {
+            self.literal(&source[pos..pos+size]);
+        } else {
+            self.flush(pos);
+            self.target.extend_from_slice(&source[pos..pos+size]);
+            self.cursor = pos + size;
+        }
+    }
+
+    /// Turn any pending data into a rewrite which replaces the source
+    /// up to a given position.
+    fn flush(&mut self, pos: usize) {
+        if pos > self.cursor || !self.pending.is_empty() {
+            let region = Region::new(self.cursor,pos - self.cursor);
+            self.hunks.push((region,core::mem::take(&mut self.pending)));
+        }
+    }
+
+    /// Construct the final delta, where any remaining source bytes are
+    /// replaced by the pending data.
+    pub(super) fn finish(mut self) -> VecDelta<u8> {
+        self.flush(self.source.len());
+        VecDelta::from_source(self.hunks.iter().map(|(r,d)| (*r,d.as_slice())))
+    }
+}
+
+/// The _near_ and _same_ address caches used to decode the addresses
+/// of `COPY` instructions (RFC 3284, Section 5.1).
+struct AddressCache {
+    near: [usize;NEAR],
+    next: usize,
+    same: [usize;SAME * 256]
+}
+
+impl AddressCache {
+    fn new() -> Self { AddressCache{near: [0;NEAR], next: 0, same: [0;SAME * 256]} }
+
+    /// Decode an address using a given mode, where `here` is the
+    /// current position (including the segment being copied from).
+    fn decode(&mut self, addrs: &mut Reader<'_>, here: usize, mode: u8) -> Result<usize,VcdiffError> {
+        let pos = addrs.pos;
+        let mode = mode as usize;
+        let addr = match mode {
+            0 => addrs.varint()?,
+            1 => here.checked_sub(addrs.varint()?).ok_or(VcdiffError::Malformed(pos))?,
+            m if m < 2 + NEAR => self.near[m - 2].checked_add(addrs.varint()?).ok_or(VcdiffError::Malformed(pos))?,
+            m => self.same[(m - 2 - NEAR) * 256 + addrs.byte()? as usize]
+        };
+        self.near[self.next] = addr;
+        self.next = (self.next + 1) % NEAR;
+        self.same[addr % (SAME * 256)] = addr;
+        Ok(addr)
+    }
+}
+
+/// Construct the default code table (RFC 3284, Section 5.6), where each
+/// entry is a pair of instructions given as `(type,size,mode)`.
+fn default_code_table() -> Vec<[(u8,u8,u8);2]> {
+    let none = (NOOP,0,0);
+    let mut table = Vec::with_capacity(256);
+    table.push([(RUN,0,0),none]);
+    for size in 0..=17 { table.push([(ADD,size,0),none]); }
+    for mode in 0..9 {
+        table.push([(COPY,0,mode),none]);
+        for size in 4..=18 { table.push([(COPY,size,mode),none]); }
+    }
+    for mode in 0..6 {
+        for add in 1..=4 {
+            for copy in 4..=6 { table.push([(ADD,add,0),(COPY,copy,mode)]); }
+        }
+    }
+    for mode in 6..9 {
+        for add in 1..=4 { table.push([(ADD,add,0),(COPY,4,mode)]); }
+    }
+    for mode in 0..9 { table.push([(COPY,4,mode),(ADD,1,0)]); }
+    table
+}
+
+/// Append an integer in the variable-length format of RFC 3284 (i.e.
+/// base 128, most significant digit first, where every byte but the
+/// last has its high bit set).
+fn write_varint(out: &mut Vec<u8>, mut n: usize) {
+    let mut buf = [0u8;10];
+    let mut i = buf.len() - 1;
+    buf[i] = (n & 0x7F) as u8;
+    n >>= 7;
+    while n > 0 {
+        i -= 1;
+        buf[i] = (n & 0x7F) as u8 | 0x80;
+        n >>= 7;
+    }
+    out.extend_from_slice(&buf[i..]);
+}
+
+/// A cursor over a section of a VCDIFF file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8,VcdiffError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8],VcdiffError> {
+        let bytes = self.bytes.get(self.pos..).and_then(|b| b.get(..n)).ok_or(VcdiffError::Truncated)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<usize,VcdiffError> {
+        let start = self.pos;
+        let mut n : usize = 0;
+        loop {
+            let b = self.byte()?;
+            n = n.checked_mul(128).ok_or(VcdiffError::Malformed(start))? | (b & 0x7F) as usize;
+            if b & 0x80 == 0 { return Ok(n); }
+        }
+    }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod vcdiff_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use crate::diff::Diff;
+    use super::{VcdiffError,decode_vcdiff,encode_vcdiff};
+
+    #[test]
+    fn vcdiff_test_01() {
+        let (source,target) = (b"hello world",b"hello there world");
+        let delta = source.diff(target);
+        let file = encode_vcdiff(&delta,source.len());
+        // COPY 6 from 0, ADD "there " and COPY 5 from 6
+        let mut expected = vec![0xD6,0xC3,0xC4,0x00,0x00,0x01,0x0B,0x00,0x10,0x11,0x00,0x06,0x03,0x02];
+        expected.extend(b"there ".iter().chain(&[0x16,0x07,0x15,0x00,0x06]));
+        assert_eq!(file,expected);
+        assert_eq!(decode_vcdiff(source,&file),Ok(delta));
+    }
+
+    #[test]
+    fn vcdiff_test_02() {
+        // Round trip for a range of inputs
+        let seqs : Vec<Vec<u8>> = (0..40).map(|i| (0..i).map(|j| ((j * i + 3) % 5) as u8).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let delta = a.diff(b);
+                let file = encode_vcdiff(&delta,a.len());
+                assert_eq!(decode_vcdiff(a,&file).unwrap(),delta);
+            }
+        }
+    }
+
+    #[test]
+    fn vcdiff_test_03() {
+        // Hand-encoded file using VCD_HERE, the near cache, a combined
+        // ADD+COPY and a copy overlapping the target.
+        let source = b"abcdefgh";
+        // COPY 4 from 4 (mode 0), then ADD "X" + COPY 4 from near[0] + 0
+        // (mode 2), then COPY 4 from here - 2 (mode 1).
+        let (data,insts,addrs) = ([b'X'],[0x14,0xBB,0x24],[0x04,0x00,0x02]);
+        let mut enc = vec![0x0D,0x00,0x01,0x03,0x03];
+        enc.extend(data.iter().chain(&insts).chain(&addrs));
+        let mut file = vec![0xD6,0xC3,0xC4,0x00,0x00,0x01,0x08,0x00,enc.len() as u8];
+        file.extend(enc);
+        let delta = decode_vcdiff(source,&file).unwrap();
+        assert_eq!(delta.apply(source),b"efghXefghghgh");
+        assert_eq!(delta.len(),2);
+    }
+
+    #[test]
+    fn vcdiff_test_04() {
+        assert_eq!(decode_vcdiff(b"",b"VCD\x00\x00"),Err(VcdiffError::InvalidHeader));
+        assert_eq!(decode_vcdiff(b"",&[0xD6,0xC3,0xC4,0x00,0x01]),Err(VcdiffError::Unsupported("secondary compression")));
+        assert_eq!(decode_vcdiff(b"",&[0xD6,0xC3,0xC4,0x00,0x00,0x00,0x05]),Err(VcdiffError::Truncated));
+        // Copy from beyond the current position
+        let file = [0xD6,0xC3,0xC4,0x00,0x00,0x01,0x02,0x00,0x07,0x04,0x00,0x00,0x01,0x01,0x14,0x02];
+        assert!(matches!(decode_vcdiff(b"ab",&file),Err(VcdiffError::Malformed(_))));
+    }
+}
@@ -1,6 +1,8 @@
-use std::ops::Range;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::ops::Range;
 use crate::util::Region;
-use super::{SliceRewrite};
+use super::{Diff,MutableSequence,SliceRewrite,Transform,TryTransform,VecRewrite};
 
 /// A `VecDelta` is a sequence of zero (or more) rewrites that can be
 /// generated from something resembling a sequence (e.g. a slice or
@@ -37,15 +39,43 @@ pub struct VecDelta<T> {
     /// `data` array being used for the rewrite.  **NOTE:** the offset
     /// of the first region is relative to the _target sequence_ rather
     /// than the _original sequence_.
-    regions: Vec<(Region,Region)>,
+    regions: Regions,
     /// Items used within this delta
-    data: Vec<T>
+    data: Data<T>
 }
 
+/// Storage for the regions of a `VecDelta`.  Since most deltas (e.g.
+/// arising from interactive editing) have only one or two rewrites,
+/// these are stored inline when the `smallvec` feature is enabled.
+#[cfg(feature = "smallvec")]
+type Regions = smallvec::SmallVec<[(Region,Region);2]>;
+#[cfg(not(feature = "smallvec"))]
+type Regions = Vec<(Region,Region)>;
+
+/// Storage for the data of a `VecDelta`.  As for `Regions`, a small
+/// amount of data is stored inline when the `smallvec` feature is
+/// enabled.
+#[cfg(feature = "smallvec")]
+type Data<T> = smallvec::SmallVec<[T;4]>;
+#[cfg(not(feature = "smallvec"))]
+type Data<T> = Vec<T>;
+
 impl<T> VecDelta<T> {
     /// Construct an empty `VecDelta`
+    #[cfg(not(feature = "smallvec"))]
     pub const fn new() -> Self { VecDelta{regions: Vec::new(), data: Vec::new()} }
 
+    /// Construct an empty `VecDelta`
+    #[cfg(feature = "smallvec")]
+    pub const fn new() -> Self { VecDelta{regions: Regions::new_const(), data: Data::new_const()} }
+
+    /// Construct an empty `VecDelta` with enough space for a given
+    /// number of rewrites, and a given number of items of replacement
+    /// data, before reallocating.
+    pub fn with_capacity(rewrites: usize, items: usize) -> Self {
+        VecDelta{regions: Regions::with_capacity(rewrites), data: Data::with_capacity(items)}
+    }
+
     /// Get the number of atomic rewrites represented by this delta.
     pub fn len(&self) -> usize { self.regions.len() }
 
@@ -55,7 +85,7 @@ impl<T> VecDelta<T> {
     /// Get the `ith` rewrite contained within this `VecDelta`.  This
     /// returns a `SliceRewrite` which refers to data held internally
     /// within this `VecDelta`.
-    pub fn get(&self, ith: usize) -> Option<SliceRewrite<T>> {
+    pub fn get(&self, ith: usize) -> Option<SliceRewrite<'_,T>> {
         match self.regions.get(ith) {
             Some((r1,r2)) => {
                 Some(SliceRewrite::new(*r1,&self.data[r2.as_range()]))
@@ -64,46 +94,1026 @@ impl<T> VecDelta<T> {
         }
     }
 
-    /// Insert a new rewrite into this delta.  This will overwrite any
-    /// existing rewrites for the given region.  This may also merge
-    /// one or more existing rewrites together.  As such, after this
-    /// operation, `len()` may have increased, decreased or remain the
-    /// same.
-    pub fn insert(&mut self, _range: Range<usize>, _data: &[T]) {
-        todo!();
+    /// Get the rewrite which covers a given position in the _target
+    /// sequence_ (i.e. the sequence produced by this delta), if any.  A
+    /// rewrite covers the positions spanned by its replacement data
+    /// and, hence, rewrites which only delete elements cover none.  For
+    /// example, given the delta `(1;1;"xy"),(5;1;"")`, positions `1`
+    /// and `2` are covered by the first rewrite, whilst no position is
+    /// covered by the second.  Since rewrites are sorted, this uses a
+    /// binary search and takes `O(log n)` time.
+    pub fn rewrite_at(&self, index: usize) -> Option<SliceRewrite<'_,T>> {
+        let i = self.regions.partition_point(|(r1,r2)| r1.offset + r2.length <= index);
+        match self.regions.get(i) {
+            Some((r1,r2)) if r1.offset <= index => {
+                Some(SliceRewrite::new(*r1,&self.data[r2.as_range()]))
+            }
+            _ => None
+        }
+    }
+
+    /// Iterate the rewrites contained within this `VecDelta`, in
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item=SliceRewrite<'_,T>> {
+        self.regions.iter().map(|(r1,r2)| SliceRewrite::new(*r1,&self.data[r2.as_range()]))
+    }
+
+    /// Iterate the rewrites contained within this `VecDelta`, in
+    /// order, with their regions given in terms of the _original
+    /// sequence_ rather than the _target sequence_.
+    pub(crate) fn iter_source(&self) -> impl Iterator<Item=(Region,&[T])> {
+        let mut shift = 0isize;
+        self.regions.iter().map(move |(r1,r2)| {
+            let offset = (r1.offset as isize - shift) as usize;
+            shift += r2.length as isize - r1.length as isize;
+            (Region::new(offset,r1.length),&self.data[r2.as_range()])
+        })
+    }
+
+    /// Refine each rewrite in this delta at a finer granularity.  For
+    /// example, given a delta over lines, we might want to know which
+    /// characters changed within each replaced hunk.  To do this, the
+    /// elements removed by each rewrite (taken from the `before`
+    /// sequence this delta applies to) and those inserted are
+    /// converted into sub-elements using a given `tokenize` function.
+    /// These are then diffed, yielding one nested delta for each
+    /// rewrite (in order).  Applying the `ith` nested delta to the
+    /// tokenized removed elements produces the tokenized inserted
+    /// elements of the `ith` rewrite.
+    pub fn refine<U,F>(&self, before: &[T], tokenize: F) -> Vec<VecDelta<U>>
+    where U:Clone+PartialEq, F:Fn(&[T])->Vec<U> {
+        self.iter_source().map(|(r,data)| {
+            let lhs = tokenize(&before[r.as_range()]);
+            let rhs = tokenize(data);
+            lhs.diff(&rhs)
+        }).collect()
+    }
+
+    /// Apply this delta to a given `Vec` by moving its replacement data
+    /// into it, thus consuming this delta.  Unlike `transform()`, this
+    /// does not require elements to be cloneable.  As for
+    /// `transform()`, the result is built in a single pass, and this
+    /// operation will `panic` if this delta is malformed with respect
+    /// to the given `Vec`.
+    pub fn into_transform(self, vec: &mut Vec<T>) {
+        let removed : usize = self.regions.iter().map(|(r1,_)| r1.length).sum();
+        let mut out = Vec::with_capacity(vec.len() + self.data.len() - removed);
+        let mut items = vec.drain(..);
+        let mut data = self.data.into_iter();
+        let (mut cursor, mut shift) = (0,0isize);
+        for (r1,r2) in self.regions {
+            let offset = (r1.offset as isize - shift) as usize;
+            shift += r2.length as isize - r1.length as isize;
+            // Move unaffected items, drop replaced items, and move
+            // replacement data.
+            let len = out.len();
+            out.extend(items.by_ref().take(offset - cursor));
+            let m = items.by_ref().take(r1.length).count();
+            assert!(out.len() - len == offset - cursor && m == r1.length,"rewrite out of bounds");
+            out.extend(data.by_ref().take(r2.length));
+            cursor = offset + r1.length;
+        }
+        out.extend(items);
+        *vec = out;
+    }
+
+    /// Translate every rewrite of this delta by a given amount.  This
+    /// is useful when a delta computed for a subslice of some sequence
+    /// (e.g. `vec[10..20]`) is to be applied to the whole sequence
+    /// (e.g. `vec`) by shifting it by the subslice's start (e.g. `10`).
+    /// If any rewrite would be moved before the start of the sequence
+    /// (or beyond `usize::MAX`), an error is returned and this delta
+    /// is left unchanged.
+    pub fn shift(&mut self, offset: isize) -> Result<(),DeltaError> {
+        // Since regions are sorted, only the first and last need to be
+        // checked.
+        for (r1,_) in [self.regions.first(),self.regions.last()].into_iter().flatten() {
+            if r1.offset.checked_add_signed(offset).and_then(|o| o.checked_add(r1.length)).is_none() {
+                return Err(DeltaError::ShiftOutOfRange{region: *r1, offset});
+            }
+        }
+        for (r1,_) in &mut self.regions {
+            r1.offset = r1.offset.wrapping_add_signed(offset);
+        }
+        Ok(())
+    }
+
+    /// Determine which rewrites of this delta conflict with those of
+    /// another delta, where both were computed against the same
+    /// original sequence.  Two rewrites conflict when their regions
+    /// overlap, or when either is an insertion at the start of the
+    /// other (since their order would then be ambiguous).  Each
+    /// conflict is returned as a pair of regions, in terms of the
+    /// original sequence, with the first from this delta and the
+    /// second from `other`.  If there are no conflicts, then the two
+    /// deltas can be merged or rebased without any tie-breaking.
+    pub fn conflicts_with(&self, other: &VecDelta<T>) -> Vec<(Region,Region)> {
+        let theirs : Vec<Region> = other.iter_source().map(|(r,_)| r).collect();
+        let mut conflicts = Vec::new();
+        let mut j = 0;
+        for (r1,_) in self.iter_source() {
+            // Skip regions which end before this one starts
+            while j < theirs.len() && theirs[j].offset + theirs[j].length <= r1.offset
+                && !conflicting(&r1,&theirs[j]) {
+                j += 1;
+            }
+            for r2 in &theirs[j..] {
+                if r2.offset > r1.offset + r1.length { break; }
+                if conflicting(&r1,r2) { conflicts.push((r1,*r2)); }
+            }
+        }
+        conflicts
+    }
+
+    /// Determine where the element at a given position in the
+    /// original sequence ends up after this delta is applied.  For
+    /// example, given the delta `(1;2;"xyz")`, position `0` is
+    /// preserved, positions `1` and `2` are deleted and position `3`
+    /// is shifted to position `4`.  For a deleted element, the
+    /// position where its replacement starts is returned.
+    pub fn map_position(&self, index: usize) -> MappedPos {
+        let mut shift = 0isize;
+        for (r,data) in self.iter_source() {
+            if index < r.offset { break; }
+            if index < r.offset + r.length {
+                return MappedPos::Deleted((r.offset as isize + shift) as usize);
+            }
+            shift += data.len() as isize - r.length as isize;
+        }
+        MappedPos::shifted(index,shift)
+    }
+
+    /// Determine where the element at a given position in the final
+    /// sequence (i.e. after this delta is applied) was in the original
+    /// sequence.  This is the reverse of `map_position()`, where an
+    /// element is reported as `Deleted` when it was written by a
+    /// rewrite (and, hence, is not present in the original sequence).
+    /// In such case, the position in the original sequence of the
+    /// region replaced by the rewrite is returned.
+    pub fn unmap_position(&self, index: usize) -> MappedPos {
+        let mut shift = 0isize;
+        for (rw,(r,_)) in self.iter().zip(self.iter_source()) {
+            let offset = rw.region().offset;
+            if index < offset { break; }
+            if index < offset + rw.data().len() {
+                return MappedPos::Deleted(r.offset);
+            }
+            shift += r.length as isize - rw.data().len() as isize;
+        }
+        MappedPos::shifted(index,shift)
+    }
+
+    /// Group the rewrites of this delta into _hunks_ for presentation,
+    /// given the length of the original sequence it applies to.  Each
+    /// hunk includes up to `context` unchanged elements on either side
+    /// of its rewrites, and rewrites whose contexts overlap (or touch)
+    /// are placed in the same hunk.  For example, given the delta
+    /// `(1;1;"x"),(5;1;"")` over a sequence of length `8`, a context of
+    /// `1` gives hunks covering `0..3` and `4..7` of the original
+    /// sequence, whilst a context of `2` gives a single hunk covering
+    /// `0..8`.
+    pub fn hunks(&self, before_len: usize, context: usize) -> Vec<Hunk<'_,T>> {
+        let mut hunks : Vec<Hunk<'_,T>> = Vec::new();
+        let mut shift = 0isize;
+        for (r,data) in self.iter_source() {
+            let start = r.offset.saturating_sub(context);
+            let finish = (r.offset + r.length + context).min(before_len).max(r.offset + r.length);
+            match hunks.last_mut() {
+                Some(h) if start <= h.before.offset + h.before.length => {
+                    h.before.length = finish - h.before.offset;
+                }
+                _ => {
+                    let after = Region::new((start as isize + shift) as usize,0);
+                    hunks.push(Hunk{before: Region::new(start,finish - start), after, rewrites: Vec::new()});
+                }
+            }
+            let h = hunks.last_mut().unwrap();
+            h.rewrites.push((r,data));
+            shift += data.len() as isize - r.length as isize;
+            h.after.length = (h.before.offset as isize + h.before.length as isize + shift) as usize - h.after.offset;
+        }
+        hunks
+    }
+
+    /// Check whether this delta and another produce the same sequence
+    /// when applied to a given `base` sequence, even if they differ
+    /// syntactically.  For example, `(1;1;"b")` and the empty delta are
+    /// equivalent on `"abc"`.  This compares the two results element by
+    /// element, without materializing either.  This operation will
+    /// `panic` if either delta is malformed with respect to `base`.
+    pub fn equivalent_on(&self, other: &VecDelta<T>, base: &[T]) -> bool
+    where T:PartialEq {
+        let lhs = self.segments(base);
+        let rhs = other.segments(base);
+        lhs.iter().flat_map(|s| s.iter()).eq(rhs.iter().flat_map(|s| s.iter()))
+    }
+
+    /// Split the result of applying this delta to a given `base`
+    /// sequence into its constituent slices, which alternate between
+    /// slices of `base` and replacement data.
+    fn segments<'a>(&'a self, base: &'a [T]) -> Vec<&'a [T]> {
+        let mut segments = Vec::with_capacity(2 * self.regions.len() + 1);
+        let mut cursor = 0;
+        for (r,data) in self.iter_source() {
+            segments.push(&base[cursor..r.offset]);
+            segments.push(data);
+            cursor = r.offset + r.length;
+        }
+        segments.push(&base[cursor..]);
+        segments
+    }
+}
+
+/// Describes how a position in one sequence maps to a position in
+/// another, across some delta (see `VecDelta::map_position()`).
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum MappedPos {
+    /// The element is unaffected and its position is unchanged.
+    Preserved(usize),
+    /// The element is unaffected, but is now at the given position.
+    Shifted(usize),
+    /// The element was replaced by a rewrite, which is located at the
+    /// given position.
+    Deleted(usize)
+}
+
+impl MappedPos {
+    fn shifted(index: usize, shift: isize) -> Self {
+        if shift == 0 {
+            MappedPos::Preserved(index)
+        } else {
+            MappedPos::Shifted((index as isize + shift) as usize)
+        }
     }
+
+    /// Get the position this maps to, regardless of whether the
+    /// element was preserved, shifted or deleted.
+    pub fn index(&self) -> usize {
+        match self {
+            MappedPos::Preserved(i)|MappedPos::Shifted(i)|MappedPos::Deleted(i) => *i
+        }
+    }
+}
+
+/// A group of nearby rewrites within a delta, along with some
+/// unchanged elements around them (see `VecDelta::hunks()`).
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Hunk<'a,T> {
+    /// Region of the original sequence covered by this hunk.
+    before: Region,
+    /// Region of the final sequence covered by this hunk.
+    after: Region,
+    /// Rewrites within this hunk, with their regions given in terms of
+    /// the original sequence.
+    rewrites: Vec<(Region,&'a [T])>
+}
+
+impl<'a,T> Hunk<'a,T> {
+    /// Get the region of the original sequence covered by this hunk
+    /// (including its context).
+    pub fn before(&self) -> Region { self.before }
+
+    /// Get the region of the final sequence (i.e. after the delta is
+    /// applied) covered by this hunk (including its context).
+    pub fn after(&self) -> Region { self.after }
+
+    /// Get the rewrites within this hunk, in order, with their regions
+    /// given in terms of the original sequence.
+    pub fn rewrites(&self) -> &[(Region,&'a [T])] { &self.rewrites }
+}
+
+/// Describes an error arising from an attempt to construct a
+/// malformed delta.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum DeltaError {
+    /// Two rewrites overlap, hence their order of application is
+    /// ambiguous.
+    Overlapping(Region,Region),
+    /// A rewrite was added which precedes an existing rewrite,
+    /// rather than following it.
+    Unordered(Region,Region),
+    /// A rewrite refers to a region beyond the end of the sequence it
+    /// is being applied to (which has the given length at that
+    /// point).
+    RegionOutOfBounds{region: Region, len: usize},
+    /// A rewrite of a string does not begin or end on a `char`
+    /// boundary (at the given offset, which is usually in bytes).
+    NotCharBoundary(usize),
+    /// A rewrite changes the length of a sequence whose length is
+    /// fixed (e.g. an array), by replacing the given region with data
+    /// of a different length.
+    LengthMismatch{region: Region, len: usize},
+    /// Shifting a rewrite by the given offset would move it out of
+    /// range (e.g. before the start of the sequence).
+    ShiftOutOfRange{region: Region, offset: isize},
+    /// A sequence does not have the checksum expected by a
+    /// `CheckedDelta`, either before or after it is applied.
+    ChecksumMismatch{expected: u64, found: u64}
+}
+
+impl core::fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DeltaError::Overlapping(r1,r2) => {
+                write!(f,"rewrites overlap ({:?} and {:?})",r1.as_range(),r2.as_range())
+            }
+            DeltaError::Unordered(r1,r2) => {
+                write!(f,"rewrite {:?} precedes {:?}",r2.as_range(),r1.as_range())
+            }
+            DeltaError::RegionOutOfBounds{region,len} => {
+                write!(f,"rewrite {:?} out of bounds for length {len}",region.as_range())
+            }
+            DeltaError::NotCharBoundary(i) => {
+                write!(f,"rewrite at byte {i} is not on a char boundary")
+            }
+            DeltaError::LengthMismatch{region,len} => {
+                write!(f,"rewrite {:?} replaced with {len} elements",region.as_range())
+            }
+            DeltaError::ShiftOutOfRange{region,offset} => {
+                write!(f,"rewrite {:?} cannot be shifted by {offset}",region.as_range())
+            }
+            DeltaError::ChecksumMismatch{expected,found} => {
+                write!(f,"checksum {found:#018x} does not match expected {expected:#018x}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for DeltaError {}
+
+/// Check that a new rewrite (whose region is in terms of the target
+/// sequence) follows the last rewrite of a delta (whose region covers
+/// its replacement data in the target sequence).
+pub(crate) fn check_follows(last: Region, region: Region) -> Result<(),DeltaError> {
+    if last < region {
+        Ok(())
+    } else if region.offset + region.length <= last.offset {
+        Err(DeltaError::Unordered(last,region))
+    } else {
+        Err(DeltaError::Overlapping(last,region))
+    }
+}
+
+impl<T:Clone> Transform for Vec<T> {
+    type Delta = VecDelta<T>;
+
+    fn transform(&mut self, d: &VecDelta<T>) {
+        d.transform(self)
+    }
+}
+
+impl<T:Clone> TryTransform for Vec<T> {
+    type Delta = VecDelta<T>;
+    type Error = DeltaError;
+
+    fn try_transform(&mut self, d: &VecDelta<T>) -> Result<(),DeltaError> {
+        d.try_transform(self)
+    }
+}
+
+/// Any `MutableSequence` can be transformed by applying each rewrite
+/// in turn.  Note that `Vec` is not a `MutableSequence`, since it
+/// is transformed in a single pass instead (see `VecDelta::transform()`).
+impl<S> Transform for S where S:MutableSequence, S::Item:Clone {
+    type Delta = VecDelta<S::Item>;
+
+    fn transform(&mut self, d: &VecDelta<S::Item>) {
+        for rw in d.iter() {
+            self.splice(rw.region().as_range(), rw.data());
+        }
+    }
+}
+
+/// Splices either at the front or back of the queue, whichever is
+/// closer, such that only the elements between the range and that
+/// end are moved.
+impl<T:Clone> MutableSequence for VecDeque<T> {
+    type Item = T;
+
+    fn len(&self) -> usize { VecDeque::len(self) }
+
+    fn splice(&mut self, range: Range<usize>, items: &[T]) {
+        let length = range.end - range.start;
+        // Overwrite the common part in place
+        let n = length.min(items.len());
+        for (i,item) in items[..n].iter().enumerate() {
+            self[range.start + i] = item.clone();
+        }
+        // Remove or insert the remainder
+        let pos = range.start + n;
+        if length > n {
+            self.drain(pos .. range.end);
+        } else if items.len() > n {
+            let extra = &items[n..];
+            if pos <= self.len() / 2 {
+                let front : Vec<T> = self.drain(..pos).collect();
+                for item in extra.iter().rev().chain(front.iter().rev()) {
+                    self.push_front(item.clone());
+                }
+            } else {
+                let back = self.split_off(pos);
+                self.extend(extra.iter().cloned());
+                self.extend(back);
+            }
+        }
+    }
+}
+
+/// Arrays can only be transformed by deltas which do not change their
+/// length.  That is, where every rewrite replaces a region with data
+/// of the same length.
+impl<T:Clone,const N: usize> TryTransform for [T;N] {
+    type Delta = VecDelta<T>;
+    type Error = DeltaError;
+
+    fn try_transform(&mut self, d: &VecDelta<T>) -> Result<(),DeltaError> {
+        for rw in d.iter() {
+            let (region,len) = (rw.region(),rw.data().len());
+            if region.length != len {
+                return Err(DeltaError::LengthMismatch{region,len});
+            } else if region.offset + region.length > N {
+                return Err(DeltaError::RegionOutOfBounds{region,len: N});
+            }
+        }
+        for rw in d.iter() {
+            self[rw.region().as_range()].clone_from_slice(rw.data());
+        }
+        Ok(())
+    }
+}
+
+impl<T:Clone> TryFrom<Vec<VecRewrite<T>>> for VecDelta<T> {
+    type Error = DeltaError;
+
+    /// Construct a delta from rewrites in terms of the original
+    /// sequence (see `VecDelta::canonicalize()`).
+    fn try_from(rewrites: Vec<VecRewrite<T>>) -> Result<Self,DeltaError> {
+        VecDelta::canonicalize(rewrites)
+    }
+}
+
+/// A delta is serialised as the sequence of its rewrites (see
+/// `Rewrite`), whose regions are in terms of the _target sequence_.
+#[cfg(feature = "serde")]
+impl<T:serde::Serialize> serde::Serialize for VecDelta<T> {
+    fn serialize<S:serde::Serializer>(&self, serializer: S) -> Result<S::Ok,S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// Deserialising a delta checks its rewrites are sorted and disjoint
+/// (see `VecDelta::try_push()`).
+#[cfg(feature = "serde")]
+impl<'de,T:Clone+serde::Deserialize<'de>> serde::Deserialize<'de> for VecDelta<T> {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer: D) -> Result<Self,D::Error> {
+        let rewrites : Vec<VecRewrite<T>> = serde::Deserialize::deserialize(deserializer)?;
+        VecDelta::from_target_coords(rewrites).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<T> Default for VecDelta<T> {
+    fn default() -> Self { Self::new() }
 }
 
 impl<T:Clone> VecDelta<T> {
     /// Append a new rewrite onto the end of this delta.  This
     /// requires that rewrite logically follows all other rewrites,
     /// and is strictly disjoint from them.
+    ///
+    /// # Safety
+    ///
+    /// The caller is responsible for ensuring the given range is
+    /// expressed in terms of the _target sequence_ and does not
+    /// overlap any existing rewrite.  Ordering is checked with an
+    /// assertion, but violating it otherwise leaves this delta
+    /// malformed.  See `try_push()` for a safe alternative.
     pub unsafe fn push_raw(&mut self, range: Range<usize>, data: &[T]) {
+        if let Err(e) = self.try_push(range, data) {
+            panic!("{e}");
+        }
+    }
+
+    /// Append a new rewrite onto the end of this delta, where the
+    /// given range is expressed in terms of the _target sequence_.
+    /// This requires that the rewrite logically follows all other
+    /// rewrites, and is disjoint from them.  Otherwise, an error is
+    /// returned and this delta is left unchanged.
+    pub fn try_push(&mut self, range: Range<usize>, data: &[T]) -> Result<(),DeltaError> {
         let region : Region = range.into();
-        let n = self.len();
-        assert!(n == 0 || self.regions[n-1].0 < region);
+        // Check new region follows the replacement data of the last
+        // rewrite, as this is where it ends in the target sequence.
+        if let Some((r1,r2)) = self.regions.last() {
+            check_follows(Region::new(r1.offset,r2.length),region)?;
+        }
         //
         let data_start = self.data.len();
         // Copy over data
-        self.data.extend_from_slice(data);
+        self.data.extend(data.iter().cloned());
         // Construct meta-data
         self.regions.push((region,Region::new(data_start,data.len())));
+        Ok(())
+    }
+
+    /// Get the rewrites of this delta with their regions given in
+    /// terms of the _target sequence_ (i.e. as stored internally).
+    /// Applying these rewrites in order, one after the other,
+    /// transforms the original sequence.  See `from_target_coords()`
+    /// for the reverse.
+    pub fn to_target_coords(&self) -> Vec<VecRewrite<T>> {
+        self.iter().map(|rw| VecRewrite::new(rw.region(),rw.data().to_vec())).collect()
+    }
+
+    /// Get the rewrites of this delta with their regions given in
+    /// terms of the _original sequence_.  For example, the delta
+    /// `(1;2;"xyz"),(5;1;"")` becomes `(1;2;"xyz"),(4;1;"")`.  This is
+    /// convenient for relating rewrites back to the original sequence,
+    /// since they can be applied _simultaneously_.  See
+    /// `canonicalize()` for the reverse.
+    pub fn to_source_coords(&self) -> Vec<VecRewrite<T>> {
+        self.iter_source().map(|(r,d)| VecRewrite::new(r,d.to_vec())).collect()
+    }
+
+    /// Construct a delta from a sequence of rewrites whose regions are
+    /// given in terms of the _target sequence_ (see
+    /// `to_target_coords()`).  An error is returned if these are not
+    /// sorted and disjoint.
+    pub fn from_target_coords<I>(rewrites: I) -> Result<Self,DeltaError>
+    where I:IntoIterator<Item=VecRewrite<T>> {
+        let mut delta = VecDelta::new();
+        for rw in rewrites {
+            delta.try_push(rw.region().as_range(),rw.data())?;
+        }
+        Ok(delta)
+    }
+
+    /// Construct a delta from a sequence of rewrites whose regions are
+    /// given in terms of the _original sequence_ (rather than the
+    /// _target sequence_).  These must be sorted and disjoint.
+    pub(crate) fn from_source<'a,I>(rewrites: I) -> Self
+    where T:'a, I:IntoIterator<Item=(Region,&'a [T])> {
+        let mut delta = VecDelta::new();
+        let mut shift = 0isize;
+        for (r,data) in rewrites {
+            let offset = (r.offset as isize + shift) as usize;
+            unsafe { delta.push_raw(offset .. offset + r.length, data); }
+            shift += data.len() as isize - r.length as isize;
+        }
+        delta
     }
 
     /// Apply this delta to a given `Vec`, thus transforming it.  This
     /// operation will `panic` if this delta is malformed with respect
-    /// to the given delta.
+    /// to the given delta.  Rather than splicing each rewrite into
+    /// the `Vec` in turn (which would shift its tail `k` times for `k`
+    /// rewrites), the result is built in a single pass over the
+    /// original `Vec`.  Thus, this takes `O(n+m)` time where `m` is
+    /// the amount of replacement data.
     pub fn transform(&self, vec: &mut Vec<T>) {
-        for i in 0..self.regions.len() {
-            let (r1,r2) = self.regions[i];
-            let data = &self.data[r2.as_range()];
-            // FIXME: it would be nice to get rid of this clone
-            // somehow.  In my mind, its possible to do this.
-            // However, I'm not sure how to express is clearly in
-            // Rust.
-	    vec.splice(r1.as_range(), data.iter().cloned());
+        // FIXME: it would be nice to get rid of this clone
+        // somehow.  In my mind, its possible to do this.
+        // However, I'm not sure how to express is clearly in
+        // Rust.
+        match self.regions.as_slice() {
+            [] => {}
+            [(r1,r2)] => {
+                // A single splice is already optimal
+                vec.splice(r1.as_range(), self.data[r2.as_range()].iter().cloned());
+            }
+            _ => {
+                let removed : usize = self.regions.iter().map(|(r1,_)| r1.length).sum();
+                let end = self.iter_source().last().map(|(r,_)| r.offset + r.length);
+                assert!(end <= Some(vec.len()),"rewrite out of bounds");
+                let mut out = Vec::with_capacity(vec.len() + self.data.len() - removed);
+                let mut items = vec.drain(..);
+                let mut cursor = 0;
+                for (r,data) in self.iter_source() {
+                    // Move unaffected items, and drop replaced items
+                    out.extend(items.by_ref().take(r.offset - cursor));
+                    items.by_ref().take(r.length).for_each(drop);
+                    out.extend_from_slice(data);
+                    cursor = r.offset + r.length;
+                }
+                out.extend(items);
+                *vec = out;
+            }
         }
     }
+
+    /// Apply this delta to a given `Vec`, thus transforming it.  Unlike
+    /// `transform()`, this first checks every rewrite lies within the
+    /// bounds of the sequence it will be applied to.  If not, an error
+    /// is returned and the given `Vec` is left unchanged.  This is
+    /// useful for safely applying deltas received from elsewhere
+    /// (e.g. over a network).
+    pub fn try_transform(&self, vec: &mut Vec<T>) -> Result<(),DeltaError> {
+        let mut len = vec.len();
+        for (r1,r2) in &self.regions {
+            if r1.offset + r1.length > len {
+                return Err(DeltaError::RegionOutOfBounds{region: *r1, len});
+            }
+            len = len - r1.length + r2.length;
+        }
+        self.transform(vec);
+        Ok(())
+    }
+
+    /// Apply this delta to a given slice, producing a new `Vec`
+    /// rather than transforming an existing one.  The result is built
+    /// in a single pass, having determined its final size beforehand.
+    /// This operation will `panic` if this delta is malformed with
+    /// respect to the given slice.
+    pub fn apply(&self, src: &[T]) -> Vec<T> {
+        let removed : usize = self.regions.iter().map(|(r1,_)| r1.length).sum();
+        let mut out = Vec::with_capacity(src.len() + self.data.len() - removed);
+        let mut cursor = 0;
+        for (r,data) in self.iter_source() {
+            out.extend_from_slice(&src[cursor..r.offset]);
+            out.extend_from_slice(data);
+            cursor = r.offset + r.length;
+        }
+        out.extend_from_slice(&src[cursor..]);
+        out
+    }
+
+    /// Insert a new rewrite into this delta.  This will overwrite any
+    /// existing rewrites for the given region.  This may also merge
+    /// one or more existing rewrites together.  As such, after this
+    /// operation, `len()` may have increased, decreased or remain the
+    /// same.  The given range is in terms of the _target sequence_
+    /// (i.e. the sequence produced by this delta), such that applying
+    /// the updated delta is equivalent to applying this delta and
+    /// then the new rewrite.  For example, inserting `2..4` with
+    /// `"z"` into the delta `(1;1;"xy")` gives `(1;2;"xz")`, since the
+    /// new rewrite overlaps the existing one.
+    pub fn insert(&mut self, range: Range<usize>, data: &[T]) {
+        let mut rewrite = VecDelta::new();
+        unsafe { rewrite.push_raw(range, data); }
+        *self = self.compose(&rewrite);
+    }
+
+    /// Construct a delta for the concatenation of two sequences `a++b`
+    /// from deltas `da` and `db` for each of them.  Here, `len_a` is
+    /// the length of `a` _after_ applying `da` (since rewrites are in
+    /// terms of the target sequence).  Applying the resulting delta to
+    /// `a++b` gives the concatenation of the results of applying `da`
+    /// to `a` and `db` to `b`.  If the last rewrite of `da` and the
+    /// first of `db` meet at the boundary, then they are merged.  This
+    /// operation will `panic` if `da` extends beyond `len_a`.
+    pub fn concat(da: &VecDelta<T>, len_a: usize, db: &VecDelta<T>) -> VecDelta<T> {
+        if let Some(last) = da.iter().last() {
+            let end = last.region().offset + last.data().len();
+            assert!(end <= len_a,"delta extends to {end}, beyond length {len_a}");
+        }
+        let mut delta = da.clone();
+        for rw in db.iter() {
+            let region = rw.region();
+            let offset = region.offset + len_a;
+            match delta.regions.last_mut() {
+                Some((r1,r2)) if r1.offset + r2.length == offset && r2.offset + r2.length == delta.data.len() => {
+                    r1.length += region.length;
+                    r2.length += rw.data().len();
+                    delta.data.extend(rw.data().iter().cloned());
+                }
+                _ => delta.try_push(offset .. offset + region.length, rw.data()).expect("invalid concatenation")
+            }
+        }
+        delta
+    }
+
+    /// Split this delta into two parts at a given position in the
+    /// _target sequence_ (i.e. the sequence produced by this delta).
+    /// The first produces the elements before the position, and the
+    /// second produces the elements from the position onwards (see
+    /// `restrict()`).  The first applies to the elements of the
+    /// original sequence before `source_position(index)`, and the
+    /// second applies to those from it onwards (with regions relative
+    /// to it).  Thus, applying each part to the corresponding part of
+    /// the original sequence and concatenating the results is
+    /// equivalent to applying this delta.  A rewrite whose data
+    /// straddles the position is split in two, with the region it
+    /// replaces going to the first part.  For example, splitting
+    /// `(1;3;"xy")` at `2` gives `(1;3;"x")` and `(0;0;"y")`, which
+    /// apply to `0..4` and `4..` of the original sequence.
+    pub fn split_at(&self, index: usize) -> (VecDelta<T>,VecDelta<T>) {
+        (self.restrict(0..index),self.restrict(index..usize::MAX))
+    }
+
+    /// Restrict this delta to those rewrites affecting a given range
+    /// of the _target sequence_ (i.e. the sequence produced by this
+    /// delta).  This is useful for rendering a window of a large
+    /// document, since only the rewrites within the window are
+    /// considered.  The resulting delta produces exactly that range of
+    /// the target sequence, when applied to the portion of the
+    /// original sequence from which it came (and its regions are
+    /// relative to the start of that portion, as determined by
+    /// `source_position()`).  A rewrite whose data only partially
+    /// overlaps the range is clipped, such that the region it replaces
+    /// is retained only if its data starts within the range.  A
+    /// deletion is retained only if it is at the start of the range,
+    /// or within it.  Thus, an empty range gives an empty delta.  For
+    /// example, restricting the rewrites `(1;2;"xyz"),(5;1;"")` (in
+    /// terms of the original sequence) to `2..7` gives
+    /// `(0;0;"yz"),(2;1;"")` (likewise), which applies to `3..7` of
+    /// the original sequence.
+    pub fn restrict(&self, range: Range<usize>) -> VecDelta<T> {
+        let start = self.source_position(range.start);
+        let mut shift = 0isize;
+        let hunks = self.iter_source().filter_map(|(r,data)| {
+            let t = (r.offset as isize + shift) as usize;
+            shift += data.len() as isize - r.length as isize;
+            let end = (t + data.len()).min(range.end);
+            if t >= range.start && t < range.end {
+                Some((Region::new(r.offset - start,r.length),&data[..end - t]))
+            } else if t < range.start && end > range.start {
+                Some((Region::new(0,0),&data[range.start - t..end - t]))
+            } else {
+                None
+            }
+        });
+        VecDelta::from_source(hunks)
+    }
+
+    /// Restrict this delta to those rewrites affecting a given range
+    /// of the _original sequence_.  The resulting delta applies to that
+    /// range of the original sequence (i.e. its regions are relative
+    /// to the start of the range).  A rewrite which only partially
+    /// overlaps the range is clipped, with its data retained only if
+    /// it starts within the range.  Likewise, an insertion is retained
+    /// only if it is at the start of the range (even when the range is
+    /// empty), or within it.  For example, restricting the rewrites
+    /// `(1;2;"x"),(5;3;"yz")` (in terms of the original sequence) to
+    /// `2..6` gives `(0;1;""),(3;1;"yz")`.
+    pub fn restrict_source(&self, range: Range<usize>) -> VecDelta<T> {
+        let hunks = self.iter_source().filter_map(|(r,data)| {
+            let end = (r.offset + r.length).min(range.end).max(r.offset);
+            if r.offset == range.start || (r.offset > range.start && r.offset < range.end) {
+                Some((Region::new(r.offset - range.start,end - r.offset),data))
+            } else if r.offset < range.start && end > range.start {
+                Some((Region::new(0,end - range.start),&data[..0]))
+            } else {
+                None
+            }
+        });
+        VecDelta::from_source(hunks)
+    }
+
+    /// Determine the first position in the original sequence from
+    /// which a given position in the target sequence is produced.  For
+    /// a position strictly within the data of a rewrite, this is the
+    /// end of the region it replaces.  Deletions at the position are
+    /// considered to come after it.  For example, given the delta
+    /// `(1;3;"xy")`, position `2` comes from position `4`.
+    pub fn source_position(&self, index: usize) -> usize {
+        let mut shift = 0isize;
+        for (r,data) in self.iter_source() {
+            let t = (r.offset as isize + shift) as usize;
+            if index <= t { break; }
+            if index < t + data.len() { return r.offset + r.length; }
+            shift += data.len() as isize - r.length as isize;
+        }
+        (index as isize - shift) as usize
+    }
+
+    /// Compose this delta with another, such that applying the
+    /// resulting delta is equivalent to applying `self` and then
+    /// applying `other`.  For example, composing `(1;1;"xy")` with
+    /// `(2;2;"z")` gives `(1;2;"xz")`.  This allows a long history of
+    /// edits to be collapsed into a single delta.
+    pub fn compose(&self, other: &VecDelta<T>) -> VecDelta<T> {
+        // Describe the intermediate sequence as a list of pieces.
+        let mut middle = Vec::new();
+        let mut pos = 0;
+        for (r,data) in self.iter_source() {
+            push_piece(&mut middle, Piece::Kept(pos,r.offset - pos));
+            push_piece(&mut middle, Piece::New(data));
+            pos = r.offset + r.length;
+        }
+        // Apply the other delta to the intermediate sequence, giving a
+        // description of the final sequence.
+        let mut cursor = Cursor{pieces: &middle, index: 0, offset: 0, tail: pos};
+        let mut last = Vec::new();
+        pos = 0;
+        for (r,data) in other.iter_source() {
+            cursor.take(r.offset - pos, Some(&mut last));
+            cursor.take(r.length, None);
+            push_piece(&mut last, Piece::New(data));
+            pos = r.offset + r.length;
+        }
+        let tail = cursor.finish(&mut last);
+        // Extract rewrites (in source coordinates) from the final
+        // sequence.
+        let mut hunks : Vec<(Region,Vec<T>)> = Vec::new();
+        let mut data = Vec::new();
+        pos = 0;
+        for piece in last.into_iter().chain([Piece::Kept(tail,0)]) {
+            match piece {
+                Piece::New(d) => data.extend_from_slice(d),
+                Piece::Kept(start,len) => {
+                    if start > pos || !data.is_empty() {
+                        hunks.push((Region::new(pos,start - pos),core::mem::take(&mut data)));
+                    }
+                    pos = start + len;
+                }
+            }
+        }
+        VecDelta::from_source(hunks.iter().map(|(r,d)| (*r,d.as_slice())))
+    }
+
+    /// Construct a delta from an arbitrary list of rewrites whose
+    /// regions are given in terms of the _original sequence_.  These
+    /// are sorted, and any rewrites which are adjacent are merged
+    /// together (e.g. `(1;1;"x")` and `(2;2;"y")` become
+    /// `(1;3;"xy")`).  Empty rewrites (i.e. which neither remove nor
+    /// insert anything) are dropped.  Multiple insertions at the same
+    /// position are merged in the order given.  However, rewrites
+    /// which overlap cannot be resolved and an error is returned
+    /// instead.
+    pub fn canonicalize<I>(rewrites: I) -> Result<Self,DeltaError>
+    where I:IntoIterator<Item=VecRewrite<T>> {
+        let mut rewrites : Vec<VecRewrite<T>> = rewrites.into_iter().collect();
+        // NOTE: must be stable to preserve order of insertions.
+        rewrites.sort_by_key(|rw| (rw.region().offset,rw.region().length));
+        let mut hunks : Vec<(Region,Vec<T>)> = Vec::new();
+        for rw in rewrites {
+            let r = rw.region();
+            if r.length == 0 && rw.data().is_empty() { continue; }
+            match hunks.last_mut() {
+                Some((pr,pd)) if r.offset <= pr.offset + pr.length => {
+                    if r.offset < pr.offset + pr.length {
+                        return Err(DeltaError::Overlapping(*pr,r));
+                    }
+                    pr.length += r.length;
+                    pd.extend_from_slice(rw.data());
+                }
+                _ => hunks.push((r,rw.into_data()))
+            }
+        }
+        Ok(VecDelta::from_source(hunks.iter().map(|(r,d)| (*r,d.as_slice()))))
+    }
+
+    /// Compute the _normal form_ of this delta with respect to a given
+    /// `base` sequence.  This is the delta consisting of (at most) one
+    /// rewrite which spans from the first to the last element changed
+    /// by this delta, having discarded any elements which are rewritten
+    /// to themselves.  For example, the normal form of `(1;1;"b")` on
+    /// `"abc"` is the empty delta.  Since the normal form depends only
+    /// on `base` and the result of applying this delta to it, two
+    /// deltas are equivalent on `base` (see `equivalent_on()`) if, and
+    /// only if, their normal forms are equal.  Hence, normal forms can
+    /// be used as keys for deduplicating deltas.  This operation will
+    /// `panic` if this delta is malformed with respect to `base`.
+    pub fn normalize(&self, base: &[T]) -> VecDelta<T>
+    where T:PartialEq {
+        let segments = self.segments(base);
+        let output = || segments.iter().flat_map(|s| s.iter());
+        let len = segments.iter().map(|s| s.len()).sum::<usize>();
+        let prefix = output().zip(base).take_while(|(l,r)| l == r).count();
+        let n = core::cmp::min(len,base.len()) - prefix;
+        let suffix = output().rev().zip(base.iter().rev()).take(n).take_while(|(l,r)| l == r).count();
+        let mut delta = VecDelta::new();
+        if prefix + suffix < core::cmp::max(len,base.len()) {
+            let data : Vec<T> = output().skip(prefix).take(len - prefix - suffix).cloned().collect();
+            delta.try_push(prefix .. base.len() - suffix, &data).unwrap();
+        }
+        delta
+    }
+
+    /// Rebase this delta so that it can be applied after `other`,
+    /// where both were computed against the same original sequence.
+    /// This is the _transform_ operation underlying _operational
+    /// transformation_, and allows concurrent edits to be combined.
+    /// Rewrites which are disjoint from those of `other` are simply
+    /// shifted.  Otherwise, the following policy is used to break
+    /// ties:
+    ///
+    /// * When both insert at the same position, the insertion from
+    ///   `other` comes first.
+    ///
+    /// * When a rewrite from `other` overlaps a rewrite from this delta
+    ///   (or inserts strictly within it), then this rewrite wins.  That
+    ///   is, the rebased rewrite also replaces any data written by
+    ///   `other` in the overlapping region.  Rebased rewrites which
+    ///   then overlap are combined.
+    ///
+    /// For example, consider the original sequence `"abcdef"`, this
+    /// delta `(1;1;"X")` and `other` delta `(3;2;"YZW")`.  Then,
+    /// applying `other` gives `"abcYZWf"`, and applying the rebased
+    /// delta `(1;1;"X")` gives `"aXcYZWf"`.
+    pub fn rebase(&self, other: &VecDelta<T>) -> VecDelta<T> {
+        let theirs : Vec<(Region,usize)> = other.iter_source().map(|(r,d)| (r,d.len())).collect();
+        let mut hunks : Vec<(Region,Vec<T>)> = Vec::new();
+        for (r,data) in self.iter_source() {
+            let start = rebase_position(&theirs, r.offset, false);
+            let end = if r.length == 0 { start } else {
+                rebase_position(&theirs, r.offset + r.length, true)
+            };
+            match hunks.last_mut() {
+                Some((pr,pd)) if start < pr.offset + pr.length => {
+                    pr.length = end.max(pr.offset + pr.length) - pr.offset;
+                    pd.extend_from_slice(data);
+                }
+                _ => hunks.push((Region::new(start,end - start),data.to_vec()))
+            }
+        }
+        VecDelta::from_source(hunks.iter().map(|(r,d)| (*r,d.as_slice())))
+    }
+}
+
+/// Check whether two regions (in terms of the same sequence) conflict
+/// (see `VecDelta::conflicts_with()`).
+fn conflicting(r1: &Region, r2: &Region) -> bool {
+    let (e1,e2) = (r1.offset + r1.length, r2.offset + r2.length);
+    (r1.offset < e2 && r2.offset < e1) || (r1.offset == r2.offset && (r1.length == 0 || r2.length == 0))
+}
+
+/// Map the start (or end) of a region in some original sequence into
+/// the sequence obtained by applying a set of hunks (given in source
+/// coordinates along with the length of their data).  This follows
+/// the tie-breaking policy of `VecDelta::rebase()`.
+fn rebase_position(hunks: &[(Region,usize)], pos: usize, end: bool) -> usize {
+    let mut shift = 0isize;
+    for (r,n) in hunks {
+        let (start,finish) = (r.offset,r.offset + r.length);
+        if start < pos && pos < finish {
+            // Position strictly within hunk, so extend to cover it.
+            let base = (start as isize + shift) as usize;
+            return if end { base + n } else { base };
+        }
+        let before = if r.length > 0 { finish <= pos } else if end { start < pos } else { start <= pos };
+        if !before { break; }
+        shift += *n as isize - r.length as isize;
+    }
+    (pos as isize + shift) as usize
+}
+
+/// Describes part of an intermediate sequence as either a block of
+/// elements retained from the original sequence (i.e. its start and
+/// length), or new data.
+enum Piece<'a,T> {
+    Kept(usize,usize),
+    New(&'a [T])
+}
+
+impl<T> Piece<'_,T> {
+    fn len(&self) -> usize {
+        match self {
+            Piece::Kept(_,n) => *n,
+            Piece::New(d) => d.len()
+        }
+    }
+}
+
+/// Append a piece, ignoring empty pieces.
+fn push_piece<'a,T>(pieces: &mut Vec<Piece<'a,T>>, piece: Piece<'a,T>) {
+    if piece.len() > 0 { pieces.push(piece); }
+}
+
+/// A cursor over a sequence described by a list of pieces, followed
+/// by an unbounded block of elements retained from the original
+/// sequence (starting at `tail`).
+struct Cursor<'b,'a,T> {
+    pieces: &'b [Piece<'a,T>],
+    index: usize,
+    offset: usize,
+    tail: usize
+}
+
+impl<'a,T> Cursor<'_,'a,T> {
+    /// Move over the next `n` elements, appending them onto `out` (if
+    /// provided).
+    fn take(&mut self, mut n: usize, mut out: Option<&mut Vec<Piece<'a,T>>>) {
+        while n > 0 {
+            let piece = match self.pieces.get(self.index) {
+                Some(p) => p,
+                None => {
+                    if let Some(out) = out { push_piece(out, Piece::Kept(self.tail,n)); }
+                    self.tail += n;
+                    return;
+                }
+            };
+            let k = n.min(piece.len() - self.offset);
+            if let Some(out) = out.as_deref_mut() {
+                match piece {
+                    Piece::Kept(s,_) => push_piece(out, Piece::Kept(s + self.offset,k)),
+                    Piece::New(d) => push_piece(out, Piece::New(&d[self.offset..self.offset+k]))
+                }
+            }
+            self.offset += k;
+            n -= k;
+            if self.offset == piece.len() {
+                self.index += 1;
+                self.offset = 0;
+            }
+        }
+    }
+
+    /// Append all remaining pieces onto `out`, returning the start of
+    /// the unbounded tail.
+    fn finish(mut self, out: &mut Vec<Piece<'a,T>>) -> usize {
+        let n : usize = self.pieces[self.index..].iter().map(|p| p.len()).sum();
+        self.take(n - self.offset, Some(out));
+        self.tail
+    }
 }
 
 // ===================================================================
@@ -112,7 +1122,14 @@ impl<T:Clone> VecDelta<T> {
 
 #[cfg(test)]
 mod vecdelta_tests {
-    use super::{VecDelta};
+    use alloc::collections::VecDeque;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::ops::Range;
+    use crate::diff::Diff;
+    use crate::diff::{MutableSequence,Transform,TryTransform,VecRewrite,merge};
+    use crate::util::Region;
+    use super::{DeltaError,MappedPos,VecDelta};
 
     #[test]
     pub fn test_vecdelta_01() {
@@ -155,4 +1172,695 @@ mod vecdelta_tests {
         unsafe { vd.push_raw(0..2, &[4,5]); }
         unsafe { vd.push_raw(1..3, &[6,7]); }
     }
+
+    #[test]
+    pub fn test_vecdelta_06() {
+        // Removal followed by replacement
+        let mut vec = vec![1,2,3,4,5,6,7,8];
+        let mut vd = VecDelta::<usize>::new();
+        unsafe { vd.push_raw(0..5, &[]); }
+        unsafe { vd.push_raw(1..2, &[9]); }
+        vd.transform(&mut vec);
+        assert_eq!(vec,vec![6,9,8]);
+    }
+
+    #[test]
+    pub fn test_vecdelta_07() {
+        // Refine lines into characters
+        let before = vec!["hello world","abc","foo"];
+        let after = vec!["hello there world","abc","bar","baz"];
+        let vd = before.diff(&after);
+        let refined = vd.refine(&before,|lines| lines.iter().flat_map(|l| l.chars()).collect());
+        assert_eq!(refined.len(),2);
+        assert_eq!(refined[0].len(),1);
+        assert_eq!(refined[0].get(0).unwrap().data(),&['t','h','e','r','e',' ']);
+        let mut chars : Vec<char> = "foo".chars().collect();
+        refined[1].transform(&mut chars);
+        assert_eq!(chars,"barbaz".chars().collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn test_vecdelta_08() {
+        let mut d1 = VecDelta::new();
+        unsafe { d1.push_raw(1..2, &['x','y']); }
+        let mut d2 = VecDelta::new();
+        unsafe { d2.push_raw(2..4, &['z']); }
+        let d3 = d1.compose(&d2);
+        assert_eq!(d3.len(),1);
+        let mut vec = vec!['a','b','c','d','e'];
+        d3.transform(&mut vec);
+        assert_eq!(vec,['a','x','z','d','e']);
+    }
+
+    #[test]
+    pub fn test_vecdelta_09() {
+        // Composition for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..12).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                for c in &seqs {
+                    let d = a.diff(b).compose(&b.diff(c));
+                    let mut vec = a.clone();
+                    d.transform(&mut vec);
+                    assert_eq!(&vec,c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_10() {
+        // Composition with empty deltas
+        let d = [1,2,3].diff(&[2,3,4]);
+        assert_eq!(d.compose(&VecDelta::new()),d);
+        assert_eq!(VecDelta::new().compose(&d),d);
+    }
+
+    #[test]
+    pub fn test_vecdelta_11() {
+        // Disjoint rewrites
+        check_rebase("abcdef","aXcdef","abcYZWf","aXcYZWf");
+        check_rebase("abcdef","abcYZWf","aXcdef","aXcYZWf");
+        // Insertions at the same position
+        check_rebase("ab","aXb","aYb","aYXb");
+        // Overlapping rewrites
+        check_rebase("abcdef","aXYef","abZf","aXYf");
+        check_rebase("abcdef","aXf","abYZef","aXf");
+        check_rebase("abcdef","abcdXf","aYf","aXf");
+    }
+
+    #[test]
+    pub fn test_vecdelta_12() {
+        // Rebasing always produces a well-formed delta
+        let seqs : Vec<Vec<usize>> = (0..10).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                for c in &seqs {
+                    let (d1,d2) = (a.diff(b),a.diff(c));
+                    let mut vec = a.clone();
+                    d2.transform(&mut vec);
+                    d1.rebase(&d2).transform(&mut vec);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_13() {
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(1..3, &['x','y','z']); }
+        unsafe { vd.push_raw(7..8, &[]); }
+        // abcdefgh => axyzdefh
+        assert_eq!(vd.map_position(0),MappedPos::Preserved(0));
+        assert_eq!(vd.map_position(1),MappedPos::Deleted(1));
+        assert_eq!(vd.map_position(2),MappedPos::Deleted(1));
+        assert_eq!(vd.map_position(3),MappedPos::Shifted(4));
+        assert_eq!(vd.map_position(5),MappedPos::Shifted(6));
+        assert_eq!(vd.map_position(6),MappedPos::Deleted(7));
+        assert_eq!(vd.map_position(7),MappedPos::Preserved(7));
+        //
+        assert_eq!(vd.unmap_position(0),MappedPos::Preserved(0));
+        assert_eq!(vd.unmap_position(3),MappedPos::Deleted(1));
+        assert_eq!(vd.unmap_position(4),MappedPos::Shifted(3));
+        assert_eq!(vd.unmap_position(6),MappedPos::Shifted(5));
+        assert_eq!(vd.unmap_position(7),MappedPos::Preserved(7));
+    }
+
+    #[test]
+    pub fn test_vecdelta_14() {
+        // Unaffected elements round trip
+        let (a,b) = ([1,2,3,4,5,6,7,8],[0,2,3,9,9,6,8]);
+        let vd = a.diff(&b);
+        for (i,x) in a.iter().enumerate() {
+            match vd.map_position(i) {
+                MappedPos::Deleted(_) => {}
+                m => {
+                    assert_eq!(*x,b[m.index()]);
+                    assert_eq!(vd.unmap_position(m.index()).index(),i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_15() {
+        // Unsorted and adjacent rewrites
+        let rws = vec![
+            VecRewrite::new(Region::new(4,1),vec!['y']),
+            VecRewrite::new(Region::new(1,1),vec!['x']),
+            VecRewrite::new(Region::new(2,0),vec!['z']),
+            VecRewrite::new(Region::new(3,0),vec![]),
+        ];
+        let vd = VecDelta::try_from(rws).unwrap();
+        assert_eq!(vd.len(),2);
+        let mut vec = vec!['a','b','c','d','e'];
+        vd.transform(&mut vec);
+        assert_eq!(vec,['a','x','z','c','d','y']);
+    }
+
+    #[test]
+    pub fn test_vecdelta_16() {
+        // Insertions at same position, and overlapping rewrites
+        let rws = vec![
+            VecRewrite::new(Region::new(1,0),vec![1]),
+            VecRewrite::new(Region::new(1,0),vec![2]),
+        ];
+        let mut vec = vec![0,0];
+        VecDelta::canonicalize(rws).unwrap().transform(&mut vec);
+        assert_eq!(vec,[0,1,2,0]);
+        let rws = vec![
+            VecRewrite::new(Region::new(1,2),vec![1]),
+            VecRewrite::new(Region::new(2,0),vec![2]),
+        ];
+        assert_eq!(VecDelta::canonicalize(rws),Err(DeltaError::Overlapping(Region::new(1,2),Region::new(2,0))));
+    }
+
+    #[test]
+    pub fn test_vecdelta_17() {
+        // Overlap
+        let vd = check_insert(&[(1..2,"xy")],2..4,"z","axzdef");
+        assert_eq!(vd.len(),1);
+        assert_eq!(vd.get(0).unwrap().region(),Region::new(1,2));
+        // Disjoint (before and after)
+        assert_eq!(check_insert(&[(3..4,"x")],0..1,"y","ybcxef").len(),2);
+        assert_eq!(check_insert(&[(1..2,"x")],4..5,"y","axcdyf").len(),2);
+    }
+
+    #[test]
+    pub fn test_vecdelta_18() {
+        // Adjacency
+        assert_eq!(check_insert(&[(1..2,"x")],2..3,"y","axydef").len(),1);
+        assert_eq!(check_insert(&[(2..3,"x")],1..2,"y","ayxdef").len(),1);
+        assert_eq!(check_insert(&[(1..2,"x")],2..2,"y","axycdef").len(),1);
+    }
+
+    #[test]
+    pub fn test_vecdelta_19() {
+        // Containment
+        assert_eq!(check_insert(&[(2..3,"x")],1..5,"y","ayf").len(),1);
+        assert_eq!(check_insert(&[(1..4,"xyz")],2..3,"w","axwzef").len(),1);
+        // Merging multiple rewrites
+        assert_eq!(check_insert(&[(1..2,"x"),(3..4,"y")],1..4,"","aef").len(),1);
+        assert_eq!(check_insert(&[(1..2,"x"),(4..5,"y")],2..4,"","axyf").len(),1);
+        // Undoing an existing rewrite
+        assert_eq!(check_insert(&[(1..2,"x")],1..2,"b","abcdef").len(),1);
+    }
+
+    #[test]
+    pub fn test_vecdelta_20() {
+        let mut vd = VecDelta::new();
+        assert_eq!(vd.try_push(2..3,&[1,2]),Ok(()));
+        // Overlaps replacement data in target sequence
+        assert_eq!(vd.try_push(3..5,&[3]),Err(DeltaError::Overlapping(Region::new(2,2),Region::new(3,2))));
+        // Precedes existing rewrite
+        assert_eq!(vd.try_push(0..1,&[3]),Err(DeltaError::Unordered(Region::new(2,2),Region::new(0,1))));
+        assert_eq!(vd.len(),1);
+        // Adjacent is fine
+        assert_eq!(vd.try_push(4..5,&[3]),Ok(()));
+        let mut vec = vec![0,0,0,0,0,0];
+        vd.transform(&mut vec);
+        assert_eq!(vec,[0,0,1,2,3,0,0]);
+    }
+
+    #[test]
+    pub fn test_vecdelta_21() {
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(1..3, &['x','y','z']); }
+        unsafe { vd.push_raw(5..6, &[]); }
+        let source = vd.to_source_coords();
+        assert_eq!(source[0].region(),Region::new(1,2));
+        assert_eq!(source[1].region(),Region::new(4,1));
+        assert_eq!(VecDelta::canonicalize(source),Ok(vd.clone()));
+        let target = vd.to_target_coords();
+        assert_eq!(target[1].region(),Region::new(5,1));
+        assert_eq!(VecDelta::from_target_coords(target),Ok(vd));
+    }
+
+    #[test]
+    pub fn test_vecdelta_22() {
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(1..4, &['x','y']); }
+        let (l,r) = vd.split_at(2);
+        assert_eq!(l.get(0).unwrap().region(),Region::new(1,3));
+        assert_eq!(l.get(0).unwrap().data(),['x']);
+        assert_eq!(r.get(0).unwrap().region(),Region::new(0,0));
+        assert_eq!(r.get(0).unwrap().data(),['y']);
+        assert_eq!(vd.source_position(2),4);
+        // Splitting at the start
+        let (l,r) = vd.split_at(0);
+        assert!(l.is_empty());
+        assert_eq!(r,vd);
+    }
+
+    #[test]
+    pub fn test_vecdelta_23() {
+        // Splitting for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..12).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let vd = a.diff(b);
+                for i in 0..=b.len() {
+                    let (l,r) = vd.split_at(i);
+                    let s = vd.source_position(i);
+                    let (mut lv, mut rv) = (a[..s].to_vec(),a[s..].to_vec());
+                    l.transform(&mut lv);
+                    r.transform(&mut rv);
+                    assert_eq!(lv,&b[..i]);
+                    lv.extend(rv);
+                    assert_eq!(&lv,b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_24() {
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(1..3, &['x']); }
+        unsafe { vd.push_raw(4..7, &['y','z']); }
+        let r = vd.restrict_source(2..6);
+        assert_eq!(r.len(),2);
+        assert_eq!(r.get(0).unwrap().region(),Region::new(0,1));
+        assert_eq!(r.get(0).unwrap().data(),[]);
+        assert_eq!(r.get(1).unwrap().region(),Region::new(2,1));
+        assert_eq!(r.get(1).unwrap().data(),['y','z']);
+        assert!(vd.restrict_source(3..4).is_empty());
+        // Insertions are retained for an empty range
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(2..2, &['x']); }
+        assert_eq!(vd.restrict_source(2..2).get(0).unwrap().data(),['x']);
+        assert!(vd.restrict_source(1..2).is_empty());
+    }
+
+    #[test]
+    pub fn test_vecdelta_25() {
+        let base : Vec<char> = "abcdef".chars().collect();
+        let d1 = base.diff(&"aXcdef".chars().collect::<Vec<_>>());
+        let d2 = base.diff(&"abcdYf".chars().collect::<Vec<_>>());
+        let d3 = base.diff(&"aZdef".chars().collect::<Vec<_>>());
+        let d4 = base.diff(&"Wabcdef".chars().collect::<Vec<_>>());
+        assert!(d1.conflicts_with(&d2).is_empty());
+        assert_eq!(d1.conflicts_with(&d3),[(Region::new(1,1),Region::new(1,2))]);
+        assert_eq!(d3.conflicts_with(&d1),[(Region::new(1,2),Region::new(1,1))]);
+        assert!(d4.conflicts_with(&d1).is_empty());
+        assert_eq!(d4.conflicts_with(&d4),[(Region::new(0,0),Region::new(0,0))]);
+    }
+
+    #[test]
+    pub fn test_vecdelta_26() {
+        // Deltas without conflicts can always be merged
+        let seqs : Vec<Vec<usize>> = (0..10).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                for c in &seqs {
+                    let (d1,d2) = (a.diff(b),a.diff(c));
+                    let conflicts = d1.conflicts_with(&d2);
+                    assert_eq!(conflicts.is_empty(),d2.conflicts_with(&d1).is_empty());
+                    if conflicts.is_empty() {
+                        assert!(merge(a,&d1,&d2).is_ok());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_27() {
+        // Apply agrees with transform
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let vd = a.diff(b);
+                let out = vd.apply(a);
+                assert_eq!(&out,b);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_28() {
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(1..2, &[7,8,9]); }
+        unsafe { vd.push_raw(5..6, &[]); }
+        // Within bounds
+        let mut vec = vec![1,2,3,4];
+        assert_eq!(vec.try_transform(&vd),Ok(()));
+        assert_eq!(vec,[1,7,8,9,3]);
+        // Out of bounds (after first rewrite is applied)
+        let mut vec = vec![1,2,3];
+        let err = DeltaError::RegionOutOfBounds{region: Region::new(5,1), len: 5};
+        assert_eq!(vec.try_transform(&vd),Err(err));
+        assert_eq!(vec,[1,2,3]);
+        // Out of bounds (empty)
+        let mut vec = Vec::new();
+        assert!(vd.try_transform(&mut vec).is_err());
+    }
+
+    #[test]
+    pub fn test_vecdelta_29() {
+        let mut vec = vec![1,2,3];
+        vec.transform(&[1,2,3].diff(&[3,2,1]));
+        assert_eq!(vec,[3,2,1]);
+    }
+
+    #[test]
+    pub fn test_vecdelta_30() {
+        // Transforming a VecDeque (including front and back)
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let mut deque : VecDeque<usize> = a.iter().cloned().collect();
+                deque.transform(&a.diff(b));
+                assert!(deque.iter().eq(b.iter()));
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_31() {
+        let mut arr = [1,2,3,4];
+        assert_eq!(arr.try_transform(&[1,2,3,4].diff(&[1,5,6,4])),Ok(()));
+        assert_eq!(arr,[1,5,6,4]);
+        // Length changing
+        let err = DeltaError::LengthMismatch{region: Region::new(1,0), len: 1};
+        assert_eq!(arr.try_transform(&[1,5,6,4].diff(&[1,7,5,6,4])),Err(err));
+        // Out of bounds
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(3..5, &[0,0]); }
+        let err = DeltaError::RegionOutOfBounds{region: Region::new(3,2), len: 4};
+        assert_eq!(arr.try_transform(&vd),Err(err));
+        assert_eq!(arr,[1,5,6,4]);
+    }
+
+    #[test]
+    pub fn test_vecdelta_32() {
+        // Elements need not be cloneable
+        #[derive(Debug,PartialEq)]
+        struct Node(usize);
+        let mut vd = VecDelta::new();
+        vd.regions.push((Region::new(1,1),Region::new(0,2)));
+        vd.data.extend([Node(7),Node(8)]);
+        vd.regions.push((Region::new(4,1),Region::new(2,0)));
+        let mut vec = vec![Node(1),Node(2),Node(3),Node(4)];
+        vd.into_transform(&mut vec);
+        assert_eq!(vec,[Node(1),Node(7),Node(8),Node(3)]);
+    }
+
+    #[test]
+    pub fn test_vecdelta_33() {
+        // Agrees with transform
+        let seqs : Vec<Vec<usize>> = (0..16).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let mut vec = a.clone();
+                a.diff(b).into_transform(&mut vec);
+                assert_eq!(&vec,b);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_vecdelta_34() {
+        let mut vec = vec![1,2];
+        [1,2,3].diff(&[1,2]).into_transform(&mut vec);
+    }
+
+    #[test]
+    pub fn test_vecdelta_35() {
+        // Apply delta for subslice to whole
+        let mut vec = vec![0,1,2,3,4,5,6,7];
+        let mut vd = vec[2..6].diff(&[2,9,4,5,8]);
+        assert_eq!(vd.shift(2),Ok(()));
+        vd.transform(&mut vec);
+        assert_eq!(vec,[0,1,2,9,4,5,8,6,7]);
+        // And back again
+        assert_eq!(vd.shift(-2),Ok(()));
+        assert_eq!(vd,[2,3,4,5].diff(&[2,9,4,5,8]));
+    }
+
+    #[test]
+    pub fn test_vecdelta_36() {
+        let mut vd = [1,2,3].diff(&[1,4,3]);
+        let err = DeltaError::ShiftOutOfRange{region: Region::new(1,1), offset: -2};
+        assert_eq!(vd.shift(-2),Err(err));
+        assert!(vd.shift(isize::MAX).is_ok());
+        assert!(vd.shift(isize::MAX).is_err());
+        assert_eq!(vd.shift(-1),Ok(()));
+        assert_eq!(VecDelta::<usize>::new().shift(-1),Ok(()));
+    }
+
+    #[test]
+    pub fn test_vecdelta_37() {
+        // Concatenation for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..8).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a1 in &seqs {
+            for a2 in &seqs {
+                let da = a1.diff(a2);
+                for b1 in &seqs {
+                    for b2 in &seqs {
+                        let vd = VecDelta::concat(&da,a2.len(),&b1.diff(b2));
+                        let mut vec = [a1.as_slice(),b1].concat();
+                        vd.transform(&mut vec);
+                        assert_eq!(vec,[a2.as_slice(),b2].concat());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_38() {
+        // Rewrites meeting at the boundary are merged
+        let vd = VecDelta::concat(&[1,2].diff(&[1,3]),2,&[4,5].diff(&[6,5]));
+        assert_eq!(vd.len(),1);
+        assert_eq!(vd,[1,2,4,5].diff(&[1,3,6,5]));
+    }
+
+    #[test]
+    pub fn test_vecdelta_39() {
+        // Syntactically different but equivalent deltas
+        let base = [1,2,2,3];
+        let mut d1 = VecDelta::new();
+        d1.try_push(1..2,&[]).unwrap();
+        let mut d2 = VecDelta::new();
+        d2.try_push(2..3,&[]).unwrap();
+        assert_ne!(d1,d2);
+        assert!(d1.equivalent_on(&d2,&base));
+        assert_eq!(d1.normalize(&base),d2.normalize(&base));
+        // No-op rewrites normalize away
+        let mut d3 = VecDelta::new();
+        d3.try_push(0..2,&[1,2]).unwrap();
+        assert!(d3.equivalent_on(&VecDelta::new(),&base));
+        assert!(d3.normalize(&base).is_empty());
+        assert!(!d1.equivalent_on(&d3,&base));
+    }
+
+    #[test]
+    pub fn test_vecdelta_40() {
+        // Normal forms agree with equivalence for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..10).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let d1 = a.diff(b);
+                let n1 = d1.normalize(a);
+                assert!(n1.len() <= 1);
+                assert_eq!(n1.apply(a),*b);
+                assert!(d1.equivalent_on(&n1,a));
+                for c in &seqs {
+                    let d2 = a.diff(c);
+                    assert_eq!(d1.equivalent_on(&d2,a),b == c);
+                    assert_eq!(n1 == d2.normalize(a),b == c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_41() {
+        // Transforming a user-defined sequence
+        struct Gap { items: Vec<usize>, splices: usize }
+        impl MutableSequence for Gap {
+            type Item = usize;
+            fn len(&self) -> usize { self.items.len() }
+            fn splice(&mut self, range: Range<usize>, items: &[usize]) {
+                self.items.splice(range, items.iter().cloned());
+                self.splices += 1;
+            }
+        }
+        let seqs : Vec<Vec<usize>> = (0..8).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let d = a.diff(b);
+                let mut gap = Gap{items: a.clone(), splices: 0};
+                gap.transform(&d);
+                assert_eq!(&gap.items,b);
+                assert_eq!(gap.splices,d.len());
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_42() {
+        let mut d = VecDelta::new();
+        d.try_push(1..2,&['x','y']).unwrap();
+        d.try_push(5..6,&[]).unwrap();
+        d.try_push(6..6,&['z']).unwrap();
+        let covering : Vec<_> = (0..8).map(|i| d.rewrite_at(i).map(|rw| rw.region().offset)).collect();
+        assert_eq!(covering,[None,Some(1),Some(1),None,None,None,Some(6),None]);
+        assert!(VecDelta::<char>::new().rewrite_at(0).is_none());
+    }
+
+    #[test]
+    pub fn test_vecdelta_43() {
+        // Agrees with a linear scan
+        let seqs : Vec<Vec<usize>> = (0..12).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let d = a.diff(b);
+                for i in 0..=b.len() {
+                    let expected = d.iter().find(|rw| rw.region().offset <= i && i < rw.region().offset + rw.data().len());
+                    assert_eq!(d.rewrite_at(i),expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_44() {
+        let mut d = VecDelta::with_capacity(2,3);
+        d.try_push(1..2,&['x','y']).unwrap();
+        d.try_push(4..4,&['z']).unwrap();
+        assert_eq!(d.apply(&['a','b','c']),['a','x','y','c','z']);
+        assert_eq!(VecDelta::<char>::with_capacity(0,0),VecDelta::new());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    pub fn test_vecdelta_45() {
+        let d = [1,2,3,4].diff(&[1,5,3]);
+        let json = serde_json::to_string(&d).unwrap();
+        assert_eq!(json,r#"[{"region":{"offset":1,"length":1},"data":[5]},{"region":{"offset":3,"length":1},"data":[]}]"#);
+        assert_eq!(serde_json::from_str::<VecDelta<i32>>(&json).unwrap(),d);
+        // Malformed deltas are rejected
+        let json = r#"[{"region":{"offset":3,"length":1},"data":[]},{"region":{"offset":1,"length":1},"data":[5]}]"#;
+        assert!(serde_json::from_str::<VecDelta<i32>>(json).is_err());
+    }
+
+    #[test]
+    pub fn test_vecdelta_46() {
+        let before = [0,1,2,3,4,5,6,7];
+        let d = before.diff(&[0,9,2,3,4,6,7]);
+        let hunks = d.hunks(before.len(),1);
+        assert_eq!(hunks.len(),2);
+        assert_eq!((hunks[0].before(),hunks[0].after()),(Region::new(0,3),Region::new(0,3)));
+        assert_eq!((hunks[1].before(),hunks[1].after()),(Region::new(4,3),Region::new(4,2)));
+        assert_eq!(hunks[1].rewrites(),[(Region::new(5,1),&[][..])]);
+        // Overlapping contexts are merged
+        let hunks = d.hunks(before.len(),2);
+        assert_eq!(hunks.len(),1);
+        assert_eq!((hunks[0].before(),hunks[0].after()),(Region::new(0,8),Region::new(0,7)));
+        assert_eq!(hunks[0].rewrites().len(),2);
+        // Touching contexts are merged
+        assert_eq!(before.diff(&[0,9,2,3,9,5,6,7]).hunks(before.len(),1).len(),1);
+        assert!(VecDelta::<i32>::new().hunks(before.len(),3).is_empty());
+    }
+
+    #[test]
+    pub fn test_vecdelta_47() {
+        // Hunks cover the rewrites they contain, and their regions
+        // agree with the sequences before and after.
+        let seqs : Vec<Vec<usize>> = (0..12).map(|i| (0..i).map(|j| (j*i+3)%5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let d = a.diff(b);
+                for context in 0..3 {
+                    let hunks = d.hunks(a.len(),context);
+                    assert_eq!(hunks.iter().map(|h| h.rewrites().len()).sum::<usize>(),d.len());
+                    for h in &hunks {
+                        let (before,after) = (h.before(),h.after());
+                        let (mut out, mut cursor) = (Vec::new(),before.offset);
+                        for (r,data) in h.rewrites() {
+                            out.extend_from_slice(&a[cursor..r.offset]);
+                            out.extend_from_slice(data);
+                            cursor = r.offset + r.length;
+                        }
+                        out.extend_from_slice(&a[cursor..before.offset + before.length]);
+                        assert_eq!(out,&b[after.as_range()]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_vecdelta_48() {
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(1..3, &['x','y','z']); }
+        unsafe { vd.push_raw(6..7, &[]); }
+        let r = vd.restrict(2..7);
+        assert_eq!(r.len(),2);
+        assert_eq!(r.get(0).unwrap().region(),Region::new(0,0));
+        assert_eq!(r.get(0).unwrap().data(),['y','z']);
+        assert_eq!(r.to_source_coords()[1].region(),Region::new(2,1));
+        assert_eq!(r.get(1).unwrap().data(),[]);
+        assert_eq!((vd.source_position(2),vd.source_position(7)),(3,7));
+        assert_eq!(vd.restrict(2..6).len(),1);
+        // Empty range
+        assert!(vd.restrict(2..2).is_empty());
+        assert!(vd.restrict(6..6).is_empty());
+        // Pure insertion at the boundary
+        let mut vd = VecDelta::new();
+        unsafe { vd.push_raw(2..2, &['x']); }
+        assert!(vd.restrict(0..2).is_empty());
+        assert_eq!(vd.restrict(2..4).get(0).unwrap().data(),['x']);
+        assert_eq!(vd.restrict(2..4).get(0).unwrap().region(),Region::new(0,0));
+    }
+
+    #[test]
+    pub fn test_vecdelta_49() {
+        // Restriction for a range of inputs
+        let seqs : Vec<Vec<usize>> = (0..12).map(|i| (0..i).map(|j| (j * i + 3) % 5).collect()).collect();
+        for a in &seqs {
+            for b in &seqs {
+                let vd = a.diff(b);
+                for i in 0..=b.len() {
+                    for j in i..=b.len() {
+                        let (s,e) = (vd.source_position(i),vd.source_position(j));
+                        assert_eq!(vd.restrict(i..j).apply(&a[s..e]),&b[i..j]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_vecdelta_50() {
+        // Delta for first half extends beyond its length
+        VecDelta::concat(&[1,2].diff(&[1,3,4]),2,&[5].diff(&[6]));
+    }
+
+    fn check_insert(rewrites: &[(core::ops::Range<usize>,&str)], range: core::ops::Range<usize>, data: &str, expected: &str) -> VecDelta<char> {
+        let mut vd = VecDelta::new();
+        for (r,d) in rewrites {
+            let d : Vec<char> = d.chars().collect();
+            unsafe { vd.push_raw(r.clone(),&d); }
+        }
+        let data : Vec<char> = data.chars().collect();
+        vd.insert(range,&data);
+        let mut vec : Vec<char> = "abcdef".chars().collect();
+        vd.transform(&mut vec);
+        assert_eq!(vec,expected.chars().collect::<Vec<_>>());
+        vd
+    }
+
+    fn check_rebase(base: &str, left: &str, right: &str, expected: &str) {
+        let base : Vec<char> = base.chars().collect();
+        let left : Vec<char> = left.chars().collect();
+        let right : Vec<char> = right.chars().collect();
+        let mut vec = right.clone();
+        base.diff(&left).rebase(&base.diff(&right)).transform(&mut vec);
+        assert_eq!(vec,expected.chars().collect::<Vec<_>>());
+    }
 }
@@ -0,0 +1,148 @@
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use super::{Diff,Transform};
+
+// ===================================================================
+// Option
+// ===================================================================
+
+/// A delta between two optional values.  When both are present, the
+/// change is described by the delta of the values themselves (`D`).
+/// Otherwise, the value is either removed or set in full.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum OptionDelta<T,D> {
+    /// The value becomes absent.
+    Clear,
+    /// The value becomes present (or is replaced) with the given value.
+    Set(T),
+    /// The value remains present, and is updated by the given delta.
+    Update(D)
+}
+
+/// Diffing of optional values yields a nested delta when both are
+/// present.  For example:
+///
+/// ```
+/// use delta_inc::diff::{Diff,OptionDelta,Transform};
+///
+/// let before = Some("hello".to_string());
+/// let after = Some("hello world".to_string());
+/// let delta = before.diff(&after);
+/// assert!(matches!(delta,OptionDelta::Update(_)));
+/// let mut value = before.clone();
+/// value.transform(&delta);
+/// assert_eq!(value,after);
+/// assert_eq!(before.diff(&None),OptionDelta::Clear);
+/// ```
+impl<T:Diff+Clone> Diff for Option<T> {
+    type Delta = OptionDelta<T,T::Delta>;
+
+    fn diff(&self, other: &Option<T>) -> Self::Delta {
+        match (self,other) {
+            (Some(a),Some(b)) => OptionDelta::Update(a.diff(b)),
+            (None,Some(b)) => OptionDelta::Set(b.clone()),
+            (_,None) => OptionDelta::Clear
+        }
+    }
+}
+
+/// Applying an `OptionDelta` will `panic` if it updates a value which
+/// is absent.
+impl<T:Transform+Clone> Transform for Option<T> {
+    type Delta = OptionDelta<T,T::Delta>;
+
+    fn transform(&mut self, d: &Self::Delta) {
+        match d {
+            OptionDelta::Clear => *self = None,
+            OptionDelta::Set(v) => *self = Some(v.clone()),
+            OptionDelta::Update(d) => self.as_mut().expect("cannot update absent value").transform(d)
+        }
+    }
+}
+
+// ===================================================================
+// Pointers
+// ===================================================================
+
+/// Boxed values are diffed by their contents.  Note that `Box<T>` does
+/// not implement `Transform` in general, since this would conflict with
+/// the implementation for every `MutableSequence` (as `Box` is a
+/// _fundamental_ type, other crates may implement `MutableSequence` for
+/// it).  Instead, a delta can be applied to the contents directly
+/// (e.g. `(*b).transform(&d)`).
+impl<T:Diff+?Sized> Diff for Box<T> {
+    type Delta = T::Delta;
+
+    fn diff(&self, other: &Box<T>) -> T::Delta { (**self).diff(other) }
+}
+
+/// Shared values are diffed by their contents.
+impl<T:Diff+?Sized> Diff for Rc<T> {
+    type Delta = T::Delta;
+
+    fn diff(&self, other: &Rc<T>) -> T::Delta { (**self).diff(other) }
+}
+
+/// Transforming a shared value first clones it, if it is not unique
+/// (see `Rc::make_mut()`), such that other references are unaffected.
+impl<T:Transform+Clone> Transform for Rc<T> {
+    type Delta = T::Delta;
+
+    fn transform(&mut self, d: &T::Delta) { Rc::make_mut(self).transform(d) }
+}
+
+/// Shared values are diffed by their contents.
+impl<T:Diff+?Sized> Diff for Arc<T> {
+    type Delta = T::Delta;
+
+    fn diff(&self, other: &Arc<T>) -> T::Delta { (**self).diff(other) }
+}
+
+/// Transforming a shared value first clones it, if it is not unique
+/// (see `Arc::make_mut()`), such that other references are unaffected.
+impl<T:Transform+Clone> Transform for Arc<T> {
+    type Delta = T::Delta;
+
+    fn transform(&mut self, d: &T::Delta) { Arc::make_mut(self).transform(d) }
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod wrapper_tests {
+    use alloc::boxed::Box;
+    use alloc::collections::BTreeSet;
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use crate::diff::{Diff,OptionDelta,Transform};
+
+    #[test]
+    fn wrapper_test_01() {
+        let sets : Vec<Option<BTreeSet<usize>>> = (0..8).map(|i| (i%3 != 0).then(|| (0..i).map(|j| (j*i+3)%5).collect())).collect();
+        for a in &sets {
+            for b in &sets {
+                let delta = a.diff(b);
+                let mut v = a.clone();
+                v.transform(&delta);
+                assert_eq!(v,*b);
+            }
+        }
+        assert_eq!(None::<BTreeSet<usize>>.diff(&None),OptionDelta::Clear);
+    }
+
+    #[test]
+    fn wrapper_test_02() {
+        let (a,b) = (Box::new(BTreeSet::from([1,2])),Box::new(BTreeSet::from([2,3])));
+        let mut v = a.clone();
+        (*v).transform(&a.diff(&b));
+        assert_eq!(v,b);
+        // Other references to a shared value are unaffected
+        let a = Rc::new(BTreeSet::from([1,2]));
+        let mut v = a.clone();
+        v.transform(&a.diff(&Rc::new(BTreeSet::from([3]))));
+        assert_eq!((&*a,&*v),(&BTreeSet::from([1,2]),&BTreeSet::from([3])));
+    }
+}
@@ -0,0 +1,284 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use std::fs::{File,OpenOptions};
+use std::io::{self,Read,Seek,SeekFrom,Write};
+use std::path::Path;
+use crate::diff::{DeltaError,VecDelta};
+use crate::util::checksum;
+
+/// Size of the header preceding each record, consisting of the length
+/// of its payload (as a `u32`) and a checksum of the payload (as a
+/// `u64`), both little-endian.  The checksum is the FNV-1a hash of the
+/// raw payload bytes (see `checksum()`), so journals can be moved
+/// between platforms.
+const HEADER : usize = 12;
+
+/// An append-only log of deltas, such as for persisting a sequence of
+/// edits to a document.  Each delta is serialised (as JSON) into a
+/// _record_, which is prefixed by its length and a checksum.  The
+/// deltas can then be read back in order, or replayed onto the
+/// sequence they were originally applied to.  For example:
+///
+/// ```
+/// use std::io::Cursor;
+/// use delta_inc::diff::Diff;
+/// use delta_inc::journal::Journal;
+///
+/// let mut journal = Journal::new(Cursor::new(Vec::new())).unwrap();
+/// journal.append(&[1,2,3].diff(&[1,3])).unwrap();
+/// journal.append(&[1,3].diff(&[0,1,3])).unwrap();
+/// let mut vec = vec![1,2,3];
+/// journal.replay_onto(&mut vec).unwrap();
+/// assert_eq!(vec,[0,1,3]);
+/// ```
+///
+/// Since a write may be interrupted part way through (e.g. by a
+/// crash), the journal is scanned when opened.  Any trailing record
+/// which is incomplete, or whose checksum does not match, is discarded
+/// (see `discarded()`) and overwritten by the next append.
+#[derive(Debug)]
+pub struct Journal<F> {
+    /// Underlying storage.
+    file: F,
+    /// Length of the valid prefix of the storage.
+    len: u64,
+    /// Number of records in the valid prefix.
+    records: usize,
+    /// Number of bytes following the valid prefix which were discarded
+    /// when opened.
+    discarded: u64
+}
+
+impl Journal<File> {
+    /// Open (or create) a journal stored in a given file.  Any damaged
+    /// records at the end of the file are truncated.
+    pub fn open<P:AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let journal = Journal::new(file)?;
+        if journal.discarded > 0 { journal.file.set_len(journal.len)?; }
+        Ok(journal)
+    }
+}
+
+impl<F:Read+Write+Seek> Journal<F> {
+    /// Construct a journal over some storage, which is scanned to find
+    /// the valid records it already contains.
+    pub fn new(mut file: F) -> io::Result<Self> {
+        let end = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(0))?;
+        let (mut len, mut records) = (0,0);
+        while let Some(payload) = read_record(&mut file, end - len)? {
+            len += (HEADER + payload.len()) as u64;
+            records += 1;
+        }
+        Ok(Journal{file, len, records, discarded: end - len})
+    }
+
+    /// Get the number of records in this journal.
+    pub fn len(&self) -> usize { self.records }
+
+    /// Check whether this journal contains any records or not.
+    pub fn is_empty(&self) -> bool { self.records == 0 }
+
+    /// Get the number of bytes discarded when this journal was opened,
+    /// because they did not form a complete and valid record.
+    pub fn discarded(&self) -> u64 { self.discarded }
+
+    /// Append a delta onto the end of this journal.  The underlying
+    /// storage is flushed once the record is written.
+    pub fn append<T:serde::Serialize>(&mut self, delta: &VecDelta<T>) -> io::Result<()> {
+        let payload = serde_json::to_vec(delta).map_err(io::Error::other)?;
+        let len = u32::try_from(payload.len()).map_err(io::Error::other)?;
+        let mut record = Vec::with_capacity(HEADER + payload.len());
+        record.extend(len.to_le_bytes());
+        record.extend(checksum(&payload).to_le_bytes());
+        record.extend(payload);
+        self.file.seek(SeekFrom::Start(self.len))?;
+        self.file.write_all(&record)?;
+        self.file.flush()?;
+        self.len += record.len() as u64;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Iterate the deltas in this journal, in the order they were
+    /// appended.
+    pub fn iter<T>(&mut self) -> Iter<'_,F,T> {
+        Iter{journal: self, pos: 0, dummy: PhantomData}
+    }
+
+    /// Apply every delta in this journal to a given `Vec`, in order.
+    /// Each is applied using `VecDelta::try_transform()` and, hence,
+    /// the first which does not fit causes an error (in which case the
+    /// `Vec` holds the result of applying the preceding deltas).
+    pub fn replay_onto<T>(&mut self, vec: &mut Vec<T>) -> Result<(),JournalError>
+    where T:Clone+for<'de> serde::Deserialize<'de> {
+        for delta in self.iter::<T>() {
+            delta?.try_transform(vec).map_err(JournalError::Delta)?;
+        }
+        Ok(())
+    }
+
+    /// Consume this journal, returning the underlying storage.
+    pub fn into_inner(self) -> F { self.file }
+}
+
+/// An iterator over the deltas of a `Journal` (see `Journal::iter()`).
+pub struct Iter<'a,F,T> {
+    journal: &'a mut Journal<F>,
+    /// Position of the next record.
+    pos: u64,
+    dummy: PhantomData<T>
+}
+
+impl<F:Read+Write+Seek,T> Iterator for Iter<'_,F,T>
+where T:Clone+for<'de> serde::Deserialize<'de> {
+    type Item = Result<VecDelta<T>,JournalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.journal.len { return None; }
+        let pos = self.pos;
+        let file = &mut self.journal.file;
+        let payload = match file.seek(SeekFrom::Start(pos)).and_then(|_| read_record(file, self.journal.len - pos)) {
+            Ok(Some(payload)) => payload,
+            Ok(None) => { self.pos = self.journal.len; return Some(Err(JournalError::Corrupt(pos))); }
+            Err(e) => { self.pos = self.journal.len; return Some(Err(JournalError::Io(e))); }
+        };
+        self.pos += (HEADER + payload.len()) as u64;
+        Some(serde_json::from_slice(&payload).map_err(|_| JournalError::Corrupt(pos)))
+    }
+}
+
+/// Errors arising from reading or replaying a `Journal`.
+#[derive(Debug)]
+pub enum JournalError {
+    /// The underlying storage could not be read.
+    Io(io::Error),
+    /// The record at the given byte offset is damaged (e.g. because the
+    /// storage was modified after the journal was opened).
+    Corrupt(u64),
+    /// A delta could not be applied.
+    Delta(DeltaError)
+}
+
+impl core::fmt::Display for JournalError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            JournalError::Io(e) => write!(f,"journal could not be read: {e}"),
+            JournalError::Corrupt(pos) => write!(f,"journal record at byte {pos} is corrupt"),
+            JournalError::Delta(e) => write!(f,"journal delta could not be applied: {e}")
+        }
+    }
+}
+
+impl core::error::Error for JournalError {}
+
+/// Read the record at the current position, given the number of bytes
+/// remaining.  This returns `None` if the record is incomplete or its
+/// checksum does not match.
+fn read_record<F:Read>(file: &mut F, remaining: u64) -> io::Result<Option<Vec<u8>>> {
+    if remaining < HEADER as u64 { return Ok(None); }
+    let mut header = [0;HEADER];
+    file.read_exact(&mut header)?;
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    let expected = u64::from_le_bytes(header[4..].try_into().unwrap());
+    if remaining - (HEADER as u64) < len as u64 { return Ok(None); }
+    let mut payload = vec![0;len];
+    file.read_exact(&mut payload)?;
+    Ok(if checksum(&payload) == expected { Some(payload) } else { None })
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod journal_tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use std::io::Cursor;
+    use crate::diff::{DeltaError,Diff,VecDelta};
+    use super::{Journal,JournalError};
+
+    #[test]
+    fn journal_test_01() {
+        let versions = [vec!['a','b','c'],vec!['a','c'],vec!['x','a','c','d'],vec![]];
+        let mut journal = Journal::new(Cursor::new(Vec::new())).unwrap();
+        for w in versions.windows(2) {
+            journal.append(&w[0].diff(&w[1])).unwrap();
+        }
+        assert_eq!(journal.len(),3);
+        let deltas : Vec<VecDelta<char>> = journal.iter().map(|d| d.unwrap()).collect();
+        assert_eq!(deltas[1],versions[1].diff(&versions[2]));
+        // Reopening finds the same records
+        let mut journal = Journal::new(journal.into_inner()).unwrap();
+        assert_eq!((journal.len(),journal.discarded()),(3,0));
+        let mut vec = versions[0].clone();
+        journal.replay_onto(&mut vec).unwrap();
+        assert!(vec.is_empty());
+        // Replaying onto the wrong sequence fails
+        let mut vec = vec!['a'];
+        assert!(matches!(journal.replay_onto(&mut vec),Err(JournalError::Delta(DeltaError::RegionOutOfBounds{..}))));
+    }
+
+    #[test]
+    fn journal_test_02() {
+        // Partial writes are discarded on recovery
+        let mut journal = Journal::new(Cursor::new(Vec::new())).unwrap();
+        journal.append(&[1,2].diff(&[1])).unwrap();
+        let first = journal.into_inner().into_inner().len();
+        let mut journal = Journal::new(Cursor::new(Vec::new())).unwrap();
+        journal.append(&[1,2].diff(&[1])).unwrap();
+        journal.append(&[1].diff(&[1,3])).unwrap();
+        let bytes = journal.into_inner().into_inner();
+        for n in 0..bytes.len() {
+            let journal = Journal::new(Cursor::new(bytes[..n].to_vec())).unwrap();
+            let expected = if n < first { 0 } else { 1 };
+            assert_eq!(journal.len(),expected);
+        }
+        // Damaged record is discarded, and overwritten by next append
+        let mut damaged = bytes.clone();
+        *damaged.last_mut().unwrap() ^= 1;
+        let mut journal = Journal::new(Cursor::new(damaged)).unwrap();
+        assert_eq!((journal.len(),journal.discarded()),(1,(bytes.len() - first) as u64));
+        journal.append(&[1].diff(&[4])).unwrap();
+        let mut vec = vec![1,2];
+        journal.replay_onto(&mut vec).unwrap();
+        assert_eq!(vec,[4]);
+    }
+
+    #[test]
+    fn journal_test_03() {
+        let path = std::env::temp_dir().join(std::format!("delta_inc_journal_{}",std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let mut journal = Journal::open(&path).unwrap();
+            journal.append(&[1,2].diff(&[2])).unwrap();
+        }
+        // Simulate an interrupted append
+        let mut bytes = std::fs::read(&path).unwrap();
+        let len = bytes.len();
+        bytes.extend_from_slice(&[9,0,0]);
+        std::fs::write(&path,&bytes).unwrap();
+        let mut journal = Journal::open(&path).unwrap();
+        assert_eq!((journal.len(),journal.discarded()),(1,3));
+        assert_eq!(std::fs::metadata(&path).unwrap().len(),len as u64);
+        let mut vec = vec![1,2];
+        journal.replay_onto(&mut vec).unwrap();
+        assert_eq!(vec,[2]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn journal_test_04() {
+        // Record layout is fixed
+        let mut journal = Journal::new(Cursor::new(Vec::new())).unwrap();
+        journal.append(&VecDelta::<u8>::new()).unwrap();
+        let bytes = journal.into_inner().into_inner();
+        let payload = b"[]";
+        assert_eq!(&bytes[12..],payload);
+        assert_eq!(bytes[..4],[2,0,0,0]);
+        assert_eq!(bytes[4..12],0x0961_2b07_b5ec_b5a5_u64.to_le_bytes());
+    }
+}
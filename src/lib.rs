@@ -1,5 +1,19 @@
+//! A library for describing delta transformations and incremental
+//! computation.  The core of this crate requires only `alloc` and,
+//! hence, can be used in `no_std` environments by disabling the
+//! default `std` feature.
+#![no_std]
+#![warn(clippy::std_instead_of_core)]
+#![warn(clippy::std_instead_of_alloc)]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 /// Tools for creating and working with _diffs_ (a.k.a _deltas_)
 /// between sequences.
 pub mod diff;
 /// Various utilities used throughout the library.
 pub mod util;
+/// An append-only log of deltas which can be persisted and replayed.
+#[cfg(feature = "journal")]
+pub mod journal;
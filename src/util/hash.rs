@@ -0,0 +1,177 @@
+use core::hash::{Hash,Hasher};
+
+/// A simple implementation of the 64-bit _Fowler–Noll–Vo_ (FNV-1a)
+/// hash function.  This is not cryptographically secure, but is fast
+/// and (importantly) is available without `std`.  It is used
+/// internally to bucket elements (e.g. when identifying elements
+/// unique to a sequence) without requiring an `Ord` bound.
+#[derive(Clone,Copy,Debug)]
+pub struct FnvHasher(u64);
+
+impl FnvHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub const fn new() -> Self { FnvHasher(Self::OFFSET_BASIS) }
+}
+
+impl Default for FnvHasher {
+    fn default() -> Self { Self::new() }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 { self.0 }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+/// Compute a checksum of some raw bytes using the 64-bit FNV-1a hash
+/// function.  Unlike `hash_of()`, this does not go through `Hash` and,
+/// hence, depends only on the bytes themselves.  As such, it is
+/// suitable for checksums which are stored or sent elsewhere (i.e. it
+/// is part of the wire format), since the result is the same on every
+/// platform.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// Compute a checksum of a sequence of items using the 64-bit FNV-1a
+/// hash function, which (like `checksum()`) is part of the wire format
+/// and is the same on every platform.  Specifically, this hashes the
+/// number of items (as a little-endian `u64`), followed by each item
+/// in turn.  Items are fed in through `Hash`, but with every integer
+/// written in little-endian order and every `usize` or `isize` widened
+/// to 64 bits.  Thus, the result is independent of platform for
+/// integers, strings and types deriving `Hash` from these.
+pub fn checksum_items<T:Hash>(items: &[T]) -> u64 {
+    let mut hasher = StableHasher(FnvHasher::new());
+    hasher.write_u64(items.len() as u64);
+    for item in items { item.hash(&mut hasher); }
+    hasher.finish()
+}
+
+/// A `Hasher` which writes integers with a fixed layout, regardless of
+/// platform (see `checksum_items()`).
+struct StableHasher(FnvHasher);
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 { self.0.finish() }
+    fn write(&mut self, bytes: &[u8]) { self.0.write(bytes); }
+    fn write_u16(&mut self, i: u16) { self.write(&i.to_le_bytes()); }
+    fn write_u32(&mut self, i: u32) { self.write(&i.to_le_bytes()); }
+    fn write_u64(&mut self, i: u64) { self.write(&i.to_le_bytes()); }
+    fn write_u128(&mut self, i: u128) { self.write(&i.to_le_bytes()); }
+    fn write_usize(&mut self, i: usize) { self.write_u64(i as u64); }
+    fn write_i16(&mut self, i: i16) { self.write(&i.to_le_bytes()); }
+    fn write_i32(&mut self, i: i32) { self.write(&i.to_le_bytes()); }
+    fn write_i64(&mut self, i: i64) { self.write(&i.to_le_bytes()); }
+    fn write_i128(&mut self, i: i128) { self.write(&i.to_le_bytes()); }
+    fn write_isize(&mut self, i: isize) { self.write_i64(i as i64); }
+}
+
+/// Compute the hash of a given item using `FnvHasher`.
+pub fn hash_of<T:Hash+?Sized>(item: &T) -> u64 {
+    let mut hasher = FnvHasher::new();
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Pairs an item with a precomputed hash.  Comparing two `Hashed`
+/// items first compares their hashes, and only falls back to
+/// comparing the items themselves when the hashes are equal.  This is
+/// useful when elements are expensive to compare (e.g. large structs)
+/// and are compared many times (e.g. when diffing).  Likewise,
+/// hashing a `Hashed` item simply hashes its precomputed hash.
+#[derive(Clone,Copy,Debug)]
+pub struct Hashed<'a,T:?Sized> {
+    hash: u64,
+    item: &'a T
+}
+
+impl<'a,T:?Sized> Hashed<'a,T> {
+    /// Pair an item with a given hash.  The hash must be consistent
+    /// with the item's notion of equality (i.e. equal items have equal
+    /// hashes).
+    pub fn new(hash: u64, item: &'a T) -> Self { Hashed{hash,item} }
+
+    /// Get the precomputed hash.
+    pub fn hash(&self) -> u64 { self.hash }
+
+    /// Get the underlying item.
+    pub fn item(&self) -> &'a T { self.item }
+}
+
+impl<T:PartialEq+?Sized> PartialEq for Hashed<'_,T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.item == other.item
+    }
+}
+
+impl<T:Eq+?Sized> Eq for Hashed<'_,T> {}
+
+impl<T:?Sized> Hash for Hashed<'_,T> {
+    fn hash<H:Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Pair each element of a slice with its hash, as determined by a
+/// given hash function.
+pub fn hash_all<T,F:Fn(&T)->u64>(items: &[T], hasher: F) -> alloc::vec::Vec<Hashed<'_,T>> {
+    items.iter().map(|item| Hashed::new(hasher(item),item)).collect()
+}
+
+// ===================================================================
+// Tests
+// ===================================================================
+
+#[cfg(test)]
+mod hash_tests {
+    use super::{Hashed,checksum,checksum_items,hash_of};
+
+    #[test]
+    fn test_hash_01() {
+        assert_eq!(hash_of(&1),hash_of(&1));
+        assert_ne!(hash_of(&1),hash_of(&2));
+    }
+
+    #[test]
+    fn test_hash_02() {
+        assert_eq!(hash_of("hello"),hash_of("hello"));
+        assert_ne!(hash_of("hello"),hash_of("world"));
+    }
+
+    #[test]
+    fn test_hash_03() {
+        // Checksums are fixed (these are the standard FNV-1a values)
+        assert_eq!(checksum(b""),0xcbf2_9ce4_8422_2325);
+        assert_eq!(checksum(b"a"),0xaf63_dc4c_8601_ec8c);
+        assert_eq!(checksum(b"foobar"),0x8594_4171_f739_67e8);
+        assert_eq!(checksum(&[0,1,2,255]),checksum(&[0,1,2,255]));
+    }
+
+    #[test]
+    fn test_hash_04() {
+        // Item checksums have a fixed layout
+        assert_eq!(checksum_items::<u8>(&[]),checksum(&[0;8]));
+        assert_eq!(checksum_items(&[1u8,2]),checksum(&[2,0,0,0,0,0,0,0,1,2]));
+        assert_eq!(checksum_items(&[1usize]),checksum(&[1,0,0,0,0,0,0,0,1,0,0,0,0,0,0,0]));
+        assert_eq!(checksum_items(&[258u32]),checksum(&[1,0,0,0,0,0,0,0,2,1,0,0]));
+        assert_eq!(checksum_items(&[1usize,2]),0x422d_ee74_521c_4b44);
+    }
+
+    #[test]
+    fn test_hashed_01() {
+        // Collisions fall back on equality
+        assert_eq!(Hashed::new(1,&1),Hashed::new(1,&1));
+        assert_ne!(Hashed::new(1,&1),Hashed::new(1,&2));
+        assert_ne!(Hashed::new(1,&1),Hashed::new(2,&1));
+    }
+}
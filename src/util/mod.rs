@@ -1,3 +1,5 @@
+mod hash;
 mod region;
 
+pub use hash::{FnvHasher,Hashed,checksum,checksum_items,hash_all,hash_of};
 pub use region::{Region};
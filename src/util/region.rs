@@ -1,8 +1,9 @@
-use std::cmp::{PartialOrd,Ordering};
-use std::convert::From;
-use std::ops::Range;
+use core::cmp::{PartialOrd,Ordering};
+use core::convert::From;
+use core::ops::Range;
 
-#[derive(Copy,Clone,Debug,PartialEq)]
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize,serde::Deserialize))]
 pub struct Region {
     /// Starting point in source hunk of this rewrite.
     pub offset: usize,
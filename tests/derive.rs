@@ -0,0 +1,68 @@
+#![cfg(feature = "derive")]
+use std::collections::{BTreeMap,BTreeSet};
+use delta_inc::diff::{Diff,Transform};
+
+#[derive(Clone,Debug,PartialEq,Diff,Transform)]
+struct Config {
+    name: String,
+    tags: BTreeSet<String>,
+    limits: BTreeMap<String,usize>
+}
+
+#[derive(Clone,Debug,PartialEq,Diff,Transform)]
+struct Pair<T:Clone+Ord>(String,BTreeSet<T>);
+
+fn config(name: &str, tags: &[&str], limits: &[(&str,usize)]) -> Config {
+    Config {
+        name: name.to_string(),
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        limits: limits.iter().map(|(k,v)| (k.to_string(),*v)).collect()
+    }
+}
+
+#[test]
+fn test_derive_01() {
+    let c1 = config("server",&["a","b"],&[("cpu",2),("mem",4)]);
+    let c2 = config("server",&["b","c"],&[("cpu",4),("mem",4)]);
+    let d = c1.diff(&c2);
+    assert!(d.name.is_none());
+    assert_eq!(d.tags.as_ref().unwrap().added(),["c".to_string()]);
+    assert_eq!(d.limits.as_ref().unwrap().changed(),[("cpu".to_string(),4)]);
+    let mut c = c1.clone();
+    c.transform(&d);
+    assert_eq!(c,c2);
+    // Identical structs produce an empty delta
+    assert_eq!(c1.diff(&c1),ConfigDelta{name: None, tags: None, limits: None});
+}
+
+#[test]
+fn test_derive_02() {
+    let p1 = Pair("abc".to_string(),BTreeSet::from([1,2]));
+    let p2 = Pair("abd".to_string(),BTreeSet::from([1,2]));
+    let d = p1.diff(&p2);
+    assert_eq!(d.0,Some("abc".diff("abd")));
+    assert_eq!(d.1,None);
+    let mut p = p1.clone();
+    p.transform(&d);
+    assert_eq!(p,p2);
+}
+
+#[derive(Clone,Debug,PartialEq,Diff,Transform)]
+struct Node {
+    label: String,
+    comment: Option<String>,
+    shared: std::rc::Rc<BTreeSet<usize>>
+}
+
+#[test]
+fn test_derive_03() {
+    let n1 = Node{label: "a".to_string(), comment: None, shared: BTreeSet::from([1]).into()};
+    let n2 = Node{label: "a".to_string(), comment: Some("b".to_string()), shared: BTreeSet::from([1,2]).into()};
+    let d = n1.diff(&n2);
+    assert!(d.label.is_none());
+    let mut n = n1.clone();
+    n.transform(&d);
+    assert_eq!(n,n2);
+    // The original shared value is unaffected
+    assert_eq!(*n1.shared,BTreeSet::from([1]));
+}
@@ -0,0 +1,24 @@
+//! Checks the core delta machinery can be used from a crate which
+//! only has access to `core` and `alloc`.  Any accidental reliance on
+//! `std` within the public API of the gated modules shows up here as
+//! a compilation failure.
+#![no_std]
+extern crate alloc;
+
+use alloc::vec;
+use delta_inc::diff::{Diff,VecDelta};
+use delta_inc::util::Region;
+
+#[test]
+fn test_no_std_01() {
+    let r = Region::new(1,2);
+    assert_eq!(r.as_range(),1..3);
+}
+
+#[test]
+fn test_no_std_02() {
+    let mut vec = vec![1,2,3];
+    let delta : VecDelta<usize> = [1,2,3].diff(&[1,4,3]);
+    delta.transform(&mut vec);
+    assert_eq!(vec,vec![1,4,3]);
+}